@@ -0,0 +1,225 @@
+//! Side-by-side line diff rendered as an HTML table.
+
+use crate::escape_html;
+use similar::{capture_diff_slices_deadline, Algorithm, DiffTag};
+use std::time::{Duration, Instant};
+
+/// Time allowed for the line diff; past it `similar` settles for a coarser
+/// edit script instead of the minimal one.
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+/// Unchanged lines kept on each side of a change; longer runs collapse.
+const CONTEXT_LINES: usize = 3;
+/// Rows emitted before the table is cut off.
+const MAX_DIFF_ROWS: usize = 10_000;
+/// Characters of a line shown before it is cut with an ellipsis.
+const MAX_LINE_CHARS: usize = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Line-level edit script from `a` to `b` (Myers, via `similar`), one Op
+/// per line; a replaced block becomes its deletions then its insertions.
+fn diff_ops(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let deadline = Instant::now() + DIFF_TIMEOUT;
+    let mut ops = Vec::new();
+    for op in capture_diff_slices_deadline(Algorithm::Myers, a, b, Some(deadline)) {
+        let (tag, old, new) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => ops.extend(old.zip(new).map(|(i, j)| Op::Equal(i, j))),
+            DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+                ops.extend(old.map(Op::Delete));
+                ops.extend(new.map(Op::Insert));
+            }
+        }
+    }
+    ops
+}
+
+/// One table row: a line index on either side (None leaves it blank).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Context(usize, usize),
+    Change(Option<usize>, Option<usize>),
+}
+
+/// Pair each run of deletions with the insertions that follow it, so a
+/// changed line sits beside its replacement.
+fn diff_rows(ops: &[Op]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let (mut dels, mut ins) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<Row>, dels: &mut Vec<usize>, ins: &mut Vec<usize>| {
+        for k in 0..dels.len().max(ins.len()) {
+            rows.push(Row::Change(dels.get(k).copied(), ins.get(k).copied()));
+        }
+        dels.clear();
+        ins.clear();
+    };
+    for op in ops {
+        match *op {
+            Op::Delete(i) => dels.push(i),
+            Op::Insert(j) => ins.push(j),
+            Op::Equal(i, j) => {
+                flush(&mut rows, &mut dels, &mut ins);
+                rows.push(Row::Context(i, j));
+            }
+        }
+    }
+    flush(&mut rows, &mut dels, &mut ins);
+    rows
+}
+
+fn line_cell(lines: &[&str], index: Option<usize>, class: &str) -> String {
+    let Some(i) = index else {
+        return "<td class=\"diff-num\"></td><td class=\"diff-empty\"></td>".to_string();
+    };
+    let line = lines[i];
+    let shown = match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}…", escape_html(&line[..cut])),
+        None => escape_html(line),
+    };
+    format!(
+        "<td class=\"diff-num\">{}</td><td class=\"{class}\">{shown}</td>",
+        i + 1
+    )
+}
+
+fn skip_row(count: usize) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    format!(
+        "<tr class=\"diff-skip\"><td colspan=\"4\">⋯ {count} unchanged line{plural}</td></tr>\n"
+    )
+}
+
+/// Two-column diff of `left` against `right` as a `diff-table`: unchanged
+/// lines are `diff-ctx`, removed ones `diff-del`, added ones `diff-add`, and a
+/// changed line shows as a `diff-del` beside its `diff-add`. Unchanged runs
+/// longer than the surrounding context collapse into a `diff-skip` row, long
+/// lines are cut, and the table stops after MAX_DIFF_ROWS rows.
+pub fn render_line_diff(left: &str, right: &str, left_name: &str, right_name: &str) -> String {
+    let a: Vec<&str> = left.lines().collect();
+    let b: Vec<&str> = right.lines().collect();
+    let rows = diff_rows(&diff_ops(&a, &b));
+
+    let (mut added, mut removed) = (0, 0);
+    for row in &rows {
+        if let Row::Change(l, r) = row {
+            removed += usize::from(l.is_some());
+            added += usize::from(r.is_some());
+        }
+    }
+    // A context row is shown when a change lies within CONTEXT_LINES of it.
+    let mut near_change = vec![false; rows.len()];
+    for (k, row) in rows.iter().enumerate() {
+        if matches!(row, Row::Change(..)) {
+            let lo = k.saturating_sub(CONTEXT_LINES);
+            let hi = (k + CONTEXT_LINES + 1).min(rows.len());
+            near_change[lo..hi].iter_mut().for_each(|v| *v = true);
+        }
+    }
+
+    let mut body = String::new();
+    let mut emitted = 0;
+    let mut skipped = 0;
+    for (k, row) in rows.iter().enumerate() {
+        if let Row::Context(..) = row {
+            if !near_change[k] {
+                skipped += 1;
+                continue;
+            }
+        }
+        if skipped > 0 {
+            body.push_str(&skip_row(skipped));
+            skipped = 0;
+        }
+        if emitted == MAX_DIFF_ROWS {
+            body.push_str(&format!(
+                "<tr class=\"diff-skip\"><td colspan=\"4\">Diff cut off after {MAX_DIFF_ROWS} rows</td></tr>\n"
+            ));
+            break;
+        }
+        emitted += 1;
+        let cells = match *row {
+            Row::Context(i, j) => format!(
+                "{}{}",
+                line_cell(&a, Some(i), "diff-ctx"),
+                line_cell(&b, Some(j), "diff-ctx")
+            ),
+            Row::Change(i, j) => format!(
+                "{}{}",
+                line_cell(&a, i, "diff-del"),
+                line_cell(&b, j, "diff-add")
+            ),
+        };
+        body.push_str(&format!("<tr>{cells}</tr>\n"));
+    }
+    if skipped > 0 {
+        body.push_str(&skip_row(skipped));
+    }
+
+    let summary = if added == 0 && removed == 0 {
+        "No differences".to_string()
+    } else {
+        format!("{removed} removed, {added} added")
+    };
+    format!(
+        "<div class=\"markdown-body diff-view\">\n<p class=\"diff-summary\">{summary}</p>\n\
+         <table class=\"diff-table\">\n<colgroup><col class=\"diff-num-col\"><col><col class=\"diff-num-col\"><col></colgroup>\n<thead><tr><th colspan=\"2\">{}</th><th colspan=\"2\">{}</th></tr></thead>\n\
+         <tbody>\n{body}</tbody>\n</table>\n</div>",
+        escape_html(left_name),
+        escape_html(right_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_changes_and_collapses_distant_context() {
+        let left: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        let right = left.replace("line 10\n", "line ten\n") + "extra <b>\n";
+        let html = render_line_diff(&left, &right, "a.md", "b.md");
+
+        assert!(
+            html.contains("<p class=\"diff-summary\">1 removed, 2 added</p>"),
+            "got: {html}"
+        );
+        assert!(
+            html.contains("<td class=\"diff-num\">10</td><td class=\"diff-del\">line 10</td><td class=\"diff-num\">10</td><td class=\"diff-add\">line ten</td>"),
+            "got: {html}"
+        );
+        assert!(
+            html.contains("<td class=\"diff-add\">extra &lt;b&gt;</td>"),
+            "got: {html}"
+        );
+        assert!(html.contains("⋯ 6 unchanged lines"), "got: {html}");
+        assert!(!html.contains(">line 2<"), "got: {html}");
+        assert!(
+            html.contains(">line 13<") && !html.contains(">line 14<"),
+            "got: {html}"
+        );
+        assert!(html.contains("⋯ 4 unchanged lines"), "got: {html}");
+    }
+
+    #[test]
+    fn identical_files_and_oversized_inputs_stay_small() {
+        let same = render_line_diff("a\nb\n", "a\nb\n", "x", "y");
+        assert!(same.contains("No differences") && same.contains("⋯ 2 unchanged lines"));
+
+        // Nothing in common: one replaced block, cut at MAX_DIFF_ROWS.
+        let left: String = (0..3000).map(|i| format!("l{i}\n")).collect();
+        let right: String = (0..30_000).map(|i| format!("r{i}\n")).collect();
+        let html = render_line_diff(&left, &right, "x", "y");
+        assert!(html.contains("3000 removed, 30000 added"));
+        assert!(html.contains("Diff cut off after 10000 rows"));
+        assert_eq!(html.matches("<tr><td").count(), MAX_DIFF_ROWS);
+
+        let long = "x".repeat(MAX_LINE_CHARS + 50);
+        let html = render_line_diff("", &long, "x", "y");
+        assert!(html.contains(&format!("{}…</td>", "x".repeat(MAX_LINE_CHARS))));
+    }
+}
@@ -0,0 +1,407 @@
+//! GitHub-style `:shortcode:` names for `MarkdownOptions::enable_emoji`.
+//! A bundled subset of the gemoji table: the faces, hands, hearts, symbols
+//! and objects that turn up in READMEs and notes.
+
+/// Sorted by name for `binary_search_by`.
+static SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("1234", "🔢"),
+    ("airplane", "✈️"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("ambulance", "🚑"),
+    ("anchor", "⚓"),
+    ("angel", "👼"),
+    ("anger", "💢"),
+    ("angry", "😠"),
+    ("ant", "🐜"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_forward", "▶️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("arrows_counterclockwise", "🔄"),
+    ("art", "🎨"),
+    ("asterisk", "*️⃣"),
+    ("astonished", "😲"),
+    ("baby", "👶"),
+    ("back", "🔙"),
+    ("balloon", "🎈"),
+    ("ballot_box_with_check", "☑️"),
+    ("bangbang", "‼️"),
+    ("bar_chart", "📊"),
+    ("battery", "🔋"),
+    ("beach_umbrella", "🏖️"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("beetle", "🐞"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("bird", "🐦"),
+    ("birthday", "🎂"),
+    ("black_circle", "⚫"),
+    ("black_heart", "🖤"),
+    ("blue_heart", "💙"),
+    ("blush", "😊"),
+    ("bomb", "💣"),
+    ("book", "📖"),
+    ("bookmark", "🔖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bow", "🙇"),
+    ("brain", "🧠"),
+    ("bread", "🍞"),
+    ("bricks", "🧱"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("bus", "🚌"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("camping", "🏕️"),
+    ("car", "🚗"),
+    ("cat", "🐱"),
+    ("chart", "💹"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("check", "✔️"),
+    ("checkered_flag", "🏁"),
+    ("cherries", "🍒"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("clock1", "🕐"),
+    ("closed_book", "📕"),
+    ("closed_lock_with_key", "🔐"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("cold_sweat", "😰"),
+    ("compass", "🧭"),
+    ("computer", "💻"),
+    ("confetti_ball", "🎊"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cookie", "🍪"),
+    ("cool", "🆒"),
+    ("copyright", "©️"),
+    ("cow", "🐮"),
+    ("crab", "🦀"),
+    ("crossed_fingers", "🤞"),
+    ("crown", "👑"),
+    ("cry", "😢"),
+    ("crystal_ball", "🔮"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("desert", "🏜️"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dna", "🧬"),
+    ("dog", "🐶"),
+    ("dollar", "💵"),
+    ("dragon", "🐉"),
+    ("droplet", "💧"),
+    ("ear", "👂"),
+    ("earth_africa", "🌍"),
+    ("earth_americas", "🌎"),
+    ("earth_asia", "🌏"),
+    ("egg", "🥚"),
+    ("eight", "8️⃣"),
+    ("email", "📧"),
+    ("end", "🔚"),
+    ("envelope", "✉️"),
+    ("exclamation", "❗"),
+    ("expressionless", "😑"),
+    ("eyes", "👀"),
+    ("face_with_head_bandage", "🤕"),
+    ("facepalm", "🤦"),
+    ("fearful", "😨"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("fish", "🐟"),
+    ("fist", "✊"),
+    ("five", "5️⃣"),
+    ("flags", "🎏"),
+    ("flashlight", "🔦"),
+    ("floppy_disk", "💾"),
+    ("flushed", "😳"),
+    ("four", "4️⃣"),
+    ("fox_face", "🦊"),
+    ("free", "🆓"),
+    ("frog", "🐸"),
+    ("frowning", "😦"),
+    ("fuelpump", "⛽"),
+    ("gear", "⚙️"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe", "🌐"),
+    ("globe_with_meridians", "🌐"),
+    ("goal_net", "🥅"),
+    ("green_circle", "🟢"),
+    ("green_heart", "💚"),
+    ("grey_question", "❔"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("guitar", "🎸"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("hand", "✋"),
+    ("handshake", "🤝"),
+    ("hankey", "💩"),
+    ("hash", "#️⃣"),
+    ("headphones", "🎧"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_exclamation_mark", "❗"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_multiplication_x", "✖️"),
+    ("heavy_plus_sign", "➕"),
+    ("hibiscus", "🌺"),
+    ("high_brightness", "🔆"),
+    ("honeybee", "🐝"),
+    ("hospital", "🏥"),
+    ("hourglass", "⌛"),
+    ("hourglass_flowing_sand", "⏳"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("ice_cream", "🍨"),
+    ("imp", "👿"),
+    ("information_source", "ℹ️"),
+    ("innocent", "😇"),
+    ("jack_o_lantern", "🎃"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("keyboard", "⌨️"),
+    ("kiss", "💋"),
+    ("kissing", "😗"),
+    ("koala", "🐨"),
+    ("label", "🏷️"),
+    ("lady_beetle", "🐞"),
+    ("ladybug", "🐞"),
+    ("large_blue_circle", "🔵"),
+    ("laughing", "😆"),
+    ("leaves", "🍃"),
+    ("ledger", "📒"),
+    ("lemon", "🍋"),
+    ("link", "🔗"),
+    ("lion", "🦁"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("lock_with_ink_pen", "🔏"),
+    ("loudspeaker", "📢"),
+    ("love_letter", "💌"),
+    ("mag", "🔍"),
+    ("mag_right", "🔎"),
+    ("mailbox", "📫"),
+    ("mask", "😷"),
+    ("medal_sports", "🏅"),
+    ("mega", "📣"),
+    ("memo", "📝"),
+    ("microbe", "🦠"),
+    ("microphone", "🎤"),
+    ("microscope", "🔬"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("monkey", "🐒"),
+    ("moon", "🌔"),
+    ("mortar_board", "🎓"),
+    ("mountain", "⛰️"),
+    ("mouse", "🐭"),
+    ("muscle", "💪"),
+    ("mushroom", "🍄"),
+    ("musical_note", "🎵"),
+    ("nail_care", "💅"),
+    ("negative_squared_cross_mark", "❎"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("nine", "9️⃣"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("no_good", "🙅"),
+    ("notebook", "📓"),
+    ("o", "⭕"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("on", "🔛"),
+    ("one", "1️⃣"),
+    ("open_mouth", "😮"),
+    ("orange_book", "📙"),
+    ("orange_heart", "🧡"),
+    ("owl", "🦉"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("paperclip", "📎"),
+    ("partly_sunny", "⛅"),
+    ("partying_face", "🥳"),
+    ("pencil", "📝"),
+    ("pencil2", "✏️"),
+    ("penguin", "🐧"),
+    ("pensive", "😔"),
+    ("phone", "☎️"),
+    ("pig", "🐷"),
+    ("pill", "💊"),
+    ("pin", "📍"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("poop", "💩"),
+    ("pray", "🙏"),
+    ("purple_heart", "💜"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rabbit", "🐰"),
+    ("racehorse", "🐎"),
+    ("radio_button", "🔘"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("registered", "®️"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("repeat", "🔁"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("round_pushpin", "📍"),
+    ("runner", "🏃"),
+    ("running", "🏃"),
+    ("sad", "😢"),
+    ("satellite", "📡"),
+    ("scissors", "✂️"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("seven", "7️⃣"),
+    ("shield", "🛡️"),
+    ("ship", "🚢"),
+    ("shrug", "🤷"),
+    ("six", "6️⃣"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slightly_frowning_face", "🙁"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smiling_imp", "😈"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("snowman", "⛄"),
+    ("sob", "😭"),
+    ("soccer", "⚽"),
+    ("soon", "🔜"),
+    ("sos", "🆘"),
+    ("space_invader", "👾"),
+    ("sparkle", "❇️"),
+    ("sparkles", "✨"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("star_struck", "🤩"),
+    ("station", "🚉"),
+    ("stop_sign", "🛑"),
+    ("stopwatch", "⏱️"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("sun_with_face", "🌞"),
+    ("sunflower", "🌻"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat", "😓"),
+    ("sweat_drops", "💦"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("taxi", "🚕"),
+    ("tea", "🍵"),
+    ("telescope", "🔭"),
+    ("ten", "🔟"),
+    ("test_tube", "🧪"),
+    ("thinking", "🤔"),
+    ("thought_balloon", "💭"),
+    ("three", "3️⃣"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("ticket", "🎫"),
+    ("tiger", "🐯"),
+    ("timer_clock", "⏲️"),
+    ("tired_face", "😫"),
+    ("tm", "™️"),
+    ("toolbox", "🧰"),
+    ("top", "🔝"),
+    ("tophat", "🎩"),
+    ("train", "🚋"),
+    ("tree", "🌳"),
+    ("triangular_flag_on_post", "🚩"),
+    ("trophy", "🏆"),
+    ("truck", "🚚"),
+    ("tulip", "🌷"),
+    ("turtle", "🐢"),
+    ("two", "2️⃣"),
+    ("umbrella", "☔"),
+    ("unamused", "😒"),
+    ("unicorn", "🦄"),
+    ("unlock", "🔓"),
+    ("up", "🆙"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("vertical_traffic_light", "🚦"),
+    ("video_camera", "📹"),
+    ("volcano", "🌋"),
+    ("warning", "⚠️"),
+    ("watch", "⌚"),
+    ("wave", "👋"),
+    ("whale", "🐳"),
+    ("white_check_mark", "✅"),
+    ("white_circle", "⚪"),
+    ("white_heart", "🤍"),
+    ("wink", "😉"),
+    ("wolf", "🐺"),
+    ("world_map", "🗺️"),
+    ("worried", "😟"),
+    ("wrench", "🔧"),
+    ("writing_hand", "✍️"),
+    ("x", "❌"),
+    ("yellow_circle", "🟡"),
+    ("yellow_heart", "💛"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zero", "0️⃣"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+/// The emoji for shortcode `name` (without colons), if it's in the table.
+pub(crate) fn lookup(name: &str) -> Option<&'static str> {
+    SHORTCODES
+        .binary_search_by(|(code, _)| (*code).cmp(name))
+        .ok()
+        .map(|i| SHORTCODES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcodes_are_sorted_and_unique() {
+        assert!(SHORTCODES.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(lookup("rocket"), Some("🚀"));
+        assert_eq!(lookup("+1"), Some("👍"));
+        assert_eq!(lookup("not_a_real_one"), None);
+    }
+}
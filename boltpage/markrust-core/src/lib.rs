@@ -1,15 +1,31 @@
-use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+mod diff;
+mod emoji;
+
+pub use diff::render_line_diff;
+
+use pulldown_cmark::{
+    html, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd, TextMergeStream,
+};
 use serde_json as serde_json_crate;
 use serde_yaml as serde_yaml_crate;
-use std::sync::OnceLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 use syntect::highlighting::ThemeSet;
-use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
-use syntect::parsing::{SyntaxDefinition, SyntaxSet};
+use syntect::html::{
+    css_for_theme_with_class_style, line_tokens_to_classed_spans, ClassStyle, ClassedHTMLGenerator,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
 
-static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
-static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+// Behind a lock so custom themes/syntaxes can be swapped in at runtime;
+// readers clone the Arc and never hold the lock while highlighting.
+static SYNTAX_SET: OnceLock<RwLock<Arc<SyntaxSet>>> = OnceLock::new();
+static THEME_SET: OnceLock<RwLock<Arc<ThemeSet>>> = OnceLock::new();
 static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+static RELAXED_SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
 static CALLOUT_RE: OnceLock<regex::Regex> = OnceLock::new();
+static INLINE_LINK_RE: OnceLock<regex::Regex> = OnceLock::new();
+static ESCAPED_URL_RE: OnceLock<regex::Regex> = OnceLock::new();
+static LOG_LEVEL_RE: OnceLock<regex::Regex> = OnceLock::new();
 
 // Vendored .sublime-syntax packs for languages absent from syntect's
 // default-fancy bundle. Embedded at compile-time via include_str! so the
@@ -37,41 +53,220 @@ const EXTRA_SYNTAXES: &[(&str, &str)] = &[
     ("TOML", include_str!("../syntaxes/TOML.sublime-syntax")),
 ];
 
-fn get_syntax_set() -> &'static SyntaxSet {
-    SYNTAX_SET.get_or_init(|| {
-        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
-        for (name, src) in EXTRA_SYNTAXES {
-            let def = SyntaxDefinition::load_from_str(src, true, Some(name))
-                .unwrap_or_else(|e| panic!("bundled syntax {name} failed to parse: {e}"));
-            builder.add(def);
+fn bundled_syntaxes() -> SyntaxSetBuilder {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    for (name, src) in EXTRA_SYNTAXES {
+        let def = SyntaxDefinition::load_from_str(src, true, Some(name))
+            .unwrap_or_else(|e| panic!("bundled syntax {name} failed to parse: {e}"));
+        builder.add(def);
+    }
+    builder
+}
+
+fn syntax_set_slot() -> &'static RwLock<Arc<SyntaxSet>> {
+    SYNTAX_SET.get_or_init(|| RwLock::new(Arc::new(bundled_syntaxes().build())))
+}
+
+fn theme_set_slot() -> &'static RwLock<Arc<ThemeSet>> {
+    THEME_SET.get_or_init(|| RwLock::new(Arc::new(ThemeSet::load_defaults())))
+}
+
+fn get_syntax_set() -> Arc<SyntaxSet> {
+    syntax_set_slot()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+fn get_theme_set() -> Arc<ThemeSet> {
+    theme_set_slot()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// A user extension file that could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExtensionLoadError {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of scanning an extensions directory: file names that loaded and
+/// the ones that were rejected, so the UI can explain a missing theme.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ExtensionLoadReport {
+    pub loaded: Vec<String>,
+    pub failed: Vec<ExtensionLoadError>,
+}
+
+/// Files directly inside `dir` with the given extension, sorted so load order
+/// (and therefore which duplicate wins) is stable across runs.
+fn extension_files(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case(ext)))
+        .collect();
+    files.sort();
+    files
+}
+
+fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Rebuild the global syntax set from the bundled syntaxes plus every
+/// `.sublime-syntax` file in `dir`. A missing directory simply restores the
+/// bundled set, which is what a reload after deleting the folder should do.
+pub fn init_custom_syntaxes(dir: &Path) -> ExtensionLoadReport {
+    let (set, report) = load_syntax_set(dir);
+    *syntax_set_slot()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Arc::new(set);
+    report
+}
+
+/// The set `init_custom_syntaxes` installs, without installing it.
+fn load_syntax_set(dir: &Path) -> (SyntaxSet, ExtensionLoadReport) {
+    let mut report = ExtensionLoadReport::default();
+    let mut builder = bundled_syntaxes();
+    for path in extension_files(dir, "sublime-syntax") {
+        let label = file_label(&path);
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|src| {
+                SyntaxDefinition::load_from_str(&src, true, name.as_deref())
+                    .map_err(|e| e.to_string())
+            });
+        match loaded {
+            Ok(def) => {
+                builder.add(def);
+                report.loaded.push(label);
+            }
+            Err(error) => report
+                .failed
+                .push(ExtensionLoadError { path: label, error }),
+        }
+    }
+    (builder.build(), report)
+}
+
+/// Rebuild the global theme set from syntect's defaults plus every
+/// `.tmTheme` file in `dir`, keyed by file stem. A custom theme with the same
+/// stem as a default replaces it.
+pub fn init_custom_themes(dir: &Path) -> ExtensionLoadReport {
+    let (set, report) = load_theme_set(dir);
+    *theme_set_slot()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Arc::new(set);
+    report
+}
+
+/// The set `init_custom_themes` installs, without installing it.
+fn load_theme_set(dir: &Path) -> (ThemeSet, ExtensionLoadReport) {
+    let mut report = ExtensionLoadReport::default();
+    let mut set = ThemeSet::load_defaults();
+    for path in extension_files(dir, "tmTheme") {
+        let label = file_label(&path);
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        match ThemeSet::get_theme(&path) {
+            Ok(theme) => {
+                set.themes.insert(stem, theme);
+                report.loaded.push(label);
+            }
+            Err(e) => report.failed.push(ExtensionLoadError {
+                path: label,
+                error: e.to_string(),
+            }),
         }
-        builder.build()
+    }
+    (set, report)
+}
+
+/// Names accepted by `get_syntax_theme_css` besides the `light`/`dark`
+/// aliases, sorted.
+pub fn syntax_theme_names() -> Vec<String> {
+    let mut names: Vec<String> = get_theme_set().themes.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// Cached ammonia sanitizer for `SanitizeLevel::Strict`.
+fn sanitizer() -> &'static ammonia::Builder<'static> {
+    SANITIZER.get_or_init(|| {
+        let mut b = strict_builder();
+        // The <sup>/<div> ids allowed for footnotes keep only the shapes
+        // link_footnotes emits; an `id` in raw HTML is still dropped.
+        b.attribute_filter(|element, attribute, value| {
+            if attribute == "id" && matches!(element, "sup" | "div") && !is_footnote_id(value) {
+                None
+            } else {
+                Some(value.into())
+            }
+        });
+        b
     })
 }
 
-fn get_theme_set() -> &'static ThemeSet {
-    THEME_SET.get_or_init(ThemeSet::load_defaults)
+/// `fn-N`, `fnref-N` or `fnref-N-K`, as emitted by link_footnotes.
+fn is_footnote_id(id: &str) -> bool {
+    let Some(rest) = id.strip_prefix("fnref-").or_else(|| id.strip_prefix("fn-")) else {
+        return false;
+    };
+    !rest.is_empty()
+        && rest
+            .split('-')
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// `SanitizeLevel::Relaxed`: the strict set plus `id`, `style` and `title`
+/// on any element, for hand-written HTML in trusted local files.
+fn relaxed_sanitizer() -> &'static ammonia::Builder<'static> {
+    RELAXED_SANITIZER.get_or_init(|| {
+        let mut b = strict_builder();
+        b.add_tags(&["details", "summary", "kbd", "mark"]);
+        b.add_generic_attributes(&["id", "style", "title"]);
+        b
+    })
 }
 
-/// Cached ammonia sanitizer.
-///
 /// Adds `class` to the generic-attribute whitelist so that syntect's
 /// `ClassedHTMLGenerator` output (many dot-split class names per token)
 /// survives sanitization, along with our own trusted classes on
-/// `<div class="callout …">`, `<span class="math …">`, `<pre class="mermaid">`.
+/// `<div class="callout …">`, `<span class="math …">`, `<pre class="mermaid">`,
+/// `<pre class="graphviz">`.
 /// Dangerous tags (script, iframe, object, etc.) are still excluded by
 /// ammonia's default tag whitelist.
-fn sanitizer() -> &'static ammonia::Builder<'static> {
-    SANITIZER.get_or_init(|| {
-        let mut b = ammonia::Builder::default();
-        b.add_generic_attributes(&["class"]);
-        // Task-list checkboxes emitted by pulldown-cmark's ENABLE_TASKLISTS
-        // option: <input type="checkbox" disabled [checked]>. `input` is not
-        // in ammonia's default tag set.
-        b.add_tags(&["input"]);
-        b.add_tag_attributes("input", &["type", "checked", "disabled"]);
-        b
-    })
+fn strict_builder() -> ammonia::Builder<'static> {
+    let mut b = ammonia::Builder::default();
+    b.add_generic_attributes(&["class"]);
+    // Task-list checkboxes emitted by pulldown-cmark's ENABLE_TASKLISTS
+    // option: <input type="checkbox" disabled [checked]>. `input` is not
+    // in ammonia's default tag set.
+    b.add_tags(&["input"]);
+    b.add_tag_attributes("input", &["type", "checked", "disabled", "data-task-line"]);
+    // <details>/<summary> are in the default set; keep the initial state.
+    b.add_tag_attributes("details", &["open"]);
+    // Heading anchors from MarkdownOptions::heading_ids.
+    for heading in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        b.add_tag_attributes(heading, &["id"]);
+    }
+    // Footnote anchors from link_footnotes: `fnref-N` on the reference's
+    // <sup>, `fn-N` on the definition's <div>.
+    b.add_tag_attributes("sup", &["id"]);
+    b.add_tag_attributes("div", &["id"]);
+    // Block wrappers from parse_markdown_with_source_lines and block
+    // anchors from parse_markdown_with_line_anchors.
+    b.add_generic_attributes(&["data-source-line"]);
+    b
 }
 
 fn callout_regex() -> &'static regex::Regex {
@@ -106,7 +301,7 @@ fn highlight_code(text: &str, syntax_tokens: &[&str], lang_class: &str) -> Resul
         .ok_or_else(|| format!("{lang_class} syntax not found"))?;
 
     let mut generator =
-        ClassedHTMLGenerator::new_with_class_style(syntax, syntax_set, ClassStyle::Spaced);
+        ClassedHTMLGenerator::new_with_class_style(syntax, &syntax_set, ClassStyle::Spaced);
     for line in text.lines() {
         let _ = generator.parse_html_for_line_which_includes_newline(&format!("{line}\n"));
     }
@@ -116,6 +311,31 @@ fn highlight_code(text: &str, syntax_tokens: &[&str], lang_class: &str) -> Resul
     ))
 }
 
+/// syntect's token for common fence-language spellings it doesn't know by
+/// that name; anything else is tried as written.
+fn syntax_alias(lang: &str) -> Option<&'static str> {
+    Some(match lang.to_ascii_lowercase().as_str() {
+        "sh" | "shell" | "zsh" | "shellscript" => "bash",
+        "yml" => "yaml",
+        "rs" => "rust",
+        "py" | "python3" => "python",
+        "js" | "node" => "javascript",
+        "rb" => "ruby",
+        "md" => "markdown",
+        _ => return None,
+    })
+}
+
+/// A fenced block whose language has no syntax: the same `highlight`
+/// wrapper and `language-*` class as a highlighted one, content escaped.
+fn plain_code_block(text: &str, lang_class: &str) -> String {
+    format!(
+        "<div class=\"highlight\"><pre><code class=\"language-{}\">{}</code></pre></div>",
+        escape_html(lang_class),
+        escape_html(text)
+    )
+}
+
 fn rewrite_callouts(input: &str) -> String {
     callout_regex()
         .replace_all(input, |caps: &regex::Captures| {
@@ -130,233 +350,3357 @@ fn rewrite_callouts(input: &str) -> String {
         .into_owned()
 }
 
-pub fn parse_markdown(content: &str) -> String {
-    parse_markdown_with_theme(content, "light")
+/// Matches `[[wikilinks]]` (group 1) and bare `http(s)://` / `www.` URLs
+/// (group 2) inside text spans.
+fn inline_link_regex() -> &'static regex::Regex {
+    INLINE_LINK_RE.get_or_init(|| {
+        regex::Regex::new(r"\[\[([^\[\]\n]+)\]\]|\b((?:https?://|www\.)[^\s<>]+)")
+            .expect("inline link regex must compile")
+    })
 }
 
-/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
-pub fn parse_markdown_with_theme(content: &str, _theme_name: &str) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
-    options.insert(Options::ENABLE_MATH);
-
-    let parser = Parser::new_ext(content, options);
-
-    let mut in_code_block = false;
-    let mut code_block_lang = String::new();
-    let mut code_block_content = String::new();
-
-    let mut events = Vec::new();
-
-    for event in parser {
-        match event {
-            Event::Start(Tag::CodeBlock(kind)) => {
-                in_code_block = true;
-                code_block_lang = match kind {
-                    pulldown_cmark::CodeBlockKind::Fenced(lang) => lang.to_string(),
-                    _ => String::new(),
-                };
-                code_block_content.clear();
-            }
-            Event::End(TagEnd::CodeBlock) => {
-                in_code_block = false;
-                if code_block_lang == "mermaid" {
-                    events.push(Event::Html(CowStr::from(format!(
-                        r#"<pre class="mermaid">{}</pre>"#,
-                        escape_html(&code_block_content)
-                    ))));
-                } else if !code_block_lang.is_empty() {
-                    if let Ok(block) =
-                        highlight_code(&code_block_content, &[&code_block_lang], &code_block_lang)
-                    {
-                        events.push(Event::Html(CowStr::from(block)));
-                    } else {
-                        events.push(Event::Start(Tag::CodeBlock(
-                            pulldown_cmark::CodeBlockKind::Fenced(CowStr::from(
-                                code_block_lang.clone(),
-                            )),
-                        )));
-                        events.push(Event::Text(CowStr::from(code_block_content.clone())));
-                        events.push(Event::End(TagEnd::CodeBlock));
-                    }
-                } else {
-                    events.push(Event::Start(Tag::CodeBlock(
-                        pulldown_cmark::CodeBlockKind::Fenced(CowStr::from(
-                            code_block_lang.clone(),
-                        )),
-                    )));
-                    events.push(Event::Text(CowStr::from(code_block_content.clone())));
-                    events.push(Event::End(TagEnd::CodeBlock));
-                }
+/// GFM autolink trimming: trailing punctuation is not part of the URL, nor is
+/// a closing paren that has no opening partner inside it.
+fn trim_autolink(url: &str) -> &str {
+    let mut end = url.len();
+    loop {
+        let candidate = &url[..end];
+        let Some(last) = candidate.chars().last() else {
+            break;
+        };
+        let unbalanced_paren =
+            last == ')' && candidate.matches(')').count() > candidate.matches('(').count();
+        if matches!(
+            last,
+            '?' | '!' | '.' | ',' | ':' | '*' | '_' | '~' | '\'' | '"' | ';'
+        ) || unbalanced_paren
+        {
+            end -= last.len_utf8();
+        } else {
+            break;
+        }
+    }
+    &url[..end]
+}
 
-                code_block_lang.clear();
-                code_block_content.clear();
+/// Split a text span into text, autolink and wikilink events according to
+/// `opts`, expanding emoji shortcodes in the plain text between links only.
+/// Callers must not pass text that is already inside a link.
+fn push_text_with_links(text: &str, opts: &MarkdownOptions, events: &mut Vec<Event<'_>>) {
+    let plain = |text: &str| {
+        let text = CowStr::from(text.to_string());
+        Event::Text(if opts.enable_emoji {
+            expand_emoji_shortcodes(text)
+        } else {
+            text
+        })
+    };
+    let mut last = 0;
+    for caps in inline_link_regex().captures_iter(text) {
+        let (start, end, replacement) = if let Some(name) = caps.get(1) {
+            if !opts.enable_wikilinks {
+                continue;
             }
-            Event::Text(text) if in_code_block => {
-                code_block_content.push_str(&text);
+            let (target, label) = match name.as_str().split_once('|') {
+                Some((target, label)) => (target.trim(), label.trim()),
+                None => (name.as_str().trim(), name.as_str().trim()),
+            };
+            if target.is_empty() {
+                continue;
             }
-            Event::InlineMath(text) => {
-                events.push(Event::Html(CowStr::from(format!(
-                    r#"<span class="math math-inline">{}</span>"#,
-                    escape_html(&text)
-                ))));
+            let label = if label.is_empty() { target } else { label };
+            let lower = target.to_ascii_lowercase();
+            let ext = if lower.ends_with(".md") || lower.ends_with(".markdown") {
+                ""
+            } else {
+                ".md"
+            };
+            let href = format!("{}{ext}", target.replace('%', "%25").replace(' ', "%20"));
+            let whole = caps.get(0).expect("group 0 always matches");
+            let html = format!(
+                r#"<a class="wikilink" href="{}">{}</a>"#,
+                escape_html(&href),
+                escape_html(label)
+            );
+            (
+                whole.start(),
+                whole.end(),
+                vec![Event::Html(CowStr::from(html))],
+            )
+        } else {
+            let url_match = caps.get(2).expect("one alternative always matches");
+            if !opts.autolink {
+                continue;
             }
-            Event::DisplayMath(text) => {
-                events.push(Event::Html(CowStr::from(format!(
-                    r#"<div class="math math-display">{}</div>"#,
-                    escape_html(&text)
-                ))));
+            let url = trim_autolink(url_match.as_str());
+            if url.ends_with("://") || url == "www." {
+                continue;
             }
-            _ => events.push(event),
+            let href = if url.starts_with("www.") {
+                format!("http://{url}")
+            } else {
+                url.to_string()
+            };
+            let link = Tag::Link {
+                link_type: LinkType::Autolink,
+                dest_url: CowStr::from(href),
+                title: CowStr::from(""),
+                id: CowStr::from(""),
+            };
+            (
+                url_match.start(),
+                url_match.start() + url.len(),
+                vec![
+                    Event::Start(link),
+                    Event::Text(CowStr::from(url.to_string())),
+                    Event::End(TagEnd::Link),
+                ],
+            )
+        };
+        if start > last {
+            events.push(plain(&text[last..start]));
         }
+        events.extend(replacement);
+        last = end;
+    }
+    if last < text.len() {
+        events.push(plain(&text[last..]));
     }
-
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, events.into_iter());
-
-    let with_callouts = rewrite_callouts(&html_output);
-    sanitizer().clean(&with_callouts).to_string()
 }
 
-pub fn get_syntax_theme_css(theme_name: &str) -> Option<String> {
-    let theme_set = get_theme_set();
-    let theme = match theme_name {
-        "dark" | "drac" => theme_set
-            .themes
-            .get("Monokai")
-            .or_else(|| theme_set.themes.get("base16-ocean.dark"))
-            .or_else(|| theme_set.themes.get("Solarized (dark)")),
-        _ => theme_set
-            .themes
-            .get("InspiredGitHub")
-            .or_else(|| theme_set.themes.get("base16-ocean.light"))
-            .or_else(|| theme_set.themes.get("Solarized (light)")),
+/// Replace each `:name:` in `text` whose name is in the bundled shortcode
+/// table with its emoji; unknown names are left as typed.
+fn expand_emoji_shortcodes(text: CowStr<'_>) -> CowStr<'_> {
+    if !text.contains(':') {
+        return text;
     }
-    .or_else(|| theme_set.themes.values().next())?;
-
-    let css = css_for_theme_with_class_style(theme, ClassStyle::Spaced).ok()?;
-    Some(css)
+    let mut out = String::with_capacity(text.len());
+    let mut rest: &str = &text;
+    let mut changed = false;
+    while let Some(open) = rest.find(':') {
+        let after = &rest[open + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "_+-".contains(c)))
+            .unwrap_or(after.len());
+        let emoji = (len > 0 && after[len..].starts_with(':'))
+            .then(|| emoji::lookup(&after[..len]))
+            .flatten();
+        if let Some(emoji) = emoji {
+            out.push_str(&rest[..open]);
+            out.push_str(emoji);
+            rest = &after[len + 1..];
+            changed = true;
+        } else {
+            // The closing colon may open the next shortcode.
+            out.push_str(&rest[..=open]);
+            rest = after;
+        }
+    }
+    if !changed {
+        return text;
+    }
+    out.push_str(rest);
+    CowStr::from(out)
 }
 
-/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
-pub fn parse_json_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
-    let json_value: serde_json_crate::Value =
-        serde_json_crate::from_str(content).map_err(|e| format!("Invalid JSON: {e}"))?;
-    let pretty = serde_json_crate::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to pretty-print JSON: {e}"))?;
-    highlight_code(&pretty, &["JSON", "json"], "json")
+/// Title of a ```` ```collapse Optional title ```` fence, the Markdown
+/// shorthand for `<details>`; `None` for any other info string.
+fn collapse_title(info: &str) -> Option<&str> {
+    let rest = info.strip_prefix("collapse")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let title = rest.trim();
+    Some(if title.is_empty() { "Details" } else { title })
 }
 
-/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
-///
-/// Note: serde_yaml 0.9 uses `IndexMap` for `Mapping`, so key insertion order
-/// from the source document is preserved through the parse/serialize round-trip.
-pub fn parse_yaml_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
-    let yaml_value: serde_yaml_crate::Value =
-        serde_yaml_crate::from_str(content).map_err(|e| format!("Invalid YAML: {e}"))?;
-    let pretty = serde_yaml_crate::to_string(&yaml_value)
-        .map_err(|e| format!("Failed to pretty-print YAML: {e}"))?;
-    highlight_code(&pretty, &["YAML", "yaml", "yml"], "yaml")
+/// Split a fence info string like `rust {2,4-6}` into the language token and
+/// the inclusive 1-based line ranges to emphasize. A malformed `{…}` spec
+/// emphasizes nothing; the language is kept either way.
+fn parse_fence_info(info: &str) -> (&str, Vec<(usize, usize)>) {
+    let (lang, spec) = match info.find('{') {
+        Some(open) => (&info[..open], Some(&info[open..])),
+        None => (info, None),
+    };
+    let lang = lang.split_whitespace().next().unwrap_or("");
+    let ranges = spec
+        .and_then(|spec| spec.trim_end().strip_prefix('{')?.strip_suffix('}'))
+        .and_then(|inner| {
+            inner
+                .split(',')
+                .map(|part| {
+                    let part = part.trim();
+                    let (start, end) = part.split_once('-').unwrap_or((part, part));
+                    let start: usize = start.trim().parse().ok()?;
+                    let end: usize = end.trim().parse().ok()?;
+                    (start >= 1 && start <= end).then_some((start, end))
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .unwrap_or_default();
+    (lang, ranges)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Named Markdown dialects, each mapping to a concrete `MarkdownOptions` set
+/// so a target platform's rendering can be matched with one choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownPreset {
+    /// Strict CommonMark: no tables, task lists, footnotes, math or callouts.
+    CommonMark,
+    /// GitHub-flavoured Markdown plus bare-URL autolinks and alert callouts.
+    #[default]
+    GitHub,
+    /// GitHub behaviour plus `[[wikilinks]]` to sibling notes.
+    Obsidian,
+}
 
-    #[test]
-    fn inline_math_emits_math_inline_span() {
-        let out = parse_markdown("A $\\alpha + \\beta$ B");
-        assert!(
-            out.contains(r#"<span class="math math-inline">"#),
-            "got: {out}"
-        );
-        assert!(out.contains(r"\alpha + \beta"), "got: {out}");
+impl MarkdownPreset {
+    /// Parse a stored preference value; case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "commonmark" => Some(Self::CommonMark),
+            "github" | "gfm" => Some(Self::GitHub),
+            "obsidian" => Some(Self::Obsidian),
+            _ => None,
+        }
     }
 
-    #[test]
-    fn display_math_emits_math_display_div() {
-        let out = parse_markdown("$$\\int_0^1 x\\,dx$$");
-        assert!(
-            out.contains(r#"<div class="math math-display">"#),
-            "got: {out}"
-        );
-        assert!(out.contains(r"\int_0^1 x\,dx"), "got: {out}");
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CommonMark => "commonmark",
+            Self::GitHub => "github",
+            Self::Obsidian => "obsidian",
+        }
     }
+}
 
-    #[test]
-    fn mermaid_fence_emits_pre_class() {
-        let out = parse_markdown("```mermaid\ngraph TD;A-->B\n```\n");
-        assert!(out.contains(r#"<pre class="mermaid">"#), "got: {out}");
-        assert!(out.contains("graph TD;A--&gt;B"), "got: {out}");
+/// How much raw HTML survives rendering. Local files are trusted, so users
+/// may opt out of the default allowlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SanitizeLevel {
+    /// BoltPage's own output plus ammonia's default safe tags.
+    #[default]
+    Strict,
+    /// Strict plus `id`, `style` and `title` attributes on any element.
+    Relaxed,
+    /// No sanitization: raw HTML, scripts included, renders as written.
+    None,
+}
+
+impl SanitizeLevel {
+    /// Parse a stored preference value; case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "strict" => Some(Self::Strict),
+            "relaxed" => Some(Self::Relaxed),
+            "none" => Some(Self::None),
+            _ => None,
+        }
     }
+}
 
-    #[test]
-    fn callout_note_rewrites_blockquote() {
-        let out = parse_markdown("> [!NOTE]\n> Hello\n");
-        assert!(
-            out.contains(r#"<div class="callout callout-note">"#),
-            "got: {out}"
-        );
-        assert!(
-            out.contains(r#"<div class="callout-title">NOTE</div>"#),
-            "got: {out}"
-        );
-        assert!(out.contains("Hello"), "got: {out}");
+/// Feature toggles for `parse_markdown_with_options`. `Default` is the set
+/// `parse_markdown_with_theme` has always rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    pub footnotes: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    /// PHP Markdown Extra definition lists: a term line, then `: definition`.
+    pub definition_lists: bool,
+    pub enable_math: bool,
+    /// Rewrite `> [!NOTE]`-style blockquotes into callout boxes.
+    pub callouts: bool,
+    /// Turn bare `http(s)://` and `www.` URLs in text into links.
+    pub autolink: bool,
+    pub smart_punctuation: bool,
+    /// Turn `[[Name]]` and `[[Name|text]]` into a link to `Name.md`
+    /// (relative, so it resolves against the current file's folder). A name
+    /// already ending in `.md` or `.markdown` keeps it as is.
+    pub enable_wikilinks: bool,
+    /// Replace known `:shortcode:`s in text (not code) with their emoji.
+    pub enable_emoji: bool,
+    /// Give headings GitHub-style slug `id`s (the anchors `build_toc` links to).
+    pub heading_ids: bool,
+    /// Wrap each top-level heading and the blocks under it, up to the next
+    /// heading of the same or a higher level, in `<details open>` with the
+    /// heading as its `<summary>`.
+    pub foldable_headings: bool,
+    /// Lay highlighted code blocks out as a `highlight-with-lines` table with
+    /// a line-number gutter.
+    pub code_line_numbers: bool,
+    /// Put `data-task-line="N"`, the 1-based source line, on each task-list
+    /// checkbox so a click on it can be written back to the file.
+    pub task_lines: bool,
+    /// Put `data-source-line="N"`, the 1-based source line, on the element
+    /// each top-level block renders to (or on its section's `<details>` when
+    /// headings fold), so a reload can return to the block being read.
+    pub line_anchors: bool,
+    /// How much raw HTML survives rendering (see `SanitizeLevel`).
+    pub sanitize: SanitizeLevel,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            definition_lists: true,
+            enable_math: true,
+            callouts: true,
+            autolink: false,
+            smart_punctuation: false,
+            enable_wikilinks: false,
+            enable_emoji: false,
+            heading_ids: true,
+            foldable_headings: false,
+            code_line_numbers: false,
+            task_lines: false,
+            line_anchors: false,
+            sanitize: SanitizeLevel::Strict,
+        }
     }
+}
 
-    #[test]
-    fn callout_all_kinds_render() {
-        for kind in ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"] {
-            let input = format!("> [!{kind}]\n> body\n");
-            let out = parse_markdown(&input);
-            let expected = format!(
-                r#"<div class="callout callout-{}">"#,
-                kind.to_ascii_lowercase()
-            );
-            assert!(out.contains(&expected), "kind={kind} got: {out}");
+impl From<MarkdownPreset> for MarkdownOptions {
+    fn from(preset: MarkdownPreset) -> Self {
+        match preset {
+            MarkdownPreset::CommonMark => Self {
+                tables: false,
+                footnotes: false,
+                strikethrough: false,
+                tasklists: false,
+                definition_lists: false,
+                enable_math: false,
+                callouts: false,
+                autolink: false,
+                smart_punctuation: false,
+                enable_wikilinks: false,
+                enable_emoji: false,
+                heading_ids: false,
+                foldable_headings: false,
+                code_line_numbers: false,
+                task_lines: false,
+                line_anchors: false,
+                sanitize: SanitizeLevel::Strict,
+            },
+            MarkdownPreset::GitHub => Self {
+                autolink: true,
+                ..Self::default()
+            },
+            MarkdownPreset::Obsidian => Self {
+                autolink: true,
+                enable_wikilinks: true,
+                ..Self::default()
+            },
         }
     }
+}
 
-    #[test]
-    fn syntect_classes_survive_sanitization() {
-        let out = parse_markdown("```rust\nfn main() { println!(\"hi\"); }\n```\n");
-        // syntect emits spans with `class="source rust …"` (ClassStyle::Spaced).
-        // The old ammonia::clean stripped them; the Builder keeps class now.
-        assert!(
-            out.contains(r#"<span class="source rust"#),
-            "syntect classes stripped; got: {out}"
-        );
-        assert!(
-            out.contains(r#"class="language-rust""#),
-            "language-rust class stripped; got: {out}"
-        );
+impl MarkdownOptions {
+    fn parser_options(&self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_DEFINITION_LIST, self.definition_lists);
+        options.set(Options::ENABLE_MATH, self.enable_math);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+        options
     }
+}
 
-    #[test]
-    fn preserves_core_features() {
-        let out = parse_markdown("| a | b |\n|---|---|\n| 1 | 2 |\n");
-        assert!(out.contains("<table>"), "table missing: {out}");
+pub fn parse_markdown(content: &str) -> String {
+    parse_markdown_with_theme(content, "light")
+}
 
-        let out = parse_markdown("- [x] done\n- [ ] todo\n");
-        assert!(
-            out.contains(r#"type="checkbox""#),
-            "task list missing: {out}"
-        );
+/// Options used by `render_markdown_canonical`: GFM tables, task lists,
+/// strikethrough, autolinks and heading ids, nothing else.
+pub const CANONICAL_OPTIONS: MarkdownOptions = MarkdownOptions {
+    tables: true,
+    footnotes: false,
+    strikethrough: true,
+    tasklists: true,
+    definition_lists: false,
+    enable_math: false,
+    callouts: false,
+    autolink: true,
+    smart_punctuation: false,
+    enable_wikilinks: false,
+    enable_emoji: false,
+    heading_ids: true,
+    foldable_headings: false,
+    code_line_numbers: false,
+    task_lines: false,
+    line_anchors: false,
+    sanitize: SanitizeLevel::Strict,
+};
 
-        let out = parse_markdown("~~gone~~\n");
-        assert!(out.contains("<del>"), "strikethrough missing: {out}");
+/// Render with `CANONICAL_OPTIONS`, independent of any preference, for
+/// comparing against GitHub and for golden-file tests. Output for a given
+/// input only changes when this crate's rendering does.
+pub fn render_markdown_canonical(content: &str) -> String {
+    parse_markdown_with_options(content, "light", CANONICAL_OPTIONS)
+}
 
-        let out = parse_markdown("ref[^1]\n\n[^1]: note\n");
-        assert!(out.contains("footnote"), "footnote missing: {out}");
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+pub fn parse_markdown_with_theme(content: &str, theme_name: &str) -> String {
+    parse_markdown_with_options(content, theme_name, MarkdownOptions::default())
+}
+
+/// `parse_markdown_with_theme` with raw HTML sanitized at `level`.
+pub fn parse_markdown_with_sanitize(
+    content: &str,
+    theme_name: &str,
+    level: SanitizeLevel,
+) -> String {
+    let opts = MarkdownOptions {
+        sanitize: level,
+        ..MarkdownOptions::default()
+    };
+    parse_markdown_with_options(content, theme_name, opts)
+}
+
+pub fn parse_markdown_with_options(
+    content: &str,
+    theme_name: &str,
+    opts: MarkdownOptions,
+) -> String {
+    parse_markdown_with_links(content, theme_name, opts, &LinkRewriter::default())
+}
+
+/// `parse_markdown_with_options`, with `links` applied to every link and
+/// image destination before sanitization.
+pub fn parse_markdown_with_links(
+    content: &str,
+    theme_name: &str,
+    opts: MarkdownOptions,
+    links: &LinkRewriter,
+) -> String {
+    let content = guard_definition_lists(content, opts);
+    let mut events = markdown_events(&content, theme_name, opts, links, 1);
+    if opts.line_anchors {
+        let lines = markdown_block_lines(&content, opts);
+        events = mark_block_lines(events, &lines, 1);
+    }
+    if opts.heading_ids {
+        assign_heading_ids(&mut events);
     }
+    if opts.foldable_headings {
+        events = fold_heading_sections(events);
+    }
+    render_events(events, opts)
+}
 
-    /// Verifies that the syntaxes we expect to be present in syntect's
-    /// `default-fancy` feature are, in fact, present. These are the ones
-    /// confirmed by an audit run against syntect 5.2 on 2026-04-23.
-    #[test]
+/// pulldown-cmark, like PHP Markdown Extra, lets a blank line separate a
+/// definition from its term and needs no space after the `:`, so ordinary
+/// colons at line starts turn paragraphs into definition lists. Only a `: `
+/// line directly under its term, outside lists, blockquotes and footnotes,
+/// should: inside those containers pulldown-cmark 0.12 can also trip its own
+/// `debug_assert!`s. The document is parsed without definition lists to find
+/// the lines at risk, so code, raw HTML and the like are never touched: a
+/// paragraph (or tight list item) that starts with `:` gets its colon
+/// escaped, and a `:` line further down (display math, say) is indented,
+/// which paragraph text drops. Line numbers are unchanged.
+fn guard_definition_lists(content: &str, opts: MarkdownOptions) -> std::borrow::Cow<'_, str> {
+    let colon_line = |l: &str| l.trim_start_matches([' ', '>']).starts_with(':');
+    if !opts.definition_lists || !content.lines().any(colon_line) {
+        return content.into();
+    }
+    let mut options = opts.parser_options();
+    options.remove(Options::ENABLE_DEFINITION_LIST);
+    let mut edits: Vec<(usize, &str)> = Vec::new();
+    let mut containers = 0usize;
+    let mut verbatim = false;
+    // Whether the next inline event starts a line, and whether that line is
+    // its block's first.
+    let (mut line_start, mut first_line) = (false, false);
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match &event {
+            Event::Start(Tag::BlockQuote(_) | Tag::Item | Tag::FootnoteDefinition(_)) => {
+                containers += 1;
+                (line_start, first_line) = (true, true);
+                continue;
+            }
+            Event::End(TagEnd::BlockQuote(_) | TagEnd::Item | TagEnd::FootnoteDefinition) => {
+                containers -= 1;
+                line_start = false;
+                continue;
+            }
+            Event::Start(Tag::Paragraph) => {
+                (line_start, first_line) = (true, true);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => {
+                (verbatim, line_start) = (true, false);
+                continue;
+            }
+            Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) => {
+                verbatim = false;
+                continue;
+            }
+            _ if verbatim => continue,
+            Event::SoftBreak | Event::HardBreak => {
+                (line_start, first_line) = (true, false);
+                continue;
+            }
+            Event::Start(_) | Event::End(_) => {
+                line_start = false;
+                continue;
+            }
+            _ => {}
+        }
+        // A definition line is kept only at the top level, with a space.
+        let at_risk =
+            |colon: usize| containers > 0 || !content[colon + 1..].starts_with([' ', '\t']);
+        if line_start && content[range.start..].starts_with(':') {
+            if first_line {
+                edits.push((range.start, "\\"));
+            } else if at_risk(range.start) {
+                edits.push((range.start, "    "));
+            }
+        }
+        line_start = false;
+        // Lines inside one event: display math, code spans.
+        for (i, _) in content[range.clone()].match_indices('\n') {
+            let next = range.start + i + 1;
+            let line = &content[next..range.end];
+            let colon = next + line.len() - line.trim_start_matches([' ', '>']).len();
+            if content[colon..range.end].starts_with(':') && at_risk(colon) {
+                edits.push((colon, "    "));
+            }
+        }
+    }
+    if edits.is_empty() {
+        return content.into();
+    }
+    let mut out = String::with_capacity(content.len() + 4 * edits.len());
+    let mut done = 0;
+    for (at, text) in edits {
+        out.push_str(&content[done..at]);
+        out.push_str(text);
+        done = at;
+    }
+    out.push_str(&content[done..]);
+    out.into()
+}
+
+/// `parse_markdown_with_links` for very large documents: `content` is
+/// parsed and highlighted in top-level-block-aligned chunks of roughly
+/// `chunk_bytes`, calling `on_progress` with the bytes done after each one.
+/// Heading ids and sanitization still run over the whole document, but
+/// reference-style link definitions and footnotes only resolve within the
+/// chunk that holds them.
+pub fn parse_markdown_chunked(
+    content: &str,
+    theme_name: &str,
+    opts: MarkdownOptions,
+    links: &LinkRewriter,
+    chunk_bytes: usize,
+    mut on_progress: impl FnMut(usize),
+) -> String {
+    let content = guard_definition_lists(content, opts);
+    let content = content.as_ref();
+    let mut events = Vec::new();
+    let mut start = 0;
+    let mut line = 1;
+    for end in markdown_chunk_ends(content, opts, chunk_bytes) {
+        let chunk = &content[start..end];
+        let chunk_events = markdown_events(chunk, theme_name, opts, links, line);
+        if opts.line_anchors {
+            let lines: Vec<usize> = markdown_block_lines(chunk, opts)
+                .into_iter()
+                .map(|l| l + line - 1)
+                .collect();
+            events.extend(mark_block_lines(chunk_events, &lines, line));
+        } else {
+            events.extend(chunk_events);
+        }
+        on_progress(end);
+        line += chunk.matches('\n').count();
+        start = end;
+    }
+    if opts.heading_ids {
+        assign_heading_ids(&mut events);
+    }
+    if opts.foldable_headings {
+        events = fold_heading_sections(events);
+    }
+    render_events(events, opts)
+}
+
+/// End offsets of `parse_markdown_chunked`'s chunks: each cut is at the start
+/// of the line holding the first top-level block at least `chunk_bytes` past
+/// the previous cut, so no block is split. The last end is `content.len()`.
+fn markdown_chunk_ends(content: &str, opts: MarkdownOptions, chunk_bytes: usize) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut last = 0usize;
+    let mut depth = 0usize;
+    for (event, range) in Parser::new_ext(content, opts.parser_options()).into_offset_iter() {
+        if depth == 0 && range.start >= last + chunk_bytes.max(1) {
+            let cut = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            if cut > last {
+                ends.push(cut);
+                last = cut;
+            }
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    ends.push(content.len());
+    ends
+}
+
+/// `parse_markdown_with_links` with every top-level block wrapped in
+/// `<div class="source-block" data-source-line="N">`, N being the 1-based
+/// line of `content` the block starts on plus `line_offset` (for a body
+/// whose front matter was split off). Drives the side-by-side source view.
+/// `foldable_headings` is ignored: a section would span several blocks.
+pub fn parse_markdown_with_source_lines(
+    content: &str,
+    theme_name: &str,
+    opts: MarkdownOptions,
+    links: &LinkRewriter,
+    line_offset: usize,
+) -> String {
+    let content = guard_definition_lists(content, opts);
+    let content = content.as_ref();
+    let mut events = markdown_events(content, theme_name, opts, links, 1);
+    if opts.heading_ids {
+        assign_heading_ids(&mut events);
+    }
+    let lines = markdown_block_lines(content, opts);
+    let mut wrapped = Vec::with_capacity(events.len() + lines.len() * 2);
+    let mut depth = 0usize;
+    let mut block = 0usize;
+    for event in events {
+        let opens = matches!(event, Event::Start(_));
+        let closes = matches!(event, Event::End(_));
+        if depth == 0 {
+            let line = lines.get(block).copied().unwrap_or(1) + line_offset;
+            block += 1;
+            wrapped.push(Event::Html(CowStr::from(format!(
+                "<div class=\"source-block\" data-source-line=\"{line}\">"
+            ))));
+        }
+        if opens {
+            depth += 1;
+        } else if closes {
+            depth = depth.saturating_sub(1);
+        }
+        wrapped.push(event);
+        if depth == 0 {
+            wrapped.push(Event::Html(CowStr::from("</div>\n")));
+        }
+    }
+    render_events(wrapped, opts)
+}
+
+/// Comment `parse_markdown_with_line_anchors` puts before each top-level
+/// block; `inject_line_anchors` turns it into an attribute before sanitizing.
+const LINE_ANCHOR_OPEN: &str = "<!--boltpage-source-line:";
+const LINE_ANCHOR_CLOSE: &str = "-->";
+
+/// `parse_markdown_with_links` with `data-source-line="N"` on the element
+/// each top-level block renders to, N counted as in
+/// `parse_markdown_with_source_lines`, but without wrapper elements, so the
+/// document is laid out and styled exactly as usual. Also returns every N in
+/// document order, for mapping a source line to its nearest block.
+/// `foldable_headings` is ignored; `line_anchors` is this with folding.
+pub fn parse_markdown_with_line_anchors(
+    content: &str,
+    theme_name: &str,
+    opts: MarkdownOptions,
+    links: &LinkRewriter,
+    line_offset: usize,
+) -> (String, Vec<usize>) {
+    let content = guard_definition_lists(content, opts);
+    let content = content.as_ref();
+    let mut events = markdown_events(content, theme_name, opts, links, 1);
+    if opts.heading_ids {
+        assign_heading_ids(&mut events);
+    }
+    let lines: Vec<usize> = markdown_block_lines(content, opts)
+        .into_iter()
+        .map(|line| line + line_offset)
+        .collect();
+    let marked = mark_block_lines(events, &lines, 1 + line_offset);
+    let opts = MarkdownOptions {
+        line_anchors: true,
+        ..opts
+    };
+    (render_events(marked, opts), lines)
+}
+
+/// `events` with a line-anchor comment before each top-level block, the
+/// block's line taken from `lines` in order (`fallback` past its end).
+fn mark_block_lines<'a>(
+    events: Vec<Event<'a>>,
+    lines: &[usize],
+    fallback: usize,
+) -> Vec<Event<'a>> {
+    let mut marked = Vec::with_capacity(events.len() + lines.len());
+    let mut depth = 0usize;
+    let mut block = 0usize;
+    for event in events {
+        if depth == 0 {
+            let line = lines.get(block).copied().unwrap_or(fallback);
+            block += 1;
+            marked.push(Event::Html(CowStr::from(format!(
+                "{LINE_ANCHOR_OPEN}{line}{LINE_ANCHOR_CLOSE}\n"
+            ))));
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        marked.push(event);
+    }
+    marked
+}
+
+/// Replace each line-anchor comment with a `data-source-line` attribute on
+/// the element that follows it. A block that doesn't open with an element
+/// (raw HTML starting with text) just loses its anchor.
+fn inject_line_anchors(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 16);
+    let mut rest = html;
+    while let Some(pos) = rest.find(LINE_ANCHOR_OPEN) {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + LINE_ANCHOR_OPEN.len()..];
+        let Some(end) = after.find(LINE_ANCHOR_CLOSE) else {
+            rest = after;
+            break;
+        };
+        let line = &after[..end];
+        rest = &after[end + LINE_ANCHOR_CLOSE.len()..];
+        // The comment's newline stands in for the one the serializer puts
+        // between blocks; keep it only where that would have gone.
+        if let Some(block) = rest.strip_prefix('\n') {
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            rest = block;
+        }
+        let name_len = rest
+            .strip_prefix('<')
+            .filter(|tag| tag.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .map_or(0, |tag| {
+                tag.find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(tag.len())
+            });
+        if name_len > 0 && line.bytes().all(|b| b.is_ascii_digit()) {
+            out.push_str(&rest[..=name_len]);
+            out.push_str(&format!(" data-source-line=\"{line}\""));
+            rest = &rest[name_len + 1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 1-based start line of each top-level block, in the order `markdown_events`
+/// emits them (a rewritten code block is still one block).
+fn markdown_block_lines(content: &str, opts: MarkdownOptions) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut line = 1usize;
+    let mut scanned = 0usize;
+    for (event, range) in Parser::new_ext(content, opts.parser_options()).into_offset_iter() {
+        if depth == 0 {
+            line += content[scanned..range.start].matches('\n').count();
+            scanned = range.start;
+            lines.push(line);
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    lines
+}
+
+fn render_events(events: Vec<Event<'_>>, opts: MarkdownOptions) -> String {
+    let html = events_to_html(events, opts);
+    if opts.line_anchors {
+        return sanitize_html(inject_line_anchors(&html), opts);
+    }
+    sanitize_html(html, opts)
+}
+
+/// Serialized `events` with callouts rewritten, not yet sanitized.
+fn events_to_html(events: Vec<Event<'_>>, opts: MarkdownOptions) -> String {
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, link_footnotes(events).into_iter());
+
+    if opts.callouts {
+        html_output = rewrite_callouts(&html_output);
+    }
+    html_output
+}
+
+fn sanitize_html(html_output: String, opts: MarkdownOptions) -> String {
+    match opts.sanitize {
+        SanitizeLevel::Strict => sanitizer().clean(&html_output).to_string(),
+        SanitizeLevel::Relaxed => relaxed_sanitizer().clean(&html_output).to_string(),
+        SanitizeLevel::None => html_output,
+    }
+}
+
+/// A link rewrite pattern that failed to compile.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LinkRuleError {
+    pub pattern: String,
+    pub error: String,
+}
+
+/// Ordered regex replacements for Markdown link and image destinations, e.g.
+/// `^/docs/` -> `https://example.com/docs/` to preview published links.
+/// Replacements use `regex` syntax (`$1`, `${name}`). Text is never touched.
+#[derive(Debug, Clone, Default)]
+pub struct LinkRewriter {
+    rules: Vec<(regex::Regex, String)>,
+}
+
+impl LinkRewriter {
+    /// Compile `(pattern, replacement)` pairs. Invalid patterns are skipped
+    /// and returned alongside so callers can report them.
+    pub fn new<I, P, R>(rules: I) -> (Self, Vec<LinkRuleError>)
+    where
+        I: IntoIterator<Item = (P, R)>,
+        P: AsRef<str>,
+        R: Into<String>,
+    {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+        for (pattern, replacement) in rules {
+            match regex::Regex::new(pattern.as_ref()) {
+                Ok(re) => compiled.push((re, replacement.into())),
+                Err(e) => errors.push(LinkRuleError {
+                    pattern: pattern.as_ref().to_string(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        (Self { rules: compiled }, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply every rule in order; each sees the previous rule's output.
+    pub fn rewrite(&self, url: &str) -> String {
+        self.rules.iter().fold(url.to_string(), |acc, (re, rep)| {
+            re.replace_all(&acc, rep.as_str()).into_owned()
+        })
+    }
+}
+
+/// One entry of a document outline; `slug` matches the anchor that
+/// `heading_ids` puts on the rendered heading, and is `None` when the options
+/// leave headings without ids, so there is nothing to link to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub slug: Option<String>,
+}
+
+/// Document outline in source order, built from the same event stream as the
+/// render so ids always agree with `MarkdownOptions::heading_ids` output.
+///
+/// Headings deeper than `max_level` (e.g. `Some(3)` for h1–h3) are left out,
+/// but still claim their slugs so the ids of the kept entries don't shift.
+/// `None` includes every level.
+pub fn build_toc(content: &str, opts: MarkdownOptions, max_level: Option<u8>) -> Vec<TocEntry> {
+    let events = markdown_events(content, "", opts, &LinkRewriter::default(), 1);
+    let mut slugger = Slugger::default();
+    let mut toc = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        if let Event::Start(Tag::Heading { level, id, .. }) = event {
+            let text = heading_text(&events[i + 1..]);
+            let slug = match id {
+                Some(id) => id.to_string(),
+                None => slugger.slug(&text),
+            };
+            let level = *level as u8;
+            if max_level.is_some_and(|max| level > max) {
+                continue;
+            }
+            toc.push(TocEntry {
+                level,
+                text,
+                slug: opts.heading_ids.then_some(slug),
+            });
+        }
+    }
+    toc
+}
+
+/// pulldown-cmark's task checkbox, tagged with its source line.
+fn task_checkbox(checked: bool, line: usize) -> String {
+    let checked = if checked { " checked=\"\"" } else { "" };
+    format!("<input disabled=\"\" type=\"checkbox\"{checked} data-task-line=\"{line}\"/>\n")
+}
+
+/// GitHub-style heading slugs: lowercase, punctuation dropped, spaces to `-`,
+/// repeats suffixed `-1`, `-2`, ….
+#[derive(Default)]
+struct Slugger {
+    seen: std::collections::HashMap<String, usize>,
+}
+
+impl Slugger {
+    fn slug(&mut self, text: &str) -> String {
+        let base: String = text
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                '-' | '_' => Some(c),
+                c if c.is_alphanumeric() => Some(c),
+                _ => None,
+            })
+            .collect();
+        let count = self.seen.entry(base.clone()).or_insert(0);
+        let slug = if *count == 0 {
+            base.clone()
+        } else {
+            format!("{base}-{count}")
+        };
+        *count += 1;
+        slug
+    }
+}
+
+/// Visible text of the heading whose start event precedes `events`.
+fn heading_text(events: &[Event<'_>]) -> String {
+    let mut text = String::new();
+    for event in events {
+        match event {
+            Event::End(TagEnd::Heading(_)) => break,
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn assign_heading_ids(events: &mut [Event<'_>]) {
+    let mut slugger = Slugger::default();
+    for i in 0..events.len() {
+        if !matches!(events[i], Event::Start(Tag::Heading { id: None, .. })) {
+            continue;
+        }
+        let slug = slugger.slug(&heading_text(&events[i + 1..]));
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(CowStr::from(slug));
+        }
+    }
+}
+
+/// Wrap each top-level heading and everything after it, up to the next
+/// heading of the same or a higher level, in `<details open>`, with the
+/// heading itself as the `<summary>`. Deeper headings nest inside their
+/// parent's section; headings in blockquotes, lists and collapse fences
+/// don't fold.
+fn fold_heading_sections(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut folded = Vec::with_capacity(events.len() + 8);
+    let mut open: Vec<HeadingLevel> = Vec::new();
+    let mut depth = 0usize;
+    for event in events {
+        match &event {
+            Event::Start(Tag::Heading { level, .. }) if depth == 0 => {
+                // The heading's line anchor moves onto its section.
+                let anchor = folded
+                    .pop_if(|e| matches!(e, Event::Html(h) if h.starts_with(LINE_ANCHOR_OPEN)));
+                while open.last().is_some_and(|l| *l >= *level) {
+                    open.pop();
+                    folded.push(Event::Html(CowStr::from("</details>\n")));
+                }
+                folded.extend(anchor);
+                open.push(*level);
+                folded.push(Event::Html(CowStr::from("<details open>\n<summary>")));
+                depth += 1;
+                folded.push(event);
+                continue;
+            }
+            Event::End(TagEnd::Heading(_)) if depth == 1 && !open.is_empty() => {
+                depth -= 1;
+                folded.push(event);
+                folded.push(Event::Html(CowStr::from("</summary>\n")));
+                continue;
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        folded.push(event);
+    }
+    for _ in open {
+        folded.push(Event::Html(CowStr::from("</details>\n")));
+    }
+    folded
+}
+
+fn rewrite_dest<'a>(links: &LinkRewriter, url: CowStr<'a>) -> CowStr<'a> {
+    if links.is_empty() {
+        url
+    } else {
+        CowStr::from(links.rewrite(&url))
+    }
+}
+
+/// Parser events after BoltPage's own rewrites (highlighted and mermaid code
+/// blocks, collapse fences, math spans, autolinks and wikilinks), ready for
+/// HTML serialization. `first_line` is the source line `content` starts on,
+/// for `MarkdownOptions::task_lines`.
+fn markdown_events<'a>(
+    content: &'a str,
+    _theme_name: &str,
+    opts: MarkdownOptions,
+    links: &LinkRewriter,
+    first_line: usize,
+) -> Vec<Event<'a>> {
+    // The line of the latest code block start, read when the loop below gets
+    // to it. TextMergeStream only looks ahead past text, so no later block
+    // start can overwrite it first.
+    let code_block_line = std::cell::Cell::new(first_line);
+    let mut line = first_line;
+    let mut scanned = 0usize;
+    let located = Parser::new_ext(content, opts.parser_options())
+        .into_offset_iter()
+        .map(|(event, range)| {
+            if !matches!(
+                event,
+                Event::TaskListMarker(_) | Event::Start(Tag::CodeBlock(_))
+            ) {
+                return event;
+            }
+            line += content[scanned..range.start].matches('\n').count();
+            scanned = range.start;
+            match event {
+                Event::TaskListMarker(checked) if opts.task_lines => {
+                    Event::InlineHtml(CowStr::from(task_checkbox(checked, line)))
+                }
+                event => {
+                    code_block_line.set(line);
+                    event
+                }
+            }
+        });
+    let parser = TextMergeStream::new(located);
+
+    let mut in_code_block = false;
+    let mut link_depth = 0usize;
+    let mut code_block_info = String::new();
+    let mut code_block_content = String::new();
+    let mut code_block_start = first_line;
+
+    let mut events = Vec::new();
+
+    for event in parser {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                code_block_start = code_block_line.get();
+                code_block_info = match kind {
+                    pulldown_cmark::CodeBlockKind::Fenced(info) => info.to_string(),
+                    _ => String::new(),
+                };
+                code_block_content.clear();
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                let (lang, emphasized) = parse_fence_info(&code_block_info);
+                let code_block_lang = lang.to_string();
+                if let Some(title) = collapse_title(&code_block_info) {
+                    // Spliced in as events rather than rendered separately, so
+                    // the body's headings share the document's slug dedup and
+                    // outline. The HtmlBlock pair writes nothing but keeps the
+                    // fence one top-level block for the block walkers.
+                    let body = guard_definition_lists(&code_block_content, opts);
+                    events.push(Event::Start(Tag::HtmlBlock));
+                    events.push(Event::Html(CowStr::from(format!(
+                        "<details><summary>{}</summary>\n",
+                        escape_html(title)
+                    ))));
+                    let inner: Vec<Event<'static>> =
+                        markdown_events(&body, _theme_name, opts, links, code_block_start + 1)
+                            .into_iter()
+                            .map(Event::into_static)
+                            .collect();
+                    events.extend(inner);
+                    events.push(Event::Html(CowStr::from("</details>\n")));
+                    events.push(Event::End(TagEnd::HtmlBlock));
+                } else if code_block_lang == "mermaid" {
+                    events.push(Event::Html(CowStr::from(format!(
+                        r#"<pre class="mermaid">{}</pre>"#,
+                        escape_html(&code_block_content)
+                    ))));
+                } else if code_block_lang == "dot" || code_block_lang == "graphviz" {
+                    // Kept verbatim for a diagram renderer; mermaid.js can't read DOT.
+                    events.push(Event::Html(CowStr::from(format!(
+                        r#"<pre class="graphviz">{}</pre>"#,
+                        escape_html(&code_block_content)
+                    ))));
+                } else if !code_block_lang.is_empty() {
+                    let tokens: Vec<&str> = syntax_alias(&code_block_lang)
+                        .into_iter()
+                        .chain([code_block_lang.as_str()])
+                        .collect();
+                    let block = if opts.code_line_numbers || !emphasized.is_empty() {
+                        let lang_class = escape_html(&code_block_lang);
+                        let lines = highlight_lines(&code_block_content, &tokens, &lang_class)
+                            .unwrap_or_else(|_| plain_lines(&code_block_content));
+                        if opts.code_line_numbers {
+                            numbered_code_table(&lines, &lang_class, &emphasized)
+                        } else {
+                            emphasized_code_block(&lines, &lang_class, &emphasized)
+                        }
+                    } else {
+                        highlight_code(&code_block_content, &tokens, &code_block_lang)
+                            .unwrap_or_else(|_| {
+                                plain_code_block(&code_block_content, &code_block_lang)
+                            })
+                    };
+                    events.push(Event::Html(CowStr::from(block)));
+                } else {
+                    events.push(Event::Start(Tag::CodeBlock(
+                        pulldown_cmark::CodeBlockKind::Fenced(CowStr::from(
+                            code_block_lang.clone(),
+                        )),
+                    )));
+                    events.push(Event::Text(CowStr::from(code_block_content.clone())));
+                    events.push(Event::End(TagEnd::CodeBlock));
+                }
+
+                code_block_info.clear();
+                code_block_content.clear();
+            }
+            Event::Text(text) if in_code_block => {
+                code_block_content.push_str(&text);
+            }
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                link_depth += 1;
+                let dest_url = rewrite_dest(links, dest_url);
+                events.push(Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }));
+            }
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                link_depth += 1;
+                let dest_url = rewrite_dest(links, dest_url);
+                events.push(Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                link_depth = link_depth.saturating_sub(1);
+                events.push(event);
+            }
+            Event::Text(text) if link_depth == 0 && (opts.autolink || opts.enable_wikilinks) => {
+                push_text_with_links(&text, &opts, &mut events);
+            }
+            Event::Text(text) if opts.enable_emoji => {
+                events.push(Event::Text(expand_emoji_shortcodes(text)));
+            }
+            Event::InlineMath(text) => {
+                events.push(Event::Html(CowStr::from(format!(
+                    r#"<span class="math math-inline">{}</span>"#,
+                    escape_html(&text)
+                ))));
+            }
+            Event::DisplayMath(text) => {
+                // A span keeps the paragraph valid; a paragraph holding only
+                // this is unwrapped to a div below.
+                events.push(Event::Html(CowStr::from(format!(
+                    r#"<span class="math math-display">{}</span>"#,
+                    escape_html(&text)
+                ))));
+            }
+            _ => events.push(event),
+        }
+    }
+    unwrap_display_math(events)
+}
+
+/// Footnote references and definitions as HTML with ids that survive
+/// sanitization: `fnref-N` (`fnref-N-K` for the Kth repeat) on each
+/// reference, `fn-N` on its definition, and a `footnote-backref` link from
+/// a referenced definition to its first reference. N is numbered in order of
+/// first appearance, as pulldown-cmark numbers them. Runs on the whole
+/// document just before serializing: the block walkers before it need the
+/// definitions' real Start/End tags, and chunks must share one numbering.
+fn link_footnotes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut numbers: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut number_of = |label: &str| {
+        let next = numbers.len() + 1;
+        *numbers.entry(label.to_string()).or_insert(next)
+    };
+    let mut referenced = std::collections::HashSet::new();
+    for event in &events {
+        match event {
+            Event::FootnoteReference(label) => {
+                referenced.insert(number_of(label));
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                number_of(label);
+            }
+            _ => {}
+        }
+    }
+    let mut refs: std::collections::HashMap<usize, usize> = std::collections::HashMap::new();
+    let mut open: Option<usize> = None;
+    let mut out = Vec::with_capacity(events.len());
+    for event in events {
+        match event {
+            Event::FootnoteReference(label) => {
+                let n = number_of(&label);
+                let k = refs.entry(n).or_insert(0);
+                *k += 1;
+                let id = if *k == 1 {
+                    format!("fnref-{n}")
+                } else {
+                    format!("fnref-{n}-{k}")
+                };
+                out.push(Event::Html(CowStr::from(format!(
+                    r##"<sup class="footnote-reference" id="{id}"><a href="#fn-{n}">{n}</a></sup>"##
+                ))));
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                let n = number_of(&label);
+                open = Some(n);
+                out.push(Event::Html(CowStr::from(format!(
+                    "<div class=\"footnote-definition\" id=\"fn-{n}\"><sup class=\"footnote-definition-label\">{n}</sup>\n"
+                ))));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                if let Some(n) = open.take().filter(|n| referenced.contains(n)) {
+                    let backref = Event::Html(CowStr::from(format!(
+                        r##" <a href="#fnref-{n}" class="footnote-backref">↩</a>"##
+                    )));
+                    // Inside the last paragraph, so it sits at the end of the text.
+                    if matches!(out.last(), Some(Event::End(TagEnd::Paragraph))) {
+                        let end = out.pop();
+                        out.push(backref);
+                        out.extend(end);
+                    } else {
+                        out.push(backref);
+                    }
+                }
+                out.push(Event::Html(CowStr::from("</div>\n")));
+            }
+            _ => out.push(event),
+        }
+    }
+    out
+}
+
+/// Replace paragraphs that contain nothing but one display-math span with a
+/// bare `<div class="math math-display">`, so `$$` blocks render as blocks
+/// instead of an empty `<p>` pair around a div the sanitizer would split.
+fn unwrap_display_math(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    const OPEN: &str = r#"<span class="math math-display">"#;
+    let mut out = Vec::with_capacity(events.len());
+    let mut iter = events.into_iter().peekable();
+    while let Some(event) = iter.next() {
+        if !matches!(event, Event::Start(Tag::Paragraph)) {
+            out.push(event);
+            continue;
+        }
+        let mut inner = Vec::new();
+        for next in iter.by_ref() {
+            if matches!(next, Event::End(TagEnd::Paragraph)) {
+                break;
+            }
+            inner.push(next);
+        }
+        let content: Vec<&Event> = inner
+            .iter()
+            .filter(|e| !matches!(e, Event::SoftBreak | Event::HardBreak))
+            .collect();
+        match content.as_slice() {
+            [Event::Html(html)] if html.starts_with(OPEN) => {
+                let body = &html[OPEN.len()..html.len() - "</span>".len()];
+                out.push(Event::Html(CowStr::from(format!(
+                    r#"<div class="math math-display">{body}</div>"#
+                ))));
+            }
+            _ => {
+                out.push(Event::Start(Tag::Paragraph));
+                out.extend(inner);
+                out.push(Event::End(TagEnd::Paragraph));
+            }
+        }
+    }
+    out
+}
+
+/// Split a leading YAML front-matter block (opened by `---`, closed by `---`
+/// or `...`) from the Markdown body. Without a closed block the whole input is
+/// the body.
+pub fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let marker = line.trim_end_matches(['\r', '\n']);
+        if marker == "---" || marker == "..." {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// Per-document display settings from a `boltpage:` front-matter mapping,
+/// merged over the global preferences for that document only.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct DocumentOverrides {
+    pub theme: Option<String>,
+    pub line_numbers: Option<bool>,
+    pub word_wrap: Option<bool>,
+    pub preset: Option<MarkdownPreset>,
+}
+
+impl DocumentOverrides {
+    /// Unknown keys, mistyped values and unparseable YAML are ignored rather
+    /// than failing the render.
+    pub fn from_front_matter(yaml: &str) -> Self {
+        let Ok(root) = serde_yaml_crate::from_str::<serde_yaml_crate::Value>(yaml) else {
+            return Self::default();
+        };
+        let Some(section) = root.get("boltpage") else {
+            return Self::default();
+        };
+        Self {
+            theme: section
+                .get("theme")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            line_numbers: section.get("line_numbers").and_then(|v| v.as_bool()),
+            word_wrap: section.get("word_wrap").and_then(|v| v.as_bool()),
+            preset: section
+                .get("preset")
+                .and_then(|v| v.as_str())
+                .and_then(MarkdownPreset::from_name),
+        }
+    }
+
+    /// Overrides declared by `content`'s front matter, if any.
+    pub fn from_markdown(content: &str) -> Self {
+        match split_front_matter(content) {
+            (Some(yaml), _) => Self::from_front_matter(yaml),
+            (None, _) => Self::default(),
+        }
+    }
+}
+
+/// The `title:` in `content`'s front matter, trimmed. `None` without front
+/// matter, when it isn't valid YAML, or when the title is missing or blank.
+pub fn front_matter_title(content: &str) -> Option<String> {
+    let yaml = split_front_matter(content).0?;
+    let root = serde_yaml_crate::from_str::<serde_yaml_crate::Value>(yaml).ok()?;
+    let title = match root.get("title")? {
+        serde_yaml_crate::Value::String(s) => s.trim().to_string(),
+        serde_yaml_crate::Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    (!title.is_empty()).then_some(title)
+}
+
+/// Render `content` with its leading front matter stripped, returning the
+/// parsed YAML alongside the HTML. Malformed YAML yields `None` and the whole
+/// document, fences included, renders unchanged.
+pub fn parse_markdown_with_frontmatter(
+    content: &str,
+    theme_name: &str,
+) -> (Option<serde_yaml_crate::Value>, String) {
+    if let (Some(yaml), body) = split_front_matter(content) {
+        if let Ok(meta) = serde_yaml_crate::from_str::<serde_yaml_crate::Value>(yaml) {
+            return (Some(meta), parse_markdown_with_theme(body, theme_name));
+        }
+    }
+    (None, parse_markdown_with_theme(content, theme_name))
+}
+
+struct ListLevel {
+    next_number: Option<u64>,
+    indent: usize,
+}
+
+/// Accumulates plain text for `markdown_to_plaintext`, applying blockquote
+/// (`> `) and list-continuation prefixes at the start of every line.
+#[derive(Default)]
+struct PlainTextWriter {
+    out: String,
+    at_line_start: bool,
+    quote_depth: usize,
+    lists: Vec<ListLevel>,
+    link_urls: Vec<String>,
+    link_text_start: Vec<usize>,
+    table_alignments: Vec<pulldown_cmark::Alignment>,
+    table_rows: Vec<Vec<String>>,
+    table_cell: Option<String>,
+}
+
+impl PlainTextWriter {
+    fn prefix(&self, list_levels: usize) -> String {
+        let mut prefix = "> ".repeat(self.quote_depth);
+        for level in self.lists.iter().take(list_levels) {
+            prefix.push_str(&" ".repeat(level.indent));
+        }
+        prefix
+    }
+
+    fn push(&mut self, text: &str) {
+        if let Some(cell) = self.table_cell.as_mut() {
+            cell.push_str(text);
+            return;
+        }
+        for ch in text.chars() {
+            if self.at_line_start && ch != '\n' {
+                let prefix = self.prefix(self.lists.len());
+                self.out.push_str(&prefix);
+                self.at_line_start = false;
+            }
+            self.out.push(ch);
+            if ch == '\n' {
+                self.at_line_start = true;
+            }
+        }
+    }
+
+    fn ensure_newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.at_line_start = true;
+    }
+
+    fn ensure_blank_line(&mut self) {
+        self.ensure_newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn start_item(&mut self) {
+        self.ensure_newline();
+        let outer = self.prefix(self.lists.len().saturating_sub(1));
+        let marker = match self.lists.last_mut() {
+            Some(ListLevel {
+                next_number: Some(n),
+                ..
+            }) => {
+                let marker = format!("{n}. ");
+                *n += 1;
+                marker
+            }
+            _ => "- ".to_string(),
+        };
+        if let Some(level) = self.lists.last_mut() {
+            level.indent = marker.chars().count();
+        }
+        self.out.push_str(&outer);
+        self.out.push_str(&marker);
+        self.at_line_start = false;
+    }
+
+    fn finish_table(&mut self) {
+        let rows = std::mem::take(&mut self.table_rows);
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0usize; columns];
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+        for (row_index, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = (0..columns)
+                .map(|i| {
+                    let cell = row.get(i).map(String::as_str).unwrap_or("");
+                    let pad = widths[i] - cell.chars().count();
+                    match self.table_alignments.get(i) {
+                        Some(pulldown_cmark::Alignment::Right) => {
+                            format!("{}{cell}", " ".repeat(pad))
+                        }
+                        Some(pulldown_cmark::Alignment::Center) => {
+                            format!("{}{cell}{}", " ".repeat(pad / 2), " ".repeat(pad - pad / 2))
+                        }
+                        _ => format!("{cell}{}", " ".repeat(pad)),
+                    }
+                })
+                .collect();
+            self.push(cells.join(" | ").trim_end());
+            self.push("\n");
+            if row_index == 0 {
+                let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+                self.push(&rule.join("-+-"));
+                self.push("\n");
+            }
+        }
+        self.table_alignments.clear();
+        self.ensure_blank_line();
+    }
+}
+
+/// Converts Markdown to readable, structure-preserving plain text (for
+/// "Copy as Plain Text" and accessibility tools).
+///
+/// Unlike stripping tags from the rendered HTML, block structure survives:
+/// headings and paragraphs are separated by blank lines, list items keep
+/// `-` / `1.` markers with nested indentation, code blocks stay fenced, links
+/// become `text (url)`, and tables are laid out as padded columns.
+pub fn markdown_to_plaintext(content: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_MATH);
+
+    let mut w = PlainTextWriter {
+        at_line_start: true,
+        ..Default::default()
+    };
+
+    for event in Parser::new_ext(content, options) {
+        match event {
+            // Inside list items the marker already opened the line.
+            Event::Start(Tag::Paragraph) | Event::Start(Tag::Heading { .. })
+                if w.lists.is_empty() =>
+            {
+                w.ensure_blank_line();
+            }
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Heading(_)) => {
+                w.ensure_blank_line();
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                w.ensure_blank_line();
+                w.quote_depth += 1;
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                w.quote_depth = w.quote_depth.saturating_sub(1);
+                w.ensure_blank_line();
+            }
+            Event::Start(Tag::List(start)) => {
+                if w.lists.is_empty() {
+                    w.ensure_blank_line();
+                } else {
+                    w.ensure_newline();
+                }
+                w.lists.push(ListLevel {
+                    next_number: start,
+                    indent: 0,
+                });
+            }
+            Event::End(TagEnd::List(_)) => {
+                w.lists.pop();
+                if w.lists.is_empty() {
+                    w.ensure_blank_line();
+                } else {
+                    w.ensure_newline();
+                }
+            }
+            Event::Start(Tag::Item) => w.start_item(),
+            Event::End(TagEnd::Item) => w.ensure_newline(),
+            Event::TaskListMarker(checked) => w.push(if checked { "[x] " } else { "[ ] " }),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                w.ensure_blank_line();
+                let lang = match kind {
+                    pulldown_cmark::CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    _ => String::new(),
+                };
+                w.push(&format!("```{lang}\n"));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                w.ensure_newline();
+                w.push("```\n");
+                w.ensure_blank_line();
+            }
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => {
+                w.link_urls.push(dest_url.to_string());
+                let start = w.table_cell.as_ref().map_or(w.out.len(), String::len);
+                w.link_text_start.push(start);
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                let url = w.link_urls.pop().unwrap_or_default();
+                let start = w.link_text_start.pop().unwrap_or(0);
+                let text = w
+                    .table_cell
+                    .as_ref()
+                    .map_or(&w.out[start..], |cell| &cell[start..])
+                    .to_string();
+                // Autolinks and links whose text is the URL itself stay bare.
+                if !url.is_empty() && text != url {
+                    w.push(&format!(" ({url})"));
+                }
+            }
+            Event::Start(Tag::Table(alignments)) => {
+                w.ensure_blank_line();
+                w.table_alignments = alignments;
+            }
+            Event::End(TagEnd::Table) => w.finish_table(),
+            Event::Start(Tag::TableHead) | Event::Start(Tag::TableRow) => {
+                w.table_rows.push(Vec::new());
+            }
+            Event::Start(Tag::TableCell) => w.table_cell = Some(String::new()),
+            Event::End(TagEnd::TableCell) => {
+                let cell = w.table_cell.take().unwrap_or_default();
+                if let Some(row) = w.table_rows.last_mut() {
+                    row.push(cell.trim().to_string());
+                }
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                w.ensure_blank_line();
+                w.push(&format!("[{label}]: "));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => w.ensure_blank_line(),
+            Event::FootnoteReference(label) => w.push(&format!("[{label}]")),
+            Event::Text(text)
+            | Event::Code(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text) => w.push(&text),
+            Event::SoftBreak => w.push(" "),
+            Event::HardBreak => w.push("\n"),
+            Event::Rule => {
+                w.ensure_blank_line();
+                w.push("----------\n");
+                w.ensure_blank_line();
+            }
+            _ => {}
+        }
+    }
+
+    w.out.trim_end().to_string()
+}
+
+/// Flattens Markdown to its visible text on one line, for previews and search
+/// snippets.
+///
+/// Only text and inline code are kept (code block contents included); image
+/// alt text, link URLs and raw HTML are dropped. Block boundaries become a
+/// single space and whitespace runs are collapsed.
+pub fn markdown_to_text(content: &str) -> String {
+    flatten_markdown(content, true).0
+}
+
+/// `markdown_to_text`, optionally leaving code blocks out, plus the number of
+/// code blocks seen.
+fn flatten_markdown(content: &str, include_code_blocks: bool) -> (String, usize) {
+    let options = MarkdownOptions::default().parser_options();
+    let mut text = String::new();
+    let mut image_depth = 0usize;
+    let mut in_code_block = false;
+    let mut code_blocks = 0;
+
+    for event in Parser::new_ext(content, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                code_blocks += 1;
+                text.push(' ');
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                text.push(' ');
+            }
+            Event::Text(_) if in_code_block && !include_code_blocks => {}
+            Event::Start(Tag::Image { .. }) => image_depth += 1,
+            Event::End(TagEnd::Image) => image_depth = image_depth.saturating_sub(1),
+            Event::Text(t) | Event::Code(t) if image_depth == 0 => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push(' '),
+            Event::Start(tag) if !is_inline_tag(&tag.to_end()) => text.push(' '),
+            Event::End(tag) if !is_inline_tag(&tag) => text.push(' '),
+            _ => {}
+        }
+    }
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (text, code_blocks)
+}
+
+/// Reading speed behind `DocumentStats::reading_minutes`.
+const READING_WORDS_PER_MINUTE: usize = 220;
+
+/// Size of a Markdown document as read, not as written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct DocumentStats {
+    pub words: usize,
+    pub characters: usize,
+    /// At `READING_WORDS_PER_MINUTE`, rounded up.
+    pub reading_minutes: u32,
+    /// Fenced and indented code blocks, which the other counts leave out.
+    pub code_blocks: usize,
+}
+
+/// Word, character and reading-time counts of the visible text (so
+/// `**bold**` is one four-character word), excluding code blocks.
+pub fn document_stats(content: &str) -> DocumentStats {
+    let (_, body) = split_front_matter(content);
+    let (text, code_blocks) = flatten_markdown(body, false);
+    let words = text.split_whitespace().count();
+    DocumentStats {
+        words,
+        characters: text.chars().count(),
+        reading_minutes: words.div_ceil(READING_WORDS_PER_MINUTE) as u32,
+        code_blocks,
+    }
+}
+
+fn is_inline_tag(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+    )
+}
+
+/// Prose of a Markdown document, for language detection: text only, with
+/// code blocks, inline code, math and raw HTML left out.
+pub fn markdown_prose(content: &str) -> String {
+    let (_, body) = split_front_matter(content);
+    let mut out = String::new();
+    let mut in_code_block = false;
+    for event in Parser::new_ext(body, Options::ENABLE_TABLES) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                out.push_str(&text);
+                out.push(' ');
+            }
+            Event::SoftBreak | Event::HardBreak => out.push(' '),
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Heading(_)) => out.push('\n'),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// BCP-47 code of a whatlang language: its ISO 639-1 code, which every
+/// language whatlang detects has.
+fn bcp47(lang: whatlang::Lang) -> &'static str {
+    use whatlang::Lang;
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+/// Best-guess natural language of `text` as a BCP-47 code, by whatlang.
+/// Returns `None` unless whatlang rates its guess reliable (too little text
+/// usually isn't), rather than a low-confidence guess.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    whatlang::detect(text)
+        .filter(whatlang::Info::is_reliable)
+        .map(|info| bcp47(info.lang()))
+}
+
+pub fn get_syntax_theme_css(theme_name: &str) -> Option<String> {
+    let theme_set = get_theme_set();
+    let theme = match theme_name {
+        // Exact names first so custom themes can be selected directly.
+        name if theme_set.themes.contains_key(name) => theme_set.themes.get(name),
+        "dark" | "drac" => theme_set
+            .themes
+            .get("Monokai")
+            .or_else(|| theme_set.themes.get("base16-ocean.dark"))
+            .or_else(|| theme_set.themes.get("Solarized (dark)")),
+        _ => theme_set
+            .themes
+            .get("InspiredGitHub")
+            .or_else(|| theme_set.themes.get("base16-ocean.light"))
+            .or_else(|| theme_set.themes.get("Solarized (light)")),
+    }
+    .or_else(|| theme_set.themes.values().next())?;
+
+    let css = css_for_theme_with_class_style(theme, ClassStyle::Spaced).ok()?;
+    Some(css)
+}
+
+/// Prefixes every selector of every rule in `css` with `scope`, so generic
+/// syntect class names (`.string`, `.keyword`, …) only match inside that
+/// container. Comments and declaration blocks are copied verbatim.
+///
+/// Returns None when `scope` is empty or contains characters that could
+/// break out of a selector list (`{`, `}`, `;`, `/*`).
+pub fn scope_css(css: &str, scope: &str) -> Option<String> {
+    let scope = scope.trim();
+    if scope.is_empty() || scope.contains(['{', '}', ';']) || scope.contains("/*") {
+        return None;
+    }
+
+    let mut out = String::with_capacity(css.len() + css.len() / 4);
+    let mut rest = css;
+    while !rest.is_empty() {
+        let trimmed = rest.trim_start();
+        out.push_str(&rest[..rest.len() - trimmed.len()]);
+        rest = trimmed;
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix("/*") {
+            let close = after.find("*/").map(|i| i + 4).unwrap_or(rest.len());
+            out.push_str(&rest[..close]);
+            rest = &rest[close..];
+            continue;
+        }
+        let Some(open) = rest.find('{') else {
+            out.push_str(rest);
+            break;
+        };
+        let close = rest[open..]
+            .find('}')
+            .map(|i| open + i + 1)
+            .unwrap_or(rest.len());
+        let selectors: Vec<String> = rest[..open]
+            .split(',')
+            .map(str::trim)
+            .filter(|sel| !sel.is_empty())
+            .map(|sel| format!("{scope} {sel}"))
+            .collect();
+        out.push_str(&selectors.join(", "));
+        out.push(' ');
+        out.push_str(&rest[open..close]);
+        rest = &rest[close..];
+    }
+    Some(out)
+}
+
+/// `get_syntax_theme_css` with every rule nested under `scope` (see
+/// [`scope_css`]). None when the theme CSS can't be generated or the scope
+/// is rejected.
+pub fn get_syntax_theme_css_scoped(theme_name: &str, scope: &str) -> Option<String> {
+    scope_css(&get_syntax_theme_css(theme_name)?, scope)
+}
+
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+pub fn parse_json_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
+    highlight_code(&pretty_json(content)?, &["JSON", "json"], "json")
+}
+
+/// Parses strict JSON first; failing that, retries with comments and trailing
+/// commas removed (the JSONC found in editor configs) and reports the strict
+/// error if that fails too. A leading BOM is ignored.
+fn pretty_json(content: &str) -> Result<String, String> {
+    let content = strip_bom(content);
+    let json_value: serde_json_crate::Value = match serde_json_crate::from_str(content) {
+        Ok(value) => value,
+        Err(e) => serde_json_crate::from_str(&strip_json_comments(content))
+            .map_err(|_| format!("Invalid JSON: {e}"))?,
+    };
+    serde_json_crate::to_string_pretty(&json_value)
+        .map_err(|e| format!("Failed to pretty-print JSON: {e}"))
+}
+
+fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// Blank out `//` and `/* */` comments outside strings (newlines kept, so
+/// line numbers in errors still match) and drop commas that directly precede
+/// `}` or `]`.
+fn strip_json_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(ch) = chars.next() {
+        if in_string {
+            out.push(ch);
+            match ch {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (ch, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(ch);
+            }
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(ch),
+        }
+    }
+
+    // Trailing commas, now that no comment can sit between one and its bracket.
+    let mut cleaned = String::with_capacity(out.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, ch) in out.char_indices() {
+        if in_string {
+            in_string = escaped || ch != '"';
+            escaped = !escaped && ch == '\\';
+        } else if ch == '"' {
+            in_string = true;
+        } else if ch == ',' && out[i + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        cleaned.push(ch);
+    }
+    cleaned
+}
+
+/// Result of `pretty_print_json_stream`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonStreamOutput {
+    pub text: String,
+    /// Values emitted (containers and scalars; object keys are not counted).
+    pub nodes: usize,
+    pub bytes_read: u64,
+    /// True when `max_nodes` cut the document short.
+    pub truncated: bool,
+}
+
+/// What the JSON grammar allows next in `pretty_print_json_stream`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonExpect {
+    Value,
+    ValueOrClose,
+    Key,
+    KeyOrClose,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+/// True if `s` is `true`, `false`, `null` or a number as RFC 8259 spells it.
+fn is_json_scalar(s: &[u8]) -> bool {
+    if matches!(s, b"true" | b"false" | b"null") {
+        return true;
+    }
+    fn digits(s: &[u8]) -> usize {
+        s.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+    let mut i = usize::from(s.first() == Some(&b'-'));
+    match digits(&s[i..]) {
+        0 => return false,
+        n if n > 1 && s[i] == b'0' => return false,
+        n => i += n,
+    }
+    if s.get(i) == Some(&b'.') {
+        i += 1;
+        match digits(&s[i..]) {
+            0 => return false,
+            n => i += n,
+        }
+    }
+    if matches!(s.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(s.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        match digits(&s[i..]) {
+            0 => return false,
+            n => i += n,
+        }
+    }
+    i == s.len()
+}
+
+/// Pretty-print JSON from a reader without building a value tree, stopping
+/// once `max_nodes` values have been emitted. Layout matches
+/// `serde_json::to_string_pretty`, but key order and number spellings stay as
+/// written in the source. Token order is checked as it streams (comma and
+/// colon placement, literal and number spelling, a single top-level value);
+/// string contents are copied through unchecked. `progress` receives the
+/// number of bytes consumed roughly every MiB.
+pub fn pretty_print_json_stream<R: std::io::Read>(
+    mut reader: R,
+    max_nodes: usize,
+    mut progress: impl FnMut(u64),
+) -> Result<JsonStreamOutput, String> {
+    const PROGRESS_EVERY: u64 = 1 << 20;
+
+    fn newline(out: &mut Vec<u8>, depth: usize) {
+        out.push(b'\n');
+        out.extend(std::iter::repeat_n(b' ', depth * 2));
+    }
+
+    fn after_value(stack: &[u8]) -> JsonExpect {
+        if stack.is_empty() {
+            JsonExpect::End
+        } else {
+            JsonExpect::CommaOrClose
+        }
+    }
+
+    fn finish_scalar(scalar: &mut Vec<u8>, bytes_read: u64) -> Result<(), String> {
+        if !is_json_scalar(scalar) {
+            return Err(format!(
+                "Invalid JSON: unexpected '{}' before byte {bytes_read}",
+                String::from_utf8_lossy(scalar)
+            ));
+        }
+        scalar.clear();
+        Ok(())
+    }
+
+    let mut out = Vec::new();
+    let mut stack: Vec<u8> = Vec::new();
+    let mut expect = JsonExpect::Value;
+    let mut pending_open = false;
+    let mut in_string = false;
+    let mut string_is_key = false;
+    let mut scalar: Vec<u8> = Vec::new();
+    let mut escaped = false;
+    let mut nodes = 0usize;
+    let mut bytes_read = 0u64;
+    let mut next_progress = PROGRESS_EVERY;
+    let mut truncated = false;
+    let mut buf = vec![0u8; 64 * 1024];
+
+    'read: loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Failed to read JSON: {e}")),
+        };
+        let start = if bytes_read == 0 && buf[..n].starts_with(b"\xEF\xBB\xBF") {
+            bytes_read = 3;
+            3
+        } else {
+            0
+        };
+        for &b in &buf[start..n] {
+            bytes_read += 1;
+            if in_string {
+                out.push(b);
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                    expect = if string_is_key {
+                        JsonExpect::Colon
+                    } else {
+                        after_value(&stack)
+                    };
+                }
+                continue;
+            }
+            if !scalar.is_empty() {
+                if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.') {
+                    out.push(b);
+                    scalar.push(b);
+                    continue;
+                }
+                finish_scalar(&mut scalar, bytes_read)?;
+                expect = after_value(&stack);
+            }
+            if b.is_ascii_whitespace() {
+                continue;
+            }
+            let wants_value = matches!(expect, JsonExpect::Value | JsonExpect::ValueOrClose);
+            let allowed = match b {
+                b'}' => {
+                    stack.last() == Some(&b'{')
+                        && matches!(expect, JsonExpect::KeyOrClose | JsonExpect::CommaOrClose)
+                }
+                b']' => {
+                    stack.last() == Some(&b'[')
+                        && matches!(expect, JsonExpect::ValueOrClose | JsonExpect::CommaOrClose)
+                }
+                b',' => expect == JsonExpect::CommaOrClose,
+                b':' => expect == JsonExpect::Colon,
+                b'"' => wants_value || matches!(expect, JsonExpect::Key | JsonExpect::KeyOrClose),
+                _ => wants_value,
+            };
+            if !allowed {
+                return Err(format!(
+                    "Invalid JSON: unexpected '{}' at byte {bytes_read}",
+                    b as char
+                ));
+            }
+            let starts_value = wants_value && !matches!(b, b']');
+            if starts_value && nodes >= max_nodes {
+                truncated = true;
+                break 'read;
+            }
+            if pending_open && !matches!(b, b'}' | b']') {
+                newline(&mut out, stack.len());
+                pending_open = false;
+            }
+            match b {
+                b'{' | b'[' => {
+                    nodes += 1;
+                    out.push(b);
+                    stack.push(b);
+                    pending_open = true;
+                    expect = if b == b'{' {
+                        JsonExpect::KeyOrClose
+                    } else {
+                        JsonExpect::ValueOrClose
+                    };
+                }
+                b'}' | b']' => {
+                    stack.pop();
+                    if !pending_open {
+                        newline(&mut out, stack.len());
+                    }
+                    pending_open = false;
+                    out.push(b);
+                    expect = after_value(&stack);
+                }
+                b',' => {
+                    out.push(b);
+                    newline(&mut out, stack.len());
+                    expect = if stack.last() == Some(&b'{') {
+                        JsonExpect::Key
+                    } else {
+                        JsonExpect::Value
+                    };
+                }
+                b':' => {
+                    out.extend_from_slice(b": ");
+                    expect = JsonExpect::Value;
+                }
+                b'"' => {
+                    string_is_key = !starts_value;
+                    if starts_value {
+                        nodes += 1;
+                    }
+                    out.push(b);
+                    in_string = true;
+                }
+                _ => {
+                    nodes += 1;
+                    out.push(b);
+                    scalar.push(b);
+                }
+            }
+        }
+        if bytes_read >= next_progress {
+            progress(bytes_read);
+            next_progress = bytes_read + PROGRESS_EVERY;
+        }
+    }
+
+    if !truncated {
+        if in_string {
+            return Err("Invalid JSON: unterminated string".to_string());
+        }
+        if !scalar.is_empty() {
+            finish_scalar(&mut scalar, bytes_read)?;
+            expect = after_value(&stack);
+        }
+        if expect != JsonExpect::End {
+            return Err("Invalid JSON: unexpected end of input".to_string());
+        }
+    }
+    progress(bytes_read);
+    Ok(JsonStreamOutput {
+        text: String::from_utf8_lossy(&out).into_owned(),
+        nodes,
+        bytes_read,
+        truncated,
+    })
+}
+
+/// Highlight pre-formatted JSON (e.g. `pretty_print_json_stream` output)
+/// without re-parsing it.
+pub fn highlight_json_text(text: &str) -> Result<String, String> {
+    highlight_code(text, &["JSON", "json"], "json")
+}
+
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+///
+/// Note: serde_yaml 0.9 uses `IndexMap` for `Mapping`, so key insertion order
+/// from the source document is preserved through the parse/serialize round-trip.
+pub fn parse_yaml_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
+    highlight_code(&pretty_yaml(content)?, &["YAML", "yaml", "yml"], "yaml")
+}
+
+fn pretty_yaml(content: &str) -> Result<String, String> {
+    let yaml_value: serde_yaml_crate::Value =
+        serde_yaml_crate::from_str(strip_bom(content)).map_err(|e| format!("Invalid YAML: {e}"))?;
+    serde_yaml_crate::to_string(&yaml_value)
+        .map_err(|e| format!("Failed to pretty-print YAML: {e}"))
+}
+
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+///
+/// `toml` is built with `preserve_order`, so tables and keys keep their
+/// source order; comments do not survive the round-trip.
+pub fn parse_toml_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
+    highlight_code(&pretty_toml(content)?, &["TOML", "toml"], "toml")
+}
+
+fn pretty_toml(content: &str) -> Result<String, String> {
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| format!("Invalid TOML: {}", e.message()))?;
+    toml::to_string_pretty(&table).map_err(|e| format!("Failed to pretty-print TOML: {e}"))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum XmlToken {
+    Open,
+    Close,
+    Text,
+    /// Self-closing tags, comments, CDATA, declarations, PIs, doctype.
+    Leaf,
+}
+
+/// Re-indent XML two spaces per level, one node per line. Each node's source
+/// text is kept verbatim (attributes, comments and CDATA included); only
+/// whitespace between nodes changes. An element holding just a single line
+/// of text stays on one line.
+fn reindent_xml(content: &str) -> Result<String, String> {
+    use quick_xml::events::Event as XmlEvent;
+
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut reader = quick_xml::Reader::from_str(content);
+    let mut tokens: Vec<(XmlToken, &str)> = Vec::new();
+    let mut open: Vec<String> = Vec::new();
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at byte {}: {e}", reader.error_position()))?;
+        let raw = &content[start..reader.buffer_position() as usize];
+        match event {
+            XmlEvent::Eof => break,
+            XmlEvent::Start(tag) => {
+                open.push(String::from_utf8_lossy(tag.name().as_ref()).into_owned());
+                tokens.push((XmlToken::Open, raw));
+            }
+            XmlEvent::End(_) => {
+                open.pop();
+                tokens.push((XmlToken::Close, raw));
+            }
+            XmlEvent::Text(_) if raw.trim().is_empty() => {}
+            XmlEvent::Text(_) => tokens.push((XmlToken::Text, raw.trim())),
+            _ => tokens.push((XmlToken::Leaf, raw.trim())),
+        }
+    }
+    if let Some(name) = open.last() {
+        return Err(format!("Invalid XML: <{name}> is never closed"));
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut push_line = |depth: usize, text: &str| {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(text);
+        out.push('\n');
+    };
+    let mut depth = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let (kind, raw) = tokens[i];
+        i += 1;
+        match kind {
+            XmlToken::Open => match (tokens.get(i), tokens.get(i + 1)) {
+                (Some(&(XmlToken::Text, text)), Some(&(XmlToken::Close, close)))
+                    if !text.contains('\n') =>
+                {
+                    push_line(depth, &format!("{raw}{text}{close}"));
+                    i += 2;
+                }
+                (Some(&(XmlToken::Close, close)), _) => {
+                    push_line(depth, &format!("{raw}{close}"));
+                    i += 1;
+                }
+                _ => {
+                    push_line(depth, raw);
+                    depth += 1;
+                }
+            },
+            XmlToken::Close => {
+                depth = depth.saturating_sub(1);
+                push_line(depth, raw);
+            }
+            XmlToken::Text | XmlToken::Leaf => push_line(depth, raw),
+        }
+    }
+    Ok(out)
+}
+
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+pub fn parse_xml_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
+    highlight_code(&reindent_xml(content)?, &["XML", "xml"], "xml")
+}
+
+/// The pretty-printed JSON, YAML, TOML or XML document highlighted with a
+/// line-number gutter (see `MarkdownOptions::code_line_numbers`).
+pub fn parse_structured_with_line_numbers(
+    content: &str,
+    extension: &str,
+) -> Result<String, String> {
+    let pretty = match extension {
+        "json" => pretty_json(content)?,
+        "yaml" | "yml" => pretty_yaml(content)?,
+        "toml" => pretty_toml(content)?,
+        "xml" => reindent_xml(content)?,
+        other => return Err(format!("No structured view for .{other}")),
+    };
+    highlight_source_with_line_numbers(&pretty, extension)
+}
+
+/// Split CSV (RFC 4180: `"`-quoted fields may hold commas, newlines and
+/// `""` escapes) into records. A leading BOM and CRLF line ends are accepted;
+/// an unterminated quote is an error.
+fn parse_csv_records(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let content = strip_bom(content);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut quote_line = 0;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\n' {
+            line += 1;
+        }
+        if in_quotes {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(ch),
+            }
+            continue;
+        }
+        match ch {
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                quote_line = line;
+            }
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(ch),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "Invalid CSV: quoted field starting on line {quote_line} is never closed"
+        ));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Render CSV as a table, the first record as the header. Short rows are
+/// padded with empty cells to the widest row.
+///
+/// Theme is applied via CSS class on the frontend; param reserved for future per-render theming.
+pub fn parse_csv_with_theme(content: &str, _theme_name: &str) -> Result<String, String> {
+    let records = parse_csv_records(content)?;
+    let width = records.iter().map(Vec::len).max().unwrap_or(0);
+    let row = |cells: &[String], tag: &str| {
+        let mut out = String::from("<tr>");
+        for i in 0..width {
+            let cell = cells.get(i).map(String::as_str).unwrap_or("");
+            out.push_str(&format!("<{tag}>{}</{tag}>", escape_html(cell)));
+        }
+        out.push_str("</tr>\n");
+        out
+    };
+    let mut html = String::from("<table class=\"markdown-body csv-table\">\n<thead>\n");
+    let mut rows = records.iter();
+    if let Some(header) = rows.next() {
+        html.push_str(&row(header, "th"));
+    }
+    html.push_str("</thead>\n<tbody>\n");
+    for record in rows {
+        html.push_str(&row(record, "td"));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    Ok(html)
+}
+
+/// Turn bare `http(s)://` URLs in already-escaped text into links. URLs are
+/// matched in their escaped form: `&amp;` stays part of one, while any other
+/// entity (an escaped quote or angle bracket) ends it, so nothing in the text
+/// can reach past the `href` value. Trailing punctuation and unbalanced
+/// closing parens are left outside the link, as with Markdown autolinks.
+pub fn linkify_escaped_text(escaped: &str) -> String {
+    let re = ESCAPED_URL_RE.get_or_init(|| {
+        regex::Regex::new(r#"\bhttps?://(?:[^\s<>&"']|&amp;)+"#)
+            .expect("escaped URL regex must compile")
+    });
+    re.replace_all(escaped, |caps: &regex::Captures| {
+        let whole = &caps[0];
+        let url = trim_escaped_url(whole);
+        if url.ends_with("://") {
+            return whole.to_string();
+        }
+        format!(r#"<a href="{url}">{url}</a>{}"#, &whole[url.len()..])
+    })
+    .into_owned()
+}
+
+/// Bytes at the start of a log line searched for a level; past this the
+/// word is more likely message text than the line's level.
+const LOG_LEVEL_SCAN_BYTES: usize = 80;
+
+/// Severity of one log line, as the suffix of its `log-*` class: `error`,
+/// `warn`, `info`, `debug` or `trace`. The level is the first of these
+/// upper-case words, matched whole, within the first LOG_LEVEL_SCAN_BYTES
+/// of the line, so timestamps, brackets and `level=` prefixes before it
+/// don't matter:
+/// `FATAL`, `CRITICAL`, `CRIT`, `ERROR`, `ERR` → `error`;
+/// `WARNING`, `WARN` → `warn`; `INFO`, `DEBUG`, `TRACE` as written.
+/// Lower-case words never match, so prose mentioning "error" stays plain.
+pub fn log_level(line: &str) -> Option<&'static str> {
+    let re = LOG_LEVEL_RE.get_or_init(|| {
+        regex::Regex::new(r"\b(FATAL|CRITICAL|CRIT|ERROR|ERR|WARNING|WARN|INFO|DEBUG|TRACE)\b")
+            .expect("log level regex must compile")
+    });
+    let mut end = line.len().min(LOG_LEVEL_SCAN_BYTES);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    let level = re.find(&line[..end])?;
+    Some(match level.as_str() {
+        "FATAL" | "CRITICAL" | "CRIT" | "ERROR" | "ERR" => "error",
+        "WARNING" | "WARN" => "warn",
+        "INFO" => "info",
+        "DEBUG" => "debug",
+        _ => "trace",
+    })
+}
+
+/// trim_autolink for an escaped URL: a trailing `&amp;` goes as a whole
+/// rather than leaving a dangling `&amp`.
+fn trim_escaped_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        if let Some(rest) = url.strip_suffix("&amp;") {
+            url = rest;
+            continue;
+        }
+        let Some(last) = url.chars().last() else {
+            break;
+        };
+        let unbalanced_paren = last == ')' && url.matches(')').count() > url.matches('(').count();
+        if matches!(last, '?' | '!' | '.' | ',' | ':' | '*' | '_' | '~' | ';') || unbalanced_paren {
+            url = &url[..url.len() - 1];
+        } else {
+            break;
+        }
+    }
+    url
+}
+
+/// Make spaces and tabs visible in already-escaped HTML by wrapping each in a
+/// `ws-space` / `ws-tab` span (the character itself is kept, so copying text
+/// still yields the original whitespace). Whitespace after the last visible
+/// character of a line also gets `ws-trailing`. Markup inside `<…>` is left
+/// untouched, so this applies equally to escaped text and highlighted code.
+pub fn mark_whitespace(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() * 2);
+    for (i, line) in html.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut visible_end = 0;
+        let mut in_tag = false;
+        for (idx, ch) in line.char_indices() {
+            match ch {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                ' ' | '\t' | '\r' => {}
+                _ if !in_tag => visible_end = idx + ch.len_utf8(),
+                _ => {}
+            }
+        }
+        in_tag = false;
+        for (idx, ch) in line.char_indices() {
+            match ch {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                ' ' | '\t' if !in_tag => {
+                    let kind = if ch == ' ' { "ws-space" } else { "ws-tab" };
+                    let trailing = if idx >= visible_end {
+                        " ws-trailing"
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!(r#"<span class="ws {kind}{trailing}">{ch}</span>"#));
+                    continue;
+                }
+                _ => {}
+            }
+            out.push(ch);
+        }
+    }
+    out
+}
+
+/// Syntax tokens and language class for a source view of `extension`.
+fn source_syntax(extension: &str) -> Result<(&'static [&'static str], &'static str), String> {
+    match extension {
+        "json" => Ok((&["JSON", "json"], "json")),
+        "yaml" | "yml" => Ok((&["YAML", "yaml", "yml"], "yaml")),
+        "toml" => Ok((&["TOML", "toml"], "toml")),
+        "xml" => Ok((&["XML", "xml"], "xml")),
+        "md" | "markdown" => Ok((&["Markdown", "md"], "markdown")),
+        other => Err(format!("No source highlighting for .{other}")),
+    }
+}
+
+/// Highlight a JSON, YAML or Markdown source verbatim, without the
+/// pretty-printing round-trip. Invalid documents still render.
+pub fn highlight_source(content: &str, extension: &str) -> Result<String, String> {
+    let (tokens, lang_class) = source_syntax(extension)?;
+    highlight_code(content, tokens, lang_class)
+}
+
+/// Highlight a snippet the way a fenced block tagged `language` renders in
+/// Markdown: the same `highlight` wrapper and `language-*` class, and the
+/// same escaped plain block when the language has no syntax. The theme only
+/// affects the stylesheet, so `_theme_name` is unused.
+pub fn highlight_snippet(code: &str, language: &str, _theme_name: &str) -> String {
+    let language = language.trim();
+    let lang_class = if language.is_empty() {
+        "text"
+    } else {
+        language
+    };
+    let tokens: Vec<&str> = syntax_alias(language)
+        .into_iter()
+        .chain([language].into_iter().filter(|l| !l.is_empty()))
+        .collect();
+    highlight_code(code, &tokens, &escape_html(lang_class))
+        .unwrap_or_else(|_| plain_code_block(code, lang_class))
+}
+
+/// Highlight `text` one line at a time. Scopes open across a line break are
+/// closed and reopened so every line's HTML is balanced on its own.
+fn highlight_lines(
+    text: &str,
+    syntax_tokens: &[&str],
+    lang_class: &str,
+) -> Result<Vec<String>, String> {
+    let syntax_set = get_syntax_set();
+    let syntax = syntax_tokens
+        .iter()
+        .find_map(|token| syntax_set.find_syntax_by_token(token))
+        .ok_or_else(|| format!("{lang_class} syntax not found"))?;
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut out = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = format!("{line}\n");
+        let ops = state
+            .parse_line(&line, &syntax_set)
+            .map_err(|e| format!("Failed to highlight line {}: {e}", i + 1))?;
+        let mut html: String = stack
+            .as_slice()
+            .iter()
+            .map(|scope| {
+                format!(
+                    "<span class=\"{}\">",
+                    scope.build_string().replace('.', " ")
+                )
+            })
+            .collect();
+        let (spans, _) = line_tokens_to_classed_spans(&line, &ops, ClassStyle::Spaced, &mut stack)
+            .map_err(|e| format!("Failed to highlight line {}: {e}", i + 1))?;
+        html.push_str(&spans);
+        html.push_str(&"</span>".repeat(stack.len()));
+        out.push(html);
+    }
+    Ok(out)
+}
+
+/// `highlight_lines` for a block with no syntax: each line escaped as is.
+fn plain_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| format!("{}\n", escape_html(line)))
+        .collect()
+}
+
+/// `highlight_code` as a `<table class="highlight-with-lines">`: one row per
+/// source line, a `lineno` cell and a `code` cell (blank lines keep a row).
+fn highlight_code_with_line_numbers(
+    text: &str,
+    syntax_tokens: &[&str],
+    lang_class: &str,
+    emphasized: &[(usize, usize)],
+) -> Result<String, String> {
+    let lines = highlight_lines(text, syntax_tokens, lang_class)?;
+    Ok(numbered_code_table(&lines, lang_class, emphasized))
+}
+
+/// The table `highlight_code_with_line_numbers` builds, from lines already
+/// rendered by `highlight_lines` or `plain_lines`.
+fn numbered_code_table(
+    lines: &[String],
+    lang_class: &str,
+    emphasized: &[(usize, usize)],
+) -> String {
+    let rows: String = lines
+        .iter()
+        .enumerate()
+        .map(|(i, html)| {
+            let row_class = if is_emphasized(emphasized, i + 1) {
+                r#" class="highlighted-line""#
+            } else {
+                ""
+            };
+            format!(
+                "<tr{row_class}><td class=\"lineno\">{}</td><td class=\"code\">{}</td></tr>",
+                i + 1,
+                html.replacen('\n', "", 1)
+            )
+        })
+        .collect();
+    format!(
+        "<div class=\"highlight\"><table class=\"highlight-with-lines language-{lang_class}\"><tbody>{rows}</tbody></table></div>"
+    )
+}
+
+fn is_emphasized(ranges: &[(usize, usize)], line: usize) -> bool {
+    ranges
+        .iter()
+        .any(|&(start, end)| (start..=end).contains(&line))
+}
+
+/// A code block with every line in its own `code-line` block, the lines in
+/// `emphasized` also carrying `highlighted-line`.
+fn emphasized_code_block(
+    lines: &[String],
+    lang_class: &str,
+    emphasized: &[(usize, usize)],
+) -> String {
+    let lines: String = lines
+        .iter()
+        .enumerate()
+        .map(|(i, html)| {
+            let class = if is_emphasized(emphasized, i + 1) {
+                "code-line highlighted-line"
+            } else {
+                "code-line"
+            };
+            format!(
+                "<span class=\"{class}\">{}</span>",
+                html.replacen('\n', "", 1)
+            )
+        })
+        .collect();
+    format!(
+        "<div class=\"highlight\"><pre><code class=\"language-{lang_class}\">{lines}</code></pre></div>"
+    )
+}
+
+/// `highlight_source` with a line-number gutter.
+pub fn highlight_source_with_line_numbers(
+    content: &str,
+    extension: &str,
+) -> Result<String, String> {
+    let (tokens, lang_class) = source_syntax(extension)?;
+    highlight_code_with_line_numbers(content, tokens, lang_class, &[])
+}
+
+/// `highlight_source` with each line wrapped in
+/// `<span class="source-line" data-source-line="N">` (1-based). Scopes open
+/// across a line break are closed and reopened so every line is balanced.
+/// `.txt`, `.log` and `.csv` are escaped without highlighting.
+pub fn highlight_source_lines(content: &str, extension: &str) -> Result<String, String> {
+    let line_span = |n: usize, inner: &str| {
+        format!("<span class=\"source-line\" data-source-line=\"{n}\">{inner}</span>")
+    };
+    if matches!(extension, "txt" | "log" | "csv") {
+        let lines: String = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| line_span(i + 1, &format!("{}\n", escape_html(line))))
+            .collect();
+        return Ok(format!("<pre class=\"plain-text\">{lines}</pre>"));
+    }
+
+    let (tokens, lang_class) = source_syntax(extension)?;
+    let out: String = highlight_lines(content, tokens, lang_class)?
+        .iter()
+        .enumerate()
+        .map(|(i, html)| line_span(i + 1, html))
+        .collect();
+    Ok(format!(
+        "<div class=\"highlight\"><pre><code class=\"language-{lang_class}\">{out}</code></pre></div>"
+    ))
+}
+
+/// `highlight_source` with visible whitespace, which is the point when
+/// diagnosing broken indentation.
+pub fn highlight_source_with_whitespace(content: &str, extension: &str) -> Result<String, String> {
+    Ok(mark_whitespace(&highlight_source(content, extension)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_math_emits_math_inline_span() {
+        let out = parse_markdown("A $\\alpha + \\beta$ B");
+        assert!(
+            out.contains(r#"<span class="math math-inline">"#),
+            "got: {out}"
+        );
+        assert!(out.contains(r"\alpha + \beta"), "got: {out}");
+    }
+
+    #[test]
+    fn display_math_emits_math_display_div() {
+        let out = parse_markdown("$$\\int_0^1 x\\,dx$$");
+        assert!(
+            out.contains(r#"<div class="math math-display">"#),
+            "got: {out}"
+        );
+        assert!(out.contains(r"\int_0^1 x\,dx"), "got: {out}");
+    }
+
+    #[test]
+    fn math_edge_cases_escape_and_stay_block_level() {
+        assert_eq!(
+            parse_markdown(r"Costs \$5 or \$6"),
+            "<p>Costs $5 or $6</p>\n"
+        );
+        let out = parse_markdown("$$\nx < y\n\\\\ z > 1\n$$\n");
+        assert!(
+            out.starts_with(r#"<div class="math math-display">"#),
+            "got: {out}"
+        );
+        assert!(out.contains("x &lt; y\n\\\\ z &gt; 1"), "got: {out}");
+        assert!(!out.contains("<p>"), "got: {out}");
+
+        // Inside running text the block stays within its paragraph.
+        let out = parse_markdown("Text\n$$x$$\nmore");
+        assert!(
+            out.contains("<p>Text\n<span class=\"math math-display\">x</span>\nmore</p>"),
+            "got: {out}"
+        );
+    }
+
+    #[test]
+    fn diagram_fences_pass_through_verbatim() {
+        let out = parse_markdown("```mermaid\ngraph TD;A-->B\n```\n");
+        assert!(out.contains(r#"<pre class="mermaid">"#), "got: {out}");
+        assert!(out.contains("graph TD;A--&gt;B"), "got: {out}");
+
+        for lang in ["dot", "graphviz"] {
+            let out = parse_markdown(&format!("```{lang}\ndigraph {{ a -> b }}\n```\n"));
+            assert!(
+                out.contains("<pre class=\"graphviz\">digraph { a -&gt; b }\n</pre>"),
+                "got: {out}"
+            );
+        }
+    }
+
+    #[test]
+    fn callout_note_rewrites_blockquote() {
+        let out = parse_markdown("> [!NOTE]\n> Hello\n");
+        assert!(
+            out.contains(r#"<div class="callout callout-note">"#),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"<div class="callout-title">NOTE</div>"#),
+            "got: {out}"
+        );
+        assert!(out.contains("Hello"), "got: {out}");
+    }
+
+    #[test]
+    fn callout_all_kinds_render() {
+        for kind in ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"] {
+            let input = format!("> [!{kind}]\n> body\n");
+            let out = parse_markdown(&input);
+            let expected = format!(
+                r#"<div class="callout callout-{}">"#,
+                kind.to_ascii_lowercase()
+            );
+            assert!(out.contains(&expected), "kind={kind} got: {out}");
+        }
+    }
+
+    #[test]
+    fn sanitize_levels_widen_the_allowlist() {
+        let src =
+            "<div id=\"x\" style=\"color:red\"><kbd>K</kbd></div>\n\n<script>alert(1)</script>\n";
+        let strict = parse_markdown_with_sanitize(src, "light", SanitizeLevel::Strict);
+        assert!(strict.contains("<kbd>K</kbd>") && !strict.contains("style="));
+        assert!(!strict.contains("id=\"x\"") && !strict.contains("<script>"));
+
+        let relaxed = parse_markdown_with_sanitize(src, "light", SanitizeLevel::Relaxed);
+        assert!(relaxed.contains(r#"<div id="x" style="color:red">"#));
+        assert!(!relaxed.contains("<script>"));
+
+        let raw = parse_markdown_with_sanitize(src, "light", SanitizeLevel::None);
+        assert!(raw.contains("<script>alert(1)</script>"));
+        assert_eq!(
+            SanitizeLevel::from_name("Relaxed"),
+            Some(SanitizeLevel::Relaxed)
+        );
+    }
+
+    #[test]
+    fn syntect_classes_survive_sanitization() {
+        let out = parse_markdown("```rust\nfn main() { println!(\"hi\"); }\n```\n");
+        // syntect emits spans with `class="source rust …"` (ClassStyle::Spaced).
+        // The old ammonia::clean stripped them; the Builder keeps class now.
+        assert!(
+            out.contains(r#"<span class="source rust"#),
+            "syntect classes stripped; got: {out}"
+        );
+        assert!(
+            out.contains(r#"class="language-rust""#),
+            "language-rust class stripped; got: {out}"
+        );
+    }
+
+    #[test]
+    fn preserves_core_features() {
+        let out = parse_markdown("| a | b |\n|---|---|\n| 1 | 2 |\n");
+        assert!(out.contains("<table>"), "table missing: {out}");
+
+        let out = parse_markdown("- [x] done\n- [ ] todo\n");
+        assert!(
+            out.contains(r#"type="checkbox""#),
+            "task list missing: {out}"
+        );
+
+        let out = parse_markdown("~~gone~~\n");
+        assert!(out.contains("<del>"), "strikethrough missing: {out}");
+
+        let out = parse_markdown("ref[^1]\n\n[^1]: note\n");
+        assert!(out.contains("footnote"), "footnote missing: {out}");
+    }
+
+    #[test]
+    fn footnote_anchors_survive_sanitization() {
+        let src = "One[^a] two[^b] again[^a].\n\n[^b]: Second *note*.\n\n[^a]: First note.\n";
+        let out = parse_markdown(src);
+        for (n, refs) in [(1, &["fnref-1", "fnref-1-2"][..]), (2, &["fnref-2"][..])] {
+            for id in refs {
+                assert!(
+                    out.contains(&format!(
+                        r##"<sup class="footnote-reference" id="{id}"><a href="#fn-{n}""##
+                    )),
+                    "reference {id} missing: {out}"
+                );
+            }
+            assert!(
+                out.contains(&format!(r#"<div class="footnote-definition" id="fn-{n}">"#)),
+                "definition {n} missing: {out}"
+            );
+            assert!(
+                out.contains(&format!(
+                    r##"<a href="#fnref-{n}" class="footnote-backref""##
+                )),
+                "backref {n} missing: {out}"
+            );
+        }
+        // The back-reference closes the definition's text, not a new block.
+        assert!(
+            out.contains("First note. <a href=\"#fnref-1\" class=\"footnote-backref\" rel=\"noopener noreferrer\">↩</a></p>"),
+            "got: {out}"
+        );
+    }
+
+    #[test]
+    fn footnote_definitions_stay_one_block_for_the_block_walkers() {
+        let src = "Ref[^a].\n\n[^a]: Note\n    more.\n\n# After\n";
+        let links = LinkRewriter::default();
+        let opts = MarkdownOptions::default();
+
+        let (html, lines) = parse_markdown_with_line_anchors(src, "light", opts, &links, 0);
+        assert_eq!(lines, [1, 3, 6]);
+        assert!(html.contains(r#"<h1 data-source-line="6""#), "got: {html}");
+        assert!(
+            html.contains(r#"<div data-source-line="3" class="footnote-definition" id="fn-1">"#),
+            "got: {html}"
+        );
+
+        let wrapped = parse_markdown_with_source_lines(src, "light", opts, &links, 0);
+        assert_eq!(
+            wrapped.matches("<div").count(),
+            wrapped.matches("</div>").count(),
+            "got: {wrapped}"
+        );
+        assert_eq!(wrapped.matches(r#"class="source-block""#).count(), 3);
+
+        // Chunks share one numbering, so ids stay unique.
+        let src = "A[^x].\n\n[^x]: X.\n\nB[^y].\n\n[^y]: Y.\n";
+        let mut chunks = 0;
+        let chunked = parse_markdown_chunked(src, "light", opts, &links, 10, |_| chunks += 1);
+        assert_eq!(chunks, 2);
+        for id in [r#"id="fn-1""#, r#"id="fn-2""#, r#"id="fnref-2""#] {
+            assert_eq!(chunked.matches(id).count(), 1, "{id}: {chunked}");
+        }
+    }
+
+    #[test]
+    fn plaintext_preserves_block_structure() {
+        let out = markdown_to_plaintext(
+            "# Title\n\nSome **bold** text with a [link](https://example.com).\n\n- one\n- two\n  1. nested\n\n```rust\nfn main() {}\n```\n",
+        );
+        assert_eq!(
+            out,
+            "Title\n\nSome bold text with a link (https://example.com).\n\n- one\n- two\n  1. nested\n\n```rust\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn detect_language_guesses_prose_and_ignores_code() {
+        let en = "The parser reads the file and builds a tree of nodes. It is not fast, \
+                  but it is simple, and that is the point of this tool for you and the team. \
+                  The rest of the code was written with care.";
+        assert_eq!(detect_language(en), Some("en"));
+        let de = "Der Parser liest die Datei und baut einen Baum auf. Das ist nicht schnell, \
+                  aber es ist einfach, und das ist der Sinn des Werkzeugs. Die Daten werden \
+                  mit dem Modul auf der Platte gespeichert, und auch die Tests sind dabei.";
+        assert_eq!(detect_language(de), Some("de"));
+        let fr = "Le programme lit le fichier et construit une arborescence pour les pages. \
+                  Ce n'est pas rapide, mais il est simple, et c'est le but de cet outil qui \
+                  sert à tous. Les données sont dans le dossier avec les tests du projet.";
+        assert_eq!(detect_language(fr), Some("fr"));
+        let short_de = "Der Parser liest die Datei und baut einen Baum auf. Das ist nicht \
+                        schnell, aber es ist einfach, und das ist der Sinn.";
+        assert_eq!(detect_language(short_de), Some("de"));
+        let pl = "Parser czyta plik i buduje drzewo węzłów. Nie jest szybki, ale jest prosty \
+                  i o to chodzi w tym narzędziu dla całego zespołu.";
+        assert_eq!(detect_language(pl), Some("pl"));
+        let zh = "解析器读取文件并构建节点树。它不快，但很简单，这就是这个工具的意义。";
+        assert_eq!(detect_language(zh), Some("zh"));
+        let ja = "パーサーはファイルを読み込み、ノードのツリーを構築します。速くはありませんが、シンプルです。";
+        assert_eq!(detect_language(ja), Some("ja"));
+
+        // Too short to call.
+        assert_eq!(detect_language("The end of the story."), None);
+        // Code blocks don't count toward the sample.
+        let md = format!("# Titel\n\n```\n{en}\n```\n\n{de}\n");
+        assert_eq!(detect_language(&markdown_prose(&md)), Some("de"));
+    }
+
+    #[test]
+    fn source_lines_anchor_blocks_and_lines() {
+        let src = "# Title\n\nPara one\ncontinued\n\n```rust\nfn x() {}\n```\n\n- a\n- b\n";
+        let html = parse_markdown_with_source_lines(
+            src,
+            "light",
+            MarkdownOptions::default(),
+            &LinkRewriter::default(),
+            3,
+        );
+        for line in [4, 6, 9, 13] {
+            assert!(
+                html.contains(&format!(
+                    r#"<div class="source-block" data-source-line="{line}">"#
+                )),
+                "missing line {line}: {html}"
+            );
+        }
+        assert_eq!(html.matches("source-block").count(), 4);
+
+        let source = highlight_source_lines("a: \"x\n  y\"\nb: 1\n", "yaml").unwrap();
+        assert_eq!(source.matches("class=\"source-line\"").count(), 3);
+        // Each line is self-contained even where a string spans lines.
+        for line in source.split("<span class=\"source-line\"").skip(1) {
+            let line = &line[..line.rfind("</span>").unwrap()];
+            assert_eq!(
+                line.matches("<span").count(),
+                line.matches("</span>").count()
+            );
+        }
+    }
+
+    #[test]
+    fn json_source_highlight_keeps_numbers_and_key_order() {
+        let src = r#"{"z": 1.0, "a": 12345678901234567890123, "m": 1e3}"#;
+        let verbatim = highlight_source(src, "json").unwrap();
+        let normalized = parse_json_with_theme(src, "light").unwrap();
+        for kept in ["1.0", "12345678901234567890123", "1e3"] {
+            assert!(verbatim.contains(kept), "missing {kept}: {verbatim}");
+        }
+        // The re-serializing path is the one that loses them.
+        assert!(!normalized.contains("12345678901234567890123"));
+    }
+
+    #[test]
+    fn task_lines_follow_source() {
+        let src = "# Todo\n\n- [ ] one\n- [x] two\n  - [ ] nested\n\n> - [X] quoted\n\n\
+                   ```collapse\n- [ ] folded\n```\n\n- not a task\n";
+        let opts = MarkdownOptions {
+            task_lines: true,
+            ..MarkdownOptions::default()
+        };
+        let task_lines = |html: &str| -> Vec<usize> {
+            html.match_indices("data-task-line=\"")
+                .map(|(i, m)| {
+                    let rest = &html[i + m.len()..];
+                    rest[..rest.find('"').unwrap()].parse().unwrap()
+                })
+                .collect()
+        };
+        let html = parse_markdown_with_options(src, "light", opts);
+        assert_eq!(task_lines(&html), [3, 4, 5, 7, 10]);
+        assert!(
+            html.contains(r#"checked="" data-task-line="4""#),
+            "got: {html}"
+        );
+
+        // Chunks count from the line they start on.
+        let chunked =
+            parse_markdown_chunked(src, "light", opts, &LinkRewriter::default(), 1, |_| {});
+        assert_eq!(task_lines(&chunked), [3, 4, 5, 7, 10]);
+
+        assert!(!parse_markdown(src).contains("data-task-line"));
+    }
+
+    #[test]
+    fn plaintext_aligns_table_columns() {
+        let out = markdown_to_plaintext("| name | n |\n|---|--:|\n| alpha | 1 |\n| b | 22 |\n");
+        assert_eq!(out, "name  |  n\n------+---\nalpha |  1\nb     | 22");
+    }
+
+    #[test]
+    fn plaintext_quotes_tasks_and_bare_links() {
+        let out =
+            markdown_to_plaintext("> quoted\n> line\n\n- [x] done\n- [ ] todo\n\n<https://a.io>\n");
+        assert_eq!(
+            out,
+            "> quoted line\n\n- [x] done\n- [ ] todo\n\nhttps://a.io"
+        );
+    }
+
+    #[test]
+    fn text_extraction_flattens_blocks_and_skips_urls() {
+        let src = "# Title\n\nSome *emphasis* and [a link](https://x.io) ![alt](i.png).\n\n- one\n  - two\n\n```rust\nfn  main() {}\n```\n\n`code`<b>raw</b>\n";
+        assert_eq!(
+            markdown_to_text(src),
+            "Title Some emphasis and a link . one two fn main() {} coderaw"
+        );
+    }
+
+    #[test]
+    fn code_line_numbers_count_blank_lines() {
+        let opts = MarkdownOptions {
+            code_line_numbers: true,
+            ..MarkdownOptions::default()
+        };
+        let html = parse_markdown_with_options("```json\n{\n\n  \"a\": 1\n}\n```\n", "light", opts);
+        assert!(html.contains(r#"<table class="highlight-with-lines language-json">"#));
+        assert_eq!(html.matches(r#"<td class="lineno">"#).count(), 4);
+        let cells: Vec<String> = html
+            .split(r#"<td class="code">"#)
+            .skip(1)
+            .map(|cell| {
+                let cell = &cell[..cell.find("</td>").unwrap()];
+                let mut text = String::new();
+                let mut in_tag = false;
+                for ch in cell.chars() {
+                    match ch {
+                        '<' => in_tag = true,
+                        '>' => in_tag = false,
+                        _ if !in_tag => text.push(ch),
+                        _ => {}
+                    }
+                }
+                text
+            })
+            .collect();
+        assert_eq!(cells, ["{", "", "  \"a\": 1", "}"]);
+
+        let toml = parse_structured_with_line_numbers("a = 1\n[t]\nb = 2\n", "toml").unwrap();
+        assert_eq!(toml.matches(r#"<td class="lineno">"#).count(), 4);
+    }
+
+    #[test]
+    fn fence_range_spec_emphasizes_lines() {
+        assert_eq!(
+            parse_fence_info("rust {2,4-6}"),
+            ("rust", vec![(2, 2), (4, 6)])
+        );
+        assert_eq!(parse_fence_info("rust{3}"), ("rust", vec![(3, 3)]));
+        assert_eq!(parse_fence_info("rust {6-4}"), ("rust", vec![]));
+        assert_eq!(parse_fence_info("rust {x}"), ("rust", vec![]));
+        assert_eq!(parse_fence_info("rust"), ("rust", vec![]));
+
+        let html = parse_markdown_with_theme("```json {2}\n[\n  1\n]\n```\n", "light");
+        assert_eq!(html.matches(r#"class="code-line""#).count(), 2);
+        assert_eq!(html.matches("code-line highlighted-line").count(), 1);
+        assert!(html.contains(r#"class="language-json""#));
+
+        let broken = parse_markdown_with_theme("```json {2-}\n[\n  1\n]\n```\n", "light");
+        assert!(broken.contains(r#"class="language-json""#) && !broken.contains("code-line"));
+    }
+
+    #[test]
+    fn document_stats_count_visible_words_outside_code() {
+        let src = "---\ntitle: T\n---\n# A **bold** title\n\n```sh\necho one two\n```\n\nSee [the docs](https://x.io).\n";
+        assert_eq!(
+            document_stats(src),
+            DocumentStats {
+                words: 6,
+                characters: 26,
+                reading_minutes: 1,
+                code_blocks: 1,
+            }
+        );
+        assert_eq!(document_stats("").reading_minutes, 0);
+        assert_eq!(document_stats(&"word ".repeat(221)).reading_minutes, 2);
+    }
+
+    #[test]
+    fn json_and_yaml_accept_bom_and_jsonc() {
+        let json = parse_json_with_theme("\u{feff}{\"a\": 1}\n", "light").unwrap();
+        assert_eq!(json, parse_json_with_theme("{\"a\": 1}", "light").unwrap());
+        assert!(parse_yaml_with_theme("\u{feff}a: 1\n", "light").is_ok());
+
+        let jsonc =
+            "{\n  // note\n  \"url\": \"http://x/*y*/\", /* gone */\n  \"list\": [1, 2,],\n}\n";
+        assert_eq!(
+            strip_json_comments(jsonc),
+            "{\n  \n  \"url\": \"http://x/*y*/\", \n  \"list\": [1, 2]\n}\n"
+        );
+        assert!(parse_json_with_theme(jsonc, "light").is_ok());
+        // Errors still describe the document as written.
+        let err = parse_json_with_theme("{\"a\": }", "light").unwrap_err();
+        assert!(err.starts_with("Invalid JSON: expected value"), "{err}");
+    }
+
+    #[test]
+    fn chunked_parse_matches_whole_parse_and_reports_progress() {
+        let doc = "# Title\n\nOne paragraph.\n\n```rust\nfn main() {}\n\n\n```\n\n- a\n\n- b\n\n    indented\n\n# Title\n";
+        let opts = MarkdownOptions::default();
+        let links = LinkRewriter::default();
+        let mut seen = Vec::new();
+        let chunked = parse_markdown_chunked(doc, "light", opts, &links, 8, |n| seen.push(n));
+        assert_eq!(
+            chunked,
+            parse_markdown_with_links(doc, "light", opts, &links)
+        );
+        assert!(seen.len() > 2);
+        assert_eq!(seen.last(), Some(&doc.len()));
+        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn scope_css_prefixes_every_selector() {
+        let css = "/*\n * theme\n */\n\n.code {\n color: #323232;\n}\n.string.regexp .begin, .string.regexp .end {\n color: #a71d5d;\n}\n";
+        let out = scope_css(css, ".boltpage-preview").unwrap();
+        assert_eq!(
+            out,
+            "/*\n * theme\n */\n\n.boltpage-preview .code {\n color: #323232;\n}\n.boltpage-preview .string.regexp .begin, .boltpage-preview .string.regexp .end {\n color: #a71d5d;\n}\n"
+        );
+
+        assert_eq!(scope_css(css, "  "), None);
+        assert_eq!(scope_css(css, "x} body {"), None);
+    }
+
+    #[test]
+    fn every_listed_theme_name_selects_that_theme() {
+        let set = get_theme_set();
+        for name in syntax_theme_names() {
+            let expected =
+                css_for_theme_with_class_style(&set.themes[&name], ClassStyle::Spaced).unwrap();
+            assert_eq!(get_syntax_theme_css(&name), Some(expected), "{name}");
+        }
+    }
+
+    #[test]
+    fn scoped_theme_css_has_no_unscoped_rules() {
+        let css = get_syntax_theme_css_scoped("dark", ".markdown-body").unwrap();
+        for line in css.lines().filter(|l| l.ends_with('{')) {
+            assert!(line.starts_with(".markdown-body "), "unscoped rule: {line}");
+        }
+    }
+
+    #[test]
+    fn unknown_fence_languages_keep_highlight_wrapper_and_aliases_resolve() {
+        let out = parse_markdown("```console\n$ echo <hi>\n```\n");
+        assert!(
+            out.contains(
+                r#"<div class="highlight"><pre><code class="language-console">$ echo &lt;hi&gt;"#
+            ),
+            "got: {out}"
+        );
+
+        // An alias highlights exactly like the name it stands for; only the
+        // language class keeps the fence's spelling.
+        let bash = parse_markdown("```bash\nls -la | grep x\n```\n");
+        assert!(bash.contains("source shell"), "got: {bash}");
+        for (alias, name) in [
+            ("sh", "bash"),
+            ("zsh", "bash"),
+            ("yml", "yaml"),
+            ("rs", "rust"),
+        ] {
+            let body = "a: [1, \"x\"]\nfn main() {}\n";
+            let via_alias = parse_markdown(&format!("```{alias}\n{body}```\n"));
+            let direct = parse_markdown(&format!("```{name}\n{body}```\n"));
+            assert!(via_alias.contains("<span class=\""), "{alias}: {via_alias}");
+            assert_eq!(
+                via_alias.replace(&format!("language-{alias}"), &format!("language-{name}")),
+                direct,
+                "{alias}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_fence_languages_keep_line_numbers_and_emphasis() {
+        let numbered = parse_markdown_with_options(
+            "```console\n$ a\n$ <b>\n```\n",
+            "light",
+            MarkdownOptions {
+                code_line_numbers: true,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(
+            numbered.contains(r#"<table class="highlight-with-lines language-console">"#),
+            "got: {numbered}"
+        );
+        assert_eq!(numbered.matches(r#"<td class="lineno">"#).count(), 2);
+        assert!(numbered.contains(r#"<td class="code">$ &lt;b&gt;</td>"#));
+
+        let emphasized = parse_markdown_with_theme("```console {2}\n$ a\n$ b\n```\n", "light");
+        assert!(emphasized.contains(r#"class="language-console""#));
+        assert_eq!(emphasized.matches(r#"class="code-line""#).count(), 1);
+        assert!(emphasized.contains(r#"<span class="code-line highlighted-line">$ b</span>"#));
+    }
+
+    /// Verifies that the syntaxes we expect to be present in syntect's
+    /// `default-fancy` feature are, in fact, present. These are the ones
+    /// confirmed by an audit run against syntect 5.2 on 2026-04-23.
+    #[test]
     fn syntax_set_covers_core_languages() {
         let set = SyntaxSet::load_defaults_newlines();
         let required = [
@@ -375,6 +3719,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn custom_extensions_load_and_report_failures() {
+        let dir = std::env::temp_dir().join(format!("markrust-ext-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Plainish.tmTheme"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>name</key><string>Plainish</string>
+<key>settings</key><array><dict><key>settings</key><dict>
+<key>background</key><string>#101010</string>
+<key>foreground</key><string>#EEEEEE</string>
+</dict></dict></array>
+</dict></plist>"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("Broken.tmTheme"), "not a plist").unwrap();
+        std::fs::write(
+            dir.join("Boltlog.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Boltlog\nfile_extensions: [boltlog]\nscope: source.boltlog\ncontexts:\n  main:\n    - match: ERROR\n      scope: invalid.boltlog\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("Bad.sublime-syntax"), "contexts: [").unwrap();
+
+        // Built without installing, so parallel tests keep the stock sets.
+        let (theme_set, themes) = load_theme_set(&dir);
+        assert_eq!(themes.loaded, vec!["Plainish.tmTheme".to_string()]);
+        assert_eq!(themes.failed.len(), 1);
+        assert_eq!(themes.failed[0].path, "Broken.tmTheme");
+        let plainish = &theme_set.themes["Plainish"];
+        assert_eq!(
+            plainish.settings.background.map(|c| (c.r, c.g, c.b)),
+            Some((0x10, 0x10, 0x10))
+        );
+        assert!(theme_set.themes.contains_key("InspiredGitHub"));
+
+        let (syntax_set, syntaxes) = load_syntax_set(&dir);
+        assert_eq!(syntaxes.loaded, vec!["Boltlog.sublime-syntax".to_string()]);
+        assert_eq!(syntaxes.failed[0].path, "Bad.sublime-syntax");
+        assert!(syntax_set.find_syntax_by_token("boltlog").is_some());
+        // Bundled syntaxes survive a rebuild.
+        assert!(syntax_set.find_syntax_by_token("toml").is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (theme_set, themes) = load_theme_set(&dir);
+        assert!(themes.loaded.is_empty());
+        assert!(!theme_set.themes.contains_key("Plainish"));
+    }
+
     /// Asserts that every token covered by the vendored `syntaxes/` pack is
     /// resolvable via `get_syntax_set()` — i.e. the previously-reported gaps
     /// (ts, tsx, swift, scss, kotlin, toml, dockerfile, ini) are all bundled.
@@ -400,4 +3793,718 @@ mod tests {
             "vendored syntaxes fail to cover: {missing:?}"
         );
     }
+
+    #[test]
+    fn commonmark_preset_disables_gfm_extras() {
+        let src = "| a |\n|---|\n| 1 |\n\n~~gone~~ $x$\n\n> [!NOTE]\n> hi\n";
+        let out = parse_markdown_with_options(src, "light", MarkdownPreset::CommonMark.into());
+        assert!(!out.contains("<table>"), "got: {out}");
+        assert!(!out.contains("<del>"), "got: {out}");
+        assert!(!out.contains("math"), "got: {out}");
+        assert!(!out.contains("callout"), "got: {out}");
+    }
+
+    #[test]
+    fn emoji_shortcodes_expand_in_text_but_not_code() {
+        let opts = MarkdownOptions {
+            enable_emoji: true,
+            ..MarkdownOptions::default()
+        };
+        let src = "Ship it :rocket: :tada:! :not_a_real_one: at 12:30:45, `:rocket:`\n\n```\n:tada:\n```\n";
+        let out = parse_markdown_with_options(src, "light", opts);
+        assert!(out.contains("Ship it 🚀 🎉!"), "got: {out}");
+        assert!(out.contains(":not_a_real_one: at 12:30:45"), "got: {out}");
+        assert!(out.contains("<code>:rocket:</code>"), "got: {out}");
+        assert!(out.contains(":tada:\n</code></pre>"), "got: {out}");
+        assert!(parse_markdown_with_options("a:q::+1:", "light", opts).contains("a:q:👍"));
+        // Shortcodes are opt-in (the markdown_emoji pref) in every preset.
+        for preset in [MarkdownPreset::GitHub, MarkdownPreset::Obsidian] {
+            let out = parse_markdown_with_options("a:q::+1:", "light", preset.into());
+            assert!(out.contains("a:q::+1:"), "{preset:?} got: {out}");
+        }
+    }
+
+    #[test]
+    fn emoji_shortcodes_leave_autolinked_urls_alone() {
+        let opts = MarkdownOptions {
+            autolink: true,
+            enable_emoji: true,
+            ..MarkdownOptions::default()
+        };
+        let out = parse_markdown_with_options(
+            "see https://example.com/:smile:/x :smile:\n",
+            "light",
+            opts,
+        );
+        assert!(
+            out.contains(r#"<a href="https://example.com/:smile:/x" rel="noopener noreferrer">https://example.com/:smile:/x</a> 😄"#),
+            "got: {out}"
+        );
+    }
+
+    #[test]
+    fn github_preset_autolinks_bare_urls_but_not_code() {
+        let src = "See https://example.com/a_(b). and www.rust-lang.org, `https://no.link`\n";
+        let out = parse_markdown_with_options(src, "light", MarkdownPreset::GitHub.into());
+        assert!(
+            out.contains(r#"<a href="https://example.com/a_(b)" rel="noopener noreferrer">https://example.com/a_(b)</a>."#),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"href="http://www.rust-lang.org""#),
+            "got: {out}"
+        );
+        assert!(out.contains("<code>https://no.link</code>"), "got: {out}");
+        assert!(
+            !parse_markdown(src).contains("<a "),
+            "default options must not autolink"
+        );
+    }
+
+    #[test]
+    fn definition_lists_render_and_survive_sanitizing() {
+        let out = parse_markdown(
+            "Term\n: The definition\n\nNot a term\n\n: no term above\n\n```\n\n: code\n```\n",
+        );
+        assert!(
+            out.contains("<dl>\n<dt>Term</dt>\n<dd>The definition</dd>\n</dl>"),
+            "got: {out}"
+        );
+        assert_eq!(out.matches("<dt>").count(), 1, "got: {out}");
+        assert!(out.contains("<p>: no term above</p>"), "got: {out}");
+        assert!(out.contains(": code"), "got: {out}");
+        assert!(!parse_markdown("Word\n:nospace\n").contains("<dl>"));
+        // Raw HTML, display math and longer fences keep their colons as is.
+        let html = parse_markdown("<pre>\n\n: foo\n</pre>\n");
+        assert!(html.contains("\n: foo\n"), "got: {html}");
+        let math = parse_markdown("$$\na\n:=b\n$$\n");
+        assert!(
+            !math.contains("<dl>") && !math.contains('\\'),
+            "got: {math}"
+        );
+        assert!(math.contains(":=b\n</div>"), "got: {math}");
+        let fence = parse_markdown("````\n```\n\n: code\n````\n");
+        assert!(fence.contains("\n: code\n"), "got: {fence}");
+        let fence = parse_markdown("- item\n\n  ```\n  x\n  :nospace\n  ```\n");
+        assert!(fence.contains("\n:nospace\n"), "got: {fence}");
+        let plain = parse_markdown_with_options(
+            "Term\n: def\n",
+            "light",
+            MarkdownPreset::CommonMark.into(),
+        );
+        assert!(!plain.contains("<dl>"), "got: {plain}");
+    }
+
+    #[test]
+    fn definition_lists_stay_out_of_lists_and_blockquotes() {
+        let out = parse_markdown("- item\n  : x\n\n> quote\n> : y\n");
+        assert!(!out.contains("<dl>"), "got: {out}");
+        assert!(out.contains("item\n: x"), "got: {out}");
+        assert!(out.contains("quote\n: y"), "got: {out}");
+        let loose = parse_markdown("- a\n\n  b\n  : c\n\n> : d\n");
+        assert!(!loose.contains("<dl>"), "got: {loose}");
+        assert!(loose.contains("<p>: d</p>"), "got: {loose}");
+    }
+
+    #[test]
+    fn smart_punctuation_only_when_enabled() {
+        let src = "Pages 1--2 -- \"quoted\"\n";
+        let plain = parse_markdown(src);
+        assert!(plain.contains("1--2"), "got: {plain}");
+        let smart = parse_markdown_with_options(
+            src,
+            "light",
+            MarkdownOptions {
+                smart_punctuation: true,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(smart.contains("1\u{2013}2"), "got: {smart}");
+        assert!(smart.contains("\u{201c}quoted\u{201d}"), "got: {smart}");
+    }
+
+    #[test]
+    fn obsidian_preset_adds_wikilinks_outside_links() {
+        let src = "Go to [[Other Note]] or [[x]](y)\n";
+        let out = parse_markdown_with_options(src, "light", MarkdownPreset::Obsidian.into());
+        assert!(
+            out.contains(r#"<a class="wikilink" href="Other%20Note.md" rel="noopener noreferrer">Other Note</a>"#),
+            "got: {out}"
+        );
+        let github = parse_markdown_with_options(src, "light", MarkdownPreset::GitHub.into());
+        assert!(github.contains("[[Other Note]]"), "got: {github}");
+    }
+
+    #[test]
+    fn wikilink_aliases_show_their_text_and_code_is_left_alone() {
+        let src = "See [[notes/Plan B|the plan]] and `[[Not a link]]`\n";
+        let out = parse_markdown_with_options(src, "light", MarkdownPreset::Obsidian.into());
+        assert!(
+            out.contains(r#"<a class="wikilink" href="notes/Plan%20B.md" rel="noopener noreferrer">the plan</a>"#),
+            "got: {out}"
+        );
+        assert!(out.contains("<code>[[Not a link]]</code>"), "got: {out}");
+        let named = parse_markdown_with_options(
+            "[[Note.md]] [[Old.markdown]] [[100% done]]\n",
+            "light",
+            MarkdownPreset::Obsidian.into(),
+        );
+        for href in [
+            r#"href="Note.md""#,
+            r#"href="Old.markdown""#,
+            r#"href="100%25%20done.md""#,
+        ] {
+            assert!(named.contains(href), "missing {href}: {named}");
+        }
+    }
+
+    #[test]
+    fn preset_names_round_trip() {
+        for preset in [
+            MarkdownPreset::CommonMark,
+            MarkdownPreset::GitHub,
+            MarkdownPreset::Obsidian,
+        ] {
+            assert_eq!(MarkdownPreset::from_name(preset.name()), Some(preset));
+        }
+        assert_eq!(
+            MarkdownPreset::from_name("GitHub"),
+            Some(MarkdownPreset::GitHub)
+        );
+        assert_eq!(MarkdownPreset::from_name("asciidoc"), None);
+    }
+
+    #[test]
+    fn frontmatter_is_parsed_and_stripped_from_the_render() {
+        let (meta, html) = parse_markdown_with_frontmatter(
+            "---\ntitle: Notes\ntags: [a, b]\n---\n# Body\n",
+            "light",
+        );
+        let meta = meta.expect("metadata");
+        assert_eq!(meta["title"].as_str(), Some("Notes"));
+        assert_eq!(meta["tags"][1].as_str(), Some("b"));
+        assert!(
+            html.contains("Body</h1>") && !html.contains("<hr"),
+            "got: {html}"
+        );
+
+        let broken = "---\ntitle: [unclosed\n---\ntext\n";
+        let (meta, html) = parse_markdown_with_frontmatter(broken, "light");
+        assert!(meta.is_none());
+        assert_eq!(html, parse_markdown_with_theme(broken, "light"));
+
+        let (meta, _) = parse_markdown_with_frontmatter("# No front matter\n", "light");
+        assert!(meta.is_none());
+    }
+
+    #[test]
+    fn csv_renders_quoted_fields_and_pads_ragged_rows() {
+        let src =
+            "name,notes\r\n\"Doe, Jane\",\"line one\nline \"\"two\"\"\"\r\nsolo\n<b>,x,extra\n";
+        let html = parse_csv_with_theme(src, "light").unwrap();
+        assert!(html.contains("<thead>\n<tr><th>name</th><th>notes</th><th></th></tr>"));
+        assert!(html.contains("<td>Doe, Jane</td><td>line one\nline &quot;two&quot;</td><td></td>"));
+        assert!(html.contains("<tr><td>solo</td><td></td><td></td></tr>"));
+        assert!(html.contains("<td>&lt;b&gt;</td><td>x</td><td>extra</td>"));
+        assert_eq!(html.matches("<tr>").count(), 4, "got: {html}");
+
+        let err = parse_csv_with_theme("a,b\n1,\"open\n", "light").unwrap_err();
+        assert!(err.contains("line 2"), "got: {err}");
+        assert!(parse_csv_with_theme("", "light")
+            .unwrap()
+            .contains("<tbody>\n</tbody>"));
+    }
+
+    #[test]
+    fn toml_is_validated_reformatted_and_highlighted() {
+        let src = "[package]\nname=\"demo\"  # trailing\nversion = \"0.1.0\"\n\n[dependencies]\nserde = { version = \"1\" }\n";
+        let html = parse_toml_with_theme(src, "light").unwrap();
+        assert!(html.contains("language-toml"), "got: {html}");
+        let package = html.find("package").unwrap();
+        let deps = html.find("dependencies").unwrap();
+        assert!(package < deps, "source order kept: {html}");
+        assert!(!html.contains("trailing"));
+
+        let err = parse_toml_with_theme("[package\nname = 1\n", "light").unwrap_err();
+        assert!(err.starts_with("Invalid TOML: "), "got: {err}");
+    }
+
+    #[test]
+    fn xml_is_reindented_with_comments_and_cdata_verbatim() {
+        let src = "<?xml version=\"1.0\"?>\n<root a=\"1\"><item>one</item><empty/>\n<!--  keep\n   me  --><code><![CDATA[ if (a < b)\n  { x(); } ]]></code><pair></pair></root>";
+        let pretty = reindent_xml(src).unwrap();
+        assert_eq!(
+            pretty,
+            "<?xml version=\"1.0\"?>\n<root a=\"1\">\n  <item>one</item>\n  <empty/>\n  <!--  keep\n   me  -->\n  <code>\n    <![CDATA[ if (a < b)\n  { x(); } ]]>\n  </code>\n  <pair></pair>\n</root>\n"
+        );
+        let html = parse_xml_with_theme(src, "light").unwrap();
+        assert!(html.starts_with("<div class=\"highlight\"><pre><code class=\"language-xml\">"));
+
+        assert!(reindent_xml("<a><b></a>")
+            .unwrap_err()
+            .starts_with("Invalid XML"));
+        let err = reindent_xml("<a><b/>").unwrap_err();
+        assert!(err.contains("<a> is never closed"), "got: {err}");
+    }
+
+    #[test]
+    fn front_matter_title_needs_a_non_blank_scalar() {
+        assert_eq!(
+            front_matter_title("---\ntitle: \" Release Notes \"\n---\n# H\n").as_deref(),
+            Some("Release Notes")
+        );
+        assert_eq!(
+            front_matter_title("---\ntitle: 2026\n---\n").as_deref(),
+            Some("2026")
+        );
+        assert_eq!(front_matter_title("---\ntitle: \"\"\n---\n"), None);
+        assert_eq!(front_matter_title("---\ntitle: [a]\n---\n"), None);
+        assert_eq!(front_matter_title("---\ntitle: x\n"), None);
+        assert_eq!(front_matter_title("# title: x\n"), None);
+    }
+
+    #[test]
+    fn front_matter_splits_only_when_closed() {
+        let (fm, body) = split_front_matter("---\ntitle: x\n---\n# Body\n");
+        assert_eq!(fm, Some("title: x\n"));
+        assert_eq!(body, "# Body\n");
+
+        let (fm, body) = split_front_matter("---\r\na: 1\r\n...\r\ntext");
+        assert_eq!(fm, Some("a: 1\r\n"));
+        assert_eq!(body, "text");
+
+        let unclosed = "---\nnot front matter\n";
+        assert_eq!(split_front_matter(unclosed), (None, unclosed));
+        assert_eq!(
+            split_front_matter("# No FM\n---\n"),
+            (None, "# No FM\n---\n")
+        );
+    }
+
+    #[test]
+    fn document_overrides_read_known_boltpage_keys() {
+        let src = "---\ntitle: Doc\nboltpage: { theme: dark, line_numbers: true, preset: Obsidian, word_wrap: maybe, extra: 1 }\n---\nbody\n";
+        let overrides = DocumentOverrides::from_markdown(src);
+        assert_eq!(
+            overrides,
+            DocumentOverrides {
+                theme: Some("dark".to_string()),
+                line_numbers: Some(true),
+                word_wrap: None,
+                preset: Some(MarkdownPreset::Obsidian),
+            }
+        );
+        assert_eq!(
+            DocumentOverrides::from_markdown("---\ntitle: Doc\n---\n"),
+            DocumentOverrides::default()
+        );
+        assert_eq!(
+            DocumentOverrides::from_front_matter(": : not yaml ["),
+            DocumentOverrides::default()
+        );
+    }
+
+    #[test]
+    fn details_keep_open_and_render_nested_markdown() {
+        let raw = parse_markdown(
+            "<details open>\n<summary>FAQ</summary>\n\n**bold** answer\n\n</details>\n",
+        );
+        assert!(raw.contains("<details open=\"\">"), "got: {raw}");
+        assert!(raw.contains("<summary>FAQ</summary>"), "got: {raw}");
+        assert!(raw.contains("<strong>bold</strong>"), "got: {raw}");
+
+        let fenced =
+            parse_markdown("```collapse Spoiler <b>\n- item `x`\n\n```rust\nfn f() {}\n```\n");
+        assert!(
+            fenced.contains("<details><summary>Spoiler &lt;b&gt;</summary>"),
+            "got: {fenced}"
+        );
+        assert!(
+            fenced.contains("<li>item <code>x</code></li>"),
+            "got: {fenced}"
+        );
+
+        let untitled = parse_markdown("```collapse\ntext\n```\n");
+        assert!(
+            untitled.contains("<summary>Details</summary>"),
+            "got: {untitled}"
+        );
+        assert!(!parse_markdown("```collapsed\nx\n```\n").contains("<details>"));
+    }
+
+    #[test]
+    fn log_level_finds_the_first_level_word_near_the_line_start() {
+        assert_eq!(
+            log_level("2024-05-01T10:00:00Z ERROR db: connection refused"),
+            Some("error")
+        );
+        assert_eq!(log_level("[WARN] disk 91% full"), Some("warn"));
+        assert_eq!(log_level("level=INFO msg=\"DEBUG flag set\""), Some("info"));
+        assert_eq!(log_level("FATAL: out of memory"), Some("error"));
+        assert_eq!(log_level("12:00 TRACE enter"), Some("trace"));
+        // Lower-case words, words inside identifiers, and levels far into the
+        // message don't count.
+        assert_eq!(log_level("no error here"), None);
+        assert_eq!(log_level("INFORMATION_SCHEMA query"), None);
+        let late = format!("{} ERROR", "x".repeat(LOG_LEVEL_SCAN_BYTES));
+        assert_eq!(log_level(&late), None);
+        let wide = format!("{}ERROR", "é".repeat(LOG_LEVEL_SCAN_BYTES / 2));
+        assert_eq!(log_level(&wide), None);
+    }
+
+    #[test]
+    fn linkify_escaped_text_links_urls_without_escaping_the_href() {
+        let text = "See https://a.example/x?q=1&r=2. (https://b.example/p) \
+                    https://c.example/\"onmouseover=alert(1) <https://d.example/>";
+        let out = linkify_escaped_text(&escape_html(text));
+        assert!(
+            out.contains(
+                r#"<a href="https://a.example/x?q=1&amp;r=2">https://a.example/x?q=1&amp;r=2</a>."#
+            ),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"(<a href="https://b.example/p">https://b.example/p</a>)"#),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"<a href="https://c.example/">https://c.example/</a>&quot;onmouseover"#),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"&lt;<a href="https://d.example/">https://d.example/</a>&gt;"#),
+            "got: {out}"
+        );
+        assert_eq!(
+            linkify_escaped_text("http:// and a&amp;b"),
+            "http:// and a&amp;b"
+        );
+    }
+
+    #[test]
+    fn mark_whitespace_spans_text_but_not_markup() {
+        let out = mark_whitespace("<span class=\"a b\">x\ty</span> \nz");
+        assert_eq!(
+            out,
+            "<span class=\"a b\">x<span class=\"ws ws-tab\">\t</span>y</span>\
+             <span class=\"ws ws-space ws-trailing\"> </span>\nz"
+        );
+        assert_eq!(
+            mark_whitespace("a b"),
+            "a<span class=\"ws ws-space\"> </span>b"
+        );
+    }
+
+    #[test]
+    fn whitespace_source_view_keeps_original_layout() {
+        let out = highlight_source_with_whitespace("a:\n\t- b  \n", "yaml").unwrap();
+        assert!(out.contains("ws-tab"), "got: {out}");
+        assert_eq!(out.matches("ws-trailing").count(), 2, "got: {out}");
+        assert!(highlight_source_with_whitespace("x", "txt").is_err());
+    }
+
+    #[test]
+    fn highlight_snippet_matches_fenced_blocks_and_falls_back() {
+        let snippet = highlight_snippet("fn main() {}\n", "rs", "light");
+        let fenced = parse_markdown("```rs\nfn main() {}\n```\n");
+        assert!(snippet.starts_with("<div class=\"highlight\"><pre><code class=\"language-rs\">"));
+        assert!(snippet.contains("<span class=\""), "got: {snippet}");
+        assert!(fenced.contains(&snippet), "got: {fenced}");
+
+        let plain = highlight_snippet("<b> & </b>", "no-such-lang", "dark");
+        assert_eq!(
+            plain,
+            "<div class=\"highlight\"><pre><code class=\"language-no-such-lang\">&lt;b&gt; &amp; &lt;/b&gt;</code></pre></div>"
+        );
+        assert!(highlight_snippet("x", "", "light").contains("language-text"));
+        assert!(highlight_snippet("x", "\"><script>", "light")
+            .contains("language-&quot;&gt;&lt;script&gt;"));
+    }
+
+    #[test]
+    fn markdown_source_view_is_verbatim() {
+        let out = highlight_source("# Title\n\n*em*\n", "md").unwrap();
+        assert!(out.contains("language-markdown"), "got: {out}");
+        assert!(!out.contains("<h1"), "got: {out}");
+        assert!(out.contains("Title") && out.contains("em"), "got: {out}");
+    }
+
+    /// Golden files live in `fixtures/canonical/`; run with
+    /// `UPDATE_GOLDEN=1` to rewrite the `.html` side after an intended change.
+    #[test]
+    fn canonical_render_matches_golden_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/canonical");
+        for name in ["gfm", "headings", "code"] {
+            let input = std::fs::read_to_string(dir.join(format!("{name}.md"))).unwrap();
+            let golden = dir.join(format!("{name}.html"));
+            let out = render_markdown_canonical(&input);
+            if std::env::var_os("UPDATE_GOLDEN").is_some() {
+                std::fs::write(&golden, &out).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&golden).unwrap();
+            assert_eq!(out, expected, "{name}.md drifted from {name}.html");
+        }
+    }
+
+    #[test]
+    fn link_rewrites_touch_destinations_only() {
+        let (links, errors) = LinkRewriter::new([
+            ("^/docs/", "https://site.example/docs/"),
+            ("(", "never"),
+            (r"\.md$", ".html"),
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pattern, "(");
+        let src = "See [guide](/docs/guide.md) and ![logo](/docs/logo.png). /docs/ stays.";
+        let out = parse_markdown_with_links(src, "light", MarkdownOptions::default(), &links);
+        assert!(
+            out.contains(r#"href="https://site.example/docs/guide.html""#),
+            "got: {out}"
+        );
+        assert!(
+            out.contains(r#"src="https://site.example/docs/logo.png""#),
+            "got: {out}"
+        );
+        assert!(out.contains("/docs/ stays"), "got: {out}");
+    }
+
+    #[test]
+    fn foldable_headings_nest_sections_by_level() {
+        let opts = MarkdownOptions {
+            foldable_headings: true,
+            ..MarkdownOptions::default()
+        };
+        let src = "# A\n\nintro\n\n## B\n\nb\n\n### C\n\nc\n\n## D\n\n> # quoted\n\n# E\n";
+        let out = parse_markdown_with_options(src, "light", opts);
+        let tags: Vec<&str> = out
+            .split('<')
+            .filter_map(|t| {
+                ["details open", "/details", "h1", "h2", "h3"]
+                    .into_iter()
+                    .find(|p| t.starts_with(p))
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                "details open",
+                "h1", // A
+                "details open",
+                "h2", // B, inside A
+                "details open",
+                "h3",
+                "/details", // C, closed by D
+                "/details",
+                "details open",
+                "h2", // D replaces B
+                "h1", // the blockquoted heading doesn't fold
+                "/details",
+                "/details",
+                "details open",
+                "h1",
+                "/details",
+            ],
+            "got: {out}"
+        );
+        assert!(
+            out.contains("<summary>\n<h2 id=\"b\">B</h2>\n</summary>"),
+            "got: {out}"
+        );
+        assert!(!parse_markdown(src).contains("<details"));
+    }
+
+    #[test]
+    fn line_anchors_mark_each_top_level_block_in_place() {
+        let src = "# Title\n\nPara one\nstill one\n\n> [!NOTE]\n> Heads up\n\n```rust\nfn main() {}\n```\n\n- a\n- b\n\n---\n";
+        let (html, lines) = parse_markdown_with_line_anchors(
+            src,
+            "light",
+            MarkdownOptions::default(),
+            &LinkRewriter::default(),
+            2,
+        );
+        assert_eq!(lines, [3, 5, 8, 11, 15, 18]);
+        for expected in [
+            r#"<h1 data-source-line="3" id="title">"#,
+            r#"<p data-source-line="5">Para one"#,
+            r#"<div data-source-line="8" class="callout callout-note">"#,
+            r#"<ul data-source-line="15">"#,
+            r#"<hr data-source-line="18">"#,
+        ] {
+            assert!(html.contains(expected), "missing {expected}: {html}");
+        }
+        assert!(html.contains(r#"data-source-line="11""#), "got: {html}");
+        assert!(!html.contains("boltpage-source-line"), "got: {html}");
+        assert_eq!(html.matches("data-source-line").count(), lines.len());
+        // Nothing but the attributes differs from the normal render.
+        let plain = parse_markdown_with_options(src, "light", MarkdownOptions::default());
+        let stripped = regex::Regex::new(r#" data-source-line="\d+""#)
+            .unwrap()
+            .replace_all(&html, "");
+        assert_eq!(stripped, plain);
+    }
+
+    #[test]
+    fn line_anchors_option_marks_normal_chunked_and_folded_renders() {
+        let src = "# Title\n\nPara\n\n## Sub\n\n- a\n- b\n";
+        let opts = MarkdownOptions {
+            line_anchors: true,
+            ..MarkdownOptions::default()
+        };
+        let links = LinkRewriter::default();
+        let (anchored, _) = parse_markdown_with_line_anchors(src, "light", opts, &links, 0);
+        assert_eq!(
+            parse_markdown_with_links(src, "light", opts, &links),
+            anchored
+        );
+        let chunked = parse_markdown_chunked(src, "light", opts, &links, 8, |_| {});
+        assert_eq!(chunked, anchored);
+
+        let folded = parse_markdown_with_links(
+            src,
+            "light",
+            MarkdownOptions {
+                foldable_headings: true,
+                ..opts
+            },
+            &links,
+        );
+        for expected in [
+            r#"<details data-source-line="1" open="">"#,
+            r#"<p data-source-line="3">"#,
+            r#"<details data-source-line="5" open="">"#,
+            r#"<ul data-source-line="7">"#,
+        ] {
+            assert!(folded.contains(expected), "missing {expected}: {folded}");
+        }
+        assert!(!folded.contains("boltpage-source-line"), "got: {folded}");
+    }
+
+    #[test]
+    fn heading_ids_match_toc_entries() {
+        let src = "# Intro\n\n## Setup & `cargo`\n\n## Setup & `cargo`\n\ntext\n";
+        let toc = build_toc(src, MarkdownOptions::default(), None);
+        let ids: Vec<&str> = toc.iter().map(|e| e.slug.as_deref().unwrap()).collect();
+        assert_eq!(ids, ["intro", "setup--cargo", "setup--cargo-1"]);
+        assert_eq!(toc[1].text, "Setup & cargo");
+        assert_eq!(toc[1].level, 2);
+
+        let html = parse_markdown_with_theme(src, "dark");
+        for id in ids {
+            assert!(html.contains(&format!("id=\"{id}\"")), "got: {html}");
+        }
+        let strict = MarkdownOptions::from(MarkdownPreset::CommonMark);
+        assert!(!parse_markdown_with_options(src, "light", strict).contains("id="));
+        assert!(build_toc(src, strict, None)
+            .iter()
+            .all(|e| e.slug.is_none()));
+    }
+
+    #[test]
+    fn toc_max_level_skips_deep_headings_but_keeps_slugs() {
+        let src = "# Top\n\n### Notes\n\n## Notes\n\n#### Deep\n";
+        let toc = build_toc(src, MarkdownOptions::default(), Some(2));
+        let got: Vec<(u8, &str)> = toc
+            .iter()
+            .map(|e| (e.level, e.slug.as_deref().unwrap()))
+            .collect();
+        // The skipped h3 still took "notes", so the h2 matches the render.
+        assert_eq!(got, [(1, "top"), (2, "notes-1")]);
+        assert_eq!(build_toc(src, MarkdownOptions::default(), None).len(), 4);
+    }
+
+    #[test]
+    fn collapse_fence_headings_share_the_document_slugs() {
+        let src = "## Notes\n\n```collapse\n## Notes\n```\n\n## Notes\n";
+        let toc = build_toc(src, MarkdownOptions::default(), None);
+        let ids: Vec<&str> = toc.iter().map(|e| e.slug.as_deref().unwrap()).collect();
+        assert_eq!(ids, ["notes", "notes-1", "notes-2"]);
+
+        let html = parse_markdown_with_theme(src, "dark");
+        for id in ids {
+            assert_eq!(
+                html.matches(&format!("id=\"{id}\"")).count(),
+                1,
+                "got: {html}"
+            );
+        }
+    }
+
+    #[test]
+    fn streamed_json_matches_serde_pretty_output() {
+        let src = r#"{"a": [1, 2.5, {"b": null, "c": "x\"}y"}], "e": {}, "f": [], "g": true}"#;
+        let streamed = pretty_print_json_stream(src.as_bytes(), usize::MAX, |_| {}).unwrap();
+        let value: serde_json_crate::Value = serde_json_crate::from_str(src).unwrap();
+        assert_eq!(
+            streamed.text,
+            serde_json_crate::to_string_pretty(&value).unwrap()
+        );
+        assert!(!streamed.truncated);
+        // root, a, 1, 2.5, inner object, null, "x}y", e, f, true
+        assert_eq!(streamed.nodes, 10);
+
+        let with_bom = format!("\u{feff}{src}");
+        let streamed_bom = pretty_print_json_stream(with_bom.as_bytes(), usize::MAX, |_| {});
+        assert_eq!(streamed_bom.unwrap().text, streamed.text);
+    }
+
+    #[test]
+    fn streamed_json_truncates_at_node_limit_and_rejects_mismatches() {
+        let src = "[1, 2, 3, 4, 5]";
+        let mut reports = Vec::new();
+        let out = pretty_print_json_stream(src.as_bytes(), 3, |n| reports.push(n)).unwrap();
+        assert!(out.truncated);
+        assert_eq!(out.nodes, 3);
+        assert_eq!(out.text, "[\n  1,\n  2,\n  ");
+        assert!(!reports.is_empty());
+
+        assert!(pretty_print_json_stream("[1}".as_bytes(), 10, |_| {}).is_err());
+        assert!(pretty_print_json_stream("{\"a\": [".as_bytes(), 10, |_| {}).is_err());
+    }
+
+    #[test]
+    fn streamed_json_rejects_malformed_token_order() {
+        for src in [
+            "",
+            "[1,2,,3]",
+            "[1,]",
+            "[,1]",
+            "[1 2]",
+            "{\"a\" \"b\"}",
+            "{\"a\": 1,}",
+            "{\"a\"}",
+            "{\"a\":: 1}",
+            "{1: 2}",
+            "{\"a\": 1 \"b\": 2}",
+            "[\"a\": 1]",
+            "nul",
+            "[True]",
+            "[01]",
+            "[1.]",
+            "[-]",
+            "[1e]",
+            "[] []",
+            "1 2",
+            "{}}",
+        ] {
+            assert!(
+                pretty_print_json_stream(src.as_bytes(), 100, |_| {}).is_err(),
+                "accepted {src:?}"
+            );
+        }
+        for src in [
+            "0",
+            "-0.5e+10",
+            "\"s\"",
+            " null ",
+            "[[], {}]",
+            "{\"a\": {\"b\": [true, false]}}",
+        ] {
+            assert!(
+                pretty_print_json_stream(src.as_bytes(), 100, |_| {}).is_ok(),
+                "rejected {src:?}"
+            );
+        }
+    }
 }
@@ -1,5 +1,7 @@
 // Event names (emitted via app.emit)
 pub const EVENT_FILE_CHANGED: &str = "file-changed";
+pub const EVENT_FILE_REMOVED: &str = "file-removed";
+pub const EVENT_FILE_SAVED_AS: &str = "file-saved-as";
 pub const EVENT_THEME_CHANGED: &str = "theme-changed";
 pub const EVENT_FONT_SIZE_CHANGED: &str = "font-size-changed";
 pub const EVENT_FONT_FAMILY_CHANGED: &str = "font-family-changed";
@@ -7,8 +9,18 @@ pub const EVENT_TOOLBAR_DENSITY_CHANGED: &str = "toolbar-density-changed";
 pub const EVENT_EDITOR_WINDOW_CLOSED: &str = "editor-window-closed";
 pub const EVENT_EDITOR_BUFFER_CHANGED: &str = "editor-buffer-changed";
 pub const EVENT_SCROLL_SYNC: &str = "scroll-sync";
+pub const EVENT_RENDER_PROGRESS: &str = "render-progress";
+pub const EVENT_RENDER_COMPLETE: &str = "render-complete";
+pub const EVENT_LARGE_FILE_WARNING: &str = "large-file-warning";
+pub const EVENT_SYNTAX_THEMES_CHANGED: &str = "syntax-themes-changed";
+pub const EVENT_ZEN_MODE_CHANGED: &str = "zen-mode-changed";
+pub const EVENT_TAIL_MODE_CHANGED: &str = "tail-mode-changed";
+pub const EVENT_INLINE_EDITOR_OPEN: &str = "inline-editor-open";
+pub const EVENT_INLINE_EDITOR_CLOSED: &str = "inline-editor-closed";
+pub const EVENT_CUSTOM_CSS_CHANGED: &str = "custom-css-changed";
 pub const EVENT_MENU_OPEN: &str = "menu-open";
 pub const EVENT_MENU_OPEN_FOLDER: &str = "menu-open-folder";
+pub const EVENT_MENU_NEW_FROM_CLIPBOARD: &str = "menu-new-from-clipboard";
 pub const EVENT_MENU_CLOSE: &str = "menu-close";
 pub const EVENT_MENU_FIND: &str = "menu-find";
 pub const EVENT_MENU_FIND_NEXT: &str = "menu-find-next";
@@ -16,6 +28,7 @@ pub const EVENT_MENU_FIND_PREV: &str = "menu-find-prev";
 pub const EVENT_MENU_FIND_USE_SELECTION: &str = "menu-find-use-selection";
 pub const EVENT_MENU_FIND_REPLACE: &str = "menu-find-replace";
 pub const EVENT_MENU_EXPORT_HTML: &str = "menu-export-html";
+pub const EVENT_MENU_EXPORT_PDF: &str = "menu-export-pdf";
 pub const EVENT_MENU_FORMAT_BOLD: &str = "menu-format-bold";
 pub const EVENT_MENU_FORMAT_ITALIC: &str = "menu-format-italic";
 pub const EVENT_MENU_FORMAT_LINK: &str = "menu-format-link";
@@ -27,6 +40,7 @@ pub const EVENT_MENU_PRINT: &str = "menu-print";
 // Menu IDs (used in rebuild_app_menu and on_menu_event)
 pub const MENU_NEW_FILE: &str = "new-file";
 pub const MENU_NEW_WINDOW: &str = "new-window";
+pub const MENU_NEW_FROM_CLIPBOARD: &str = "new-from-clipboard";
 pub const MENU_OPEN: &str = "open";
 pub const MENU_OPEN_FOLDER: &str = "open-folder";
 pub const MENU_PRINT: &str = "print";
@@ -50,19 +64,27 @@ pub const MENU_ABOUT: &str = "about";
 // The Clear id must NOT share the prefix or strip_prefix would decode it.
 pub const MENU_RECENT_PREFIX: &str = "recent-file-";
 pub const MENU_RECENT_CLEAR: &str = "recent-clear";
+pub const MENU_REOPEN_CLOSED: &str = "reopen-closed";
+pub const MENU_ZOOM_IN: &str = "zoom-in";
+pub const MENU_ZOOM_OUT: &str = "zoom-out";
+pub const MENU_ZOOM_RESET: &str = "zoom-reset";
+pub const MENU_ALWAYS_ON_TOP: &str = "always-on-top";
+pub const MENU_TOGGLE_MAXIMIZE: &str = "toggle-maximize";
+pub const MENU_CENTER_WINDOW: &str = "center-window";
+pub const MENU_REVEAL_FILE: &str = "reveal-file";
 
-// Scroll sync kinds (ScrollSyncPayload.kind) — used by JS only
-#[allow(dead_code)]
+// Document kinds (ScrollSyncPayload.kind, OpenVerdict.kind)
 pub const KIND_MARKDOWN: &str = "markdown";
-#[allow(dead_code)]
 pub const KIND_JSON: &str = "json";
-#[allow(dead_code)]
 pub const KIND_YAML: &str = "yaml";
-#[allow(dead_code)]
+pub const KIND_TOML: &str = "toml";
+pub const KIND_XML: &str = "xml";
 pub const KIND_TXT: &str = "txt";
+pub const KIND_CSV: &str = "csv";
+pub const KIND_PDF: &str = "pdf";
 
 // Recent files cap (most-recent first)
-pub const MAX_RECENT_FILES: usize = 10;
+pub const MAX_RECENT_FILES: usize = 15;
 
 // Window label prefixes
 pub const WINDOW_PREFIX_MARKDOWN: &str = "markdown-";
@@ -0,0 +1,109 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Error for commands whose failures the frontend needs to tell apart.
+/// Serializes as `{ "code": "not_found", "message": "…" }`; `message` is what
+/// a String error would have said.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AppError {
+    NotFound(String),
+    /// Denied by the OS, or a path the app was never granted.
+    PermissionDenied(String),
+    /// Bytes that aren't text in any encoding we decode.
+    InvalidEncoding(String),
+    /// The document couldn't be parsed or rendered as its kind.
+    ParseError(String),
+    /// A file type BoltPage doesn't open.
+    Unsupported(String),
+    /// Over the `max_open_size_mb` limit and not confirmed.
+    TooLarge(String),
+    Io(String),
+}
+
+impl AppError {
+    /// Stable snake_case name of the variant, for `code`.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::InvalidEncoding(_) => "invalid_encoding",
+            Self::ParseError(_) => "parse_error",
+            Self::Unsupported(_) => "unsupported",
+            Self::TooLarge(_) => "too_large",
+            Self::Io(_) => "io",
+        }
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        match self {
+            Self::NotFound(m)
+            | Self::PermissionDenied(m)
+            | Self::InvalidEncoding(m)
+            | Self::ParseError(m)
+            | Self::Unsupported(m)
+            | Self::TooLarge(m)
+            | Self::Io(m) => m,
+        }
+    }
+
+    /// Classify an I/O failure by its kind; the message reads
+    /// "`context`: `e`", like the String errors it replaces.
+    pub(crate) fn from_io(context: &str, e: &std::io::Error) -> Self {
+        let message = format!("{context}: {e}");
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(message),
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied(message),
+            std::io::ErrorKind::InvalidData => Self::InvalidEncoding(message),
+            _ => Self::Io(message),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", self.message())?;
+        s.end()
+    }
+}
+
+/// Lets String-returning callers use `?` on AppError results.
+impl From<AppError> for String {
+    fn from(e: AppError) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_and_message_and_classifies_io_kinds() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let err = AppError::from_io("Failed to read file", &missing);
+        assert_eq!(err.code(), "not_found");
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "not_found");
+        assert_eq!(json["message"], err.to_string());
+        assert!(err.to_string().starts_with("Failed to read file: "));
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(AppError::from_io("x", &denied).code(), "permission_denied");
+        let other = std::io::Error::other("disk on fire");
+        assert_eq!(
+            AppError::from_io("x", &other),
+            AppError::Io("x: disk on fire".to_string())
+        );
+        assert_eq!(String::from(AppError::Io("plain".to_string())), "plain");
+    }
+}
@@ -1,14 +1,29 @@
 use base64::Engine;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use lru::LruCache;
-use std::collections::HashSet;
+use markrust_core::{
+    DocumentOverrides, LinkRewriter, MarkdownOptions, MarkdownPreset, SanitizeLevel, TocEntry,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Manager};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_store::StoreExt;
 use url::Url;
 
-use crate::constants::MAX_RECENT_FILES;
+use crate::constants::{
+    EVENT_CUSTOM_CSS_CHANGED, EVENT_FILE_SAVED_AS, EVENT_LARGE_FILE_WARNING, EVENT_RENDER_COMPLETE,
+    EVENT_RENDER_PROGRESS, KIND_CSV, KIND_JSON, KIND_MARKDOWN, KIND_PDF, KIND_TOML, KIND_TXT,
+    KIND_XML, KIND_YAML, MAX_RECENT_FILES,
+};
+use crate::error::AppError;
+use crate::prefs::{self, LinkRewriteRule, ViewMode};
 use crate::AppState;
 
 // --- Path helpers ---
@@ -136,13 +151,34 @@ pub(crate) fn allow_path(app: &AppHandle, path: &str) {
 }
 
 /// Record `path` at the head of the recent-files list (most-recent first),
-/// deduplicated and capped at MAX_RECENT_FILES. Holds pref_lock for the full
-/// read-modify-write so concurrent file-open events cannot lose entries.
+/// deduplicated and capped at MAX_RECENT_FILES.
 pub(crate) async fn push_to_recents(app: &AppHandle, path: &str) -> Result<(), String> {
     let canonical = fs::canonicalize(path)
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_else(|_| path.to_string());
 
+    update_recents(app, |vec| {
+        vec.retain(|p| p != &canonical);
+        vec.insert(0, canonical);
+        vec.truncate(MAX_RECENT_FILES);
+    })
+    .await?;
+
+    // Keep the File > Open Recent submenu current with every recents mutation.
+    let _ = crate::menu::rebuild_app_menu(app);
+
+    Ok(())
+}
+
+/// Drop `missing` (files found gone while building the menu) from the
+/// recent-files list. The menu already skipped them, so no rebuild.
+pub(crate) async fn prune_recents(app: &AppHandle, missing: &[String]) -> Result<(), String> {
+    update_recents(app, |vec| vec.retain(|p| !missing.contains(p))).await
+}
+
+/// Apply `f` to the stored recent-files list. Holds pref_lock for the full
+/// read-modify-write so concurrent file-open events cannot lose entries.
+async fn update_recents(app: &AppHandle, f: impl FnOnce(&mut Vec<String>)) -> Result<(), String> {
     let state = app.state::<AppState>();
     let _lock = state.pref_lock.lock().await;
 
@@ -162,9 +198,7 @@ pub(crate) async fn push_to_recents(app: &AppHandle, path: &str) -> Result<(), S
         .and_then(|v| serde_json::from_value(v.clone()).ok())
         .unwrap_or_default();
 
-    vec.retain(|p| p != &canonical);
-    vec.insert(0, canonical);
-    vec.truncate(MAX_RECENT_FILES);
+    f(&mut vec);
 
     map.insert(
         "recent_files".into(),
@@ -174,13 +208,7 @@ pub(crate) async fn push_to_recents(app: &AppHandle, path: &str) -> Result<(), S
     store.set("preferences", serde_json::Value::Object(map));
     store
         .save()
-        .map_err(|e| format!("Failed to save preferences: {e}"))?;
-
-    drop(_lock);
-    // Keep the File > Open Recent submenu current with every recents mutation.
-    let _ = crate::menu::rebuild_app_menu(app);
-
-    Ok(())
+        .map_err(|e| format!("Failed to save preferences: {e}"))
 }
 
 /// Mutate the ordered session list ("session_files" pref) under pref_lock.
@@ -281,6 +309,117 @@ pub(crate) async fn open_tracked_file(
     Ok(())
 }
 
+// --- Open-target validation ---
+
+/// Largest file the viewer will open. JSON past `LARGE_JSON_THRESHOLD` is
+/// streamed, but everything else is read whole into memory.
+pub(crate) const MAX_OPEN_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Whether a path is safe to hand to the renderer, and as which kind.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub(crate) struct OpenVerdict {
+    pub ok: bool,
+    /// Frontend kind (`markdown`, `json`, `yaml`, `txt`, `pdf`) when `ok`.
+    pub kind: Option<String>,
+    pub size: u64,
+    /// User-facing explanation when refused.
+    pub reason: Option<String>,
+}
+
+impl OpenVerdict {
+    fn refuse(size: u64, reason: String) -> Self {
+        Self {
+            ok: false,
+            kind: None,
+            size,
+            reason: Some(reason),
+        }
+    }
+}
+
+/// Same extension rules the frontend uses to pick a renderer.
+pub(crate) fn detect_kind(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" => KIND_PDF,
+        "json" => KIND_JSON,
+        "yaml" | "yml" => KIND_YAML,
+        "toml" => KIND_TOML,
+        "xml" => KIND_XML,
+        "txt" | "log" => KIND_TXT,
+        "csv" => KIND_CSV,
+        _ => KIND_MARKDOWN,
+    }
+}
+
+/// Refuses anything that is not a regular file: FIFOs and character devices
+/// would block `read_to_string` forever, sockets and directories just fail
+/// with a confusing error. Symlinks are followed.
+pub(crate) fn validate_open_target_inner(path: &Path, max_bytes: u64) -> OpenVerdict {
+    let name = path.display();
+    let meta = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return OpenVerdict::refuse(0, format!("{name} does not exist"));
+        }
+        Err(e) => return OpenVerdict::refuse(0, format!("Cannot open {name}: {e}")),
+    };
+    let ft = meta.file_type();
+    if ft.is_dir() {
+        return OpenVerdict::refuse(0, format!("{name} is a folder, not a file"));
+    }
+    if !ft.is_file() {
+        #[cfg(unix)]
+        let what = {
+            use std::os::unix::fs::FileTypeExt;
+            if ft.is_fifo() {
+                "a named pipe"
+            } else if ft.is_socket() {
+                "a socket"
+            } else if ft.is_char_device() || ft.is_block_device() {
+                "a device"
+            } else {
+                "not a regular file"
+            }
+        };
+        #[cfg(not(unix))]
+        let what = "not a regular file";
+        return OpenVerdict::refuse(0, format!("{name} is {what} and can't be opened"));
+    }
+    let size = meta.len();
+    if size > max_bytes {
+        return OpenVerdict::refuse(
+            size,
+            format!(
+                "{name} is {} MiB; the limit is {} MiB",
+                size / (1024 * 1024),
+                max_bytes / (1024 * 1024)
+            ),
+        );
+    }
+    OpenVerdict {
+        ok: true,
+        kind: Some(detect_kind(path).to_string()),
+        size,
+        reason: None,
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn validate_open_target(
+    app: AppHandle,
+    path: String,
+) -> Result<OpenVerdict, String> {
+    check_path_allowed(&app, &path)?;
+    Ok(validate_open_target_inner(
+        Path::new(&path),
+        MAX_OPEN_FILE_BYTES,
+    ))
+}
+
 // --- File system utils ---
 
 pub(crate) fn paths_match(a: &Path, b: &Path) -> bool {
@@ -293,8 +432,229 @@ pub(crate) fn paths_match(a: &Path, b: &Path) -> bool {
     }
 }
 
+/// Whether a watcher event concerns `target`. Watchers sit on the parent
+/// directory, so siblings are rejected by file name before any
+/// canonicalization; a rename's destination counts like a write.
 pub(crate) fn event_targets_file(paths: &[PathBuf], target: &Path) -> bool {
-    paths.iter().any(|candidate| paths_match(candidate, target))
+    let Some(name) = target.file_name() else {
+        return false;
+    };
+    paths.iter().any(|candidate| {
+        candidate
+            .file_name()
+            .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            && paths_match(candidate, target)
+    })
+}
+
+// --- Include directives ---
+
+/// Includes nested deeper than this are refused.
+pub(crate) const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Target of an include directive, `{{include: path}}` or
+/// `<!-- include: path -->`, written alone on its line.
+fn include_target(line: &str) -> Option<&str> {
+    let t = line.trim();
+    let inner = t
+        .strip_prefix("{{")
+        .and_then(|r| r.strip_suffix("}}"))
+        .or_else(|| t.strip_prefix("<!--").and_then(|r| r.strip_suffix("-->")))?;
+    let target = inner.trim().strip_prefix("include:")?.trim();
+    (!target.is_empty()).then_some(target)
+}
+
+/// Replace include directives in `body` (the Markdown of `path`, front
+/// matter already removed) with the included files' bodies, recursively and
+/// relative to each including file. Returns the expanded text and every file
+/// pulled in. Directives inside fenced code are left as written.
+///
+/// Included files must sit inside the root document's folder; missing or
+/// out-of-tree targets render as an inline notice. A cycle or nesting past
+/// `MAX_INCLUDE_DEPTH` fails the whole render as a `ParseError`; an include
+/// that resolves but can't be read fails it as the I/O error it was, and one
+/// over `limit` (see `checked_open_size`) as TooLarge.
+pub(crate) fn expand_markdown_includes(
+    body: &str,
+    path: &Path,
+    limit: Option<u64>,
+) -> Result<(String, Vec<String>), AppError> {
+    expand_markdown_includes_mapped(body, path, limit).map(|e| (e.text, e.includes))
+}
+
+/// A Markdown body with its includes expanded.
+pub(crate) struct ExpandedMarkdown {
+    pub text: String,
+    /// Every file pulled in.
+    pub includes: Vec<String>,
+    /// The original body line (1-based) of each line of `text`; `None` for
+    /// lines an include added.
+    pub lines: Vec<Option<usize>>,
+}
+
+/// `expand_markdown_includes`, keeping where each expanded line came from.
+pub(crate) fn expand_markdown_includes_mapped(
+    body: &str,
+    path: &Path,
+    limit: Option<u64>,
+) -> Result<ExpandedMarkdown, AppError> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let root_dir = canonical
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| canonical.clone());
+    let mut stack = vec![canonical];
+    let mut included = Vec::new();
+    let mut lines = Vec::new();
+    let out = expand_includes_into(
+        body,
+        &root_dir,
+        &mut stack,
+        &mut included,
+        limit,
+        Some(&mut lines),
+    )?;
+    Ok(ExpandedMarkdown {
+        text: out,
+        includes: included,
+        lines,
+    })
+}
+
+fn expand_includes_into(
+    body: &str,
+    root_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<String>,
+    limit: Option<u64>,
+    mut lines: Option<&mut Vec<Option<usize>>>,
+) -> Result<String, AppError> {
+    let base = stack
+        .last()
+        .and_then(|p| p.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| root_dir.to_path_buf());
+    let mut out = String::with_capacity(body.len());
+    let mut fence: Option<(char, usize)> = None;
+    for (n, line) in body.split_inclusive('\n').enumerate() {
+        let bare = line.trim_end_matches(['\r', '\n']);
+        let target = match fence {
+            Some((ch, len)) => {
+                if fence_marker(bare).is_some_and(|(c, l)| c == ch && l >= len) {
+                    fence = None;
+                }
+                None
+            }
+            None => {
+                fence = fence_marker(bare);
+                fence.is_none().then(|| include_target(bare)).flatten()
+            }
+        };
+        let Some(target) = target else {
+            out.push_str(line);
+            if let Some(lines) = lines.as_deref_mut() {
+                lines.push(Some(n + 1));
+            }
+            continue;
+        };
+        let before = out.len();
+
+        let resolved = match fs::canonicalize(base.join(target)) {
+            Ok(p) if p.starts_with(root_dir) => p,
+            Ok(_) => {
+                out.push_str(&format!(
+                    "\n> **Include skipped:** `{target}` is outside this document's folder.\n\n"
+                ));
+                push_included_lines(lines.as_deref_mut(), &out[before..]);
+                continue;
+            }
+            Err(e) => {
+                out.push_str(&format!("\n> **Include failed:** `{target}`: {e}\n\n"));
+                push_included_lines(lines.as_deref_mut(), &out[before..]);
+                continue;
+            }
+        };
+        if stack.contains(&resolved) {
+            let chain: Vec<String> = stack
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .collect();
+            return Err(AppError::ParseError(format!(
+                "Include cycle: {}",
+                chain.join(" -> ")
+            )));
+        }
+        if stack.len() > MAX_INCLUDE_DEPTH {
+            return Err(AppError::ParseError(format!(
+                "Includes nested deeper than {MAX_INCLUDE_DEPTH} levels at `{target}`"
+            )));
+        }
+
+        checked_open_size(&resolved, limit)?;
+        let content = fs::read_to_string(&resolved)
+            .map_err(|e| AppError::from_io(&format!("Failed to read include `{target}`"), &e))?;
+        let (_, inner_body) = markrust_core::split_front_matter(&content);
+        let resolved_str = pathbuf_to_string(&resolved);
+        if !included.contains(&resolved_str) {
+            included.push(resolved_str);
+        }
+        stack.push(resolved);
+        let expanded = expand_includes_into(inner_body, root_dir, stack, included, limit, None)?;
+        stack.pop();
+
+        // Blank lines around the inclusion keep it from merging into the
+        // surrounding paragraph.
+        out.push('\n');
+        out.push_str(&expanded);
+        if !expanded.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push('\n');
+        push_included_lines(lines.as_deref_mut(), &out[before..]);
+    }
+    Ok(out)
+}
+
+/// Record the lines of `added`, text an include put in place of one body
+/// line, as coming from no body line.
+fn push_included_lines(lines: Option<&mut Vec<Option<usize>>>, added: &str) {
+    if let Some(lines) = lines {
+        lines.extend(std::iter::repeat_n(None, added.matches('\n').count()));
+    }
+}
+
+/// `html` with each `data-task-line` and `data-source-line`, a line of the
+/// expanded body, turned into a line of the file: mapped through `lines`
+/// (see `expand_markdown_includes_mapped`) and moved past `front_lines` of
+/// front matter. Elements from included files lose the attribute, so their
+/// checkboxes stay read-only.
+fn remap_source_lines(html: &str, lines: &[Option<usize>], front_lines: usize) -> String {
+    let html = remap_line_attr(html, " data-task-line=\"", lines, front_lines);
+    remap_line_attr(&html, " data-source-line=\"", lines, front_lines)
+}
+
+fn remap_line_attr(html: &str, attr: &str, lines: &[Option<usize>], front_lines: usize) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(pos) = rest.find(attr) {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + attr.len()..];
+        let Some(end) = after.find('"') else {
+            rest = &rest[pos..];
+            break;
+        };
+        let line = after[..end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| lines.get(n.checked_sub(1)?).copied().flatten());
+        if let Some(line) = line {
+            out.push_str(&format!("{attr}{}\"", line + front_lines));
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
 }
 
 // --- Cache ---
@@ -304,15 +664,183 @@ pub(crate) struct CacheKey {
     pub path: String,
     pub size: u64,
     pub mtime_secs: u64,
+    /// Effective dialect: the global preference unless front matter overrides it.
+    pub preset: MarkdownPreset,
+    pub overrides: DocumentOverrides,
+    /// Only ever true for plain-text and code kinds; Markdown ignores it.
+    pub show_whitespace: bool,
+    pub view: ViewMode,
+    pub link_rules: Vec<LinkRewriteRule>,
+    /// `json_preserve_formatting`; only ever true for JSON.
+    pub preserve_json: bool,
+    /// `show_code_line_numbers`.
+    pub code_line_numbers: bool,
+    /// `sanitize_level`; only affects Markdown.
+    pub sanitize: SanitizeLevel,
+    /// Effective `markdown_autolink` / `markdown_smart_punctuation`; only
+    /// ever true for Markdown.
+    pub autolink: bool,
+    pub smart_punctuation: bool,
+    /// `markdown_foldable_headings`; only ever true for Markdown.
+    pub foldable_headings: bool,
+    /// Effective `markdown_emoji`; only ever true for Markdown.
+    pub emoji: bool,
+    /// Block `data-source-line` anchors: Markdown rendered for a window, not
+    /// for export or the clipboard.
+    pub line_anchors: bool,
+    /// Plain-text links and wrapping; default for everything else.
+    pub plain_text: PlainTextOptions,
+    /// `hash_contents` of the source text when the filesystem reports no
+    /// mtime (`mtime_secs` 0), so a same-size rewrite there still misses;
+    /// None otherwise, where size and mtime already tell versions apart.
+    pub content_hash: Option<String>,
+}
+
+/// Rendered HTML plus what the idle sweeper needs: the LRU bounds entry
+/// count, `evict_idle_entries` bounds memory held by big, stale renders.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedHtml {
+    pub html: String,
+    pub last_access: Instant,
+    /// Files pulled in by include directives; a change to any of them
+    /// invalidates this entry too.
+    pub deps: Vec<String>,
+}
+
+impl CachedHtml {
+    pub(crate) fn new(html: String, deps: Vec<String>) -> Self {
+        Self {
+            html,
+            last_access: Instant::now(),
+            deps,
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.html.len()
+    }
+}
+
+pub(crate) const DEFAULT_CACHE_IDLE_TTL_SECS: u64 = 600;
+pub(crate) const DEFAULT_CACHE_IDLE_MIN_BYTES: usize = 1024 * 1024;
+/// How often the sweeper wakes; coarse on purpose, TTLs are minutes.
+pub(crate) const CACHE_SWEEP_INTERVAL_SECS: u64 = 60;
+pub(crate) const DEFAULT_HTML_CACHE_CAPACITY: usize = 50;
+/// Entries are whole rendered documents; past this a typo costs gigabytes.
+pub(crate) const MAX_HTML_CACHE_CAPACITY: usize = 2000;
+
+/// Validate a requested HTML cache capacity: zero and anything above
+/// `MAX_HTML_CACHE_CAPACITY` are rejected.
+pub(crate) fn html_cache_capacity(capacity: usize) -> Result<NonZeroUsize, String> {
+    if capacity > MAX_HTML_CACHE_CAPACITY {
+        return Err(format!(
+            "Cache capacity must be at most {MAX_HTML_CACHE_CAPACITY}, got {capacity}"
+        ));
+    }
+    NonZeroUsize::new(capacity).ok_or_else(|| "Cache capacity must be at least 1".to_string())
+}
+
+/// Apply the `cache_capacity` preference at startup. An invalid stored value
+/// is logged and the default kept.
+pub(crate) async fn apply_cache_capacity_pref(app: &AppHandle) {
+    let Some(capacity) = prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.cache_capacity)
+    else {
+        return;
+    };
+    match html_cache_capacity(capacity) {
+        Ok(cap) => app.state::<AppState>().html_cache.write().await.resize(cap),
+        Err(e) => eprintln!("Ignoring cache_capacity preference: {e}"),
+    }
+}
+
+/// Resize the HTML render cache and persist the new capacity. Shrinking
+/// evicts the least-recently-used entries immediately.
+#[tauri::command]
+pub(crate) async fn resize_html_cache(app: AppHandle, capacity: usize) -> Result<(), String> {
+    let cap = html_cache_capacity(capacity)?;
+    app.state::<AppState>().html_cache.write().await.resize(cap);
+    prefs::save_preference_key_inner(&app, "cache_capacity", serde_json::json!(capacity)).await
+}
+
+/// Drop entries of at least `min_bytes` not read since `now - ttl`. Small
+/// entries are left to the LRU. Returns the number evicted.
+pub(crate) fn evict_idle_entries(
+    cache: &mut LruCache<CacheKey, CachedHtml>,
+    now: Instant,
+    ttl: Duration,
+    min_bytes: usize,
+) -> usize {
+    let stale: Vec<CacheKey> = cache
+        .iter()
+        .filter(|(_, v)| v.size() >= min_bytes && now.duration_since(v.last_access) >= ttl)
+        .map(|(k, _)| k.clone())
+        .collect();
+    for key in &stale {
+        cache.pop(key);
+    }
+    stale.len()
+}
+
+/// Background task: every `CACHE_SWEEP_INTERVAL_SECS`, apply the
+/// `cache_idle_ttl_secs` / `cache_idle_min_bytes` preferences. Re-reads the
+/// preferences each pass so changes apply without a restart.
+pub(crate) async fn run_cache_sweeper(app: AppHandle) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(CACHE_SWEEP_INTERVAL_SECS)).await;
+        let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+        let ttl = Duration::from_secs(
+            prefs
+                .cache_idle_ttl_secs
+                .unwrap_or(DEFAULT_CACHE_IDLE_TTL_SECS),
+        );
+        let min_bytes = prefs
+            .cache_idle_min_bytes
+            .unwrap_or(DEFAULT_CACHE_IDLE_MIN_BYTES);
+        if let Some(state) = app.try_state::<AppState>() {
+            let mut cache = state.html_cache.write().await;
+            evict_idle_entries(&mut cache, Instant::now(), ttl, min_bytes);
+        }
+    }
+}
+
+/// Render cache effectiveness since launch or the last `reset_cache_stats`.
+#[derive(Debug, Serialize)]
+pub(crate) struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+#[tauri::command]
+pub(crate) async fn get_cache_stats(app: AppHandle) -> Result<CacheStats, String> {
+    let state = app.state::<AppState>();
+    let cache = state.html_cache.read().await;
+    Ok(CacheStats {
+        hits: state.cache_hits.load(Ordering::Relaxed),
+        misses: state.cache_misses.load(Ordering::Relaxed),
+        entries: cache.len(),
+        capacity: cache.cap().get(),
+    })
+}
+
+/// Zero the hit/miss counters; cached entries are kept.
+#[tauri::command]
+pub(crate) fn reset_cache_stats(app: AppHandle) {
+    let state = app.state::<AppState>();
+    state.cache_hits.store(0, Ordering::Relaxed);
+    state.cache_misses.store(0, Ordering::Relaxed);
 }
 
 pub(crate) fn remove_cache_entries_for_path(
-    cache: &mut LruCache<CacheKey, String>,
+    cache: &mut LruCache<CacheKey, CachedHtml>,
     file_path: &str,
 ) {
     let keys_to_remove: Vec<CacheKey> = cache
         .iter()
-        .filter(|(k, _)| k.path == file_path)
+        .filter(|(k, v)| k.path == file_path || v.deps.iter().any(|d| d == file_path))
         .map(|(k, _)| k.clone())
         .collect();
 
@@ -335,15 +863,114 @@ pub(crate) async fn invalidate_cache_for_path(app: &AppHandle, file_path: &str)
     }
 }
 
+// --- Stylesheet cache ---
+
+pub(crate) type StylesheetCache = HashMap<(String, Option<String>), String>;
+
+/// The `custom_css_path` preference.
+pub(crate) fn custom_css_path(app: &AppHandle) -> Option<String> {
+    prefs::read_string_pref(app, "custom_css_path").filter(|p| !p.is_empty())
+}
+
+/// Contents of the custom stylesheet; a missing or unreadable file is
+/// reported and skipped so it never blocks a render.
+fn read_custom_css(path: Option<&str>) -> Option<String> {
+    let path = path?;
+    match fs::read_to_string(path) {
+        Ok(css) => Some(css),
+        Err(e) => {
+            eprintln!("Failed to read custom CSS {path}: {e}");
+            None
+        }
+    }
+}
+
+/// Cached stylesheet for `theme` with the custom CSS at `custom_css_path`,
+/// built by `build` on a miss. Switching paths misses naturally; edits to the
+/// file itself need `invalidate_css_caches`.
+pub(crate) fn cached_stylesheet(
+    cache: &mut StylesheetCache,
+    theme: &str,
+    custom_css_path: Option<&str>,
+    build: impl FnOnce() -> String,
+) -> String {
+    cache
+        .entry((theme.to_string(), custom_css_path.map(str::to_string)))
+        .or_insert_with(build)
+        .clone()
+}
+
+/// Full export stylesheet: app styles, syntax theme, then the user's CSS so
+/// it wins ties.
+fn export_stylesheet(app: &AppHandle, theme: &str) -> String {
+    let custom_path = custom_css_path(app);
+    let build = || {
+        let syntax_css = markrust_core::get_syntax_theme_css(theme).unwrap_or_default();
+        let base_css = include_str!("../../src/styles.css");
+        let custom_css = read_custom_css(custom_path.as_deref()).unwrap_or_default();
+        format!("{base_css}\n{syntax_css}\n{custom_css}")
+    };
+    let Some(state) = app.try_state::<AppState>() else {
+        return build();
+    };
+    let Ok(mut cache) = state.stylesheet_cache.write() else {
+        return build();
+    };
+    cached_stylesheet(&mut cache, theme, custom_path.as_deref(), build)
+}
+
+/// Drop every CSS-dependent cache: run when the custom stylesheet is edited
+/// or replaced, or syntax themes are reloaded.
+pub(crate) fn invalidate_css_caches(app: &AppHandle) {
+    if let Some(state) = app.try_state::<AppState>() {
+        if let Ok(mut cache) = state.stylesheet_cache.write() {
+            cache.clear();
+        }
+    }
+}
+
+/// The custom stylesheet's contents, for windows to inject after their own.
+#[tauri::command]
+pub(crate) async fn get_custom_css(app: AppHandle) -> Result<Option<String>, String> {
+    let path = custom_css_path(&app);
+    tauri::async_runtime::spawn_blocking(move || read_custom_css(path.as_deref()))
+        .await
+        .map_err(|e| format!("Join error: {e}"))
+}
+
+/// Point `custom_css_path` at a new file (or clear it), re-arm its watcher
+/// and tell every window to restyle.
+#[tauri::command]
+pub(crate) async fn set_custom_css_path(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<(), String> {
+    let path = path.filter(|p| !p.is_empty());
+    if let Some(ref p) = path {
+        if !Path::new(p).is_file() {
+            return Err(format!("Custom CSS not found: {p}"));
+        }
+    }
+    let value = path
+        .clone()
+        .map(serde_json::Value::String)
+        .unwrap_or(serde_json::Value::Null);
+    prefs::save_preference_key_inner(&app, "custom_css_path", value).await?;
+    crate::watchers::watch_custom_css(&app, path).await?;
+    invalidate_css_caches(&app);
+    app.emit(EVENT_CUSTOM_CSS_CHANGED, ())
+        .map_err(|e| format!("Failed to emit custom CSS change: {e}"))
+}
+
 // --- Atomic write ---
 
 #[cfg(not(target_os = "windows"))]
-fn replace_file_atomically(temp_path: &Path, target_path: &Path) -> Result<(), String> {
-    fs::rename(temp_path, target_path).map_err(|e| format!("Failed to replace file: {e}"))
+fn replace_file_atomically(temp_path: &Path, target_path: &Path) -> Result<(), AppError> {
+    fs::rename(temp_path, target_path).map_err(|e| AppError::from_io("Failed to replace file", &e))
 }
 
 #[cfg(target_os = "windows")]
-fn replace_file_atomically(temp_path: &Path, target_path: &Path) -> Result<(), String> {
+fn replace_file_atomically(temp_path: &Path, target_path: &Path) -> Result<(), AppError> {
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
     use windows_sys::Win32::Storage::FileSystem::{
@@ -361,40 +988,40 @@ fn replace_file_atomically(temp_path: &Path, target_path: &Path) -> Result<(), S
     if success != 0 {
         Ok(())
     } else {
-        Err(format!(
-            "Failed to replace file: {}",
-            std::io::Error::last_os_error()
+        Err(AppError::from_io(
+            "Failed to replace file",
+            &std::io::Error::last_os_error(),
         ))
     }
 }
 
-fn atomic_write_file(path: &Path, content: &str) -> Result<(), String> {
+fn atomic_write_file(path: &Path, content: &str) -> Result<(), AppError> {
     let parent = path
         .parent()
-        .ok_or_else(|| "Cannot write file without a parent directory".to_string())?;
+        .ok_or_else(|| AppError::Io("Cannot write file without a parent directory".to_string()))?;
     let file_name = path
         .file_name()
         .and_then(|value| value.to_str())
-        .ok_or_else(|| "Cannot write file with a non-UTF-8 name".to_string())?;
-    let metadata = fs::metadata(path).map_err(|e| format!("Failed to stat file: {e}"))?;
+        .ok_or_else(|| AppError::Io("Cannot write file with a non-UTF-8 name".to_string()))?;
+    let metadata = fs::metadata(path).map_err(|e| AppError::from_io("Failed to stat file", &e))?;
     let temp_path = parent.join(format!(".{file_name}.{}.tmp", uuid::Uuid::new_v4()));
 
-    let result = (|| -> Result<(), String> {
+    let result = (|| -> Result<(), AppError> {
         let mut temp_file = fs::OpenOptions::new()
             .write(true)
             .create_new(true)
             .open(&temp_path)
-            .map_err(|e| format!("Failed to create temp file: {e}"))?;
+            .map_err(|e| AppError::from_io("Failed to create temp file", &e))?;
         temp_file
             .write_all(content.as_bytes())
-            .map_err(|e| format!("Failed to write temp file: {e}"))?;
+            .map_err(|e| AppError::from_io("Failed to write temp file", &e))?;
         temp_file
             .sync_all()
-            .map_err(|e| format!("Failed to flush temp file: {e}"))?;
+            .map_err(|e| AppError::from_io("Failed to flush temp file", &e))?;
         drop(temp_file);
 
         fs::set_permissions(&temp_path, metadata.permissions())
-            .map_err(|e| format!("Failed to preserve file permissions: {e}"))?;
+            .map_err(|e| AppError::from_io("Failed to preserve file permissions", &e))?;
         replace_file_atomically(&temp_path, path)?;
 
         if let Ok(parent_dir) = fs::File::open(parent) {
@@ -428,164 +1055,2189 @@ fn escape_html(input: &str) -> String {
     out
 }
 
-// --- Tauri commands: file I/O ---
+/// File extensions render_file_to_html (and its windowed variant) accepts.
+const RENDERABLE_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "json", "yaml", "yml", "toml", "xml", "txt", "log", "csv",
+];
 
-#[tauri::command]
-pub(crate) fn read_file(app: AppHandle, path: String) -> Result<String, String> {
-    check_path_allowed(&app, &path)?;
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {e}"))
+/// Lowercased extension of `path`, or an error when it is not renderable.
+fn renderable_extension(path: &str) -> Result<String, String> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+    if RENDERABLE_EXTENSIONS.contains(&ext.as_str()) {
+        Ok(ext)
+    } else {
+        Err(format!("Unsupported file extension: .{ext}"))
+    }
 }
 
-#[tauri::command]
-pub(crate) fn read_file_bytes_b64(app: AppHandle, path: String) -> Result<String, String> {
-    check_path_allowed(&app, &path)?;
-    fs::read(&path)
-        .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
-        .map_err(|e| format!("Failed to read file bytes: {e}"))
+/// Bytes scanned on each side of a requested window to find the enclosing
+/// line boundaries. A window read never exceeds `byte_len + 2 * slack + 1`.
+const WINDOW_ALIGN_SLACK: u64 = 4096;
+
+/// Byte range of a file snapped to whole lines, as read by
+/// read_line_aligned_window. `start..end` are absolute file offsets.
+#[derive(Debug, PartialEq)]
+pub(crate) struct LineWindow {
+    pub text: String,
+    pub start: u64,
+    pub end: u64,
+    pub total_size: u64,
 }
 
-#[tauri::command]
-pub(crate) fn write_file(app: AppHandle, path: String, content: String) -> Result<(), String> {
-    check_path_allowed(&app, &path)?;
-    if !Path::new(&path).exists() {
-        return Err("File does not exist. Use create to make new files.".to_string());
+/// Read roughly `byte_len` bytes starting at `byte_offset`, widened or narrowed
+/// to line boundaries: a start that falls mid-line moves to the next line, and
+/// the end extends to finish its line. Either snap gives up after
+/// WINDOW_ALIGN_SLACK bytes (a pathological single huge line) and cuts there.
+/// Offsets at or past EOF yield an empty window carrying `total_size`.
+pub(crate) fn read_line_aligned_window(
+    path: &Path,
+    byte_offset: u64,
+    byte_len: u64,
+) -> Result<LineWindow, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+    let total_size = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat file: {e}"))?
+        .len();
+    if byte_offset >= total_size || byte_len == 0 {
+        return Ok(LineWindow {
+            text: String::new(),
+            start: total_size.min(byte_offset),
+            end: total_size.min(byte_offset),
+            total_size,
+        });
     }
-    atomic_write_file(Path::new(&path), &content)?;
-    invalidate_cache_for_path_sync(&app, &path);
-    Ok(())
+
+    // Start one byte early so a window that begins exactly on a line start
+    // (previous byte is '\n') is recognized as aligned.
+    let read_from = byte_offset.saturating_sub(1);
+    let lead = byte_offset - read_from;
+    let budget = lead
+        .saturating_add(WINDOW_ALIGN_SLACK)
+        .saturating_add(byte_len)
+        .saturating_add(WINDOW_ALIGN_SLACK);
+    file.seek(SeekFrom::Start(read_from))
+        .map_err(|e| format!("Failed to seek file: {e}"))?;
+    let mut buf = Vec::with_capacity(budget.min(total_size - read_from) as usize);
+    file.take(budget)
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Failed to read file: {e}"))?;
+
+    let slack = WINDOW_ALIGN_SLACK as usize;
+    let start = if byte_offset == 0 {
+        0
+    } else {
+        let scan_end = buf.len().min(slack + 1);
+        buf[..scan_end]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(lead as usize)
+    };
+    let target_end = start
+        .saturating_add(usize::try_from(byte_len).unwrap_or(usize::MAX))
+        .min(buf.len());
+    let end = if target_end == buf.len() || buf[target_end - 1] == b'\n' {
+        target_end
+    } else {
+        let scan_end = buf.len().min(target_end.saturating_add(slack));
+        buf[target_end..scan_end]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|i| target_end + i + 1)
+            .unwrap_or(scan_end)
+    };
+    let end = end.max(start);
+
+    Ok(LineWindow {
+        text: String::from_utf8_lossy(&buf[start..end]).into_owned(),
+        start: read_from + start as u64,
+        end: read_from + end as u64,
+        total_size,
+    })
+}
+
+/// 128-bit XXH3 of `bytes` as lowercase hex; the same digest
+/// hash_file_contents gives a file holding them.
+pub(crate) fn hash_contents(bytes: &[u8]) -> String {
+    format!("{:032x}", xxhash_rust::xxh3::xxh3_128(bytes))
+}
+
+/// Streaming 128-bit XXH3 of a file's bytes as lowercase hex. Reads in
+/// fixed-size chunks so huge files never sit in memory whole.
+pub(crate) fn hash_file_contents(path: &Path) -> Result<String, String> {
+    use std::io::Read;
+
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Failed to read file: {e}")),
+        };
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:032x}", hasher.digest128()))
+}
+
+/// Lines in a file, counted in fixed-size chunks like hash_file_contents: a
+/// final line without a newline still counts, an empty file has none.
+pub(crate) fn count_file_lines(path: &Path) -> Result<u64, String> {
+    use std::io::Read;
+
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut lines = 0u64;
+    let mut last = None;
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Failed to read file: {e}")),
+        };
+        lines += buf[..n].iter().filter(|b| **b == b'\n').count() as u64;
+        last = Some(buf[n - 1]);
+    }
+    Ok(lines + u64::from(last.is_some_and(|b| b != b'\n')))
+}
+
+/// Modification time in whole seconds since the epoch; 0 when the platform
+/// or filesystem doesn't report one.
+pub(crate) fn mtime_secs(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Show `path` in the OS file manager: selected when it exists, otherwise its
+/// (created if needed) parent folder is opened.
+pub(crate) fn reveal_path(app: &AppHandle, path: &Path) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+
+    if path.exists() {
+        return app
+            .opener()
+            .reveal_item_in_dir(path)
+            .map_err(|e| format!("Failed to reveal {}: {e}", path.display()));
+    }
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("No parent folder for {}", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {e}"))?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {e}", dir.display()))
 }
 
+/// Select `path` in Finder, Explorer or the Linux file manager (which opens
+/// the folder when it can't select items).
 #[tauri::command]
-pub(crate) fn is_writable(app: AppHandle, path: String) -> Result<bool, String> {
+pub(crate) fn reveal_in_file_manager(app: AppHandle, path: String) -> Result<(), String> {
     check_path_allowed(&app, &path)?;
-    match fs::OpenOptions::new().write(true).open(&path) {
-        Ok(_) => Ok(true),
-        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Ok(false),
-        Err(e) => Err(format!("Failed to check writability: {e}")),
+    if !Path::new(&path).exists() {
+        return Err(format!(
+            "Cannot reveal {path}: the file no longer exists (moved or deleted?)"
+        ));
     }
+    reveal_path(&app, Path::new(&path))
 }
 
-// --- Tauri commands: markrust_core wrappers ---
+// --- Tauri commands: file I/O ---
 
-#[tauri::command]
-pub(crate) fn parse_markdown(content: String) -> String {
-    markrust_core::parse_markdown(&content)
+/// What a status bar shows about an open file.
+#[derive(Debug, Serialize)]
+pub(crate) struct FileMeta {
+    pub size_bytes: u64,
+    /// Seconds since the epoch; 0 when unknown.
+    pub modified_unix: u64,
+    pub line_count: u64,
+    pub is_writable: bool,
 }
 
+/// Size, mtime, line count and writability of `path`. Lines are counted by
+/// streaming, so large files never load whole.
 #[tauri::command]
-pub(crate) fn parse_markdown_with_theme(content: String, theme: String) -> String {
-    markrust_core::parse_markdown_with_theme(&content, &theme)
+pub(crate) async fn get_file_metadata(app: AppHandle, path: String) -> Result<FileMeta, String> {
+    check_path_allowed(&app, &path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let meta = fs::metadata(path).map_err(|e| format!("Failed to stat file: {e}"))?;
+        Ok(FileMeta {
+            size_bytes: meta.len(),
+            modified_unix: mtime_secs(&meta),
+            line_count: count_file_lines(path)?,
+            is_writable: path_is_writable(path)?,
+        })
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
 }
 
+/// Stable content hash for sync and cache coordination; identical bytes give
+/// identical hashes regardless of path or mtime.
 #[tauri::command]
-pub(crate) fn parse_json_with_theme(content: String, theme: String) -> Result<String, String> {
-    markrust_core::parse_json_with_theme(&content, &theme)
+pub(crate) async fn file_content_hash(app: AppHandle, path: String) -> Result<String, String> {
+    check_path_allowed(&app, &path)?;
+    tauri::async_runtime::spawn_blocking(move || hash_file_contents(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Join error: {e}"))?
 }
 
-#[tauri::command]
-pub(crate) fn parse_yaml_with_theme(content: String, theme: String) -> Result<String, String> {
-    markrust_core::parse_yaml_with_theme(&content, &theme)
+/// BOM-less UTF-16, which chardetng does not detect: text never contains
+/// NULs, so a lane of them in the first 4 KiB means UTF-16 code units.
+fn bomless_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 2 {
+        return None;
+    }
+    let lane_nuls = |lane: usize| {
+        sample
+            .iter()
+            .skip(lane)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let threshold = sample.len() / 4;
+    if lane_nuls(1) > threshold && lane_nuls(0) == 0 {
+        Some(UTF_16LE)
+    } else if lane_nuls(0) > threshold && lane_nuls(1) == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
 }
 
-#[tauri::command]
-pub(crate) fn format_json_pretty(content: String) -> Result<String, String> {
-    let value: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {e}"))?;
-    serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to pretty-print JSON: {e}"))
+/// UTF-8 with a few bad bytes: more well-formed multi-byte sequences than
+/// malformed ones. Legacy 8-bit text almost never forms valid sequences, so
+/// this keeps one stray byte from turning a whole UTF-8 file into mojibake.
+fn mostly_utf8(bytes: &[u8]) -> bool {
+    let (mut multibyte, mut malformed) = (0usize, 0usize);
+    for chunk in bytes.utf8_chunks() {
+        multibyte += chunk.valid().chars().filter(|c| !c.is_ascii()).count();
+        malformed += usize::from(!chunk.invalid().is_empty());
+    }
+    multibyte > malformed
 }
 
-// --- Tauri commands: rendering ---
+/// Decode file bytes as text without failing: a BOM wins (and is dropped),
+/// then BOM-less UTF-16, then UTF-8 (lossily when only a few bytes are
+/// malformed), and otherwise whatever legacy encoding chardetng guesses.
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    let encoding = if let Some((bom, _)) = Encoding::for_bom(bytes) {
+        bom
+    } else if let Some(utf16) = bomless_utf16(bytes) {
+        utf16
+    } else if std::str::from_utf8(bytes).is_ok() || mostly_utf8(bytes) {
+        UTF_8
+    } else {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    };
+    // `decode` sniffs the BOM again and strips it.
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// Read a text file whatever its encoding (see `decode_text`), so Latin-1
+/// and UTF-16 files preview instead of failing `read_to_string`. Files over
+/// `limit` are refused (see `checked_open_size`).
+pub(crate) fn read_text_best_effort(path: &Path, limit: Option<u64>) -> Result<String, AppError> {
+    checked_open_size(path, limit)?;
+    fs::read(path)
+        .map(|bytes| decode_text(&bytes))
+        .map_err(|e| AppError::from_io("Failed to read file", &e))
+}
+
+/// Read a file for editing. Unlike `read_text_best_effort` this refuses
+/// anything that isn't UTF-8, because every save writes UTF-8 and would
+/// silently transcode a Latin-1 or UTF-16 file. Files over `limit` are
+/// refused too (see `checked_open_size`).
+pub(crate) fn read_editable_text(path: &Path, limit: Option<u64>) -> Result<String, AppError> {
+    checked_open_size(path, limit)?;
+    let bytes = fs::read(path).map_err(|e| AppError::from_io("Failed to read file", &e))?;
+    String::from_utf8(bytes).map_err(|_| {
+        AppError::InvalidEncoding(format!(
+            "{} is not UTF-8 text; it can be previewed but not edited",
+            path.file_name().and_then(|n| n.to_str()).unwrap_or("File")
+        ))
+    })
+}
 
+/// The editor's read path, so strict UTF-8 (see read_editable_text). Files
+/// over `max_open_size_mb` need `force`, as for render_file_view.
 #[tauri::command]
-pub(crate) async fn render_file_to_html(
+pub(crate) fn read_file(
     app: AppHandle,
     path: String,
-    theme: String,
-) -> Result<String, String> {
-    use std::time::UNIX_EPOCH;
+    force: Option<bool>,
+) -> Result<String, AppError> {
+    check_path_allowed(&app, &path).map_err(AppError::PermissionDenied)?;
+    read_editable_text(Path::new(&path), open_limit(&app, force.unwrap_or(false)))
+}
 
-    check_path_allowed(&app, &path)?;
+/// One find hit: 1-based `line`, with `col` and `length` in UTF-16 code
+/// units so they index straight into a JS string of that line.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub(crate) struct FindMatch {
+    pub line: u32,
+    pub col: u32,
+    pub length: u32,
+}
 
-    let allowed = ["md", "markdown", "json", "yaml", "yml", "txt"];
-    let ext = Path::new(&path)
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase())
+fn find_regex(
+    query: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+    regex: bool,
+) -> Result<regex::Regex, String> {
+    let pattern = if regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let pattern = if whole_word {
+        format!(r"\b(?:{pattern})\b")
+    } else {
+        pattern
+    };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {e}"))
+}
+
+/// Every non-empty match of `query` in `text`, line by line (matches never
+/// span lines). `query` is literal unless `regex`; `whole_word` wraps it in
+/// word boundaries either way.
+pub(crate) fn find_matches(
+    text: &str,
+    query: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+    regex: bool,
+) -> Result<Vec<FindMatch>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let re = find_regex(query, case_sensitive, whole_word, regex)?;
+
+    let utf16_len = |s: &str| s.encode_utf16().count() as u32;
+    let mut matches = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        for m in re.find_iter(line).filter(|m| !m.is_empty()) {
+            matches.push(FindMatch {
+                line: i as u32 + 1,
+                col: utf16_len(&line[..m.start()]),
+                length: utf16_len(m.as_str()),
+            });
+        }
+    }
+    Ok(matches)
+}
+
+/// Characters of context kept on each side of a search match.
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+/// Longest snippet returned, match included, before ellipses.
+const MAX_SNIPPET_CHARS: usize = 160;
+/// `search_files` stops collecting after this many hits.
+const MAX_SEARCH_HITS: usize = 500;
+
+/// One matching line of `search_files`; `line` is 1-based.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub(crate) struct FileSearchHit {
+    pub path: String,
+    pub line: u32,
+    pub snippet: String,
+}
+
+/// The match at `start..end` of `line` with up to SNIPPET_CONTEXT_CHARS of
+/// context each side, trimmed, capped at MAX_SNIPPET_CHARS and marked with
+/// `…` where cut.
+fn search_snippet(line: &str, start: usize, end: usize) -> String {
+    let before: Vec<char> = line[..start].chars().collect();
+    let from = before.len().saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let mut snippet: String = before[from..].iter().collect();
+    snippet.push_str(&line[start..end]);
+    snippet.extend(line[end..].chars().take(SNIPPET_CONTEXT_CHARS));
+    let trimmed = snippet.trim();
+    let cut_end = line[end..].chars().count() > SNIPPET_CONTEXT_CHARS
+        || trimmed.chars().count() > MAX_SNIPPET_CHARS;
+    let mut snippet: String = trimmed.chars().take(MAX_SNIPPET_CHARS).collect();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if cut_end {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Grep `paths` (the recent-files list when empty) for `query` as literal
+/// text: one hit per matching line, at most MAX_SEARCH_HITS in all. Files
+/// that are unreadable, or neither recent nor opened this session, are
+/// skipped.
+#[tauri::command]
+pub(crate) async fn search_files(
+    app: AppHandle,
+    paths: Vec<String>,
+    query: String,
+    case_sensitive: bool,
+) -> Result<Vec<FileSearchHit>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let re = find_regex(&query, case_sensitive, false, false)?;
+    let recents = prefs::read_recent_paths(&app);
+    let paths: Vec<String> = if paths.is_empty() {
+        recents
+    } else {
+        paths
+            .into_iter()
+            .filter(|p| recents.contains(p) || check_path_allowed(&app, p).is_ok())
+            .collect()
+    };
+
+    let limit = open_limit(&app, false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut hits = Vec::new();
+        for path in paths {
+            let Ok(text) = read_text_best_effort(Path::new(&path), limit) else {
+                continue;
+            };
+            for (i, line) in text.lines().enumerate() {
+                let Some(m) = re.find_iter(line).find(|m| !m.is_empty()) else {
+                    continue;
+                };
+                hits.push(FileSearchHit {
+                    path: path.clone(),
+                    line: i as u32 + 1,
+                    snippet: search_snippet(line, m.start(), m.end()),
+                });
+                if hits.len() >= MAX_SEARCH_HITS {
+                    return hits;
+                }
+            }
+        }
+        hits
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))
+}
+
+/// Word count and reading time of a document (front matter and code blocks
+/// excluded).
+#[tauri::command]
+pub(crate) async fn get_document_stats(
+    app: AppHandle,
+    path: String,
+) -> Result<markrust_core::DocumentStats, String> {
+    check_path_allowed(&app, &path)?;
+    let limit = open_limit(&app, false);
+    tauri::async_runtime::spawn_blocking(move || {
+        read_text_best_effort(Path::new(&path), limit)
+            .map(|text| markrust_core::document_stats(&text))
+            .map_err(String::from)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+/// Positions of `query` in the file's raw text, for highlighting and the
+/// match count.
+#[tauri::command]
+pub(crate) async fn find_in_file(
+    app: AppHandle,
+    path: String,
+    query: String,
+    case_sensitive: bool,
+    whole_word: bool,
+    regex: Option<bool>,
+) -> Result<Vec<FindMatch>, String> {
+    check_path_allowed(&app, &path)?;
+    let limit = open_limit(&app, false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let text = read_text_best_effort(Path::new(&path), limit)?;
+        find_matches(
+            &text,
+            &query,
+            case_sensitive,
+            whole_word,
+            regex.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+#[tauri::command]
+pub(crate) fn read_file_bytes_b64(app: AppHandle, path: String) -> Result<String, String> {
+    check_path_allowed(&app, &path)?;
+    fs::read(&path)
+        .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+        .map_err(|e| format!("Failed to read file bytes: {e}"))
+}
+
+/// Opening fence marker (`` ` `` or `~`) and its length, if `line` opens or
+/// closes a fenced code block.
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let ch = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then_some((ch, len))
+}
+
+/// Strip trailing spaces/tabs from every line and end with exactly one
+/// newline, keeping CRLF files CRLF. For Markdown, lines inside fenced code
+/// blocks are left alone, and with `keep_hard_breaks` a run of two or more
+/// trailing spaces becomes exactly two so hard line breaks survive.
+pub(crate) fn trim_trailing_whitespace(
+    content: &str,
+    is_markdown: bool,
+    keep_hard_breaks: bool,
+) -> String {
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut out: Vec<String> = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    for raw in content.split('\n') {
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        let mut fence_line = false;
+        if is_markdown {
+            if let Some((ch, len)) = fence {
+                if fence_marker(line).is_some_and(|(c, l)| c == ch && l >= len)
+                    && line.trim_start().trim_start_matches(ch).trim().is_empty()
+                {
+                    fence = None;
+                    fence_line = true;
+                } else {
+                    out.push(line.to_string());
+                    continue;
+                }
+            } else if let Some(marker) = fence_marker(line) {
+                fence = Some(marker);
+                fence_line = true;
+            }
+        }
+        let trimmed = line.trim_end_matches([' ', '\t']);
+        let tail = &line[trimmed.len()..];
+        if is_markdown
+            && !fence_line
+            && keep_hard_breaks
+            && !trimmed.is_empty()
+            && tail.len() >= 2
+            && !tail.contains('\t')
+        {
+            out.push(format!("{trimmed}  "));
+        } else {
+            out.push(trimmed.to_string());
+        }
+    }
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    if out.is_empty() {
+        return String::new();
+    }
+    // A hard break on the last line has nothing to break before.
+    if is_markdown && fence.is_none() {
+        if let Some(last) = out.last_mut() {
+            last.truncate(last.trim_end_matches(' ').len());
+        }
+    }
+    let mut joined = out.join(eol);
+    joined.push_str(eol);
+    joined
+}
+
+/// Writes `content` and returns what actually landed on disk, which differs
+/// when `trim_trailing_whitespace_on_save` is on.
+#[tauri::command]
+pub(crate) fn write_file(
+    app: AppHandle,
+    path: String,
+    content: String,
+) -> Result<String, AppError> {
+    check_path_allowed(&app, &path).map_err(AppError::PermissionDenied)?;
+    if !Path::new(&path).exists() {
+        return Err(AppError::NotFound(
+            "File does not exist. Use create to make new files.".to_string(),
+        ));
+    }
+    let content = content_for_save(&app, &path, content);
+    atomic_write_file(Path::new(&path), &content)?;
+    invalidate_cache_for_path_sync(&app, &path);
+    Ok(content)
+}
+
+/// `content` as it should land at `path`: trailing whitespace trimmed when
+/// `trim_trailing_whitespace_on_save` is on, then line endings and the final
+/// newline per `line_ending` and `ensure_final_newline`.
+fn content_for_save(app: &AppHandle, path: &str, content: String) -> String {
+    let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+    let content = if prefs.trim_trailing_whitespace_on_save.unwrap_or(false) {
+        let is_markdown = detect_kind(Path::new(path)) == KIND_MARKDOWN;
+        let keep_hard_breaks = prefs.trim_keeps_hard_breaks.unwrap_or(true);
+        trim_trailing_whitespace(&content, is_markdown, keep_hard_breaks)
+    } else {
+        content
+    };
+    let line_ending = prefs.line_ending.as_deref();
+    // Only `auto` needs the file as it stands; a new file has none.
+    let on_disk = (line_ending == Some("auto"))
+        .then(|| {
+            checked_open_size(Path::new(path), open_limit(app, false)).ok()?;
+            fs::read(path).ok()
+        })
+        .flatten()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    normalize_for_save(
+        content,
+        line_ending,
+        on_disk.as_deref(),
+        prefs.ensure_final_newline.unwrap_or(false),
+    )
+}
+
+/// CRLF when more than half of `text`'s line breaks are CRLF, LF otherwise;
+/// None when it has no line breaks.
+pub(crate) fn dominant_line_ending(text: &str) -> Option<&'static str> {
+    let breaks = text.matches('\n').count();
+    if breaks == 0 {
+        return None;
+    }
+    let crlf = text.matches("\r\n").count();
+    Some(if crlf * 2 > breaks { "\r\n" } else { "\n" })
+}
+
+/// Line endings of `content` rewritten to `line_ending` (`lf`, `crlf`, or
+/// `auto`: the dominant ending of `on_disk`, else of `content`), and a final
+/// newline added when `ensure_final_newline`. Unset or unknown `line_ending`
+/// leaves the endings alone; a lone `\r` is never treated as one.
+pub(crate) fn normalize_for_save(
+    content: String,
+    line_ending: Option<&str>,
+    on_disk: Option<&str>,
+    ensure_final_newline: bool,
+) -> String {
+    let eol = match line_ending {
+        Some("lf") => Some("\n"),
+        Some("crlf") => Some("\r\n"),
+        Some("auto") => on_disk
+            .and_then(dominant_line_ending)
+            .or_else(|| dominant_line_ending(&content))
+            .or(Some("\n")),
+        _ => None,
+    };
+    let mut content = match eol {
+        Some(eol) => {
+            let lf = content.replace("\r\n", "\n");
+            if eol == "\n" {
+                lf
+            } else {
+                lf.replace('\n', eol)
+            }
+        }
+        None => content,
+    };
+    if ensure_final_newline && !content.is_empty() && !content.ends_with('\n') {
+        let eol = eol
+            .or_else(|| dominant_line_ending(&content))
+            .unwrap_or("\n");
+        content.push_str(eol);
+    }
+    content
+}
+
+/// Byte offset of the `[ ]`/`[x]` state character when `line` is a task-list
+/// item (after any indentation, `>` quote markers and a list marker).
+fn task_marker_offset(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    loop {
+        while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {
+            i += 1;
+        }
+        if bytes.get(i) == Some(&b'>') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    match bytes.get(i)? {
+        b'-' | b'*' | b'+' => i += 1,
+        b'0'..=b'9' => {
+            while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            }
+            if !matches!(bytes.get(i), Some(b'.' | b')')) {
+                return None;
+            }
+            i += 1;
+        }
+        _ => return None,
+    }
+    let marker_end = i;
+    while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {
+        i += 1;
+    }
+    if i == marker_end || bytes.get(i) != Some(&b'[') || bytes.get(i + 2) != Some(&b']') {
+        return None;
+    }
+    matches!(bytes[i + 1], b' ' | b'x' | b'X').then_some(i + 1)
+}
+
+/// `content` with the task item on 1-based `line` set to `checked`. Only the
+/// state character changes; everything else, line endings included, is kept.
+pub(crate) fn set_task_marker(content: &str, line: usize, checked: bool) -> Result<String, String> {
+    let mut start = 0;
+    for (n, text) in content.split_inclusive('\n').enumerate() {
+        if n + 1 == line {
+            let offset = task_marker_offset(text)
+                .ok_or_else(|| format!("Line {line} no longer holds a task item"))?;
+            let mark = if checked { "x" } else { " " };
+            let at = start + offset;
+            return Ok(format!("{}{mark}{}", &content[..at], &content[at + 1..]));
+        }
+        start += text.len();
+    }
+    Err(format!("Line {line} is past the end of the file"))
+}
+
+/// Check or uncheck the task item on 1-based `line` and save, so the preview
+/// works as a checklist. Fails without writing if the line has changed.
+#[tauri::command]
+pub(crate) fn toggle_task_item(
+    app: AppHandle,
+    path: String,
+    line: u32,
+    checked: bool,
+) -> Result<(), String> {
+    check_path_allowed(&app, &path)?;
+    let content = read_editable_text(Path::new(&path), open_limit(&app, false))?;
+    let updated = set_task_marker(&content, line as usize, checked)?;
+    atomic_write_file(Path::new(&path), &updated)?;
+    invalidate_cache_for_path_sync(&app, &path);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn is_writable(app: AppHandle, path: String) -> Result<bool, AppError> {
+    check_path_allowed(&app, &path).map_err(AppError::PermissionDenied)?;
+    path_is_writable(Path::new(&path))
+}
+
+/// Whether `path` opens for writing; permission errors mean false.
+pub(crate) fn path_is_writable(path: &Path) -> Result<bool, AppError> {
+    match fs::OpenOptions::new().write(true).open(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Ok(false),
+        Err(e) => Err(AppError::from_io("Failed to check writability", &e)),
+    }
+}
+
+// --- Tauri commands: markrust_core wrappers ---
+
+/// Dialect from the `markdown_preset` preference; missing or unknown values
+/// fall back to the default preset.
+pub(crate) fn markdown_preset(app: &AppHandle) -> MarkdownPreset {
+    prefs::read_string_pref(app, "markdown_preset")
+        .and_then(|name| MarkdownPreset::from_name(&name))
+        .unwrap_or_default()
+}
+
+/// Rendering options for `preset` with the preference-driven extras: code
+/// line numbers, the sanitize level, and autolink / smart punctuation when
+/// set (otherwise the preset decides).
+pub(crate) fn markdown_options(app: &AppHandle, preset: MarkdownPreset) -> MarkdownOptions {
+    let base: MarkdownOptions = preset.into();
+    let prefs = prefs::get_preferences(app.clone()).ok();
+    MarkdownOptions {
+        code_line_numbers: code_line_numbers(app),
+        sanitize: prefs::read_string_pref(app, "sanitize_level")
+            .and_then(|name| SanitizeLevel::from_name(&name))
+            .unwrap_or_default(),
+        autolink: prefs
+            .as_ref()
+            .and_then(|p| p.markdown_autolink)
+            .unwrap_or(base.autolink),
+        smart_punctuation: prefs
+            .as_ref()
+            .and_then(|p| p.markdown_smart_punctuation)
+            .unwrap_or(base.smart_punctuation),
+        foldable_headings: prefs
+            .as_ref()
+            .and_then(|p| p.markdown_foldable_headings)
+            .unwrap_or(base.foldable_headings),
+        enable_emoji: prefs
+            .as_ref()
+            .and_then(|p| p.markdown_emoji)
+            .unwrap_or(base.enable_emoji),
+        ..base
+    }
+}
+
+/// Split off front matter and resolve the effective preset for a Markdown
+/// document: its `boltpage.preset` override, else the global preference.
+pub(crate) fn resolve_markdown_overrides<'a>(
+    app: &AppHandle,
+    content: &'a str,
+) -> (&'a str, DocumentOverrides, MarkdownPreset) {
+    let (front_matter, body) = markrust_core::split_front_matter(content);
+    let overrides = front_matter
+        .map(DocumentOverrides::from_front_matter)
         .unwrap_or_default();
-    if !allowed.contains(&ext.as_str()) {
-        return Err(format!("Unsupported file extension: .{ext}"));
+    let preset = overrides.preset.unwrap_or_else(|| markdown_preset(app));
+    (body, overrides, preset)
+}
+
+#[tauri::command]
+pub(crate) fn parse_markdown(content: String) -> String {
+    markrust_core::parse_markdown(&content)
+}
+
+pub(crate) fn link_rewrite_rules(app: &AppHandle) -> Vec<LinkRewriteRule> {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.link_rewrite_rules)
+        .unwrap_or_default()
+}
+
+/// The `toc_max_level` preference; `None` lists every heading level.
+pub(crate) fn toc_max_level_pref(app: &AppHandle) -> Option<u8> {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.toc_max_level)
+}
+
+/// Compile the `link_rewrite_rules` preference. Bad patterns are skipped
+/// here and surfaced by `check_link_rewrite_rules`.
+pub(crate) fn link_rewriter(rules: &[LinkRewriteRule]) -> LinkRewriter {
+    LinkRewriter::new(
+        rules
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.replacement.clone())),
+    )
+    .0
+}
+
+/// Patterns in the `link_rewrite_rules` preference that fail to compile.
+#[tauri::command]
+pub(crate) fn check_link_rewrite_rules(app: AppHandle) -> Vec<markrust_core::LinkRuleError> {
+    let rules = link_rewrite_rules(&app);
+    LinkRewriter::new(
+        rules
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.replacement.clone())),
+    )
+    .1
+}
+
+#[tauri::command]
+pub(crate) fn parse_markdown_with_theme(app: AppHandle, content: String, theme: String) -> String {
+    let (body, _, preset) = resolve_markdown_overrides(&app, &content);
+    let links = link_rewriter(&link_rewrite_rules(&app));
+    let opts = markdown_options(&app, preset);
+    markrust_core::parse_markdown_with_links(body, &theme, opts, &links)
+}
+
+/// `render_markdown_string` output: the HTML and, when asked for, the outline
+/// its heading anchors belong to.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RenderResult {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+/// Render Markdown that no file backs (e.g. a clipboard preview). Never
+/// cached: there is no path or mtime to key it on.
+#[tauri::command]
+pub(crate) async fn render_markdown_string(
+    app: AppHandle,
+    content: String,
+    theme: String,
+    with_toc: bool,
+) -> Result<RenderResult, String> {
+    let (body, _, preset) = resolve_markdown_overrides(&app, &content);
+    let front_len = content.len() - body.len();
+    let base = markdown_options(&app, preset);
+    let opts = MarkdownOptions {
+        heading_ids: with_toc || base.heading_ids,
+        ..base
+    };
+    let links = link_rewriter(&link_rewrite_rules(&app));
+    let max_level = toc_max_level_pref(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let body = &content[front_len..];
+        RenderResult {
+            html: markrust_core::parse_markdown_with_links(body, &theme, opts, &links),
+            toc: if with_toc {
+                markrust_core::build_toc(body, opts, max_level)
+            } else {
+                Vec::new()
+            },
+        }
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))
+}
+
+/// Global `show_whitespace` preference; windows may override it per render.
+pub(crate) fn show_whitespace_pref(app: &AppHandle) -> bool {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.show_whitespace)
+        .unwrap_or(false)
+}
+
+/// How plain text is laid out: `links` from `autolink_plaintext`, `wrap`
+/// from `word_wrap`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) struct PlainTextOptions {
+    pub links: bool,
+    pub wrap: bool,
+}
+
+pub(crate) fn plain_text_options(app: &AppHandle) -> PlainTextOptions {
+    let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+    PlainTextOptions {
+        links: prefs.autolink_plaintext.unwrap_or(false),
+        wrap: prefs.word_wrap.unwrap_or(false),
+    }
+}
+
+/// Inner HTML of the `plain-text` pre, so appended tails match the render.
+fn plain_text_body(content: &str, show_whitespace: bool, plain: PlainTextOptions) -> String {
+    let escaped = escape_html(content);
+    let linked = if plain.links {
+        markrust_core::linkify_escaped_text(&escaped)
+    } else {
+        escaped
+    };
+    if show_whitespace {
+        markrust_core::mark_whitespace(&linked)
+    } else {
+        linked
+    }
+}
+
+/// The `.txt` render: escaped text in a `plain-text` pre classed `wrap` or
+/// `nowrap`, with bare URLs linked (after escaping) and whitespace marked
+/// when requested.
+pub(crate) fn render_plain_text_html(
+    content: &str,
+    show_whitespace: bool,
+    plain: PlainTextOptions,
+) -> String {
+    let body = plain_text_body(content, show_whitespace, plain);
+    let wrap = if plain.wrap { "wrap" } else { "nowrap" };
+    format!("<div class=\"markdown-body\"><pre class=\"plain-text {wrap}\">{body}</pre></div>")
+}
+
+/// `plain_text_body` for a log, a line at a time: each line with a level
+/// (`markrust_core::log_level`) is wrapped in a `log-error`, `log-warn`,
+/// `log-info`, `log-debug` or `log-trace` span; the newline stays outside.
+fn log_text_body(content: &str, show_whitespace: bool, plain: PlainTextOptions) -> String {
+    let mut out = String::with_capacity(content.len() + content.len() / 4);
+    for line in content.split_inclusive('\n') {
+        let text = line.strip_suffix('\n').unwrap_or(line);
+        let body = plain_text_body(text, show_whitespace, plain);
+        match markrust_core::log_level(text) {
+            Some(level) => {
+                out.push_str("<span class=\"log-");
+                out.push_str(level);
+                out.push_str("\">");
+                out.push_str(&body);
+                out.push_str("</span>");
+            }
+            None => out.push_str(&body),
+        }
+        if text.len() < line.len() {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The `.log` render: `render_plain_text_html` with lines colored by level.
+pub(crate) fn render_log_html(
+    content: &str,
+    show_whitespace: bool,
+    plain: PlainTextOptions,
+) -> String {
+    let body = log_text_body(content, show_whitespace, plain);
+    let wrap = if plain.wrap { "wrap" } else { "nowrap" };
+    format!(
+        "<div class=\"markdown-body\"><pre class=\"plain-text log-text {wrap}\">{body}</pre></div>"
+    )
+}
+
+/// The `json_preserve_formatting` preference.
+pub(crate) fn json_preserve_formatting(app: &AppHandle) -> bool {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.json_preserve_formatting)
+        .unwrap_or(false)
+}
+
+/// The `show_code_line_numbers` preference.
+pub(crate) fn code_line_numbers(app: &AppHandle) -> bool {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.show_code_line_numbers)
+        .unwrap_or(false)
+}
+
+/// JSON/YAML preview. `preserve_json` highlights JSON verbatim instead of
+/// round-tripping it through `serde_json::Value`, which sorts keys, respells
+/// numbers and rounds integers beyond f64 precision.
+/// With visible whitespace the source is shown verbatim instead of
+/// pretty-printed, since reformatting would hide the very indentation and
+/// trailing spaces being inspected.
+fn render_structured_html(
+    content: &str,
+    ext: &str,
+    theme: &str,
+    show_whitespace: bool,
+    preserve_json: bool,
+    line_numbers: bool,
+) -> Result<String, String> {
+    if show_whitespace {
+        markrust_core::highlight_source_with_whitespace(content, ext)
+    } else if ext == "json" && preserve_json && line_numbers {
+        markrust_core::highlight_source_with_line_numbers(content, ext)
+    } else if ext == "json" && preserve_json {
+        markrust_core::highlight_source(content, ext)
+    } else if line_numbers {
+        markrust_core::parse_structured_with_line_numbers(content, ext)
+    } else if ext == "json" {
+        markrust_core::parse_json_with_theme(content, theme)
+    } else if ext == "toml" {
+        markrust_core::parse_toml_with_theme(content, theme)
+    } else if ext == "xml" {
+        markrust_core::parse_xml_with_theme(content, theme)
+    } else {
+        markrust_core::parse_yaml_with_theme(content, theme)
     }
+}
+
+#[tauri::command]
+pub(crate) fn parse_json_with_theme(
+    app: AppHandle,
+    content: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+) -> Result<String, String> {
+    render_structured_html(
+        &content,
+        "json",
+        &theme,
+        show_whitespace.unwrap_or(false),
+        json_preserve_formatting(&app),
+        code_line_numbers(&app),
+    )
+}
+
+#[tauri::command]
+pub(crate) fn parse_yaml_with_theme(
+    app: AppHandle,
+    content: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+) -> Result<String, String> {
+    render_structured_html(
+        &content,
+        "yaml",
+        &theme,
+        show_whitespace.unwrap_or(false),
+        false,
+        code_line_numbers(&app),
+    )
+}
+
+#[tauri::command]
+pub(crate) fn parse_toml_with_theme(
+    app: AppHandle,
+    content: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+) -> Result<String, String> {
+    render_structured_html(
+        &content,
+        "toml",
+        &theme,
+        show_whitespace.unwrap_or(false),
+        false,
+        code_line_numbers(&app),
+    )
+}
+
+#[tauri::command]
+pub(crate) fn parse_xml_with_theme(
+    app: AppHandle,
+    content: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+) -> Result<String, String> {
+    render_structured_html(
+        &content,
+        "xml",
+        &theme,
+        show_whitespace.unwrap_or(false),
+        false,
+        code_line_numbers(&app),
+    )
+}
+
+#[tauri::command]
+pub(crate) fn parse_csv_with_theme(content: String, theme: String) -> Result<String, String> {
+    markrust_core::parse_csv_with_theme(&content, &theme)
+}
+
+/// Syntax-highlighted HTML for a snippet, as a fenced block in `language`
+/// would render; unknown languages come back as escaped plain text.
+#[tauri::command]
+pub(crate) fn highlight_code(
+    code: String,
+    language: String,
+    theme: String,
+) -> Result<String, String> {
+    Ok(markrust_core::highlight_snippet(&code, &language, &theme))
+}
+
+#[tauri::command]
+pub(crate) fn render_plain_text(app: AppHandle, content: String, show_whitespace: bool) -> String {
+    render_plain_text_html(&content, show_whitespace, plain_text_options(&app))
+}
+
+/// Most bytes appended in one tail step; a bigger jump re-renders instead.
+const MAX_TAIL_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Lines appended to a plain-text file since `end_byte` was last reported.
+#[derive(Debug, Serialize)]
+pub(crate) struct FileTail {
+    /// Inner HTML to append to the `plain-text` pre; empty when no complete
+    /// line was added.
+    pub html: String,
+    /// Offset to pass as `from_byte` next time.
+    pub end_byte: u64,
+    /// The file shrank or jumped by more than MAX_TAIL_BYTES: re-render it
+    /// whole rather than appending.
+    pub reset: bool,
+}
+
+/// Complete lines in `path` from `from_byte` on, with the offset just past
+/// the last newline read; a trailing partial line waits for the next call.
+/// `None` when the file is now shorter than `from_byte` or the new bytes
+/// exceed MAX_TAIL_BYTES.
+pub(crate) fn read_file_tail(path: &Path, from_byte: u64) -> Result<Option<(String, u64)>, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat file: {e}"))?
+        .len();
+    if len < from_byte || len - from_byte > MAX_TAIL_BYTES {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(from_byte))
+        .map_err(|e| format!("Failed to seek file: {e}"))?;
+    let mut bytes = Vec::new();
+    file.take(len - from_byte)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read file: {e}"))?;
+    let complete = bytes.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let text = decode_text(&bytes[..complete]);
+    Ok(Some((text, from_byte + complete as u64)))
+}
+
+/// Tail-mode step for `.txt`/`.log` windows: the lines appended since
+/// `from_byte` (first a render's `byte_len`), rendered like
+/// `render_plain_text` so they can go straight into the open `plain-text` pre.
+#[tauri::command]
+pub(crate) async fn render_file_tail(
+    app: AppHandle,
+    path: String,
+    from_byte: u64,
+    show_whitespace: Option<bool>,
+) -> Result<FileTail, String> {
+    check_path_allowed(&app, &path)?;
+    let is_log = match renderable_extension(&path)?.as_str() {
+        "txt" => false,
+        "log" => true,
+        _ => return Err("Tail mode only follows .txt and .log files".to_string()),
+    };
+    let plain = plain_text_options(&app);
+    let show_whitespace = show_whitespace.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(match read_file_tail(Path::new(&path), from_byte)? {
+            Some((text, end_byte)) => FileTail {
+                html: if is_log {
+                    log_text_body(&text, show_whitespace, plain)
+                } else {
+                    plain_text_body(&text, show_whitespace, plain)
+                },
+                end_byte,
+                reset: false,
+            },
+            None => FileTail {
+                html: String::new(),
+                end_byte: from_byte,
+                reset: true,
+            },
+        })
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+/// Preference-independent render (`markrust_core::CANONICAL_OPTIONS`) for
+/// checking output against GitHub.
+#[tauri::command]
+pub(crate) fn render_markdown_canonical(content: String) -> String {
+    markrust_core::render_markdown_canonical(&content)
+}
+
+/// Only this much of a file is sampled by `detect_language`.
+const LANGUAGE_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Natural language of a Markdown or text document as a BCP-47 code, for the
+/// frontend's `lang` attribute. Code blocks are ignored; `None` when the
+/// document is too short or mixed to call. Kept off the render path.
+#[tauri::command]
+pub(crate) async fn detect_language(
+    app: AppHandle,
+    path: String,
+) -> Result<Option<String>, String> {
+    use std::io::Read;
+
+    check_path_allowed(&app, &path)?;
+    let ext = renderable_extension(&path)?;
+    if !matches!(ext.as_str(), "md" | "markdown" | "txt") {
+        return Ok(None);
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let file = fs::File::open(&path).map_err(|e| format!("Failed to open file: {e}"))?;
+        let mut bytes = Vec::new();
+        file.take(LANGUAGE_SAMPLE_BYTES)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read file: {e}"))?;
+        // The cut may split a character; the lossy tail is a single word.
+        let sample = String::from_utf8_lossy(&bytes);
+        let prose = if ext == "txt" {
+            sample.into_owned()
+        } else {
+            markrust_core::markdown_prose(&sample)
+        };
+        Ok(markrust_core::detect_language(&prose).map(str::to_string))
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+#[tauri::command]
+pub(crate) fn markdown_to_plaintext(content: String) -> String {
+    markrust_core::markdown_to_plaintext(&content)
+}
+
+/// A rendered document in both clipboard flavors.
+#[derive(Debug, Serialize)]
+pub(crate) struct ClipboardDocument {
+    pub html: String,
+    /// For plain-text fields: Markdown laid out by `markdown_to_plaintext`,
+    /// other kinds as their source.
+    pub text: String,
+}
+
+/// `path` rendered as `render_file_to_html` would (rendered view, no
+/// whitespace marks) for pasting into rich-text editors, plus a plain-text
+/// flavor made from the same read. The webview puts both on the clipboard.
+#[tauri::command]
+pub(crate) async fn rendered_clipboard_document(
+    app: AppHandle,
+    path: String,
+    theme: String,
+    force: Option<bool>,
+) -> Result<ClipboardDocument, String> {
+    let force = force.unwrap_or(false);
+    let limit = open_limit(&app, force);
+    let rendered = render_file_view_inner(
+        app,
+        None,
+        path.clone(),
+        theme,
+        Some(false),
+        Some(ViewMode::Rendered),
+        false,
+        force,
+    )
+    .await?;
+    // Stream-formatted JSON keeps no source; its text flavor is the file as is.
+    let content = match rendered.source {
+        Some(content) => content,
+        None => {
+            let read_path = path.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                read_text_best_effort(Path::new(&read_path), limit)
+            })
+            .await
+            .map_err(|e| format!("Join error: {e}"))??
+        }
+    };
+    let text = match renderable_extension(&path)?.as_str() {
+        "md" | "markdown" => {
+            let (_, body) = markrust_core::split_front_matter(&content);
+            markrust_core::markdown_to_plaintext(body)
+        }
+        _ => content,
+    };
+    Ok(ClipboardDocument {
+        html: rendered.html,
+        text,
+    })
+}
+
+#[tauri::command]
+pub(crate) fn format_json_pretty(content: String) -> Result<String, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {e}"))?;
+    serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to pretty-print JSON: {e}"))
+}
+
+// --- Tauri commands: rendering ---
+
+/// JSON files above this size are stream-formatted with a value cap instead
+/// of parsed whole; see render_large_json.
+const LARGE_JSON_THRESHOLD: u64 = 8 * 1024 * 1024;
+const DEFAULT_LARGE_JSON_NODE_LIMIT: usize = 100_000;
+/// Largest file of any kind `render_dual` shows: it highlights every source
+/// line and renders the whole document at once, with no streaming or chunks.
+const MAX_DUAL_VIEW_BYTES: u64 = 8 * 1024 * 1024;
+/// Markdown above the `large_render_threshold` preference (default 2 MB)
+/// renders in chunks of about RENDER_CHUNK_BYTES, reporting progress.
+const DEFAULT_LARGE_RENDER_THRESHOLD: u64 = 2 * 1024 * 1024;
+const RENDER_CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Clone, Serialize)]
+struct RenderProgress {
+    path: String,
+    bytes_read: u64,
+    total_bytes: u64,
+    percent: u8,
+}
+
+/// Where a long render reports `render-progress` and `render-complete`: the
+/// requesting window, or every window when there is none to name.
+fn emit_render_event<S: Serialize + Clone>(
+    app: &AppHandle,
+    window_label: Option<&str>,
+    event: &str,
+    payload: S,
+) {
+    let _ = match window_label {
+        Some(label) => app.emit_to(label, event, payload),
+        None => app.emit(event, payload),
+    };
+}
+
+fn emit_render_progress(
+    app: &AppHandle,
+    window_label: Option<&str>,
+    path: &str,
+    bytes_read: u64,
+    total_bytes: u64,
+) {
+    let percent = (bytes_read.min(total_bytes) * 100)
+        .checked_div(total_bytes)
+        .unwrap_or(100) as u8;
+    emit_render_event(
+        app,
+        window_label,
+        EVENT_RENDER_PROGRESS,
+        RenderProgress {
+            path: path.to_string(),
+            bytes_read,
+            total_bytes,
+            percent,
+        },
+    );
+}
+
+/// Default for the `max_open_size_mb` preference.
+const DEFAULT_MAX_OPEN_SIZE_MB: u64 = 50;
+
+/// Bytes above which a render waits for `force`, from `max_open_size_mb`;
+/// `None` when the preference is 0. Never above MAX_OPEN_FILE_BYTES, which
+/// refuses outright.
+fn open_size_limit(max_open_size_mb: Option<u64>) -> Option<u64> {
+    match max_open_size_mb.unwrap_or(DEFAULT_MAX_OPEN_SIZE_MB) {
+        0 => None,
+        mb => Some(mb.saturating_mul(1024 * 1024).min(MAX_OPEN_FILE_BYTES)),
+    }
+}
+
+/// `open_size_limit` for `app`'s preferences, or `None` once the user chose
+/// to open the file anyway (`force`).
+pub(crate) fn open_limit(app: &AppHandle, force: bool) -> Option<u64> {
+    open_size_limit(
+        prefs::get_preferences(app.clone())
+            .ok()
+            .and_then(|p| p.max_open_size_mb),
+    )
+    .filter(|_| !force)
+}
+
+/// The size check every whole-file read shares: over MAX_OPEN_FILE_BYTES is
+/// refused outright, and over `limit` (see `open_limit`) fails with
+/// TooLarge until the user confirms.
+fn check_open_size(path: &str, size: u64, limit: Option<u64>) -> Result<(), AppError> {
+    let over = |limit: u64| {
+        format!(
+            "{} is {} MiB, over the {} MiB limit for opening files",
+            file_name_or_path(path),
+            size.div_ceil(1024 * 1024),
+            limit / (1024 * 1024)
+        )
+    };
+    if size > MAX_OPEN_FILE_BYTES {
+        return Err(AppError::Unsupported(over(MAX_OPEN_FILE_BYTES)));
+    }
+    match limit.filter(|limit| size > *limit) {
+        Some(limit) => Err(AppError::TooLarge(over(limit))),
+        None => Ok(()),
+    }
+}
+
+/// Stat `path` and apply `check_open_size`; its size when it may be read.
+pub(crate) fn checked_open_size(path: &Path, limit: Option<u64>) -> Result<u64, AppError> {
+    let size = fs::metadata(path)
+        .map_err(|e| AppError::from_io("Failed to stat file", &e))?
+        .len();
+    check_open_size(&path.to_string_lossy(), size, limit)?;
+    Ok(size)
+}
+
+/// Payload of `large-file-warning`, sent to the window whose render was held
+/// back so it can ask before rendering with `force`.
+#[derive(Clone, Serialize)]
+struct LargeFileWarning {
+    path: String,
+    size: u64,
+    limit: u64,
+}
+
+fn large_render_threshold(app: &AppHandle) -> u64 {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.large_render_threshold)
+        .unwrap_or(DEFAULT_LARGE_RENDER_THRESHOLD)
+}
+
+/// Render a huge JSON file without loading or parsing it whole: values are
+/// formatted as they stream in, up to the `large_json_node_limit` preference,
+/// with `render-progress` events along the way. Truncated output is prefixed
+/// with a `render-truncated` notice. Not cached: the LRU is sized by entry
+/// count, and entries this large would crowd out everything else.
+fn render_large_json(
+    app: &AppHandle,
+    window_label: Option<&str>,
+    path: &str,
+    total_bytes: u64,
+) -> Result<String, AppError> {
+    let max_nodes = prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.large_json_node_limit)
+        .unwrap_or(DEFAULT_LARGE_JSON_NODE_LIMIT);
+    let file = fs::File::open(path).map_err(|e| AppError::from_io("Failed to open file", &e))?;
+    let streamed = markrust_core::pretty_print_json_stream(file, max_nodes, |bytes_read| {
+        emit_render_progress(app, window_label, path, bytes_read, total_bytes);
+    })
+    .map_err(AppError::ParseError)?;
+    let html = markrust_core::highlight_json_text(&streamed.text).map_err(AppError::ParseError)?;
+    if !streamed.truncated {
+        return Ok(html);
+    }
+    Ok(format!(
+        "<div class=\"render-truncated\">Showing the first {} values ({} of {} bytes read).</div>{html}",
+        streamed.nodes, streamed.bytes_read, total_bytes
+    ))
+}
+
+/// The `default_view_by_kind` preference for `path`'s kind; kinds without
+/// an entry open rendered.
+pub(crate) fn default_view_for(app: &AppHandle, path: &Path) -> ViewMode {
+    prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.default_view_by_kind)
+        .and_then(|m| m.get(detect_kind(path)).copied())
+        .unwrap_or_default()
+}
+
+/// A render plus the view it was rendered in, so a window's first load can
+/// adopt the per-kind default.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RenderedFile {
+    pub html: String,
+    pub view: ViewMode,
+    /// Files expanded by include directives (watched alongside `path`).
+    pub includes: Vec<String>,
+    /// Bytes of `path` the render was made from, where a tail step resumes.
+    pub byte_len: u64,
+    /// The decoded text those bytes hold, for callers that need it with the
+    /// render; `None` for stream-formatted JSON. Not sent to the webview.
+    #[serde(skip)]
+    pub source: Option<String>,
+}
+
+/// Render `path` to HTML. Without `theme` the file's extension theme
+/// (`per_extension_theme`) is used, falling back to the global theme. Files
+/// over `max_open_size_mb` need `force`, as for render_file_view.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn render_file_to_html(
+    app: AppHandle,
+    window: tauri::Window,
+    path: String,
+    theme: Option<String>,
+    show_whitespace: Option<bool>,
+    view: Option<ViewMode>,
+    bypass_cache: Option<bool>,
+    force: Option<bool>,
+) -> Result<String, AppError> {
+    let theme = theme.unwrap_or_else(|| {
+        let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+        prefs::theme_for_path(&prefs, Path::new(&path)).to_string()
+    });
+    let label = Some(window.label().to_string());
+    let fresh = bypass_cache.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    render_file_view_inner(app, label, path, theme, show_whitespace, view, fresh, force)
+        .await
+        .map(|r| r.html)
+}
+
+/// Render `path` in `view`, or in the per-kind default view when `view` is
+/// None (a window's first load of the file). Large JSON and Markdown report
+/// `render-progress` to the calling window, then `render-complete`.
+/// Rendered Markdown carries `data-source-line` on its top-level blocks, so
+/// a reload can return to the block being read.
+/// `bypass_cache` (a hard reload) renders from disk without reading or
+/// filling the HTML cache. A file over `max_open_size_mb` is not read unless
+/// `force` is set: the window gets `large-file-warning` and the render fails
+/// with `too_large`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn render_file_view(
+    app: AppHandle,
+    window: tauri::Window,
+    path: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+    view: Option<ViewMode>,
+    bypass_cache: Option<bool>,
+    force: Option<bool>,
+) -> Result<RenderedFile, AppError> {
+    let label = Some(window.label().to_string());
+    let fresh = bypass_cache.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    render_file_view_inner(app, label, path, theme, show_whitespace, view, fresh, force).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn render_file_view_inner(
+    app: AppHandle,
+    window_label: Option<String>,
+    path: String,
+    theme: String,
+    show_whitespace: Option<bool>,
+    view: Option<ViewMode>,
+    bypass_cache: bool,
+    force: bool,
+) -> Result<RenderedFile, AppError> {
+    check_path_allowed(&app, &path).map_err(AppError::PermissionDenied)?;
+
+    let ext = renderable_extension(&path).map_err(AppError::Unsupported)?;
+    let view = view.unwrap_or_else(|| default_view_for(&app, Path::new(&path)));
 
     let read_path = path.clone();
-    let (size, mtime_secs, raw_content) =
-        tauri::async_runtime::spawn_blocking(move || -> Result<(u64, u64, String), String> {
-            let meta = fs::metadata(&read_path).map_err(|e| format!("Failed to stat file: {e}"))?;
+    let read_ext = ext.clone();
+    let limit = open_limit(&app, force);
+    let warn_app = app.clone();
+    let warn_label = window_label.clone();
+    let (size, mtime_secs, raw_content) = tauri::async_runtime::spawn_blocking(
+        move || -> Result<(u64, u64, Option<String>), AppError> {
+            let meta = fs::metadata(&read_path)
+                .map_err(|e| AppError::from_io("Failed to stat file", &e))?;
             let size = meta.len();
-            let mtime_secs = meta
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            let content =
-                fs::read_to_string(&read_path).map_err(|e| format!("Failed to read file: {e}"))?;
-            Ok((size, mtime_secs, content))
+            let mtime_secs = mtime_secs(&meta);
+            let checked = check_open_size(&read_path, size, limit);
+            if let (Err(AppError::TooLarge(_)), Some(label), Some(limit)) =
+                (&checked, &warn_label, limit)
+            {
+                let warning = LargeFileWarning {
+                    path: read_path.clone(),
+                    size,
+                    limit,
+                };
+                let _ = warn_app.emit_to(label.as_str(), EVENT_LARGE_FILE_WARNING, warning);
+            }
+            checked?;
+            if read_ext == "json" && size > LARGE_JSON_THRESHOLD {
+                return Ok((size, mtime_secs, None));
+            }
+            let bytes =
+                fs::read(&read_path).map_err(|e| AppError::from_io("Failed to read file", &e))?;
+            // What was read, which a growing file can make longer than the stat.
+            Ok((bytes.len() as u64, mtime_secs, Some(decode_text(&bytes))))
+        },
+    )
+    .await
+    .map_err(|e| AppError::Io(format!("Join error: {e}")))??;
+
+    let Some(raw_content) = raw_content else {
+        // Too big for a verbatim source view either way; always stream-format.
+        let html = tauri::async_runtime::spawn_blocking(move || {
+            let html = render_large_json(&app, window_label.as_deref(), &path, size)?;
+            emit_render_event(&app, window_label.as_deref(), EVENT_RENDER_COMPLETE, &path);
+            Ok::<_, AppError>(html)
         })
         .await
-        .map_err(|e| format!("Join error: {e}"))??;
+        .map_err(|e| AppError::Io(format!("Join error: {e}")))??;
+        return Ok(RenderedFile {
+            html,
+            view,
+            includes: Vec::new(),
+            byte_len: size,
+            source: None,
+        });
+    };
 
+    let is_markdown = ext == "md" || ext == "markdown";
+    let (overrides, preset) = if is_markdown {
+        let (_, overrides, preset) = resolve_markdown_overrides(&app, &raw_content);
+        (overrides, preset)
+    } else {
+        (DocumentOverrides::default(), markdown_preset(&app))
+    };
+    let show_whitespace = (!is_markdown || view == ViewMode::Source)
+        && show_whitespace.unwrap_or_else(|| show_whitespace_pref(&app));
+    let opts = MarkdownOptions {
+        task_lines: true,
+        line_anchors: window_label.is_some(),
+        ..markdown_options(&app, preset)
+    };
     let key = CacheKey {
         path: path.clone(),
         size,
         mtime_secs,
+        preset,
+        overrides,
+        show_whitespace,
+        view,
+        link_rules: if is_markdown {
+            link_rewrite_rules(&app)
+        } else {
+            Vec::new()
+        },
+        preserve_json: ext == "json" && json_preserve_formatting(&app),
+        code_line_numbers: opts.code_line_numbers,
+        sanitize: if is_markdown {
+            opts.sanitize
+        } else {
+            SanitizeLevel::default()
+        },
+        autolink: is_markdown && opts.autolink,
+        smart_punctuation: is_markdown && opts.smart_punctuation,
+        foldable_headings: is_markdown && opts.foldable_headings,
+        emoji: is_markdown && opts.enable_emoji,
+        line_anchors: is_markdown && opts.line_anchors,
+        plain_text: if matches!(ext.as_str(), "txt" | "log")
+            || (ext == "csv" && view == ViewMode::Source)
+        {
+            plain_text_options(&app)
+        } else {
+            PlainTextOptions::default()
+        },
+        content_hash: (mtime_secs == 0).then(|| hash_contents(raw_content.as_bytes())),
     };
+    let preserve_json = key.preserve_json;
+    let plain = key.plain_text;
+    let line_numbers = key.code_line_numbers;
 
-    if let Some(state) = app.try_state::<AppState>() {
+    if let Some(state) = app.try_state::<AppState>().filter(|_| !bypass_cache) {
         let mut cache = state.html_cache.write().await;
-        if let Some(cached) = cache.get(&key).cloned() {
-            return Ok(cached);
+        if let Some(cached) = cache.get_mut(&key) {
+            state.cache_hits.fetch_add(1, Ordering::Relaxed);
+            cached.last_access = Instant::now();
+            let includes = cached.deps.clone();
+            let html = cached.html.clone();
+            drop(cache);
+            crate::watchers::watch_includes(&app, &path, includes.clone()).await;
+            return Ok(RenderedFile {
+                html,
+                view,
+                includes,
+                byte_len: size,
+                source: Some(raw_content),
+            });
         }
+        state.cache_misses.fetch_add(1, Ordering::Relaxed);
     }
 
-    let html = tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
-        if ext == "txt" {
-            let escaped = escape_html(&raw_content);
-            Ok(format!(
-                "<div class=\"markdown-body\"><pre class=\"plain-text\">{escaped}</pre></div>"
-            ))
-        } else if ext == "json" {
-            markrust_core::parse_json_with_theme(&raw_content, &theme)
-        } else if ext == "yaml" || ext == "yml" {
-            markrust_core::parse_yaml_with_theme(&raw_content, &theme)
+    let links = link_rewriter(&key.link_rules);
+    let root = PathBuf::from(&path);
+    let chunked = is_markdown && view == ViewMode::Rendered && size > large_render_threshold(&app);
+    let progress_app = app.clone();
+    let progress_path = path.clone();
+    let render = move |raw_content: &str| -> Result<(String, Vec<String>), AppError> {
+        let html = if ext == "log" {
+            render_log_html(raw_content, show_whitespace, plain)
+        } else if ext == "txt" || (ext == "csv" && view == ViewMode::Source) {
+            render_plain_text_html(raw_content, show_whitespace, plain)
+        } else if ext == "csv" {
+            markrust_core::parse_csv_with_theme(raw_content, &theme)
+                .map_err(AppError::ParseError)?
+        } else if view == ViewMode::Source && show_whitespace {
+            markrust_core::highlight_source_with_whitespace(raw_content, &ext)
+                .map_err(AppError::ParseError)?
+        } else if view == ViewMode::Source {
+            markrust_core::highlight_source(raw_content, &ext).map_err(AppError::ParseError)?
+        } else if matches!(ext.as_str(), "json" | "yaml" | "yml" | "toml" | "xml") {
+            render_structured_html(
+                raw_content,
+                &ext,
+                &theme,
+                show_whitespace,
+                preserve_json,
+                line_numbers,
+            )
+            .map_err(AppError::ParseError)?
         } else {
-            Ok(markrust_core::parse_markdown_with_theme(
-                &raw_content,
+            let (_, body) = markrust_core::split_front_matter(raw_content);
+            let front_lines = raw_content[..raw_content.len() - body.len()]
+                .matches('\n')
+                .count();
+            let ExpandedMarkdown {
+                text: body,
+                includes,
+                lines,
+            } = expand_markdown_includes_mapped(body, &root, limit)?;
+            if !chunked {
+                let html = markrust_core::parse_markdown_with_links(&body, &theme, opts, &links);
+                return Ok((remap_source_lines(&html, &lines, front_lines), includes));
+            }
+            let label = window_label.as_deref();
+            let total = body.len() as u64;
+            let html = markrust_core::parse_markdown_chunked(
+                &body,
                 &theme,
-            ))
+                opts,
+                &links,
+                RENDER_CHUNK_BYTES,
+                |done| {
+                    emit_render_progress(&progress_app, label, &progress_path, done as u64, total)
+                },
+            );
+            emit_render_event(&progress_app, label, EVENT_RENDER_COMPLETE, &progress_path);
+            return Ok((remap_source_lines(&html, &lines, front_lines), includes));
+        };
+        Ok((html, Vec::new()))
+    };
+    let (html, includes, raw_content) = tauri::async_runtime::spawn_blocking(move || {
+        render(&raw_content).map(|(html, includes)| (html, includes, raw_content))
+    })
+    .await
+    .map_err(|e| AppError::Io(format!("Join error: {e}")))??;
+
+    // A fresh render isn't stored either: it could overwrite an entry a
+    // concurrent cached render of the same key is about to return.
+    if let Some(state) = app.try_state::<AppState>().filter(|_| !bypass_cache) {
+        let mut cache = state.html_cache.write().await;
+        cache.put(key, CachedHtml::new(html.clone(), includes.clone()));
+    }
+    if is_markdown {
+        crate::watchers::watch_includes(&app, &path, includes.clone()).await;
+    }
+
+    Ok(RenderedFile {
+        html,
+        view,
+        includes,
+        byte_len: size,
+        source: Some(raw_content),
+    })
+}
+
+/// Both halves of the side-by-side review view. `source` lines carry
+/// `data-source-line`; for Markdown, so do the top-level blocks of
+/// `rendered`, and `mapped` is true. Other kinds render as usual.
+#[derive(Serialize)]
+pub(crate) struct DualRender {
+    pub source: String,
+    pub rendered: String,
+    pub mapped: bool,
+}
+
+#[tauri::command]
+pub(crate) async fn render_dual(
+    app: AppHandle,
+    path: String,
+    theme: String,
+    force: Option<bool>,
+) -> Result<DualRender, String> {
+    check_path_allowed(&app, &path)?;
+    let ext = renderable_extension(&path)?;
+    let read_path = path.clone();
+    let limit = open_limit(&app, force.unwrap_or(false));
+    let content = tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+        let size = checked_open_size(Path::new(&read_path), limit)?;
+        if size > MAX_DUAL_VIEW_BYTES {
+            return Err("File is too large for the side-by-side view".to_string());
         }
+        Ok(read_text_best_effort(Path::new(&read_path), limit)?)
     })
     .await
     .map_err(|e| format!("Join error: {e}"))??;
 
-    if let Some(state) = app.try_state::<AppState>() {
-        let mut cache = state.html_cache.write().await;
-        cache.put(key, html.clone());
+    let is_markdown = ext == "md" || ext == "markdown";
+    let preset = if is_markdown {
+        resolve_markdown_overrides(&app, &content).2
+    } else {
+        markdown_preset(&app)
+    };
+    let links = link_rewriter(&link_rewrite_rules(&app));
+    let preserve_json = json_preserve_formatting(&app);
+    let opts = markdown_options(&app, preset);
+    let line_numbers = opts.code_line_numbers;
+    let plain = plain_text_options(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = markrust_core::highlight_source_lines(&content, &ext)?;
+        let rendered = if is_markdown {
+            let (_, body) = markrust_core::split_front_matter(&content);
+            let front_lines = content[..content.len() - body.len()].matches('\n').count();
+            markrust_core::parse_markdown_with_source_lines(body, &theme, opts, &links, front_lines)
+        } else if ext == "log" {
+            render_log_html(&content, false, plain)
+        } else if ext == "txt" {
+            render_plain_text_html(&content, false, plain)
+        } else if ext == "csv" {
+            markrust_core::parse_csv_with_theme(&content, &theme)?
+        } else {
+            render_structured_html(&content, &ext, &theme, false, preserve_json, line_numbers)?
+        };
+        Ok(DualRender {
+            source,
+            rendered,
+            mapped: is_markdown,
+        })
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+/// How much of a Markdown file front_matter_title reads; front matter still
+/// open past this counts as absent.
+const FRONT_MATTER_TITLE_BYTES: u64 = 8 * 1024;
+
+/// The front-matter `title` of a Markdown file, from its first
+/// FRONT_MATTER_TITLE_BYTES only so opening a window never reads much.
+fn front_matter_title(path: &Path) -> Option<String> {
+    use std::io::Read;
+
+    if detect_kind(path) != KIND_MARKDOWN {
+        return None;
     }
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(FRONT_MATTER_TITLE_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    // The cut may split a UTF-8 character; drop the partial one so the head
+    // still decodes as UTF-8.
+    if let Err(e) = std::str::from_utf8(&head) {
+        if e.error_len().is_none() {
+            head.truncate(e.valid_up_to());
+        }
+    }
+    markrust_core::front_matter_title(&decode_text(&head))
+}
+
+/// Name a window shows for `path`: a Markdown file's front-matter title,
+/// else the file name.
+pub(crate) fn document_title(path: &Path) -> Option<String> {
+    front_matter_title(path).or_else(|| {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+    })
+}
 
-    Ok(html)
+/// `path`'s file name, or the path itself when it has none.
+pub(crate) fn file_name_or_path(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Files bigger than this are refused by render_diff rather than compared.
+const MAX_DIFF_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Side-by-side line diff of two allowed files as a `diff-table` (see
+/// markrust_core::render_line_diff). Columns are headed by file name, or
+/// by full path when both names match.
+#[tauri::command]
+pub(crate) async fn render_diff(
+    app: AppHandle,
+    left_path: String,
+    right_path: String,
+) -> Result<String, String> {
+    check_path_allowed(&app, &left_path)?;
+    check_path_allowed(&app, &right_path)?;
+    let limit = open_limit(&app, false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let read = |path: &str| -> Result<String, String> {
+            let size = checked_open_size(Path::new(path), limit)?;
+            if size > MAX_DIFF_FILE_BYTES {
+                return Err(format!(
+                    "{} is too large to compare (over {} MB)",
+                    file_name_or_path(path),
+                    MAX_DIFF_FILE_BYTES / (1024 * 1024)
+                ));
+            }
+            Ok(read_text_best_effort(Path::new(path), limit)?)
+        };
+        let (left, right) = (read(&left_path)?, read(&right_path)?);
+        let (mut left_name, mut right_name) = (
+            file_name_or_path(&left_path),
+            file_name_or_path(&right_path),
+        );
+        if left_name == right_name {
+            (left_name, right_name) = (left_path, right_path);
+        }
+        Ok(markrust_core::render_line_diff(
+            &left,
+            &right,
+            &left_name,
+            &right_name,
+        ))
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
+}
+
+#[tauri::command]
+pub(crate) async fn get_document_title(
+    app: AppHandle,
+    path: String,
+) -> Result<Option<String>, String> {
+    check_path_allowed(&app, &path)?;
+    tauri::async_runtime::spawn_blocking(move || document_title(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Join error: {e}"))
+}
+
+/// Front-matter display overrides for a Markdown file, so windows can apply a
+/// document's pinned theme, wrapping and line numbers without saving them.
+/// Other kinds have no front matter and get empty overrides.
+#[tauri::command]
+pub(crate) async fn get_document_overrides(
+    app: AppHandle,
+    path: String,
+) -> Result<DocumentOverrides, String> {
+    check_path_allowed(&app, &path)?;
+    let ext = renderable_extension(&path)?;
+    if ext != "md" && ext != "markdown" {
+        return Ok(DocumentOverrides::default());
+    }
+    let limit = open_limit(&app, false);
+    let content = tauri::async_runtime::spawn_blocking(move || {
+        read_text_best_effort(Path::new(&path), limit)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))??;
+    Ok(DocumentOverrides::from_markdown(&content))
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FileWindowRender {
+    pub html: String,
+    pub start: u64,
+    pub end: u64,
+    pub total_size: u64,
+}
+
+/// Render only a line-aligned byte range of a (potentially huge) file, the
+/// primitive behind large-file pagination and tailing. `total_size` lets the
+/// UI size a scrollbar without reading the rest. Markdown slices render as
+/// Markdown; structured formats can't parse from a slice, so they (and txt)
+/// render as escaped plain text. Not cached: windows are cheap and transient.
+#[tauri::command]
+pub(crate) async fn render_file_window(
+    app: AppHandle,
+    path: String,
+    byte_offset: u64,
+    byte_len: u64,
+    theme: String,
+    show_whitespace: Option<bool>,
+) -> Result<FileWindowRender, String> {
+    check_path_allowed(&app, &path)?;
+    let ext = renderable_extension(&path)?;
+    // Only the window is read, so it is what the open limit applies to.
+    check_open_size(&path, byte_len, open_limit(&app, false))?;
+    let opts = markdown_options(&app, markdown_preset(&app));
+    let links = link_rewriter(&link_rewrite_rules(&app));
+    let show_whitespace = show_whitespace.unwrap_or_else(|| show_whitespace_pref(&app));
+    let plain = plain_text_options(&app);
+
+    tauri::async_runtime::spawn_blocking(move || -> Result<FileWindowRender, String> {
+        let window = read_line_aligned_window(Path::new(&path), byte_offset, byte_len)?;
+        let html = if window.text.is_empty() {
+            String::new()
+        } else if ext == "md" || ext == "markdown" {
+            markrust_core::parse_markdown_with_links(&window.text, &theme, opts, &links)
+        } else if ext == "log" {
+            render_log_html(&window.text, show_whitespace, plain)
+        } else {
+            render_plain_text_html(&window.text, show_whitespace, plain)
+        };
+        Ok(FileWindowRender {
+            html,
+            start: window.start,
+            end: window.end,
+            total_size: window.total_size,
+        })
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?
 }
 
 // --- Tauri commands: export ---
 
+/// Outline of a Markdown document, with slugs matching the heading anchors
+/// of its preview (none under presets that render headings without ids).
+/// `toc_max_level` overrides the preference of the same name.
+#[tauri::command]
+pub(crate) async fn get_document_toc(
+    app: AppHandle,
+    path: String,
+    toc_max_level: Option<u8>,
+) -> Result<Vec<TocEntry>, String> {
+    check_path_allowed(&app, &path)?;
+    let ext = renderable_extension(&path)?;
+    if ext != "md" && ext != "markdown" {
+        return Ok(Vec::new());
+    }
+    let max_level = toc_max_level.or_else(|| toc_max_level_pref(&app));
+    let read_path = path.clone();
+    let limit = open_limit(&app, false);
+    let content = tauri::async_runtime::spawn_blocking(move || {
+        read_text_best_effort(Path::new(&read_path), limit)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))??;
+
+    let (body, _, preset) = resolve_markdown_overrides(&app, &content);
+    let (body, _) = expand_markdown_includes(body, Path::new(&path), limit)?;
+    let opts = markdown_options(&app, preset);
+    Ok(markrust_core::build_toc(&body, opts, max_level))
+}
+
+/// Markdown rendered with heading anchors, plus a sidebar linking to them.
+/// The sidebar is empty when the document has no headings.
+async fn render_markdown_with_toc(
+    app: &AppHandle,
+    path: &str,
+    theme: &str,
+) -> Result<(String, String), String> {
+    let read_path = path.to_string();
+    let limit = open_limit(app, false);
+    let content = tauri::async_runtime::spawn_blocking(move || {
+        read_text_best_effort(Path::new(&read_path), limit)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))??;
+
+    let (body, _, preset) = resolve_markdown_overrides(app, &content);
+    let (expanded, _) = expand_markdown_includes(body, Path::new(path), limit)?;
+    let body = expanded.as_str();
+    let opts = MarkdownOptions {
+        heading_ids: true,
+        ..markdown_options(app, preset)
+    };
+    let links = link_rewriter(&link_rewrite_rules(app));
+    let fragment = markrust_core::parse_markdown_with_links(body, theme, opts, &links);
+    let toc = markrust_core::build_toc(body, opts, toc_max_level_pref(app));
+    if toc.is_empty() {
+        return Ok((fragment, String::new()));
+    }
+
+    let mut nav =
+        String::from("<nav class=\"export-toc\"><details open><summary>Contents</summary><ul>\n");
+    for entry in toc {
+        nav.push_str(&format!(
+            "<li class=\"toc-level-{}\"><a href=\"#{}\">{}</a></li>\n",
+            entry.level,
+            escape_html(entry.slug.as_deref().unwrap_or_default()),
+            escape_html(&entry.text)
+        ));
+    }
+    nav.push_str("</ul></details></nav>");
+    Ok((fragment, nav))
+}
+
 async fn export_html_inner(
     app: &AppHandle,
     path: &str,
     theme: &str,
     document_font_stack: Option<&str>,
+    include_toc: bool,
 ) -> Result<String, String> {
-    let fragment = render_file_to_html(app.clone(), path.to_string(), theme.to_string()).await?;
-    let syntax_css = markrust_core::get_syntax_theme_css(theme).unwrap_or_default();
-    let base_css = include_str!("../../src/styles.css");
+    let is_markdown = matches!(renderable_extension(path)?.as_str(), "md" | "markdown");
+    let (fragment, toc_nav) = if include_toc && is_markdown {
+        render_markdown_with_toc(app, path, theme).await?
+    } else {
+        let fragment = render_file_view_inner(
+            app.clone(),
+            None,
+            path.to_string(),
+            theme.to_string(),
+            Some(false),
+            Some(ViewMode::Rendered),
+            false,
+            true,
+        )
+        .await?
+        .html;
+        (fragment, String::new())
+    };
+    let body_class = if toc_nav.is_empty() {
+        ""
+    } else {
+        r#" class="has-export-toc""#
+    };
+    let stylesheet = export_stylesheet(app, theme);
 
     let data_theme = match theme {
         "dark" => r#" data-theme="dark""#,
@@ -610,14 +3262,12 @@ async fn export_html_inner(
 <meta name="viewport" content="width=device-width, initial-scale=1.0">
 <title>{title}</title>
 <style>
-{base_css}
-</style>
-<style>
-{syntax_css}
+{stylesheet}
 </style>
 {font_override}
 </head>
-<body>
+<body{body_class}>
+{toc_nav}
 <div class="content-wrapper">
 <div class="markdown-body">
 {fragment}
@@ -628,18 +3278,29 @@ async fn export_html_inner(
     ))
 }
 
+/// Render `path` as a standalone HTML document (stylesheet and syntax theme
+/// inlined) and write it where the user picks. Returns the written path, or
+/// None when the dialog is cancelled.
 #[tauri::command]
 pub(crate) async fn save_html_export(
     app: AppHandle,
     path: String,
     theme: String,
     document_font_stack: Option<String>,
+    include_toc: Option<bool>,
 ) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
 
     check_path_allowed(&app, &path)?;
 
-    let html = export_html_inner(&app, &path, &theme, document_font_stack.as_deref()).await?;
+    let html = export_html_inner(
+        &app,
+        &path,
+        &theme,
+        document_font_stack.as_deref(),
+        include_toc.unwrap_or(false),
+    )
+    .await?;
 
     let app_clone = app.clone();
     let selection = tauri::async_runtime::spawn_blocking(move || {
@@ -664,13 +3325,199 @@ pub(crate) async fn save_html_export(
         save_path.set_extension("html");
     }
 
+    let written = save_path.to_string_lossy().to_string();
     tauri::async_runtime::spawn_blocking(move || {
         fs::write(&save_path, html).map_err(|e| format!("Failed to write HTML: {e}"))
     })
     .await
     .map_err(|e| format!("Join error: {e}"))??;
 
-    Ok(Some("ok".to_string()))
+    Ok(Some(written))
+}
+
+const DEFAULT_PDF_PAGE_SIZE: &str = "a4";
+const DEFAULT_PDF_MARGIN_MM: f64 = 15.0;
+/// A hidden export webview that hasn't printed by then is abandoned.
+const PDF_EXPORT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Page setup for export_pdf: the `pdf_page_size` preference (`a3`, `a4`,
+/// `a5`, `letter`, `legal`) and `pdf_margin_mm` on every side.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct PdfPageSetup {
+    page_size: String,
+    margin_mm: f64,
+}
+
+fn pdf_page_setup(app: &AppHandle) -> PdfPageSetup {
+    let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+    PdfPageSetup {
+        page_size: prefs
+            .pdf_page_size
+            .unwrap_or_else(|| DEFAULT_PDF_PAGE_SIZE.to_string()),
+        margin_mm: prefs
+            .pdf_margin_mm
+            .filter(|m| m.is_finite() && *m >= 0.0)
+            .unwrap_or(DEFAULT_PDF_MARGIN_MM),
+    }
+}
+
+/// Render `path` like save_html_export, load it into a hidden webview and
+/// print that straight to a PDF where the user picks, with no print dialog.
+/// Returns the written path, or None when the dialog is cancelled. Only
+/// WebKitGTK exposes a dialog-free print, so the menu item and palette entry
+/// only exist on Linux; elsewhere this fails with `unsupported`.
+#[tauri::command]
+pub(crate) async fn export_pdf(
+    app: AppHandle,
+    path: String,
+    theme: String,
+) -> Result<Option<String>, AppError> {
+    if !cfg!(target_os = "linux") {
+        return Err(AppError::Unsupported(
+            "PDF export without the print dialog is not supported on this platform".to_string(),
+        ));
+    }
+    check_path_allowed(&app, &path).map_err(AppError::PermissionDenied)?;
+    export_pdf_inner(app, path, theme)
+        .await
+        .map_err(AppError::Io)
+}
+
+async fn export_pdf_inner(
+    app: AppHandle,
+    path: String,
+    theme: String,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let html = export_html_inner(&app, &path, &theme, None, false).await?;
+
+    let app_clone = app.clone();
+    let selection = tauri::async_runtime::spawn_blocking(move || {
+        app_clone
+            .dialog()
+            .file()
+            .add_filter("PDF", &["pdf"])
+            .blocking_save_file()
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?;
+
+    let Some(selection) = selection else {
+        return Ok(None);
+    };
+    let mut save_path = selection
+        .into_path()
+        .map_err(|e| format!("Failed to resolve path: {e}"))?;
+    if save_path.extension().is_none() {
+        save_path.set_extension("pdf");
+    }
+
+    let setup = pdf_page_setup(&app);
+    let data_url = format!(
+        "data:text/html;charset=utf-8;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(html)
+    );
+    let url = Url::parse(&data_url).map_err(|e| format!("Failed to build export URL: {e}"))?;
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    let tx = std::sync::Arc::new(std::sync::Mutex::new(Some(tx)));
+    let target = save_path.clone();
+    let label = format!("pdf-export-{}", uuid::Uuid::new_v4());
+    let window = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::External(url))
+        .visible(false)
+        .on_page_load(move |window, payload| {
+            if payload.event() != tauri::webview::PageLoadEvent::Finished {
+                return;
+            }
+            let Some(tx) = tx.lock().ok().and_then(|mut t| t.take()) else {
+                return;
+            };
+            print_webview_to_pdf(&window, &target, &setup, tx);
+        })
+        .build()
+        .map_err(|e| format!("Failed to create export window: {e}"))?;
+
+    let result = tokio::time::timeout(PDF_EXPORT_TIMEOUT, rx).await;
+    let _ = window.destroy();
+    match result {
+        Ok(Ok(outcome)) => outcome?,
+        Ok(Err(_)) => return Err("PDF export was interrupted".to_string()),
+        Err(_) => return Err("PDF export timed out".to_string()),
+    }
+    Ok(Some(save_path.to_string_lossy().to_string()))
+}
+
+/// Print `window`'s page to `target` through WebKitGTK's print operation,
+/// reporting on `done` once the file is written or printing fails.
+#[cfg(target_os = "linux")]
+fn print_webview_to_pdf(
+    window: &tauri::WebviewWindow,
+    target: &Path,
+    setup: &PdfPageSetup,
+    done: tokio::sync::oneshot::Sender<Result<(), String>>,
+) {
+    use webkit2gtk::PrintOperationExt;
+
+    let uri = match Url::from_file_path(target) {
+        Ok(uri) => uri.to_string(),
+        Err(()) => {
+            let _ = done.send(Err("Failed to resolve export path".to_string()));
+            return;
+        }
+    };
+    let paper = match setup.page_size.to_ascii_lowercase().as_str() {
+        "a3" => "iso_a3",
+        "a5" => "iso_a5",
+        "letter" => "na_letter",
+        "legal" => "na_legal",
+        _ => "iso_a4",
+    };
+    let margin_mm = setup.margin_mm;
+    let done = std::sync::Arc::new(std::sync::Mutex::new(Some(done)));
+    let result = window.with_webview(move |webview| {
+        let settings = gtk::PrintSettings::new();
+        settings.set_printer("Print to File");
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(&uri));
+        let page_setup = gtk::PageSetup::new();
+        page_setup.set_paper_size(&gtk::PaperSize::new(Some(paper)));
+        page_setup.set_top_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_bottom_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_left_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_right_margin(margin_mm, gtk::Unit::Mm);
+
+        let operation = webkit2gtk::PrintOperation::new(&webview.inner());
+        operation.set_print_settings(&settings);
+        operation.set_page_setup(&page_setup);
+        let on_failed = done.clone();
+        operation.connect_failed(move |_, e| {
+            if let Some(tx) = on_failed.lock().ok().and_then(|mut t| t.take()) {
+                let _ = tx.send(Err(format!("Failed to print PDF: {e}")));
+            }
+        });
+        // `finished` also fires after `failed`; the sender is gone by then.
+        operation.connect_finished(move |_| {
+            if let Some(tx) = done.lock().ok().and_then(|mut t| t.take()) {
+                let _ = tx.send(Ok(()));
+            }
+        });
+        operation.print();
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to reach export webview: {e}");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn print_webview_to_pdf(
+    _window: &tauri::WebviewWindow,
+    _target: &Path,
+    _setup: &PdfPageSetup,
+    done: tokio::sync::oneshot::Sender<Result<(), String>>,
+) {
+    let _ = done.send(Err(
+        "PDF export without the print dialog is not supported here".to_string(),
+    ));
 }
 
 // --- Tauri commands: dialogs ---
@@ -686,12 +3533,18 @@ pub(crate) async fn open_file_dialog(app: AppHandle) -> Result<Option<String>, S
             .file()
             .add_filter(
                 "Supported",
-                &["md", "markdown", "json", "yaml", "yml", "txt", "pdf"],
+                &[
+                    "md", "markdown", "json", "yaml", "yml", "toml", "xml", "txt", "log", "csv",
+                    "pdf",
+                ],
             )
             .add_filter("Markdown", &["md", "markdown"])
             .add_filter("JSON", &["json"])
             .add_filter("YAML", &["yaml", "yml"])
-            .add_filter("Text", &["txt"])
+            .add_filter("TOML", &["toml"])
+            .add_filter("XML", &["xml"])
+            .add_filter("Text", &["txt", "log"])
+            .add_filter("CSV", &["csv"])
             .add_filter("PDF", &["pdf"])
             .blocking_pick_file()
     })
@@ -748,10 +3601,6 @@ pub(crate) async fn create_new_markdown_file(app: AppHandle) -> Result<Option<St
         .open(&path)
         .map_err(|e| format!("Failed to create file: {e}"))?;
 
-    if let Err(e) = push_to_recents(&app, &path.to_string_lossy()).await {
-        eprintln!("Failed to push recents (create_new_markdown_file): {e}");
-    }
-
     let window_label = crate::window::create_window_with_file(&app, Some(path))
         .await
         .map_err(|e| format!("Failed to open window: {e}"))?;
@@ -759,13 +3608,141 @@ pub(crate) async fn create_new_markdown_file(app: AppHandle) -> Result<Option<St
     Ok(Some(window_label))
 }
 
+/// The window showing `new_path`, when it isn't the one showing `current_path`.
+fn other_window_for<'a>(
+    open: &'a HashMap<String, String>,
+    current_path: &str,
+    new_path: &str,
+) -> Option<&'a String> {
+    open.get(new_path)
+        .filter(|owner| open.get(current_path) != Some(*owner))
+}
+
+/// Move `from`'s open_windows entry to `to`, returning the window label.
+/// Refuses to replace another window's entry for `to`.
+fn retarget_open_window(
+    open: &mut HashMap<String, String>,
+    from: &str,
+    to: &str,
+) -> Result<Option<String>, String> {
+    if other_window_for(open, from, to).is_some() {
+        return Err(format!(
+            "{} is already open in another window",
+            file_name_or_path(to)
+        ));
+    }
+    let label = open.remove(from);
+    if let Some(label) = &label {
+        open.insert(to.to_string(), label.clone());
+    }
+    Ok(label)
+}
+
+/// Save `content` under a name picked in a save dialog (starting beside
+/// `current_path`, keeping its extension when none is typed) and move
+/// everything tracking `current_path` over: the viewer window's
+/// open_windows entry and title, the session, recents and file watchers.
+/// Returns the new path, or None when the dialog is cancelled.
+#[tauri::command]
+pub(crate) async fn save_file_as(
+    app: AppHandle,
+    current_path: String,
+    content: String,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    check_path_allowed(&app, &current_path)?;
+    let current = PathBuf::from(&current_path);
+    let extension = current.extension().map(|e| e.to_os_string());
+
+    let app_clone = app.clone();
+    let dialog_current = current.clone();
+    let selection = tauri::async_runtime::spawn_blocking(move || {
+        let mut dialog = app_clone.dialog().file();
+        if let Some(dir) = dialog_current.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = dialog_current.file_name().and_then(|n| n.to_str()) {
+            dialog = dialog.set_file_name(name);
+        }
+        dialog.blocking_save_file()
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))?;
+
+    let Some(selection) = selection else {
+        return Ok(None);
+    };
+    let mut save_path = selection
+        .into_path()
+        .map_err(|e| format!("Failed to resolve path: {e}"))?;
+    if let (None, Some(ext)) = (save_path.extension(), extension) {
+        save_path.set_extension(ext);
+    }
+    let new_path = pathbuf_to_string(&save_path);
+
+    // Another window already shows the chosen file: bring it forward rather
+    // than leave two windows on one path.
+    let state = app.state::<AppState>();
+    let owner = {
+        let open = state.open_windows.read().await;
+        other_window_for(&open, &current_path, &new_path).cloned()
+    };
+    if let Some(owner) = owner {
+        if let Some(window) = app.get_webview_window(&owner) {
+            let _ = window.set_focus();
+        }
+        return Err(format!(
+            "{} is already open in another window",
+            file_name_or_path(&new_path)
+        ));
+    }
+
+    allow_path(&app, &new_path);
+    let content = content_for_save(&app, &new_path, content);
+    if save_path.exists() {
+        atomic_write_file(&save_path, &content)?;
+    } else {
+        fs::write(&save_path, &content).map_err(|e| format!("Failed to write file: {e}"))?;
+    }
+    invalidate_cache_for_path_sync(&app, &new_path);
+    if new_path == current_path {
+        return Ok(Some(new_path));
+    }
+
+    let label = {
+        let mut open = state.open_windows.write().await;
+        retarget_open_window(&mut open, &current_path, &new_path)?
+    };
+    if let Some(label) = label {
+        // Sequential awaits: each call is a read-modify-write under pref_lock.
+        if let Err(e) = session_remove(&app, &current_path).await {
+            eprintln!("Failed to update session list: {e}");
+        }
+        if let Err(e) = session_add(&app, &new_path).await {
+            eprintln!("Failed to update session list: {e}");
+        }
+        if let Some(window) = app.get_webview_window(&label) {
+            if let Some(name) = save_path.file_name().and_then(|n| n.to_str()) {
+                let _ = window.set_title(&format!("BoltPage - {name}"));
+            }
+            let _ = window.emit(EVENT_FILE_SAVED_AS, &new_path);
+        }
+    }
+    if let Err(e) = push_to_recents(&app, &new_path).await {
+        eprintln!("Failed to push recents (save_file_as): {e}");
+    }
+    crate::watchers::move_subscribers(&app, &current_path, &new_path).await;
+
+    Ok(Some(new_path))
+}
+
 // --- Tests ---
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::env;
-    use std::num::NonZeroUsize;
 
     fn unique_temp_dir() -> PathBuf {
         let dir = env::temp_dir().join(format!("boltpage-tests-{}", uuid::Uuid::new_v4()));
@@ -773,34 +3750,159 @@ mod tests {
         dir
     }
 
+    /// A Markdown cache key for `path` with every option at its default.
+    fn test_key(path: &str) -> CacheKey {
+        CacheKey {
+            path: path.to_string(),
+            size: 1,
+            mtime_secs: 1,
+            preset: MarkdownPreset::GitHub,
+            overrides: DocumentOverrides::default(),
+            show_whitespace: false,
+            view: ViewMode::Rendered,
+            link_rules: Vec::new(),
+            preserve_json: false,
+            code_line_numbers: false,
+            sanitize: SanitizeLevel::Strict,
+            autolink: false,
+            smart_punctuation: false,
+            foldable_headings: false,
+            emoji: false,
+            line_anchors: false,
+            plain_text: PlainTextOptions::default(),
+            content_hash: None,
+        }
+    }
+
     #[test]
     fn remove_cache_entries_for_path_removes_all_versions() {
         let mut cache = LruCache::new(NonZeroUsize::new(8).unwrap());
         let key_a1 = CacheKey {
-            path: "/tmp/a.md".to_string(),
             size: 10,
-            mtime_secs: 1,
+            ..test_key("/tmp/a.md")
         };
         let key_a2 = CacheKey {
-            path: "/tmp/a.md".to_string(),
             size: 11,
             mtime_secs: 2,
+            ..test_key("/tmp/a.md")
         };
         let key_b = CacheKey {
-            path: "/tmp/b.md".to_string(),
             size: 20,
-            mtime_secs: 1,
+            ..test_key("/tmp/b.md")
         };
 
-        cache.put(key_a1.clone(), "old".to_string());
-        cache.put(key_a2.clone(), "new".to_string());
-        cache.put(key_b.clone(), "other".to_string());
+        cache.put(
+            key_a1.clone(),
+            CachedHtml::new("old".to_string(), Vec::new()),
+        );
+        cache.put(
+            key_a2.clone(),
+            CachedHtml::new("new".to_string(), Vec::new()),
+        );
+        cache.put(
+            key_b.clone(),
+            CachedHtml::new("other".to_string(), vec!["/tmp/part.md".to_string()]),
+        );
 
         remove_cache_entries_for_path(&mut cache, "/tmp/a.md");
 
         assert!(cache.get(&key_a1).is_none());
         assert!(cache.get(&key_a2).is_none());
-        assert_eq!(cache.get(&key_b).cloned(), Some("other".to_string()));
+        assert_eq!(cache.get(&key_b).map(|v| v.html.as_str()), Some("other"));
+
+        // Editing an included file drops the documents that include it.
+        remove_cache_entries_for_path(&mut cache, "/tmp/part.md");
+        assert!(cache.get(&key_b).is_none());
+    }
+
+    #[test]
+    fn validate_open_target_refuses_oversized_files() {
+        let dir = unique_temp_dir();
+        let big = dir.join("big.json");
+        let file = fs::File::create(&big).unwrap();
+        file.set_len(2048).unwrap();
+        drop(file);
+
+        let refused = validate_open_target_inner(&big, 1024);
+        assert!(!refused.ok);
+        assert_eq!(refused.size, 2048);
+        assert!(refused.reason.unwrap().contains("limit"));
+
+        let accepted = validate_open_target_inner(&big, 4096);
+        assert!(accepted.ok);
+        assert_eq!(accepted.kind.as_deref(), Some(KIND_JSON));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn validate_open_target_refuses_fifos() {
+        let dir = unique_temp_dir();
+        let fifo = dir.join("pipe.md");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let verdict = validate_open_target_inner(&fifo, MAX_OPEN_FILE_BYTES);
+        assert!(!verdict.ok);
+        assert!(verdict.reason.unwrap().contains("named pipe"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn event_targets_file_matches_atomic_save_renames() {
+        let dir = unique_temp_dir();
+        let target = dir.join("note.md");
+        let temp = dir.join(".note.md.swp");
+        fs::write(&target, "v1").unwrap();
+
+        // Rename-over reports [from, to]; the destination is the target.
+        assert!(event_targets_file(&[temp.clone(), target.clone()], &target));
+        assert!(event_targets_file(std::slice::from_ref(&target), &target));
+        assert!(!event_targets_file(&[temp, dir.join("other.md")], &target));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn html_cache_capacity_rejects_zero_and_absurd_values() {
+        assert!(html_cache_capacity(0).is_err());
+        assert!(html_cache_capacity(MAX_HTML_CACHE_CAPACITY + 1).is_err());
+        assert_eq!(html_cache_capacity(200).unwrap().get(), 200);
+
+        let mut cache: LruCache<u32, u32> = LruCache::new(html_cache_capacity(3).unwrap());
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        cache.get(&0);
+        cache.resize(html_cache_capacity(1).unwrap());
+        assert_eq!(cache.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn evict_idle_entries_only_drops_large_stale_renders() {
+        let now = Instant::now();
+        let stale = now - Duration::from_secs(120);
+        let entry = |len: usize, last_access: Instant| CachedHtml {
+            html: "x".repeat(len),
+            last_access,
+            deps: Vec::new(),
+        };
+        let mut cache = LruCache::new(NonZeroUsize::new(8).unwrap());
+        cache.put(test_key("/big-stale"), entry(100, stale));
+        cache.put(test_key("/big-fresh"), entry(100, now));
+        cache.put(test_key("/small-stale"), entry(10, stale));
+
+        let evicted = evict_idle_entries(&mut cache, now, Duration::from_secs(60), 50);
+
+        assert_eq!(evicted, 1);
+        assert!(cache.peek(&test_key("/big-stale")).is_none());
+        assert!(cache.peek(&test_key("/big-fresh")).is_some());
+        assert!(cache.peek(&test_key("/small-stale")).is_some());
     }
 
     #[test]
@@ -876,6 +3978,360 @@ mod tests {
         fs::remove_dir_all(outside_dir).unwrap();
     }
 
+    #[test]
+    fn line_aligned_window_snaps_to_whole_lines() {
+        let dir = unique_temp_dir();
+        let path = dir.join("big.log");
+        // Five 10-byte lines: "line-0000\n" .. "line-0004\n".
+        let body: String = (0..5).map(|i| format!("line-{i:04}\n")).collect();
+        fs::write(&path, &body).unwrap();
+
+        // Mid-line start moves to the next line; the end finishes its line.
+        let w = read_line_aligned_window(&path, 13, 12).unwrap();
+        assert_eq!(w.text, "line-0002\nline-0003\n");
+        assert_eq!((w.start, w.end, w.total_size), (20, 40, 50));
+
+        // An offset exactly on a line start is kept.
+        let w = read_line_aligned_window(&path, 10, 10).unwrap();
+        assert_eq!(w.text, "line-0001\n");
+
+        // The tail window stops at EOF.
+        let w = read_line_aligned_window(&path, 40, 1000).unwrap();
+        assert_eq!(w.text, "line-0004\n");
+
+        // Huge lengths saturate instead of overflowing.
+        let w = read_line_aligned_window(&path, 13, u64::MAX).unwrap();
+        assert_eq!(w.text, "line-0002\nline-0003\nline-0004\n");
+
+        // Past EOF: empty, but still reports the file size.
+        let w = read_line_aligned_window(&path, 500, 10).unwrap();
+        assert_eq!(w.text, "");
+        assert_eq!(w.total_size, 50);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn line_aligned_window_bounds_overshoot_on_long_lines() {
+        let dir = unique_temp_dir();
+        let path = dir.join("one-line.txt");
+        fs::write(&path, "x".repeat(100_000)).unwrap();
+
+        let w = read_line_aligned_window(&path, 0, 100).unwrap();
+        assert!(w.end - w.start <= 100 + WINDOW_ALIGN_SLACK);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn trim_trailing_whitespace_keeps_fences_and_hard_breaks() {
+        let src = "Line one   \nbreak  \n\t\n```rust\nlet x = 1;   \n```  \ntail \t\n\n\n";
+        assert_eq!(
+            trim_trailing_whitespace(src, true, true),
+            "Line one  \nbreak  \n\n```rust\nlet x = 1;   \n```\ntail\n"
+        );
+        assert_eq!(
+            trim_trailing_whitespace(src, true, false),
+            "Line one\nbreak\n\n```rust\nlet x = 1;   \n```\ntail\n"
+        );
+        // Non-Markdown files have no fences to protect.
+        assert_eq!(
+            trim_trailing_whitespace("a: 1  \n```\nb  \n", false, true),
+            "a: 1\n```\nb\n"
+        );
+    }
+
+    #[test]
+    fn save_normalizes_line_endings_and_final_newline() {
+        let mixed = "a\r\nb\nc\r\n".to_string();
+        assert_eq!(
+            normalize_for_save(mixed.clone(), Some("lf"), None, false),
+            "a\nb\nc\n"
+        );
+        assert_eq!(
+            normalize_for_save(mixed.clone(), Some("crlf"), None, false),
+            "a\r\nb\r\nc\r\n"
+        );
+        // auto follows the file on disk, then the content itself.
+        assert_eq!(
+            normalize_for_save(mixed.clone(), Some("auto"), Some("x\ny\n"), false),
+            "a\nb\nc\n"
+        );
+        assert_eq!(
+            normalize_for_save(mixed.clone(), Some("auto"), Some("one line"), false),
+            "a\r\nb\r\nc\r\n"
+        );
+        assert_eq!(normalize_for_save(mixed.clone(), None, None, false), mixed);
+        assert_eq!(
+            normalize_for_save(mixed.clone(), Some("cr"), None, false),
+            mixed
+        );
+
+        assert_eq!(
+            normalize_for_save("a\r\nb".into(), None, None, true),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(
+            normalize_for_save("a".into(), Some("crlf"), None, true),
+            "a\r\n"
+        );
+        assert_eq!(normalize_for_save("a\n".into(), None, None, true), "a\n");
+        assert_eq!(normalize_for_save(String::new(), None, None, true), "");
+    }
+
+    #[test]
+    fn trim_trailing_whitespace_preserves_crlf_and_adds_final_newline() {
+        assert_eq!(
+            trim_trailing_whitespace("a \r\nb\t", true, true),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(trim_trailing_whitespace("  \n\n", true, true), "");
+        // A hard break on the final line is dropped.
+        assert_eq!(trim_trailing_whitespace("end  ", true, true), "end\n");
+    }
+
+    #[test]
+    fn expand_markdown_includes_nests_and_skips_fences() {
+        let dir = unique_temp_dir();
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        fs::write(
+            dir.join("parts/intro.md"),
+            "---\ntitle: x\n---\nIntro\n<!-- include: detail.md -->\n",
+        )
+        .unwrap();
+        fs::write(dir.join("parts/detail.md"), "Detail").unwrap();
+        let main = dir.join("main.md");
+        let body = "# Doc\n{{include: parts/intro.md}}\n```\n{{include: parts/intro.md}}\n```\n{{include: gone.md}}\n";
+        fs::write(&main, body).unwrap();
+
+        let (out, included) = expand_markdown_includes(body, &main, None).unwrap();
+        assert!(out.starts_with("# Doc\n\nIntro\n\nDetail\n\n\n"));
+        assert!(!out.contains("title: x"));
+        assert!(out.contains("```\n{{include: parts/intro.md}}\n```"));
+        assert!(out.contains("> **Include failed:** `gone.md`"));
+        assert_eq!(included.len(), 2);
+        assert!(included[0].ends_with("intro.md") && included[1].ends_with("detail.md"));
+
+        // Each expanded line knows its body line; include output has none.
+        let lines = expand_markdown_includes_mapped(body, &main, None)
+            .unwrap()
+            .lines;
+        let mut expected = vec![Some(1)];
+        expected.extend([None; 6]);
+        expected.extend([Some(3), Some(4), Some(5)]);
+        expected.extend([None; 3]);
+        assert_eq!(lines, expected);
+
+        // Task and block lines map back to the file; included ones lose theirs.
+        let html = r#"<input type="checkbox" data-task-line="8"/><input type="checkbox" data-task-line="2"/>"#;
+        assert_eq!(
+            remap_source_lines(html, &lines, 3),
+            r#"<input type="checkbox" data-task-line="6"/><input type="checkbox"/>"#
+        );
+        assert_eq!(
+            remap_source_lines(
+                r#"<h1 data-source-line="1"><p data-source-line="3">"#,
+                &lines,
+                3
+            ),
+            r#"<h1 data-source-line="4"><p>"#
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn expand_markdown_includes_rejects_cycles_and_escapes() {
+        let dir = unique_temp_dir();
+        let a = dir.join("a.md");
+        fs::write(&a, "{{include: b.md}}").unwrap();
+        fs::write(dir.join("b.md"), "{{include: a.md}}").unwrap();
+        let err = expand_markdown_includes("{{include: b.md}}", &a, None).unwrap_err();
+        assert_eq!(
+            err,
+            AppError::ParseError("Include cycle: a.md -> b.md -> a.md".to_string())
+        );
+
+        // Resolves but can't be read as a file: an I/O failure, not a parse one.
+        fs::create_dir(dir.join("sub")).unwrap();
+        let err = expand_markdown_includes("{{include: sub}}", &a, None).unwrap_err();
+        assert_eq!(err.code(), "io", "{err}");
+
+        let outside = unique_temp_dir();
+        fs::write(outside.join("secret.md"), "secret").unwrap();
+        let escape = format!("{{{{include: {}}}}}", outside.join("secret.md").display());
+        let (out, included) = expand_markdown_includes(&escape, &a, None).unwrap();
+        assert!(out.contains("Include skipped"));
+        assert!(included.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    fn stylesheet_cache_refreshes_on_path_toggle_and_invalidation() {
+        let dir = unique_temp_dir();
+        let a = dir.join("a.css");
+        let b = dir.join("b.css");
+        fs::write(&a, "body{color:red}").unwrap();
+        fs::write(&b, "body{color:blue}").unwrap();
+        let (a, b) = (pathbuf_to_string(&a), pathbuf_to_string(&b));
+        let read = |path: &str| read_custom_css(Some(path)).unwrap_or_default();
+
+        let mut cache = StylesheetCache::new();
+        assert!(cached_stylesheet(&mut cache, "light", Some(&a), || read(&a)).contains("red"));
+        assert!(cached_stylesheet(&mut cache, "light", Some(&b), || read(&b)).contains("blue"));
+
+        // An edit in place is served stale until the caches are dropped.
+        fs::write(&a, "body{color:green}").unwrap();
+        assert!(cached_stylesheet(&mut cache, "light", Some(&a), || read(&a)).contains("red"));
+        cache.clear();
+        assert!(cached_stylesheet(&mut cache, "light", Some(&a), || read(&a)).contains("green"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn find_matches_reports_utf16_positions() {
+        let text = "Café cafe\n\nCAFE café-bar\n";
+        let at = |line, col, length| FindMatch { line, col, length };
+
+        assert_eq!(
+            find_matches(text, "café", false, false, false).unwrap(),
+            [at(1, 0, 4), at(3, 5, 4)]
+        );
+        assert_eq!(
+            find_matches(text, "cafe", true, true, false).unwrap(),
+            [at(1, 5, 4)]
+        );
+        assert_eq!(
+            find_matches(text, r"caf\w", false, true, true).unwrap(),
+            [at(1, 0, 4), at(1, 5, 4), at(3, 0, 4), at(3, 5, 4)]
+        );
+        assert_eq!(find_matches(text, "x*", false, false, true).unwrap(), []);
+        assert!(find_matches(text, "(", false, false, true)
+            .unwrap_err()
+            .starts_with("Invalid pattern"));
+        // Literal mode escapes metacharacters.
+        assert_eq!(
+            find_matches("a(b", "(", true, false, false).unwrap(),
+            [at(1, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn save_as_retargets_only_its_own_window() {
+        let mut open = HashMap::from([
+            ("/a.md".to_string(), "w1".to_string()),
+            ("/taken.md".to_string(), "w2".to_string()),
+        ]);
+        assert_eq!(
+            other_window_for(&open, "/a.md", "/taken.md"),
+            Some(&"w2".to_string())
+        );
+        assert!(retarget_open_window(&mut open, "/a.md", "/taken.md").is_err());
+        assert_eq!(open.get("/taken.md").unwrap(), "w2");
+        assert_eq!(open.get("/a.md").unwrap(), "w1");
+
+        assert_eq!(
+            retarget_open_window(&mut open, "/a.md", "/b.md").unwrap(),
+            Some("w1".to_string())
+        );
+        assert_eq!(open.get("/b.md").unwrap(), "w1");
+        assert!(!open.contains_key("/a.md"));
+        // Saving over the file it already shows is not a takeover.
+        assert_eq!(other_window_for(&open, "/b.md", "/b.md"), None);
+        // A fileless caller moves nothing.
+        assert_eq!(
+            retarget_open_window(&mut open, "/none.md", "/c.md").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn search_snippet_keeps_context_and_marks_cuts() {
+        assert_eq!(search_snippet("  a needle here  ", 4, 10), "a needle here");
+        let long = format!("{}needle{}", "é".repeat(50), "z".repeat(50));
+        let start = "é".repeat(50).len();
+        assert_eq!(
+            search_snippet(&long, start, start + 6),
+            format!("…{}needle{}…", "é".repeat(40), "z".repeat(40))
+        );
+        // A match longer than the snippet is cut, and says so.
+        let huge = "n".repeat(200);
+        assert_eq!(
+            search_snippet(&huge, 0, 200),
+            format!("{}…", "n".repeat(160))
+        );
+    }
+
+    #[test]
+    fn decode_text_handles_boms_utf16_and_latin1() {
+        assert_eq!(decode_text(b"\xEF\xBB\xBFcaf\xC3\xA9"), "café");
+        assert_eq!(decode_text(b"\xFF\xFEh\x00i\x00"), "hi");
+        assert_eq!(decode_text(b"\xFE\xFF\x00h\x00i"), "hi");
+        assert_eq!(decode_text(b"h\x00i\x00\n\x00"), "hi\n");
+        assert_eq!(
+            decode_text(b"caf\xE9 \x93q\x94 \x80"),
+            "café \u{201C}q\u{201D} €"
+        );
+        assert_eq!(decode_text("plain ✓".as_bytes()), "plain ✓");
+    }
+
+    #[test]
+    fn decode_text_keeps_utf8_with_one_stray_byte() {
+        assert_eq!(
+            decode_text(b"caf\xC3\xA9 \xE2\x9C\x93 \xFF na\xC3\xAFve"),
+            "café ✓ \u{FFFD} naïve"
+        );
+    }
+
+    #[test]
+    fn front_matter_title_survives_a_character_split_at_the_cut() {
+        let dir = unique_temp_dir();
+        let path = dir.join("note.md");
+        let mut text = "---\ntitle: Naïve\n---\n".to_string();
+        text.push_str(&"a".repeat(FRONT_MATTER_TITLE_BYTES as usize - text.len() - 1));
+        text.push_str("é and more");
+        fs::write(&path, &text).unwrap();
+        assert_eq!(front_matter_title(&path).as_deref(), Some("Naïve"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn editable_reads_refuse_files_that_are_not_utf8() {
+        let dir = unique_temp_dir();
+        let utf8 = dir.join("utf8.md");
+        let latin1 = dir.join("latin1.md");
+        fs::write(&utf8, "\u{FEFF}café").unwrap();
+        fs::write(&latin1, b"caf\xE9").unwrap();
+
+        // The BOM survives, so saving writes back the same bytes.
+        assert_eq!(read_editable_text(&utf8, None).unwrap(), "\u{FEFF}café");
+        let err = read_editable_text(&latin1, None).unwrap_err();
+        assert_eq!(err.code(), "invalid_encoding");
+        let err = read_editable_text(&utf8, Some(4)).unwrap_err();
+        assert_eq!(err.code(), "too_large");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn set_task_marker_flips_only_the_state_character() {
+        let src = "# List\r\n  - [ ] one\r\n> 1. [x] two\n* [X]\n- plain\n-[ ] tight\n";
+        assert_eq!(
+            set_task_marker(src, 2, true).unwrap(),
+            "# List\r\n  - [x] one\r\n> 1. [x] two\n* [X]\n- plain\n-[ ] tight\n"
+        );
+        assert_eq!(
+            set_task_marker(src, 3, false).unwrap(),
+            "# List\r\n  - [ ] one\r\n> 1. [ ] two\n* [X]\n- plain\n-[ ] tight\n"
+        );
+        assert!(set_task_marker(src, 4, false).unwrap().contains("* [ ]\n"));
+        assert!(set_task_marker(src, 5, true).is_err());
+        assert!(set_task_marker(src, 6, true).is_err());
+        assert!(set_task_marker(src, 40, true).is_err());
+    }
+
     #[test]
     fn atomic_write_file_replaces_contents() {
         let dir = unique_temp_dir();
@@ -901,4 +4357,120 @@ mod tests {
 
         fs::remove_dir_all(dir).unwrap();
     }
+
+    #[test]
+    fn file_content_hash_streams_across_chunks() {
+        let dir = unique_temp_dir();
+        let empty = dir.join("empty.txt");
+        fs::write(&empty, "").unwrap();
+        assert_eq!(
+            hash_file_contents(&empty).unwrap(),
+            "99aa06d3014798d86001c324468d497f"
+        );
+
+        // Larger than one read chunk: same bytes, same hash, wherever they live.
+        let big = vec![b'x'; 64 * 1024 * 2 + 17];
+        let a = dir.join("a.md");
+        let b = dir.join("b.md");
+        fs::write(&a, &big).unwrap();
+        fs::write(&b, &big).unwrap();
+        assert_eq!(
+            hash_file_contents(&a).unwrap(),
+            hash_file_contents(&b).unwrap()
+        );
+        fs::write(&b, b"y").unwrap();
+        assert_ne!(
+            hash_file_contents(&a).unwrap(),
+            hash_file_contents(&b).unwrap()
+        );
+        assert_eq!(hash_contents(&big), hash_file_contents(&a).unwrap());
+        assert!(hash_file_contents(&dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn count_file_lines_counts_an_unterminated_last_line() {
+        let dir = unique_temp_dir();
+        let count = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            count_file_lines(&path).unwrap()
+        };
+        assert_eq!(count("empty.txt", b""), 0);
+        assert_eq!(count("one.txt", b"a"), 1);
+        assert_eq!(count("two.txt", b"a\nb\n"), 2);
+        assert_eq!(count("crlf.txt", b"a\r\nb"), 2);
+        // Newlines straddling a read chunk boundary.
+        let big = b"x\n".repeat(64 * 1024 + 3);
+        assert_eq!(count("big.txt", &big), 64 * 1024 + 3);
+    }
+
+    #[test]
+    fn read_file_tail_returns_whole_new_lines_and_flags_truncation() {
+        let dir = unique_temp_dir();
+        let path = dir.join("app.log");
+        fs::write(&path, "one\n").unwrap();
+        let start = fs::metadata(&path).unwrap().len();
+
+        fs::write(&path, "one\ntwo\nthr").unwrap();
+        let (text, end) = read_file_tail(&path, start).unwrap().unwrap();
+        assert_eq!(text, "two\n");
+        assert_eq!(end, 8);
+        // The partial line is held back until its newline arrives.
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(
+            read_file_tail(&path, end).unwrap(),
+            Some(("three\n".to_string(), 14))
+        );
+        assert_eq!(
+            read_file_tail(&path, 14).unwrap(),
+            Some((String::new(), 14))
+        );
+
+        // Decoded like a full render, not lossily as UTF-8.
+        fs::write(&path, b"one\ntwo\nthree\ncaf\xE9\n").unwrap();
+        assert_eq!(
+            read_file_tail(&path, 14).unwrap(),
+            Some(("café\n".to_string(), 19))
+        );
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(read_file_tail(&path, 14).unwrap(), None);
+    }
+
+    #[test]
+    fn open_size_limit_defaults_to_50_mib_and_zero_disables_it() {
+        assert_eq!(open_size_limit(None), Some(50 * 1024 * 1024));
+        assert_eq!(open_size_limit(Some(0)), None);
+        assert_eq!(open_size_limit(Some(10)), Some(10 * 1024 * 1024));
+        // Never past the hard cap, which refuses instead of asking.
+        assert_eq!(open_size_limit(Some(u64::MAX)), Some(MAX_OPEN_FILE_BYTES));
+
+        let mib = 1024 * 1024;
+        assert_eq!(check_open_size("a.md", 2 * mib, Some(2 * mib)), Ok(()));
+        assert_eq!(
+            check_open_size("/x/a.md", 2 * mib + 1, Some(2 * mib)),
+            Err(AppError::TooLarge(
+                "a.md is 3 MiB, over the 2 MiB limit for opening files".to_string()
+            ))
+        );
+        // Confirming lifts the preference, never the hard cap.
+        assert_eq!(check_open_size("a.md", 2 * mib + 1, None), Ok(()));
+        assert_eq!(
+            check_open_size("a.md", MAX_OPEN_FILE_BYTES + 1, None).map_err(|e| e.code()),
+            Err("unsupported")
+        );
+    }
+
+    #[test]
+    fn log_render_wraps_leveled_lines_and_escapes_the_rest() {
+        let log = "10:00 INFO start <main>\nplain line\n10:01 ERROR boom\nWARN trailing";
+        let html = render_log_html(log, false, PlainTextOptions::default());
+        assert!(html.contains("<pre class=\"plain-text log-text nowrap\">"));
+        assert!(html.contains(
+            "<span class=\"log-info\">10:00 INFO start &lt;main&gt;</span>\nplain line\n\
+             <span class=\"log-error\">10:01 ERROR boom</span>\n\
+             <span class=\"log-warn\">WARN trailing</span></pre>"
+        ));
+        assert_eq!(renderable_extension("x/app.LOG").unwrap(), "log");
+    }
 }
@@ -23,9 +23,11 @@ macro_rules! debug_log {
 }
 
 mod constants;
+mod error;
 mod io;
 mod menu;
 mod prefs;
+mod remote;
 mod watchers;
 mod window;
 mod workspace;
@@ -36,8 +38,14 @@ mod workspace;
 pub(crate) static QUITTING: std::sync::atomic::AtomicBool =
     std::sync::atomic::AtomicBool::new(false);
 
+/// Set once `flush_before_quit` has run, so the ExitRequested handler lets the
+/// follow-up `app.exit(0)` through instead of intercepting it again.
+static QUIT_FLUSHED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
 /// Type alias for resize task map to reduce complexity
 type ResizeTaskMap = HashMap<String, (tauri::async_runtime::JoinHandle<()>, u32, u32)>;
+/// Debounced position saves per window label, with the latest logical x, y.
+type MoveTaskMap = HashMap<String, (tauri::async_runtime::JoinHandle<()>, i32, i32)>;
 
 /// Application state with optimized concurrency patterns
 ///
@@ -52,9 +60,16 @@ struct AppState {
     /// Writes on every resize event, so Mutex is appropriate
     resize_tasks: Arc<Mutex<ResizeTaskMap>>,
 
+    /// Debounced move tasks per preview window label, like resize_tasks
+    move_tasks: Arc<Mutex<MoveTaskMap>>,
+
     /// HTML render cache: (path, size, mtime_secs) -> HTML
     /// Read-heavy workload with LRU eviction
-    html_cache: Arc<RwLock<LruCache<io::CacheKey, String>>>,
+    html_cache: Arc<RwLock<LruCache<io::CacheKey, io::CachedHtml>>>,
+
+    /// html_cache lookups by render_file_view, for io::get_cache_stats.
+    cache_hits: std::sync::atomic::AtomicU64,
+    cache_misses: std::sync::atomic::AtomicU64,
 
     /// Set of canonicalized paths the user has explicitly opened.
     /// Uses std::sync::RwLock (not tokio) so sync commands can read it.
@@ -82,6 +97,39 @@ struct AppState {
     /// reclaim only closes it while still pristine (no file in open_windows), so
     /// it never touches a document-bearing window.
     startup_blank_label: std::sync::Mutex<Option<String>>,
+
+    /// Preview windows currently showing the inline editor pane. Such a window
+    /// is its file's editor, so a separate editor window is not spawned for it.
+    inline_editors: std::sync::Mutex<HashSet<String>>,
+
+    /// Maps canonical file_path -> editor window label, so a file has at most
+    /// one editor window however its path was spelled.
+    editor_windows: Arc<RwLock<HashMap<String, String>>>,
+
+    /// Files of recently closed preview windows, most recent last, for
+    /// "Reopen Closed Window". Capped at window::RECENTLY_CLOSED_CAP.
+    recently_closed: std::sync::Mutex<std::collections::VecDeque<String>>,
+
+    /// Current webview zoom per window label; webviews can't report it back.
+    zoom_levels: std::sync::Mutex<HashMap<String, f64>>,
+
+    /// Labels of windows pinned with View > Always on Top. Not persisted.
+    always_on_top: std::sync::Mutex<HashSet<String>>,
+
+    /// Fileless documents (`--stdin`) by window label, served to the window by
+    /// window::get_inline_content and dropped when it closes.
+    inline_documents: std::sync::Mutex<HashMap<String, window::InlineDocument>>,
+
+    /// Debounce state for menu::broadcast_theme_change.
+    theme_broadcast: std::sync::Mutex<menu::ThemeBroadcast>,
+
+    /// Last scroll-sync broadcast per file, for menu::broadcast_scroll_sync's
+    /// echo suppression.
+    scroll_sync_log: std::sync::Mutex<menu::ScrollSyncLog>,
+
+    /// Export stylesheets by (theme, custom CSS path). They depend on files
+    /// outside any document, so io::invalidate_css_caches drops them wholesale.
+    stylesheet_cache: StdRwLock<io::StylesheetCache>,
 }
 
 impl Default for AppState {
@@ -89,15 +137,27 @@ impl Default for AppState {
         Self {
             open_windows: Arc::new(RwLock::new(HashMap::new())),
             resize_tasks: Arc::new(Mutex::new(HashMap::new())),
+            move_tasks: Arc::new(Mutex::new(HashMap::new())),
             html_cache: Arc::new(RwLock::new(LruCache::new(
-                std::num::NonZeroUsize::new(50).unwrap(),
+                std::num::NonZeroUsize::new(io::DEFAULT_HTML_CACHE_CAPACITY).unwrap(),
             ))),
+            cache_hits: std::sync::atomic::AtomicU64::new(0),
+            cache_misses: std::sync::atomic::AtomicU64::new(0),
             allowed_paths: Arc::new(StdRwLock::new(HashSet::new())),
             allowed_dirs: Arc::new(StdRwLock::new(HashSet::new())),
             pref_lock: Arc::new(Mutex::new(())),
             had_cli_args: std::sync::atomic::AtomicBool::new(false),
             startup_opened_file: std::sync::atomic::AtomicBool::new(false),
             startup_blank_label: std::sync::Mutex::new(None),
+            inline_editors: std::sync::Mutex::new(HashSet::new()),
+            editor_windows: Arc::new(RwLock::new(HashMap::new())),
+            recently_closed: std::sync::Mutex::new(std::collections::VecDeque::new()),
+            zoom_levels: std::sync::Mutex::new(HashMap::new()),
+            always_on_top: std::sync::Mutex::new(HashSet::new()),
+            inline_documents: std::sync::Mutex::new(HashMap::new()),
+            theme_broadcast: std::sync::Mutex::new(menu::ThemeBroadcast::default()),
+            scroll_sync_log: std::sync::Mutex::new(HashMap::new()),
+            stylesheet_cache: StdRwLock::new(HashMap::new()),
         }
     }
 }
@@ -250,6 +310,141 @@ async fn setup_cli_access() -> Result<String, String> {
     Err("CLI setup is not supported on this platform".to_string())
 }
 
+// --- Build info ---
+
+/// What this build can do, so the frontend hides controls for missing
+/// capabilities instead of guessing. Every flag is derived from how the crate
+/// was compiled.
+#[derive(serde::Serialize)]
+struct AppFeatures {
+    version: &'static str,
+    /// "debug" or "release".
+    profile: &'static str,
+    os: &'static str,
+    /// The webview print dialog, whose destinations include Save as PDF
+    /// (desktop only).
+    print: bool,
+    /// export_pdf writes PDFs without a print dialog (WebKitGTK only).
+    pdf_export: bool,
+    /// Folder scanned for custom `.tmTheme` files, when resolvable.
+    custom_themes_dir: Option<String>,
+    /// External links and "Reveal" via the opener plugin, when registered.
+    url_opening: bool,
+    /// `$…$` / `$$…$$` typeset by KaTeX, when its script is bundled.
+    math_rendering: bool,
+    /// A CLI launcher can be installed (macOS wrapper or Windows PATH entry).
+    cli_install: bool,
+}
+
+/// Loaded on demand by main.js's ensureKatex.
+const KATEX_SCRIPT: &str = "/assets/vendor/katex/katex.min.js";
+
+#[tauri::command]
+fn app_features(app: tauri::AppHandle) -> AppFeatures {
+    AppFeatures {
+        version: env!("CARGO_PKG_VERSION"),
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        os: std::env::consts::OS,
+        print: cfg!(desktop),
+        pdf_export: cfg!(target_os = "linux"),
+        custom_themes_dir: menu::extension_dirs(&app)
+            .ok()
+            .map(|(themes, _)| io::pathbuf_to_string(&themes)),
+        url_opening: app
+            .try_state::<tauri_plugin_opener::Opener<tauri::Wry>>()
+            .is_some(),
+        math_rendering: app.asset_resolver().get(KATEX_SCRIPT.to_string()).is_some(),
+        cli_install: cfg!(any(target_os = "macos", target_os = "windows")),
+    }
+}
+
+// --- Quit handling ---
+
+/// Store a window's logical size under the preference keys for its kind.
+fn record_window_size(p: &mut prefs::AppPreferences, label: &str, lw: u32, lh: u32) {
+    if window::is_editor_window_label(label) {
+        p.editor_window_width = Some(lw);
+        p.editor_window_height = Some(lh);
+    } else {
+        p.window_width = lw;
+        p.window_height = lh;
+    }
+}
+
+/// Persist state that debounced tasks would otherwise lose on exit: pending
+/// resize saves are aborted and their latest sizes written immediately, the
+/// store is flushed to disk, all file watchers are torn down, and downloaded
+/// remote documents are deleted.
+async fn flush_before_quit(app: &tauri::AppHandle) {
+    if let Some(state) = app.try_state::<AppState>() {
+        let pending: Vec<(String, u32, u32)> = {
+            let mut tasks = state.resize_tasks.lock().await;
+            tasks
+                .drain()
+                .map(|(label, (handle, lw, lh))| {
+                    handle.abort();
+                    (label, lw, lh)
+                })
+                .collect()
+        };
+        // Preview windows share one saved position; any pending move will do.
+        let pending_move: Option<(i32, i32)> = {
+            let mut tasks = state.move_tasks.lock().await;
+            tasks
+                .drain()
+                .map(|(_, (handle, x, y))| {
+                    handle.abort();
+                    (x, y)
+                })
+                .last()
+        };
+        if !pending.is_empty() || pending_move.is_some() {
+            let _lock = state.pref_lock.lock().await;
+            let mut p = prefs::get_preferences(app.clone()).unwrap_or_default();
+            for (label, lw, lh) in &pending {
+                record_window_size(&mut p, label, *lw, *lh);
+            }
+            if let Some((x, y)) = pending_move {
+                p.window_x = Some(x);
+                p.window_y = Some(y);
+            }
+            if let Err(e) = prefs::save_preferences(app.clone(), p) {
+                eprintln!("Failed to save window size on quit: {e}");
+            }
+        }
+    }
+
+    {
+        use tauri_plugin_store::StoreExt;
+        if let Ok(store) = app.store(".boltpage.dat") {
+            if let Err(e) = store.save() {
+                eprintln!("Failed to flush store on quit: {e}");
+            }
+        }
+    }
+
+    if let Some(watchers) = app.try_state::<watchers::FileWatchers>() {
+        let mut inner = watchers.inner.lock().await;
+        watchers::stop_all(&mut inner);
+    }
+    remote::remove_all_downloads();
+
+    QUIT_FLUSHED.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Flush pending window-size saves, the store, and file watchers ahead of an
+/// exit. Quit paths call this automatically; it is exposed so the frontend can
+/// run it before closing the app itself.
+#[tauri::command]
+async fn prepare_quit(app: tauri::AppHandle) -> Result<(), String> {
+    flush_before_quit(&app).await;
+    Ok(())
+}
+
 // --- App entry point ---
 
 /// Grace period after Ready to let a Launch Services file-open (RunEvent::Opened)
@@ -340,16 +535,41 @@ async fn resolve_startup_windows(app: tauri::AppHandle) {
     }
 }
 
+/// Most files one launch opens; a shell glob can expand to thousands, and
+/// each file gets its own window.
+const MAX_CLI_FILES: usize = 50;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    launch(None);
+}
+
+/// `run`, plus a window showing `content` (Markdown piped to `--stdin`).
+pub fn run_with_stdin(content: String) {
+    launch(Some(content));
+}
+
+fn launch(stdin_content: Option<String>) {
     let args: Vec<String> = std::env::args().collect();
     // All non-flag arguments are files to open; the CLI wrapper passes every
     // argument through, so dropping all but the first would lose files.
     let mut file_paths: Vec<String> = Vec::new();
+    let mut remote_urls: Vec<url::Url> = Vec::new();
     for raw in args.iter().skip(1) {
         if raw.starts_with('-') {
             continue;
         }
+        if file_paths.len() + remote_urls.len() == MAX_CLI_FILES {
+            eprintln!("Opening only the first {MAX_CLI_FILES} files; ignoring the rest");
+            break;
+        }
+        if remote::is_remote_url(raw) {
+            match url::Url::parse(raw) {
+                Ok(url) => remote_urls.push(url),
+                Err(e) => eprintln!("Skipping invalid URL {raw}: {e}"),
+            }
+            continue;
+        }
         if let Some(pathbuf) = io::resolve_file_path(raw) {
             if !pathbuf.exists() {
                 if let Some(parent) = pathbuf.parent() {
@@ -364,6 +584,16 @@ pub fn run() {
                     eprintln!("Failed to create file from CLI arg {pathbuf:?}: {e}");
                 }
             }
+            // Unreadable entries (permissions, directories) are skipped so
+            // one bad glob match doesn't abort the launch.
+            if let Err(e) = fs::File::open(&pathbuf).and_then(|f| f.metadata()) {
+                eprintln!("Skipping unreadable file {pathbuf:?}: {e}");
+                continue;
+            }
+            if pathbuf.is_dir() {
+                eprintln!("Skipping directory {pathbuf:?}");
+                continue;
+            }
             file_paths.push(pathbuf.to_string_lossy().to_string());
         }
     }
@@ -377,22 +607,68 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             io::read_file,
+            io::find_in_file,
+            io::search_files,
+            io::get_document_stats,
+            io::file_content_hash,
+            io::get_file_metadata,
             io::read_file_bytes_b64,
             io::write_file,
+            io::toggle_task_item,
             io::is_writable,
+            window::open_file_readonly,
+            window::get_inline_content,
+            window::new_window_from_clipboard,
+            window::open_wikilink,
+            window::open_diff_window,
+            io::render_diff,
+            window::reload_all_windows,
+            io::reveal_in_file_manager,
+            remote::open_url,
             io::parse_markdown,
             io::parse_markdown_with_theme,
+            io::render_markdown_string,
             io::parse_json_with_theme,
             io::parse_yaml_with_theme,
+            io::parse_toml_with_theme,
+            io::parse_xml_with_theme,
+            io::parse_csv_with_theme,
+            io::highlight_code,
             io::format_json_pretty,
+            io::markdown_to_plaintext,
+            io::rendered_clipboard_document,
+            io::detect_language,
+            io::get_custom_css,
+            io::set_custom_css_path,
+            io::resize_html_cache,
+            io::get_cache_stats,
+            io::reset_cache_stats,
+            io::render_markdown_canonical,
+            io::get_document_toc,
+            io::check_link_rewrite_rules,
             io::render_file_to_html,
+            io::render_file_view,
+            io::render_dual,
+            io::render_file_window,
+            io::render_plain_text,
+            io::render_file_tail,
+            io::get_document_overrides,
+            io::get_document_title,
             io::save_html_export,
+            io::export_pdf,
             io::open_file_dialog,
             io::open_tracked_file,
+            io::validate_open_target,
             io::create_new_markdown_file,
+            io::save_file_as,
             prefs::save_preference_key,
             prefs::get_preferences,
+            prefs::get_theme_ids,
             prefs::save_preferences,
+            prefs::get_preferences_path,
+            prefs::reveal_preferences,
+            prefs::export_preferences,
+            prefs::import_preferences,
             prefs::mark_cli_setup_declined,
             prefs::get_recent_files,
             menu::broadcast_scroll_sync,
@@ -403,8 +679,15 @@ pub fn run() {
             menu::broadcast_editor_window_closed,
             menu::broadcast_editor_buffer,
             menu::get_syntax_css,
+            menu::get_syntax_css_scoped,
+            menu::list_syntax_themes,
+            menu::preview_theme_css,
+            menu::reload_extensions,
             watchers::start_file_watcher,
             watchers::stop_file_watcher,
+            watchers::start_folder_watcher,
+            watchers::stop_folder_watcher,
+            watchers::get_folder_watch,
             workspace::open_folder_dialog,
             workspace::get_workspace_folder,
             workspace::clear_workspace_folder,
@@ -418,15 +701,44 @@ pub fn run() {
             window::remove_window_from_tracking,
             window::get_file_path_from_window_label,
             window::get_all_windows,
+            window::window_overview,
+            window::reopen_last_closed,
+            window::set_zoom,
+            window::get_zoom,
+            window::set_always_on_top,
             window::focus_window,
+            window::toggle_zen_mode,
+            window::set_tail_mode,
+            window::toggle_maximize,
+            window::center_window,
+            prefs::set_extension_theme,
+            window::open_inline_editor,
+            window::close_inline_editor,
             is_cli_installed,
-            setup_cli_access
+            app_features,
+            setup_cli_access,
+            prepare_quit
         ])
         .setup(move |app| {
             app.manage(watchers::FileWatchers::default());
 
             menu::rebuild_app_menu(app.handle())?;
 
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || menu::init_extensions(&handle));
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                io::apply_cache_capacity_pref(&handle).await;
+                io::run_cache_sweeper(handle).await;
+            });
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let path = io::custom_css_path(&handle);
+                if let Err(e) = watchers::watch_custom_css(&handle, path).await {
+                    eprintln!("Failed to watch custom CSS: {e}");
+                }
+            });
+
             app.on_menu_event(|app, event| {
                 use crate::constants::*;
 
@@ -436,6 +748,7 @@ pub fn run() {
                 const EMIT_ACTIONS: &[(&str, &str)] = &[
                     (MENU_OPEN, EVENT_MENU_OPEN),
                     (MENU_OPEN_FOLDER, EVENT_MENU_OPEN_FOLDER),
+                    (MENU_NEW_FROM_CLIPBOARD, EVENT_MENU_NEW_FROM_CLIPBOARD),
                     (MENU_CLOSE, EVENT_MENU_CLOSE),
                     (MENU_FIND, EVENT_MENU_FIND),
                     (MENU_FIND_NEXT, EVENT_MENU_FIND_NEXT),
@@ -443,6 +756,7 @@ pub fn run() {
                     (MENU_FIND_USE_SELECTION, EVENT_MENU_FIND_USE_SELECTION),
                     (MENU_FIND_REPLACE, EVENT_MENU_FIND_REPLACE),
                     (MENU_EXPORT_HTML, EVENT_MENU_EXPORT_HTML),
+                    (MENU_EXPORT_PDF, EVENT_MENU_EXPORT_PDF),
                     (MENU_FORMAT_BOLD, EVENT_MENU_FORMAT_BOLD),
                     (MENU_FORMAT_ITALIC, EVENT_MENU_FORMAT_ITALIC),
                     (MENU_FORMAT_LINK, EVENT_MENU_FORMAT_LINK),
@@ -481,9 +795,6 @@ pub fn run() {
                     io::allow_path(app, &path);
                     let app_clone = app.clone();
                     tauri::async_runtime::spawn(async move {
-                        if let Err(e) = io::push_to_recents(&app_clone, &path).await {
-                            eprintln!("Failed to push recents (menu): {e}");
-                        }
                         if let Some(resolved) = io::resolve_file_path(&path) {
                             if let Err(e) =
                                 window::create_window_with_file(&app_clone, Some(resolved)).await
@@ -502,13 +813,41 @@ pub fn run() {
                                 }
                             });
                         }
+                        MENU_REOPEN_CLOSED => {
+                            let app_clone = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = window::reopen_last_closed(app_clone).await {
+                                    eprintln!("Failed to reopen closed window: {e}");
+                                }
+                            });
+                        }
                         MENU_NEW_WINDOW => {
                             let app_clone = app.clone();
                             tauri::async_runtime::spawn(async move {
                                 let _ = window::create_new_window_command(app_clone).await;
                             });
                         }
-                        MENU_PRINT | MENU_EXPORT_PDF => {
+                        MENU_ZOOM_IN | MENU_ZOOM_OUT | MENU_ZOOM_RESET => {
+                            let step = match id {
+                                MENU_ZOOM_IN => Some(window::ZOOM_STEP),
+                                MENU_ZOOM_OUT => Some(-window::ZOOM_STEP),
+                                _ => None,
+                            };
+                            window::zoom_focused_webview(app, step);
+                        }
+                        MENU_ALWAYS_ON_TOP => {
+                            window::toggle_always_on_top_focused(app);
+                        }
+                        MENU_TOGGLE_MAXIMIZE | MENU_CENTER_WINDOW => {
+                            window::place_focused_window(app, id == MENU_CENTER_WINDOW);
+                        }
+                        MENU_REVEAL_FILE => {
+                            let app = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                window::reveal_focused_file(&app).await;
+                            });
+                        }
+                        MENU_PRINT => {
                             window::print_focused_webview(app);
                         }
                         MENU_QUIT => {
@@ -565,23 +904,46 @@ pub fn run() {
                 for p in &file_paths {
                     io::allow_path(app.handle(), p);
                 }
+            }
+
+            let mut resolved: Vec<std::path::PathBuf> = file_paths
+                .iter()
+                .filter_map(|p| io::resolve_file_path(p))
+                .collect();
+
+            if !remote_urls.is_empty() {
+                // Downloads can take a while; open them as they arrive.
+                let state = app.handle().state::<AppState>();
+                state
+                    .had_cli_args
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
                 let handle = app.handle().clone();
-                let paths = file_paths.clone();
                 tauri::async_runtime::spawn(async move {
-                    // Sequential awaits: recents order must match argument order
-                    // (each push is a read-modify-write under pref_lock).
-                    for path_str in paths {
-                        if let Err(e) = io::push_to_recents(&handle, &path_str).await {
-                            eprintln!("Failed to push recents (CLI arg): {e}");
+                    for url in remote_urls {
+                        if let Err(e) = remote::open_remote_url(&handle, &url).await {
+                            eprintln!("{e}");
                         }
                     }
+                    // Every download failed and nothing else opened: don't
+                    // leave the app running without a window.
+                    if handle.webview_windows().is_empty() {
+                        let _ = window::create_window_with_file(&handle, None).await;
+                    }
                 });
             }
 
-            let mut resolved: Vec<std::path::PathBuf> = file_paths
-                .iter()
-                .filter_map(|p| io::resolve_file_path(p))
-                .collect();
+            if let Some(content) = stdin_content {
+                // Piped input counts as a CLI open, so no session restore.
+                let state = app.handle().state::<AppState>();
+                state
+                    .had_cli_args
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+                if let Err(e) =
+                    window::create_window_with_inline_content(app.handle(), content, "stdin")
+                {
+                    eprintln!("Failed to open window for stdin: {e}");
+                }
+            }
 
             if !resolved.is_empty() {
                 // CLI files: open them now, before the event loop starts. Flag
@@ -597,10 +959,12 @@ pub fn run() {
                         .store(true, std::sync::atomic::Ordering::SeqCst);
                 }
                 let first = resolved.remove(0);
-                tauri::async_runtime::block_on(window::create_window_with_file(
+                if let Err(e) = tauri::async_runtime::block_on(window::create_window_with_file(
                     app.handle(),
-                    Some(first),
-                ))?;
+                    Some(first.clone()),
+                )) {
+                    eprintln!("Failed to open window for {first:?}: {e}");
+                }
                 if !resolved.is_empty() {
                     let handle = app.handle().clone();
                     tauri::async_runtime::spawn(async move {
@@ -659,13 +1023,7 @@ pub fn run() {
                             let _lock = pref_lock.lock().await;
                             let mut p =
                                 prefs::get_preferences(app_clone2.clone()).unwrap_or_default();
-                            if window::is_editor_window_label(&label_for_prefs) {
-                                p.editor_window_width = Some(lw);
-                                p.editor_window_height = Some(lh);
-                            } else {
-                                p.window_width = lw;
-                                p.window_height = lh;
-                            }
+                            record_window_size(&mut p, &label_for_prefs, lw, lh);
                             let _ = prefs::save_preferences(app_clone2, p);
                         });
 
@@ -673,6 +1031,50 @@ pub fn run() {
                     });
                 }
             }
+            tauri::WindowEvent::Moved(position) => {
+                let app = win.app_handle().clone();
+                let label = win.label().to_string();
+                if !window::is_preview_window_label(&label) {
+                    return;
+                }
+                let (lx, ly) = window::position_to_logical(win, position.x, position.y);
+
+                let arcs = app.try_state::<AppState>().map(|state| {
+                    (
+                        state.inner().move_tasks.clone(),
+                        state.inner().pref_lock.clone(),
+                    )
+                });
+
+                if let Some((move_tasks, pref_lock)) = arcs {
+                    let app_clone = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let mut tasks = move_tasks.lock().await;
+
+                        if let Some((handle, _, _)) = tasks.remove(&label) {
+                            handle.abort();
+                        }
+
+                        let app_clone2 = app_clone.clone();
+                        let handle = tauri::async_runtime::spawn(async move {
+                            sleep(Duration::from_millis(450)).await;
+
+                            let _lock = pref_lock.lock().await;
+                            let mut p =
+                                prefs::get_preferences(app_clone2.clone()).unwrap_or_default();
+                            p.window_x = Some(lx);
+                            p.window_y = Some(ly);
+                            let _ = prefs::save_preferences(app_clone2, p);
+                        });
+
+                        tasks.insert(label, (handle, lx, ly));
+                    });
+                }
+            }
+            tauri::WindowEvent::Focused(true) => {
+                let app = win.app_handle();
+                menu::set_always_on_top_checked(app, window::is_always_on_top(app, win.label()));
+            }
             tauri::WindowEvent::CloseRequested { .. } => {
                 let app = win.app_handle().clone();
                 let window_label = win.label().to_string();
@@ -702,9 +1104,18 @@ pub fn run() {
         .expect("error while building tauri application")
         .run(|_app, _event| {
             // Covers Cmd+Q (PredefinedMenuItem::quit), the Windows File > Quit
-            // item (app.exit), and last-window-closed exits.
-            if matches!(_event, tauri::RunEvent::ExitRequested { .. }) {
+            // item (app.exit), and last-window-closed exits. The first request is
+            // held back until pending saves are flushed, then re-issued.
+            if let tauri::RunEvent::ExitRequested { api, .. } = &_event {
                 QUITTING.store(true, std::sync::atomic::Ordering::SeqCst);
+                if !QUIT_FLUSHED.load(std::sync::atomic::Ordering::SeqCst) {
+                    api.prevent_exit();
+                    let app = _app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        flush_before_quit(&app).await;
+                        app.exit(0);
+                    });
+                }
             }
             // Startup window resolution runs here (not in setup) so any Launch
             // Services file-open delivered via RunEvent::Opened is already known.
@@ -725,11 +1136,6 @@ pub fn run() {
                     let mut opened_any = false;
                     for url in urls {
                         if let Some(path) = io::resolve_file_path(url.as_ref()) {
-                            if let Err(e) =
-                                io::push_to_recents(&app_clone, &path.to_string_lossy()).await
-                            {
-                                eprintln!("Failed to push recents (Launch Services): {e}");
-                            }
                             match window::create_window_with_file(&app_clone, Some(path.clone()))
                                 .await
                             {
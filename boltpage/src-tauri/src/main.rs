@@ -2,6 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::env;
+use std::io::Read;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -13,14 +14,17 @@ fn main() {
                 println!("BoltPage - Fast Markdown viewer and editor");
                 println!();
                 println!("USAGE:");
-                println!("    boltpage [FILE]");
+                println!("    boltpage [FILE]...");
                 println!();
                 println!("OPTIONS:");
+                println!("    --stdin          Show Markdown piped on standard input");
                 println!("    -h, --help       Print help information");
                 println!("    -v, --version    Print version information");
                 println!();
                 println!("EXAMPLES:");
                 println!("    boltpage README.md     Open README.md in BoltPage");
+                println!("    boltpage *.md          Open each file in its own window");
+                println!("    cat notes.md | boltpage --stdin");
                 println!("    boltpage               Launch BoltPage without opening a file");
                 return;
             }
@@ -32,6 +36,16 @@ fn main() {
         }
     }
 
+    if args.iter().skip(1).any(|a| a == "--stdin") {
+        let mut content = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut content) {
+            eprintln!("Failed to read standard input: {e}");
+            std::process::exit(1);
+        }
+        boltpage::run_with_stdin(content);
+        return;
+    }
+
     // Launch the Tauri application (with or without file argument)
     boltpage::run();
 }
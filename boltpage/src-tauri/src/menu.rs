@@ -2,10 +2,21 @@ use crate::constants::*;
 use crate::prefs;
 use base64::Engine;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tauri::menu::{MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::menu::{
+    CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder,
+};
 use tauri::{AppHandle, Emitter, Manager};
 
+#[cfg(target_os = "macos")]
+const REVEAL_FILE_LABEL: &str = "Reveal in Finder";
+#[cfg(target_os = "windows")]
+const REVEAL_FILE_LABEL: &str = "Reveal in Explorer";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const REVEAL_FILE_LABEL: &str = "Show in File Manager";
+
 /// Encode a file path into an Open Recent menu item id.
 fn recent_menu_id(path: &str) -> String {
     let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(path.as_bytes());
@@ -76,6 +87,11 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
                 .accelerator("CmdOrCtrl+Shift+N")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::with_id(MENU_NEW_FROM_CLIPBOARD, "New Window from Clipboard")
+                .accelerator("CmdOrCtrl+Alt+N")
+                .build(app)?,
+        )
         .item(
             &MenuItemBuilder::with_id(MENU_OPEN, "Open")
                 .accelerator("CmdOrCtrl+O")
@@ -85,11 +101,19 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
             // Open Recent: rebuilt with the rest of the menu whenever recents
             // change (push_to_recents triggers a rebuild).
             let mut recent_builder = SubmenuBuilder::new(app, "Open Recent");
+            let (recents, missing): (Vec<String>, Vec<String>) = prefs::read_recent_paths(app)
+                .into_iter()
+                .partition(|p| Path::new(p).exists());
+            if !missing.is_empty() {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = crate::io::prune_recents(&app, &missing).await {
+                        eprintln!("Failed to prune recents: {e}");
+                    }
+                });
+            }
             let mut any = false;
-            for path in prefs::read_recent_paths(app)
-                .iter()
-                .filter(|p| Path::new(p).exists())
-            {
+            for path in &recents {
                 recent_builder = recent_builder.item(
                     &MenuItemBuilder::with_id(recent_menu_id(path), recent_menu_label(path))
                         .build(app)?,
@@ -103,11 +127,17 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
                 .item(&MenuItemBuilder::with_id(MENU_RECENT_CLEAR, "Clear Menu").build(app)?)
                 .build()?
         })
+        .item(
+            &MenuItemBuilder::with_id(MENU_REOPEN_CLOSED, "Reopen Closed Window")
+                .accelerator("CmdOrCtrl+Shift+T")
+                .build(app)?,
+        )
         .item(
             &MenuItemBuilder::with_id(MENU_OPEN_FOLDER, "Open Folder…")
                 .accelerator("CmdOrCtrl+Shift+O")
                 .build(app)?,
         )
+        .item(&MenuItemBuilder::with_id(MENU_REVEAL_FILE, REVEAL_FILE_LABEL).build(app)?)
         .item(
             &MenuItemBuilder::with_id(MENU_PRINT, "Print")
                 .accelerator("CmdOrCtrl+P")
@@ -117,18 +147,23 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
             &MenuItemBuilder::with_id(MENU_EXPORT_HTML, "Export as HTML...")
                 .accelerator("CmdOrCtrl+Shift+E")
                 .build(app)?,
-        )
-        .item(
+        );
+
+    // Only WebKitGTK can print to a file without the print dialog.
+    #[cfg(target_os = "linux")]
+    {
+        file_menu_builder = file_menu_builder.item(
             &MenuItemBuilder::with_id(MENU_EXPORT_PDF, "Export as PDF...")
                 .accelerator("CmdOrCtrl+Shift+P")
                 .build(app)?,
-        )
-        .separator()
-        .item(
-            &MenuItemBuilder::with_id(MENU_CLOSE, "Close Window")
-                .accelerator("CmdOrCtrl+W")
-                .build(app)?,
         );
+    }
+
+    file_menu_builder = file_menu_builder.separator().item(
+        &MenuItemBuilder::with_id(MENU_CLOSE, "Close Window")
+            .accelerator("CmdOrCtrl+W")
+            .build(app)?,
+    );
 
     #[cfg(not(target_os = "macos"))]
     {
@@ -210,13 +245,54 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
         )
         .build()?;
 
+    // View menu: per-window zoom ("=" is the unshifted Plus key) and pinning,
+    // whose check mark follows the focused window.
+    let pinned = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .is_some_and(|(label, _)| crate::window::is_always_on_top(app, &label));
+    let view_menu = SubmenuBuilder::new(app, "View")
+        .item(
+            &MenuItemBuilder::with_id(MENU_ZOOM_IN, "Zoom In")
+                .accelerator("CmdOrCtrl+=")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(MENU_ZOOM_OUT, "Zoom Out")
+                .accelerator("CmdOrCtrl+-")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::with_id(MENU_ZOOM_RESET, "Actual Size")
+                .accelerator("CmdOrCtrl+0")
+                .build(app)?,
+        )
+        .separator()
+        .item(
+            &CheckMenuItemBuilder::with_id(MENU_ALWAYS_ON_TOP, "Always on Top")
+                .checked(pinned)
+                .build(app)?,
+        )
+        .item(&MenuItemBuilder::with_id(MENU_TOGGLE_MAXIMIZE, "Maximize / Restore").build(app)?)
+        .item(&MenuItemBuilder::with_id(MENU_CENTER_WINDOW, "Center Window").build(app)?)
+        .build()?;
+
     // Window menu: Minimize (cross-platform) + dynamic list of open windows
     let mut window_menu_builder = SubmenuBuilder::new(app, "Window")
         .item(&PredefinedMenuItem::minimize(app, None)?)
         .separator();
 
-    for (label, window) in app.webview_windows() {
-        let title = window.title().unwrap_or_else(|_| "Untitled".to_string());
+    let mut windows: Vec<(String, String)> = app
+        .webview_windows()
+        .into_iter()
+        .map(|(label, window)| {
+            let title = window.title().unwrap_or_else(|_| "Untitled".to_string());
+            (label, title)
+        })
+        .collect();
+    sort_window_menu(&mut windows);
+    for (label, title) in windows {
         let window_id = format!("{MENU_WINDOW_PREFIX}{label}");
         window_menu_builder =
             window_menu_builder.item(&MenuItemBuilder::with_id(&window_id, &title).build(app)?);
@@ -253,6 +329,7 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
         .item(&file_menu)
         .item(&edit_menu)
         .item(&format_menu)
+        .item(&view_menu)
         .item(&window_menu)
         .item(&help_menu)
         .build()?;
@@ -260,6 +337,30 @@ pub(crate) fn rebuild_app_menu(app: &AppHandle) -> tauri::Result<()> {
     Ok(())
 }
 
+/// Set the View > Always on Top check mark without rebuilding the menu.
+pub(crate) fn set_always_on_top_checked(app: &AppHandle, checked: bool) {
+    let Some(menu) = app.menu() else {
+        return;
+    };
+    let items = match menu.items() {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Failed to read the menu: {e}");
+            return;
+        }
+    };
+    for item in items {
+        if let Some(entry) = item.as_submenu().and_then(|s| s.get(MENU_ALWAYS_ON_TOP)) {
+            if let Some(check) = entry.as_check_menuitem() {
+                if let Err(e) = check.set_checked(checked) {
+                    eprintln!("Failed to update the Always on Top check mark: {e}");
+                }
+            }
+            return;
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct ScrollSyncPayload {
     pub source: String,
@@ -269,6 +370,59 @@ pub(crate) struct ScrollSyncPayload {
     pub percent: Option<f64>,
 }
 
+/// Window menu order for `(label, title)` pairs: document windows by title,
+/// then editor windows by title, so each list is stable between rebuilds.
+pub(crate) fn sort_window_menu(windows: &mut [(String, String)]) {
+    windows.sort_by(|(a_label, a_title), (b_label, b_title)| {
+        (
+            crate::window::is_editor_window_label(a_label),
+            a_title,
+            a_label,
+        )
+            .cmp(&(
+                crate::window::is_editor_window_label(b_label),
+                b_title,
+                b_label,
+            ))
+    });
+}
+
+/// The last scroll sync broadcast for a file: which window sent it, and when.
+pub(crate) struct ScrollSyncOrigin {
+    source: String,
+    at: Instant,
+}
+
+pub(crate) type ScrollSyncLog = HashMap<String, ScrollSyncOrigin>;
+
+/// A follower re-broadcasts the scroll it just applied within its JS debounce
+/// (50ms) plus programmatic-scroll guard (100ms); syncs from other windows
+/// inside this window are treated as that echo.
+const SCROLL_ECHO_WINDOW: Duration = Duration::from_millis(250);
+
+/// False when a sync from `source` is the echo of another window's broadcast
+/// for the same file; otherwise records it as the latest broadcast.
+fn accept_scroll_sync(
+    log: &mut ScrollSyncLog,
+    file_path: &str,
+    source: &str,
+    now: Instant,
+) -> bool {
+    if let Some(last) = log.get(file_path) {
+        if last.source != source && now.duration_since(last.at) < SCROLL_ECHO_WINDOW {
+            return false;
+        }
+    }
+    log.insert(
+        file_path.to_string(),
+        ScrollSyncOrigin {
+            source: source.to_string(),
+            at: now,
+        },
+    );
+    true
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct EditorWindowClosedPayload {
     pub preview_window: String,
@@ -294,10 +448,47 @@ pub(crate) fn broadcast_editor_buffer(
         .map_err(|e| format!("Failed to broadcast editor buffer: {e}"))
 }
 
+/// Quiet period before a theme change is broadcast; a burst of changes
+/// (OS appearance flapping, a user cycling themes) emits only its last.
+const THEME_BROADCAST_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// The pending debounced `theme-changed` emit and the last theme sent.
+#[derive(Default)]
+pub(crate) struct ThemeBroadcast {
+    pending: Option<tauri::async_runtime::JoinHandle<()>>,
+    emitted: Option<String>,
+}
+
+/// Emit `theme-changed` once `theme` has been the latest request for
+/// THEME_BROADCAST_DEBOUNCE. A burst that settles back on the theme last
+/// broadcast emits nothing. Rendered HTML is theme-independent (syntax
+/// colors come from scoped CSS), so no cache entries need dropping.
 #[tauri::command]
 pub(crate) fn broadcast_theme_change(app: AppHandle, theme: String) -> Result<(), String> {
-    app.emit(EVENT_THEME_CHANGED, &theme)
+    let state = app.state::<crate::AppState>();
+    let mut broadcast = state
+        .theme_broadcast
+        .lock()
         .map_err(|e| format!("Failed to broadcast theme change: {e}"))?;
+    if let Some(pending) = broadcast.pending.take() {
+        pending.abort();
+    }
+    let app_clone = app.clone();
+    broadcast.pending = Some(tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(THEME_BROADCAST_DEBOUNCE).await;
+        let state = app_clone.state::<crate::AppState>();
+        let Ok(mut broadcast) = state.theme_broadcast.lock() else {
+            return;
+        };
+        broadcast.pending = None;
+        if broadcast.emitted.as_deref() == Some(theme.as_str()) {
+            return;
+        }
+        if let Err(e) = app_clone.emit(EVENT_THEME_CHANGED, &theme) {
+            eprintln!("Failed to broadcast theme change: {e}");
+        }
+        broadcast.emitted = Some(theme);
+    }));
     Ok(())
 }
 
@@ -318,11 +509,35 @@ pub(crate) fn broadcast_font_size_change(app: AppHandle, font_size: u16) -> Resu
     Ok(())
 }
 
+/// Relay a window's scroll position to the other windows on the same file.
+/// Echoes are dropped (see `accept_scroll_sync`), and with
+/// `scroll_sync_bidirectional` off editors stop leading their previews.
 #[tauri::command]
 pub(crate) fn broadcast_scroll_sync(
     app: AppHandle,
     payload: ScrollSyncPayload,
 ) -> Result<(), String> {
+    let bidirectional = prefs::get_preferences(app.clone())
+        .ok()
+        .and_then(|p| p.scroll_sync_bidirectional)
+        .unwrap_or(true);
+    if !bidirectional && payload.source.starts_with(WINDOW_PREFIX_EDITOR) {
+        return Ok(());
+    }
+    if let Some(state) = app.try_state::<crate::AppState>() {
+        let mut log = state
+            .scroll_sync_log
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if !accept_scroll_sync(
+            &mut log,
+            &payload.file_path,
+            &payload.source,
+            Instant::now(),
+        ) {
+            return Ok(());
+        }
+    }
     app.emit(EVENT_SCROLL_SYNC, &payload)
         .map_err(|e| format!("Failed to broadcast scroll sync: {e}"))
 }
@@ -342,6 +557,105 @@ pub(crate) fn get_syntax_css(theme: String) -> Result<String, String> {
         .ok_or_else(|| "Failed to generate syntax CSS".to_string())
 }
 
+/// Syntax CSS nested under `scope` (e.g. `.markdown-body`) so the generic
+/// syntect class names can't style app chrome.
+#[tauri::command]
+pub(crate) fn get_syntax_css_scoped(theme: String, scope: String) -> Result<String, String> {
+    markrust_core::get_syntax_theme_css_scoped(&theme, &scope)
+        .ok_or_else(|| "Failed to generate scoped syntax CSS (invalid scope?)".to_string())
+}
+
+/// Every syntax theme, bundled and custom, by the exact name
+/// get_syntax_css and preview_theme_css accept.
+#[tauri::command]
+pub(crate) fn list_syntax_themes() -> Vec<String> {
+    markrust_core::syntax_theme_names()
+}
+
+/// `.markdown-body`-scoped CSS for one listed theme, for previewing it
+/// without touching preferences. Unlike get_syntax_css there is no fallback:
+/// a name not in list_syntax_themes is an error.
+#[tauri::command]
+pub(crate) fn preview_theme_css(theme: String) -> Result<String, String> {
+    if !markrust_core::syntax_theme_names().contains(&theme) {
+        return Err(format!("Unknown syntax theme: {theme}"));
+    }
+    markrust_core::get_syntax_theme_css_scoped(&theme, ".markdown-body")
+        .ok_or_else(|| format!("Failed to generate CSS for {theme}"))
+}
+
+/// User extension folders: `themes/` (`.tmTheme`) and `syntaxes/`
+/// (`.sublime-syntax`) under the app config dir. The `custom_themes_dir`
+/// preference replaces the themes folder.
+pub(crate) fn extension_dirs(app: &AppHandle) -> Result<(PathBuf, PathBuf), String> {
+    let base = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config dir: {e}"))?;
+    let themes = prefs::read_string_pref(app, "custom_themes_dir")
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| base.join("themes"));
+    Ok((themes, base.join("syntaxes")))
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ExtensionsReport {
+    pub themes: markrust_core::ExtensionLoadReport,
+    pub syntaxes: markrust_core::ExtensionLoadReport,
+    pub theme_names: Vec<String>,
+}
+
+/// Startup load. Skips the rebuild when neither folder exists so the common
+/// case keeps the lazily-built bundled sets.
+pub(crate) fn init_extensions(app: &AppHandle) {
+    let Ok((themes, syntaxes)) = extension_dirs(app) else {
+        return;
+    };
+    for (dir, report) in [
+        (
+            &themes,
+            themes
+                .is_dir()
+                .then(|| markrust_core::init_custom_themes(&themes)),
+        ),
+        (
+            &syntaxes,
+            syntaxes
+                .is_dir()
+                .then(|| markrust_core::init_custom_syntaxes(&syntaxes)),
+        ),
+    ] {
+        for f in report.map(|r| r.failed).unwrap_or_default() {
+            eprintln!("Failed to load {} from {dir:?}: {}", f.path, f.error);
+        }
+    }
+}
+
+/// Re-scan the extension folders, drop cached renders (highlighting may have
+/// changed) and tell every window the theme list changed.
+#[tauri::command]
+pub(crate) async fn reload_extensions(app: AppHandle) -> Result<ExtensionsReport, String> {
+    let (themes_dir, syntaxes_dir) = extension_dirs(&app)?;
+    let report = tauri::async_runtime::spawn_blocking(move || ExtensionsReport {
+        themes: markrust_core::init_custom_themes(&themes_dir),
+        syntaxes: markrust_core::init_custom_syntaxes(&syntaxes_dir),
+        theme_names: markrust_core::syntax_theme_names(),
+    })
+    .await
+    .map_err(|e| format!("Failed to reload extensions: {e}"))?;
+
+    app.state::<crate::AppState>()
+        .html_cache
+        .write()
+        .await
+        .clear();
+    crate::io::invalidate_css_caches(&app);
+    app.emit(EVENT_SYNTAX_THEMES_CHANGED, &report.theme_names)
+        .map_err(|e| format!("Failed to emit theme list: {e}"))?;
+    Ok(report)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct FontFamilyPayload {
     pub document: Option<String>,
@@ -362,6 +676,20 @@ pub(crate) fn broadcast_font_family_change(
 mod tests {
     use super::*;
 
+    #[test]
+    fn window_menu_lists_editors_after_documents() {
+        let pair = |l: &str, t: &str| (l.to_string(), t.to_string());
+        let mut windows = vec![
+            pair("editor-b", "BoltPage Editor - b.md"),
+            pair("markdown-2", "BoltPage - z.md"),
+            pair("editor-a", "BoltPage Editor - a.md"),
+            pair("markdown-1", "BoltPage - a.md"),
+        ];
+        sort_window_menu(&mut windows);
+        let labels: Vec<&str> = windows.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(labels, ["markdown-1", "markdown-2", "editor-a", "editor-b"]);
+    }
+
     #[test]
     fn recent_menu_id_round_trips() {
         let path = "/Users/someone/notes/a file with spaces.md";
@@ -372,4 +700,26 @@ mod tests {
         assert_eq!(decode_recent_menu_id(MENU_RECENT_CLEAR), None);
         assert_eq!(decode_recent_menu_id("other-id"), None);
     }
+
+    #[test]
+    fn scroll_sync_drops_echoes_from_followers() {
+        let mut log = ScrollSyncLog::new();
+        let t0 = Instant::now();
+        assert!(accept_scroll_sync(&mut log, "/a.md", "markdown-1", t0));
+        // The editor re-broadcasting the position it was just moved to.
+        let echo = t0 + Duration::from_millis(150);
+        assert!(!accept_scroll_sync(&mut log, "/a.md", "editor-1", echo));
+        // The leader keeps scrolling; other files are unaffected.
+        assert!(accept_scroll_sync(&mut log, "/a.md", "markdown-1", echo));
+        assert!(accept_scroll_sync(&mut log, "/b.md", "editor-2", echo));
+        // Once the leader has been quiet, the editor may lead.
+        let later = echo + SCROLL_ECHO_WINDOW;
+        assert!(accept_scroll_sync(&mut log, "/a.md", "editor-1", later));
+        assert!(!accept_scroll_sync(
+            &mut log,
+            "/a.md",
+            "markdown-1",
+            later + Duration::from_millis(10)
+        ));
+    }
 }
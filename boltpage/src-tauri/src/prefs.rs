@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::time::UNIX_EPOCH;
@@ -15,40 +16,163 @@ use crate::AppState;
 #[serde(default)]
 pub(crate) struct AppPreferences {
     pub theme: String,
+    /// Theme files open in, keyed by lowercase extension without the dot;
+    /// extensions without an entry use `theme`.
+    pub per_extension_theme: Option<HashMap<String, String>>,
     pub window_width: u32,
     pub window_height: u32,
     pub editor_window_width: Option<u32>,
     pub editor_window_height: Option<u32>,
+    /// Logical top-left of the last moved preview window.
+    pub window_x: Option<i32>,
+    pub window_y: Option<i32>,
+    /// Webview zoom new preview windows open at; the last one set.
+    pub zoom_level: Option<f64>,
+    /// Widest the reading column may grow, in CSS pixels (clamped to
+    /// `window::MIN_CONTENT_WIDTH..=window::MAX_CONTENT_WIDTH`); new windows
+    /// default wide enough to show it.
+    pub content_max_width: Option<u32>,
     pub font_size: Option<u16>,
     pub word_wrap: Option<bool>,
+    /// Make bare http(s) URLs in plain-text documents clickable.
+    pub autolink_plaintext: Option<bool>,
     pub show_line_numbers: Option<bool>,
     pub toc_visible: Option<bool>,
+    /// Deepest heading level listed in outlines and exported TOCs (1–6).
+    pub toc_max_level: Option<u8>,
     pub cli_setup_prompted: Option<bool>,
     pub toolbar_density: Option<String>,
     pub editor_inspector_visible: Option<bool>,
     pub recent_files: Option<Vec<String>>,
     pub document_font_family: Option<String>,
     pub editor_font_family: Option<String>,
+    pub markdown_preset: Option<String>,
+    pub show_whitespace: Option<bool>,
+    pub large_json_node_limit: Option<usize>,
+    /// Bytes above which Markdown renders in chunks with progress events.
+    pub large_render_threshold: Option<u64>,
+    /// Files above this many MiB (default 50) render only after the window
+    /// confirms; 0 turns the prompt off.
+    pub max_open_size_mb: Option<u64>,
+    pub cache_idle_ttl_secs: Option<u64>,
+    pub cache_idle_min_bytes: Option<usize>,
+    /// Keyed by document kind (`markdown`, `json`, `yaml`, `txt`).
+    pub default_view_by_kind: Option<HashMap<String, ViewMode>>,
+    pub trim_trailing_whitespace_on_save: Option<bool>,
+    /// Markdown two-space hard breaks survive trimming unless set to false.
+    pub trim_keeps_hard_breaks: Option<bool>,
+    /// Line endings written on save: `lf`, `crlf`, or `auto` for the file's
+    /// dominant ending. Unset keeps them as the editor sent them.
+    pub line_ending: Option<String>,
+    /// Add a trailing newline on save when the file lacks one.
+    pub ensure_final_newline: Option<bool>,
+    /// Zen mode for new windows. Each window toggles its own; the last
+    /// toggle in any window becomes the default.
+    pub zen_mode: Option<bool>,
+    /// Keep plain-text windows scrolled to the end as the file grows.
+    pub tail_mode: Option<bool>,
+    /// Applied in order to Markdown link/image destinations when rendering.
+    pub link_rewrite_rules: Option<Vec<LinkRewriteRule>>,
+    /// User stylesheet applied after the built-in styles, in windows and exports.
+    pub custom_css_path: Option<String>,
+    /// Show JSON as written (highlighted verbatim) rather than re-serialized,
+    /// so big integers, number spellings and key order stay exact.
+    pub json_preserve_formatting: Option<bool>,
+    /// Number the lines of highlighted code blocks and structured documents.
+    pub show_code_line_numbers: Option<bool>,
+    /// Editors scroll their previews as well as following them (default on).
+    pub scroll_sync_bidirectional: Option<bool>,
+    /// Raw HTML allowlist for Markdown: "strict" (default), "relaxed" or
+    /// "none". Local files are trusted, so this is an explicit opt-in.
+    pub sanitize_level: Option<String>,
+    /// Folder of `.tmTheme` files to load instead of `<config>/themes`.
+    pub custom_themes_dir: Option<String>,
+    /// Paper for Export as PDF: `a3`, `a4` (default), `a5`, `letter`, `legal`.
+    pub pdf_page_size: Option<String>,
+    /// Export as PDF margin on every side, in millimetres (default 15).
+    pub pdf_margin_mm: Option<f64>,
+    /// Override the dialect's bare-URL autolinking when set.
+    pub markdown_autolink: Option<bool>,
+    /// Override the dialect's smart quotes and dashes when set.
+    pub markdown_smart_punctuation: Option<bool>,
+    /// Render Markdown headings as collapsible sections.
+    pub markdown_foldable_headings: Option<bool>,
+    /// Override the dialect's `:shortcode:` emoji expansion when set.
+    pub markdown_emoji: Option<bool>,
+    /// Rendered documents kept in the HTML cache (`io::DEFAULT_HTML_CACHE_CAPACITY`).
+    pub cache_capacity: Option<usize>,
+}
+
+/// One `link_rewrite_rules` entry: a regex and its `regex`-syntax replacement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct LinkRewriteRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+/// How a window shows a document: the rendered preview, or its highlighted
+/// source as written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ViewMode {
+    #[default]
+    Rendered,
+    Source,
 }
 
 impl Default for AppPreferences {
     fn default() -> Self {
         Self {
             theme: "drac".to_string(),
+            per_extension_theme: None,
             window_width: 900,
             window_height: 800,
             editor_window_width: None,
             editor_window_height: None,
+            window_x: None,
+            window_y: None,
+            zoom_level: None,
+            content_max_width: None,
             font_size: None,
             word_wrap: None,
+            autolink_plaintext: None,
             show_line_numbers: None,
             toc_visible: None,
+            toc_max_level: None,
             cli_setup_prompted: None,
             toolbar_density: None,
             editor_inspector_visible: None,
             recent_files: None,
             document_font_family: None,
             editor_font_family: None,
+            markdown_preset: None,
+            show_whitespace: None,
+            large_json_node_limit: None,
+            large_render_threshold: None,
+            max_open_size_mb: None,
+            cache_idle_ttl_secs: None,
+            cache_idle_min_bytes: None,
+            default_view_by_kind: None,
+            trim_trailing_whitespace_on_save: None,
+            trim_keeps_hard_breaks: None,
+            line_ending: None,
+            ensure_final_newline: None,
+            zen_mode: None,
+            tail_mode: None,
+            link_rewrite_rules: None,
+            custom_css_path: None,
+            json_preserve_formatting: None,
+            show_code_line_numbers: None,
+            scroll_sync_bidirectional: None,
+            sanitize_level: None,
+            custom_themes_dir: None,
+            pdf_page_size: None,
+            pdf_margin_mm: None,
+            markdown_autolink: None,
+            markdown_smart_punctuation: None,
+            markdown_foldable_headings: None,
+            markdown_emoji: None,
+            cache_capacity: None,
         }
     }
 }
@@ -85,6 +209,140 @@ pub(crate) fn save_preferences(app: AppHandle, preferences: AppPreferences) -> R
     Ok(())
 }
 
+/// Every preference as pretty-printed JSON, for editing by hand and handing
+/// back to `import_preferences`.
+#[tauri::command]
+pub(crate) fn export_preferences(app: AppHandle) -> Result<String, String> {
+    serde_json::to_string_pretty(&get_preferences(app)?)
+        .map_err(|e| format!("Failed to serialize preferences: {e}"))
+}
+
+/// `stored` with the `AppPreferences` fields `json` sets replaced by its
+/// values. Fields the JSON omits keep their stored values, unknown ones are
+/// dropped, and keys `AppPreferences` doesn't own (session) are kept.
+pub(crate) fn merge_imported_preferences(
+    stored: serde_json::Map<String, serde_json::Value>,
+    json: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid preferences JSON: {e}"))?;
+    let serde_json::Value::Object(imported) = &value else {
+        return Err("Invalid preferences JSON: expected an object".to_string());
+    };
+    // Parsed whole so a bad value fails the import before anything is kept.
+    let prefs: AppPreferences =
+        serde_json::from_value(value.clone()).map_err(|e| format!("Invalid preferences: {e}"))?;
+    let serde_json::Value::Object(fields) = serde_json::to_value(&prefs)
+        .map_err(|e| format!("Failed to serialize preferences: {e}"))?
+    else {
+        return Err("Failed to serialize preferences: not an object".to_string());
+    };
+    let mut merged = stored;
+    merged.extend(
+        fields
+            .into_iter()
+            .filter(|(key, _)| imported.contains_key(key)),
+    );
+    Ok(merged)
+}
+
+/// Apply the JSON from `export_preferences` over the current preferences; a
+/// partial document changes only the fields it lists. Nothing is written
+/// unless the whole document parses.
+#[tauri::command]
+pub(crate) async fn import_preferences(app: AppHandle, json: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _lock = state.pref_lock.lock().await;
+
+    let store = app
+        .store(".boltpage.dat")
+        .map_err(|e| format!("Failed to access store: {e}"))?;
+    let stored = store
+        .get("preferences")
+        .and_then(|v| {
+            serde_json::from_value::<serde_json::Map<String, serde_json::Value>>(v.clone()).ok()
+        })
+        .unwrap_or_default();
+    let merged = merge_imported_preferences(stored, &json)?;
+
+    store.set("preferences", serde_json::Value::Object(merged));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save preferences: {e}"))?;
+    Ok(())
+}
+
+/// Theme ids the frontend styles; it reads them through `get_theme_ids`.
+pub(crate) const THEME_IDS: &[&str] = &["light", "dark", "drac"];
+
+#[tauri::command]
+pub(crate) fn get_theme_ids() -> Vec<&'static str> {
+    THEME_IDS.to_vec()
+}
+
+/// Theme `path` opens in: its extension's `per_extension_theme` entry when
+/// that names a known theme, else the global `theme`.
+pub(crate) fn theme_for_path<'a>(prefs: &'a AppPreferences, path: &Path) -> &'a str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    prefs
+        .per_extension_theme
+        .as_ref()
+        .and_then(|themes| themes.get(&ext))
+        .map(String::as_str)
+        .filter(|theme| THEME_IDS.contains(theme))
+        .unwrap_or(&prefs.theme)
+}
+
+/// Remember `theme` for files with extension `ext` (with or without the
+/// leading dot); an empty `theme` forgets the entry so the global theme
+/// applies again.
+#[tauri::command]
+pub(crate) async fn set_extension_theme(
+    app: AppHandle,
+    ext: String,
+    theme: String,
+) -> Result<(), String> {
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    if ext.is_empty() {
+        return Err("Extension must not be empty".to_string());
+    }
+    if !theme.is_empty() && !THEME_IDS.contains(&theme.as_str()) {
+        return Err(format!("Unknown theme: {theme}"));
+    }
+    let mut themes = get_preferences(app.clone())?
+        .per_extension_theme
+        .unwrap_or_default();
+    if theme.is_empty() {
+        themes.remove(&ext);
+    } else {
+        themes.insert(ext, theme);
+    }
+    let value = serde_json::to_value(&themes)
+        .map_err(|e| format!("Failed to serialize extension themes: {e}"))?;
+    save_preference_key_inner(&app, "per_extension_theme", value).await
+}
+
+/// Resolved location of the preferences store. Valid before the store has
+/// ever been saved (it is the path the first save will create).
+pub(crate) fn preferences_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    tauri_plugin_store::resolve_store_path(app, ".boltpage.dat")
+        .map_err(|e| format!("Failed to resolve preferences path: {e}"))
+}
+
+#[tauri::command]
+pub(crate) fn get_preferences_path(app: AppHandle) -> Result<String, String> {
+    preferences_path(&app).map(|p| p.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub(crate) fn reveal_preferences(app: AppHandle) -> Result<(), String> {
+    let path = preferences_path(&app)?;
+    crate::io::reveal_path(&app, &path)
+}
+
 /// Atomically read-modify-write a single preference key while holding pref_lock.
 /// Used by both JS (via the command) and internal Rust code.
 pub(crate) async fn save_preference_key_inner(
@@ -212,3 +470,51 @@ pub(crate) async fn get_recent_files(app: AppHandle) -> Result<Vec<RecentFile>,
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_windows_take_their_extension_theme() {
+        let prefs = AppPreferences {
+            theme: "light".to_string(),
+            per_extension_theme: Some(HashMap::from([
+                ("json".to_string(), "dark".to_string()),
+                ("md".to_string(), "sepia".to_string()),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(theme_for_path(&prefs, Path::new("/a/b.JSON")), "dark");
+        // Unknown theme ids and unlisted extensions use the global theme.
+        assert_eq!(theme_for_path(&prefs, Path::new("/a/b.md")), "light");
+        assert_eq!(theme_for_path(&prefs, Path::new("/a/b.txt")), "light");
+        assert_eq!(theme_for_path(&prefs, Path::new("/a/README")), "light");
+    }
+
+    #[test]
+    fn imported_preferences_merge_into_the_stored_ones() {
+        let stored = serde_json::json!({
+            "theme": "light",
+            "font_size": 20,
+            "recent_files": ["/a.md"]
+        });
+        let serde_json::Value::Object(stored) = stored else {
+            unreachable!()
+        };
+        let merged =
+            merge_imported_preferences(stored.clone(), r#"{ "theme": "drac", "no_such_pref": 1 }"#)
+                .unwrap();
+        assert_eq!(merged["theme"], "drac");
+        // Omitted fields keep their stored values; unknown ones are dropped.
+        assert_eq!(merged["font_size"], 20);
+        assert!(!merged.contains_key("no_such_pref"));
+        assert_eq!(merged["recent_files"], serde_json::json!(["/a.md"]));
+
+        let err = merge_imported_preferences(stored.clone(), "{ \"theme\": ").unwrap_err();
+        assert!(err.starts_with("Invalid preferences JSON: "), "{err}");
+        assert!(merge_imported_preferences(stored.clone(), "[]").is_err());
+        let err = merge_imported_preferences(stored, r#"{ "window_width": "wide" }"#).unwrap_err();
+        assert!(err.starts_with("Invalid preferences: "), "{err}");
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+use url::Url;
+
+use crate::io;
+use crate::window;
+
+/// Remote documents larger than this are refused mid-download.
+const MAX_REMOTE_BYTES: usize = 16 * 1024 * 1024;
+/// Whole-request budget: connect, headers and body.
+const REMOTE_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Extensions a downloaded document may keep from its URL; anything else is
+/// inferred from the Content-Type.
+const REMOTE_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "json", "yaml", "yml", "toml", "xml", "txt", "csv",
+];
+
+pub(crate) fn is_remote_url(input: &str) -> bool {
+    let lower = input.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Local file name for `url`'s body: its last path segment, with the URL's
+/// extension when renderable, else one inferred from `content_type`
+/// (Markdown when nothing matches; raw hosts often say text/plain).
+fn remote_file_name(url: &Url, content_type: Option<&str>) -> String {
+    let segment = url
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("document");
+    let segment: String = segment
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = Path::new(&segment);
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    if ext
+        .as_deref()
+        .is_some_and(|e| REMOTE_EXTENSIONS.contains(&e))
+    {
+        return segment;
+    }
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let ext = match mime.as_str() {
+        "application/json" => "json",
+        "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => "yaml",
+        "application/toml" | "text/x-toml" => "toml",
+        "application/xml" | "text/xml" => "xml",
+        "text/csv" => "csv",
+        _ => "md",
+    };
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("document");
+    format!("{stem}.{ext}")
+}
+
+/// GET `url`, refusing bodies over MAX_REMOTE_BYTES. Returns the body and
+/// its Content-Type.
+async fn fetch(url: &Url) -> Result<(Vec<u8>, Option<String>), String> {
+    let client = reqwest::Client::builder()
+        .timeout(REMOTE_FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+    let mut response = client
+        .get(url.as_str())
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_REMOTE_BYTES as u64)
+    {
+        return Err(format!(
+            "{url} is larger than {} MB",
+            MAX_REMOTE_BYTES / (1024 * 1024)
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read {url}: {e}"))?
+    {
+        if body.len() + chunk.len() > MAX_REMOTE_BYTES {
+            return Err(format!(
+                "{url} is larger than {} MB",
+                MAX_REMOTE_BYTES / (1024 * 1024)
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, content_type))
+}
+
+/// Parent of the per-download folders.
+fn downloads_root() -> PathBuf {
+    std::env::temp_dir().join("boltpage-remote")
+}
+
+/// Delete the download folder holding `path`, if it is one. Called when the
+/// window showing it closes.
+pub(crate) fn remove_download(path: &str) {
+    let Some(dir) = Path::new(path).parent() else {
+        return;
+    };
+    if dir.parent() != Some(downloads_root().as_path()) {
+        return;
+    }
+    if let Err(e) = fs::remove_dir_all(dir) {
+        eprintln!("Failed to remove download folder {}: {e}", dir.display());
+    }
+}
+
+/// Delete every download folder; run on quit.
+pub(crate) fn remove_all_downloads() {
+    match fs::remove_dir_all(downloads_root()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            eprintln!("Failed to remove downloads: {e}");
+        }
+        _ => {}
+    }
+}
+
+/// Download `url` into its own folder under the temp dir and open it in a
+/// window like a local file. Returns the window label.
+pub(crate) async fn open_remote_url(app: &AppHandle, url: &Url) -> Result<String, String> {
+    let (body, content_type) = fetch(url).await?;
+    let dir = downloads_root().join(uuid::Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create temp folder: {e}"))?;
+    let path = dir.join(remote_file_name(url, content_type.as_deref()));
+    fs::write(&path, body).map_err(|e| format!("Failed to save download: {e}"))?;
+    window::create_window_with_file(app, Some(path))
+        .await
+        .map_err(|e| format!("Failed to create window: {e}"))
+}
+
+/// Open `url` in a new window: http(s) URLs are downloaded first; anything
+/// else resolves as a local path or file:// URL, which must already be
+/// allowed (this is reachable from the webview).
+#[tauri::command]
+pub(crate) async fn open_url(app: AppHandle, url: String) -> Result<String, String> {
+    if is_remote_url(&url) {
+        let parsed = Url::parse(&url).map_err(|e| format!("Invalid URL: {e}"))?;
+        return open_remote_url(&app, &parsed).await;
+    }
+    let path = io::resolve_file_path(&url).ok_or_else(|| format!("Invalid path: {url}"))?;
+    io::check_path_allowed(&app, &io::pathbuf_to_string(&path))?;
+    window::create_window_with_file(&app, Some(path))
+        .await
+        .map_err(|e| format!("Failed to create window: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_file_name_prefers_url_extension_then_content_type() {
+        let name = |u: &str, ct: Option<&str>| remote_file_name(&Url::parse(u).unwrap(), ct);
+        assert_eq!(
+            name(
+                "https://raw.example.com/o/r/main/README.md",
+                Some("text/plain")
+            ),
+            "README.md"
+        );
+        assert_eq!(
+            name(
+                "https://api.example.com/items",
+                Some("application/json; charset=utf-8")
+            ),
+            "items.json"
+        );
+        assert_eq!(name("https://example.com/", None), "document.md");
+        assert_eq!(
+            name("https://example.com/a%20b.php", Some("text/html")),
+            "a_20b.md"
+        );
+        assert!(is_remote_url("HTTPS://example.com"));
+        assert!(!is_remote_url("file:///tmp/a.md"));
+    }
+
+    #[test]
+    fn remove_download_only_deletes_download_folders() {
+        let dir = downloads_root().join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("README.md");
+        fs::write(&file, "# hi").unwrap();
+        let outside = std::env::temp_dir().join(format!("boltpage-{}.md", uuid::Uuid::new_v4()));
+        fs::write(&outside, "# keep").unwrap();
+
+        remove_download(&outside.to_string_lossy());
+        assert!(outside.exists());
+        remove_download(&file.to_string_lossy());
+        assert!(!dir.exists());
+        fs::remove_file(outside).unwrap();
+    }
+}
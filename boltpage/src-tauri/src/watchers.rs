@@ -1,23 +1,60 @@
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::{sleep, Duration};
 
-use crate::constants::EVENT_FILE_CHANGED;
+use crate::constants::{EVENT_CUSTOM_CSS_CHANGED, EVENT_FILE_CHANGED, EVENT_FILE_REMOVED};
 use crate::io;
+use crate::window;
+use crate::AppState;
 
 // Global file watchers storage with dedup by file path and debounced emits
+#[derive(Default)]
 pub(crate) struct FileWatcherInner {
     watchers: HashMap<String, RecommendedWatcher>,
     /// Stored to keep the channel alive; dropping the sender closes the receiver.
     senders: HashMap<String, mpsc::UnboundedSender<()>>,
     debounce_tasks: HashMap<String, tauri::async_runtime::JoinHandle<()>>,
     subs: HashMap<String, Vec<String>>,
+    /// Files each document pulls in through include directives, as of its
+    /// last render. Windows showing the document are subscribed to these too.
+    includes: HashMap<String, Vec<String>>,
+    /// Watcher on the `custom_css_path` stylesheet, independent of windows.
+    css_watcher: Option<RecommendedWatcher>,
+    /// Polls for watched files that were deleted or moved away, re-arming
+    /// their watcher when they reappear.
+    rearm_tasks: HashMap<String, tauri::async_runtime::JoinHandle<()>>,
+    /// The recursive watch from start_folder_watcher. App-wide and owned
+    /// here rather than by the window that started it, so any window can see
+    /// and stop it.
+    folder: Option<FolderWatch>,
 }
 
+/// A folder watch and the task handling its batched changes.
+pub(crate) struct FolderWatch {
+    info: FolderWatchInfo,
+    _watcher: RecommendedWatcher,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// What get_folder_watch reports about the active folder watch.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FolderWatchInfo {
+    /// The folder as it was passed to start_folder_watcher.
+    pub dir: String,
+    pub auto_open: bool,
+}
+
+/// Most windows one batch of folder changes opens; the rest are logged.
+const MAX_AUTO_OPEN_PER_BATCH: usize = 5;
+
+/// How often a removed file is checked for reappearing.
+const REARM_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 pub(crate) struct FileWatchers {
     pub inner: Arc<Mutex<FileWatcherInner>>,
 }
@@ -25,16 +62,13 @@ pub(crate) struct FileWatchers {
 impl Default for FileWatchers {
     fn default() -> Self {
         Self {
-            inner: Arc::new(Mutex::new(FileWatcherInner {
-                watchers: HashMap::new(),
-                senders: HashMap::new(),
-                debounce_tasks: HashMap::new(),
-                subs: HashMap::new(),
-            })),
+            inner: Arc::new(Mutex::new(FileWatcherInner::default())),
         }
     }
 }
 
+/// Create and rename events count: editors that save atomically write a
+/// temp file and rename it over the original, replacing the inode.
 fn is_refresh_relevant_event(kind: &notify::EventKind) -> bool {
     matches!(
         kind,
@@ -60,6 +94,9 @@ fn prune_orphaned_watchers(inner: &mut FileWatcherInner) {
         if let Some(handle) = inner.debounce_tasks.remove(&file) {
             handle.abort();
         }
+        if let Some(handle) = inner.rearm_tasks.remove(&file) {
+            handle.abort();
+        }
     }
 }
 
@@ -70,6 +107,25 @@ pub(crate) fn unsubscribe_window_from_all(inner: &mut FileWatcherInner, window_l
     prune_orphaned_watchers(inner);
 }
 
+/// Drop every watcher and abort pending debounced emits. Used on quit so no
+/// `file-changed` event races the teardown of the windows it targets.
+pub(crate) fn stop_all(inner: &mut FileWatcherInner) {
+    inner.subs.clear();
+    inner.includes.clear();
+    inner.css_watcher = None;
+    inner.watchers.clear();
+    inner.senders.clear();
+    for (_, handle) in inner.debounce_tasks.drain() {
+        handle.abort();
+    }
+    for (_, handle) in inner.rearm_tasks.drain() {
+        handle.abort();
+    }
+    if let Some(folder) = inner.folder.take() {
+        folder.task.abort();
+    }
+}
+
 #[tauri::command]
 pub(crate) async fn start_file_watcher(
     app: AppHandle,
@@ -81,25 +137,118 @@ pub(crate) async fn start_file_watcher(
     let mut inner = watchers.inner.lock().await;
     unsubscribe_window_from_all(&mut inner, &window_label);
 
-    // Register subscription
-    let entry = inner.subs.entry(file_path.clone()).or_default();
-    if !entry.iter().any(|w| w == &window_label) {
-        entry.push(window_label.clone());
+    subscribe(&mut inner, &file_path, &window_label);
+    ensure_watcher(&app, &mut inner, &file_path)?;
+
+    let includes = inner.includes.get(&file_path).cloned().unwrap_or_default();
+    for include in includes {
+        subscribe(&mut inner, &include, &window_label);
+        if let Err(e) = ensure_watcher(&app, &mut inner, &include) {
+            eprintln!("Failed to watch include {include}: {e}");
+        }
     }
 
+    Ok(())
+}
+
+/// Point every window watching `from` at `to` instead (after Save As). The
+/// include subscriptions follow on `to`'s next render.
+pub(crate) async fn move_subscribers(app: &AppHandle, from: &str, to: &str) {
+    let Some(watchers) = app.try_state::<FileWatchers>() else {
+        return;
+    };
+    let mut inner = watchers.inner.lock().await;
+    if !move_subscriptions(&mut inner, from, to) {
+        return;
+    }
+    if let Err(e) = ensure_watcher(app, &mut inner, to) {
+        eprintln!("Failed to watch {to}: {e}");
+    }
+}
+
+/// The subscription half of move_subscribers: each window on `from` drops
+/// its subscriptions and is subscribed to `to`. False when none was on `from`.
+fn move_subscriptions(inner: &mut FileWatcherInner, from: &str, to: &str) -> bool {
+    let labels = inner.subs.get(from).cloned().unwrap_or_default();
+    for label in &labels {
+        unsubscribe_window_from_all(inner, label);
+        subscribe(inner, to, label);
+    }
+    !labels.is_empty()
+}
+
+/// Record the files `root` includes and subscribe every window showing `root`
+/// to them, so saving an included file refreshes the parent. Includes dropped
+/// since the last render are unsubscribed.
+pub(crate) async fn watch_includes(app: &AppHandle, root: &str, includes: Vec<String>) {
+    let Some(watchers) = app.try_state::<FileWatchers>() else {
+        return;
+    };
+    let mut inner = watchers.inner.lock().await;
+    let previous = inner
+        .includes
+        .insert(root.to_string(), includes.clone())
+        .unwrap_or_default();
+    if previous == includes {
+        return;
+    }
+    let labels = inner.subs.get(root).cloned().unwrap_or_default();
+    for stale in previous.iter().filter(|p| !includes.contains(p)) {
+        if let Some(subs) = inner.subs.get_mut(stale) {
+            subs.retain(|label| !labels.contains(label));
+        }
+    }
+    for include in &includes {
+        for label in &labels {
+            subscribe(&mut inner, include, label);
+        }
+        if !labels.is_empty() {
+            if let Err(e) = ensure_watcher(app, &mut inner, include) {
+                eprintln!("Failed to watch include {include}: {e}");
+            }
+        }
+    }
+    prune_orphaned_watchers(&mut inner);
+}
+
+/// Directory to watch for `target`. Watching the file itself would follow
+/// its inode and go quiet after the first atomic save.
+fn watch_dir(target: &Path) -> PathBuf {
+    match target.parent() {
+        Some(p) if p.as_os_str().is_empty() => PathBuf::from("."),
+        Some(p) => p.to_path_buf(),
+        None => target.to_path_buf(),
+    }
+}
+
+fn subscribe(inner: &mut FileWatcherInner, file_path: &str, window_label: &str) {
+    let entry = inner.subs.entry(file_path.to_string()).or_default();
+    if !entry.iter().any(|w| w == window_label) {
+        entry.push(window_label.to_string());
+    }
+}
+
+/// Start watching `file_path` unless a watcher already exists. Called with
+/// the lock held so concurrent subscribers never create duplicates.
+fn ensure_watcher(
+    app: &AppHandle,
+    inner: &mut FileWatcherInner,
+    file_path: &str,
+) -> Result<(), String> {
+    let file_path = file_path.to_string();
     // If watcher already exists for this file, we're done
     if inner.watchers.contains_key(&file_path) {
         return Ok(());
     }
 
-    // Create watcher while holding lock to prevent duplicate creation.
+    if let Some(handle) = inner.rearm_tasks.remove(&file_path) {
+        handle.abort();
+    }
+
     // Watcher creation is fast (OS notification setup only).
     let (tx, mut rx) = mpsc::unbounded_channel();
     let target_path = PathBuf::from(&file_path);
-    let watch_path = target_path
-        .parent()
-        .map(Path::to_path_buf)
-        .unwrap_or_else(|| target_path.clone());
+    let watch_path = watch_dir(&target_path);
 
     let tx_for_watcher = tx.clone();
     let mut watcher = RecommendedWatcher::new(
@@ -136,15 +285,11 @@ pub(crate) async fn start_file_watcher(
                 sleep(Duration::from_millis(250)).await;
                 // Invalidate any cached HTML for this file
                 io::invalidate_cache_for_path(&app2, &file2).await;
-                if let Some(state) = app2.try_state::<FileWatchers>() {
+                if !Path::new(&file2).exists() {
+                    handle_file_removed(&app2, &file2).await;
+                } else if let Some(state) = app2.try_state::<FileWatchers>() {
                     let guard = state.inner.lock().await;
-                    if let Some(labels) = guard.subs.get(&file2) {
-                        for label in labels.iter() {
-                            if let Some(win) = app2.get_webview_window(label) {
-                                let _ = win.emit(EVENT_FILE_CHANGED, ());
-                            }
-                        }
-                    }
+                    emit_to_subscribers(&app2, &guard, &file2, EVENT_FILE_CHANGED);
                 }
             }));
         }
@@ -158,6 +303,112 @@ pub(crate) async fn start_file_watcher(
     Ok(())
 }
 
+fn emit_to_subscribers(app: &AppHandle, inner: &FileWatcherInner, file_path: &str, event: &str) {
+    if let Some(labels) = inner.subs.get(file_path) {
+        for label in labels.iter() {
+            if let Some(win) = app.get_webview_window(label) {
+                let _ = win.emit(event, ());
+            }
+        }
+    }
+}
+
+/// The watched file was deleted or moved away: drop its OS watch, tell the
+/// subscribed windows, and poll for it to come back. Subscriptions are kept.
+async fn handle_file_removed(app: &AppHandle, file_path: &str) {
+    let Some(state) = app.try_state::<FileWatchers>() else {
+        return;
+    };
+    let mut inner = state.inner.lock().await;
+    // This runs on a task the debounce loop spawned, so the loop isn't
+    // aborted: dropping the watcher and sender closes its channel and it
+    // ends on its own.
+    inner.watchers.remove(file_path);
+    inner.senders.remove(file_path);
+    inner.debounce_tasks.remove(file_path);
+    emit_to_subscribers(app, &inner, file_path, EVENT_FILE_REMOVED);
+    let handle =
+        tauri::async_runtime::spawn(rearm_when_restored(app.clone(), file_path.to_string()));
+    if let Some(previous) = inner.rearm_tasks.insert(file_path.to_string(), handle) {
+        previous.abort();
+    }
+}
+
+/// Re-arm the watcher for `file_path` once it exists again and refresh its
+/// windows. Aborted by pruning when the last window unsubscribes.
+async fn rearm_when_restored(app: AppHandle, file_path: String) {
+    loop {
+        sleep(REARM_POLL_INTERVAL).await;
+        if !Path::new(&file_path).exists() {
+            continue;
+        }
+        io::invalidate_cache_for_path(&app, &file_path).await;
+        let Some(state) = app.try_state::<FileWatchers>() else {
+            return;
+        };
+        let mut inner = state.inner.lock().await;
+        // Our own handle: drop it before ensure_watcher would abort it.
+        inner.rearm_tasks.remove(&file_path);
+        if let Err(e) = ensure_watcher(&app, &mut inner, &file_path) {
+            eprintln!("Failed to re-watch {file_path}: {e}");
+            return;
+        }
+        emit_to_subscribers(&app, &inner, &file_path, EVENT_FILE_CHANGED);
+        return;
+    }
+}
+
+/// Watch the custom stylesheet (replacing any previous watch): edits drop
+/// the CSS-dependent caches and restyle every window. `None` stops watching.
+pub(crate) async fn watch_custom_css(app: &AppHandle, path: Option<String>) -> Result<(), String> {
+    let watchers = app.state::<FileWatchers>();
+    let mut inner = watchers.inner.lock().await;
+    inner.css_watcher = None;
+    let Some(path) = path else {
+        return Ok(());
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let target_path = PathBuf::from(&path);
+    let watch_path = watch_dir(&target_path);
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                if is_refresh_relevant_event(&event.kind)
+                    && io::event_targets_file(&event.paths, &target_path)
+                {
+                    let _ = tx.send(());
+                }
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| format!("Failed to create watcher: {e}"))?;
+    watcher
+        .watch(&watch_path, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch file: {e}"))?;
+
+    // Ends when the watcher (and with it the sender) is dropped.
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut pending: Option<tauri::async_runtime::JoinHandle<()>> = None;
+        while rx.recv().await.is_some() {
+            if let Some(h) = pending.take() {
+                h.abort();
+            }
+            let app = app.clone();
+            pending = Some(tauri::async_runtime::spawn(async move {
+                sleep(Duration::from_millis(250)).await;
+                io::invalidate_css_caches(&app);
+                let _ = app.emit(EVENT_CUSTOM_CSS_CHANGED, ());
+            }));
+        }
+    });
+
+    inner.css_watcher = Some(watcher);
+    Ok(())
+}
+
 #[tauri::command]
 pub(crate) async fn stop_file_watcher(app: AppHandle, window_label: String) -> Result<(), String> {
     let watchers = app.state::<FileWatchers>();
@@ -165,3 +416,190 @@ pub(crate) async fn stop_file_watcher(app: AppHandle, window_label: String) -> R
     unsubscribe_window_from_all(&mut inner, &window_label);
     Ok(())
 }
+
+/// Watch `dir` and everything under it: a changed document refreshes the
+/// window showing it and, with `auto_open`, gets a window when none shows it
+/// (at most MAX_AUTO_OPEN_PER_BATCH per batch). The folder must be a granted
+/// workspace folder or inside one. There is one folder watch at a time;
+/// starting another stops the current one.
+#[tauri::command]
+pub(crate) async fn start_folder_watcher(
+    app: AppHandle,
+    dir: String,
+    auto_open: bool,
+) -> Result<(), String> {
+    io::check_path_allowed(&app, &dir)?;
+    let root = std::fs::canonicalize(&dir).map_err(|e| format!("Failed to resolve folder: {e}"))?;
+    if !root.is_dir() {
+        return Err("Not a directory".to_string());
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                // Removals are left to the per-file watchers.
+                if matches!(
+                    event.kind,
+                    notify::EventKind::Modify(_)
+                        | notify::EventKind::Create(_)
+                        | notify::EventKind::Any
+                ) {
+                    for path in event.paths {
+                        if crate::workspace::is_supported_file(&path) {
+                            let _ = tx.send(path);
+                        }
+                    }
+                }
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| format!("Failed to create watcher: {e}"))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch folder: {e}"))?;
+
+    // Ends when the watcher (and with it the sender) is dropped. Changes
+    // arriving within 250ms of each other are handled once per file.
+    let app_clone = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        while let Some(first) = rx.recv().await {
+            let mut changed = BTreeSet::from([first]);
+            while let Ok(Some(path)) =
+                tokio::time::timeout(Duration::from_millis(250), rx.recv()).await
+            {
+                changed.insert(path);
+            }
+            let mut opened = 0;
+            let mut skipped = 0;
+            for path in changed {
+                let may_open = auto_open && opened < MAX_AUTO_OPEN_PER_BATCH;
+                match folder_file_changed(&app_clone, &path, may_open).await {
+                    FolderChange::Opened => opened += 1,
+                    FolderChange::NotOpened if auto_open => skipped += 1,
+                    _ => {}
+                }
+            }
+            if skipped > 0 {
+                eprintln!(
+                    "Folder watch: opened {opened} changed files, left {skipped} more unopened"
+                );
+            }
+        }
+    });
+
+    let watchers = app.state::<FileWatchers>();
+    let mut inner = watchers.inner.lock().await;
+    let watch = FolderWatch {
+        info: FolderWatchInfo { dir, auto_open },
+        _watcher: watcher,
+        task,
+    };
+    if let Some(previous) = inner.folder.replace(watch) {
+        previous.task.abort();
+    }
+    Ok(())
+}
+
+/// What folder_file_changed did with a changed file.
+#[derive(Debug, PartialEq, Eq)]
+enum FolderChange {
+    /// Not a file any more, or shown in a window (which was refreshed).
+    Handled,
+    Opened,
+    /// No window shows it and none was opened.
+    NotOpened,
+}
+
+/// One file under a watched folder changed: refresh its window, or open one
+/// for it when `may_open` is set.
+async fn folder_file_changed(app: &AppHandle, path: &Path, may_open: bool) -> FolderChange {
+    if !path.is_file() {
+        return FolderChange::Handled;
+    }
+    let path_str = io::pathbuf_to_string(path);
+    io::invalidate_cache_for_path(app, &path_str).await;
+    let label = app
+        .state::<AppState>()
+        .open_windows
+        .read()
+        .await
+        .iter()
+        .find(|(open, _)| io::paths_match(Path::new(open), path))
+        .map(|(_, label)| label.clone());
+    match label {
+        Some(label) => {
+            // A window already watching the file is refreshed by that watcher.
+            let watched = app
+                .state::<FileWatchers>()
+                .inner
+                .lock()
+                .await
+                .subs
+                .iter()
+                .any(|(file, labels)| {
+                    labels.contains(&label) && io::paths_match(Path::new(file), path)
+                });
+            if !watched {
+                if let Some(win) = app.get_webview_window(&label) {
+                    let _ = win.emit(EVENT_FILE_CHANGED, ());
+                }
+            }
+            FolderChange::Handled
+        }
+        None if may_open => {
+            match window::create_window_with_file(app, Some(path.to_path_buf())).await {
+                Ok(_) => FolderChange::Opened,
+                Err(e) => {
+                    eprintln!("Failed to open {path_str}: {e}");
+                    FolderChange::NotOpened
+                }
+            }
+        }
+        None => FolderChange::NotOpened,
+    }
+}
+
+/// Stop the folder watch, whichever window started it.
+#[tauri::command]
+pub(crate) async fn stop_folder_watcher(app: AppHandle) -> Result<(), String> {
+    let watchers = app.state::<FileWatchers>();
+    let mut inner = watchers.inner.lock().await;
+    if let Some(folder) = inner.folder.take() {
+        folder.task.abort();
+    }
+    Ok(())
+}
+
+/// The active folder watch, if any.
+#[tauri::command]
+pub(crate) async fn get_folder_watch(app: AppHandle) -> Result<Option<FolderWatchInfo>, String> {
+    let watchers = app.state::<FileWatchers>();
+    let inner = watchers.inner.lock().await;
+    Ok(inner.folder.as_ref().map(|folder| folder.info.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_subscriptions_follows_the_renamed_file_only() {
+        let mut inner = FileWatcherInner::default();
+        subscribe(&mut inner, "/a.md", "w1");
+        subscribe(&mut inner, "/part.md", "w1");
+        subscribe(&mut inner, "/a.md", "w2");
+        subscribe(&mut inner, "/other.md", "w3");
+
+        assert!(move_subscriptions(&mut inner, "/a.md", "/b.md"));
+        assert_eq!(inner.subs.get("/b.md").unwrap(), &["w1", "w2"]);
+        assert!(!inner.subs.contains_key("/a.md"));
+        // Includes are re-subscribed on the next render.
+        assert!(!inner.subs.contains_key("/part.md"));
+        assert_eq!(inner.subs.get("/other.md").unwrap(), &["w3"]);
+
+        assert!(!move_subscriptions(&mut inner, "/missing.md", "/c.md"));
+        assert!(!inner.subs.contains_key("/c.md"));
+    }
+}
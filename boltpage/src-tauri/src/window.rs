@@ -1,12 +1,18 @@
 use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
 
-use crate::constants::{WINDOW_PREFIX_EDITOR, WINDOW_PREFIX_FILE, WINDOW_PREFIX_MARKDOWN};
+use crate::constants::{
+    EVENT_CUSTOM_CSS_CHANGED, EVENT_INLINE_EDITOR_CLOSED, EVENT_INLINE_EDITOR_OPEN,
+    EVENT_TAIL_MODE_CHANGED, EVENT_ZEN_MODE_CHANGED, WINDOW_PREFIX_EDITOR, WINDOW_PREFIX_FILE,
+    WINDOW_PREFIX_MARKDOWN,
+};
 use crate::io;
 use crate::menu;
 use crate::prefs::{self, AppPreferences};
+use crate::remote;
 use crate::AppState;
 
 // --- Label helpers ---
@@ -80,55 +86,208 @@ pub(crate) fn stored_window_size(
     }
 }
 
+/// A usable scale factor: missing, zero, negative or non-finite values (seen
+/// when a monitor vanishes mid-session) fall back to 1.0 rather than
+/// producing infinite or negative logical sizes.
+fn sane_scale_factor(scale_factor: Option<f64>) -> f64 {
+    match scale_factor {
+        Some(sf) if sf.is_finite() && sf > 0.0 => sf,
+        _ => 1.0,
+    }
+}
+
+fn physical_to_logical(width: u32, height: u32, scale_factor: Option<f64>) -> (u32, u32) {
+    let sf = sane_scale_factor(scale_factor);
+    (
+        (width as f64 / sf).round() as u32,
+        (height as f64 / sf).round() as u32,
+    )
+}
+
+/// Shrink a window size so it fits the monitor it will open on; with no
+/// monitor information the size passes through unchanged.
+fn fit_to_monitor(size: (f64, f64), monitor: Option<(f64, f64)>) -> (f64, f64) {
+    match monitor {
+        Some((mw, mh)) if mw > 200.0 && mh > 200.0 => (size.0.min(mw), size.1.min(mh)),
+        _ => size,
+    }
+}
+
+/// Logical size of the primary monitor, or of any connected monitor when the
+/// primary is gone (e.g. the display it was on was unplugged).
+fn monitor_logical_size(app: &AppHandle) -> Option<(f64, f64)> {
+    let monitor = match app.primary_monitor() {
+        Ok(Some(monitor)) => monitor,
+        _ => app.available_monitors().ok()?.into_iter().next()?,
+    };
+    let sf = sane_scale_factor(Some(monitor.scale_factor()));
+    let size = monitor.size();
+    Some((size.width as f64 / sf, size.height as f64 / sf))
+}
+
+/// Bounds for `content_max_width`, in CSS pixels.
+pub(crate) const MIN_CONTENT_WIDTH: u32 = 400;
+pub(crate) const MAX_CONTENT_WIDTH: u32 = 2000;
+/// Room beside the reading column: `.content-wrapper` at its widest padding
+/// plus a scrollbar.
+const CONTENT_CHROME_WIDTH: u32 = 128;
+
+pub(crate) fn clamp_content_width(width: u32) -> u32 {
+    width.clamp(MIN_CONTENT_WIDTH, MAX_CONTENT_WIDTH)
+}
+
+/// Width a new preview window opens at when none was saved: 900, or enough
+/// for a custom `content_max_width` column.
+fn default_page_width(content_max_width: Option<u32>) -> f64 {
+    content_max_width
+        .map(|w| f64::from(clamp_content_width(w) + CONTENT_CHROME_WIDTH))
+        .unwrap_or(900.0)
+}
+
+/// Initialization-script statement giving the page the preferred reading
+/// width as `--content-max-width`; empty when unset.
+fn content_width_script(prefs: &AppPreferences) -> String {
+    prefs
+        .content_max_width
+        .map(|w| {
+            format!(
+                " document.documentElement.style.setProperty('--content-max-width', '{}px');",
+                clamp_content_width(w)
+            )
+        })
+        .unwrap_or_default()
+}
+
 fn calculate_window_size(app: &AppHandle, prefs: &AppPreferences) -> tauri::Result<(f64, f64)> {
+    let monitor = monitor_logical_size(app);
+    let page_width = default_page_width(prefs.content_max_width);
     if let Some(size) = stored_window_size(
         Some(prefs.window_width),
         Some(prefs.window_height),
         900,
         800,
     ) {
-        return Ok(size);
-    }
-
-    if let Ok(Some(monitor)) = app.primary_monitor() {
-        let monitor_size = monitor.size();
-        let scale_factor = monitor.scale_factor();
-        let logical_height = monitor_size.height as f64 / scale_factor;
-        let page_width = 900.0;
-        let page_height = logical_height;
-
-        debug_log!(
-            "[DEBUG] Monitor size: {}x{} (scale: {}), Using calculated window size: {}x{}",
-            monitor_size.width as f64 / scale_factor,
-            logical_height,
-            scale_factor,
-            page_width,
-            page_height
-        );
+        return Ok(fit_to_monitor(size, monitor));
+    }
 
-        Ok((page_width, page_height))
-    } else {
-        Ok((900.0, 800.0))
+    match monitor {
+        Some((monitor_width, logical_height)) if logical_height > 200.0 => {
+            let page_height = logical_height;
+
+            debug_log!(
+                "[DEBUG] Monitor size: {}x{}, Using calculated window size: {}x{}",
+                monitor_width,
+                logical_height,
+                page_width,
+                page_height
+            );
+
+            Ok(fit_to_monitor((page_width, page_height), monitor))
+        }
+        _ => Ok((page_width, 800.0)),
     }
 }
 
-pub(crate) fn convert_to_logical(window: &tauri::Window, width: u32, height: u32) -> (u32, u32) {
-    if let Ok(Some(monitor)) = window.current_monitor() {
-        let scale_factor = monitor.scale_factor();
-        let logical_width = (width as f64 / scale_factor).round() as u32;
-        let logical_height = (height as f64 / scale_factor).round() as u32;
-        debug_log!(
-            "Converting physical {}x{} to logical {}x{} (scale: {})",
-            width,
-            height,
-            logical_width,
-            logical_height,
-            scale_factor
-        );
-        (logical_width, logical_height)
-    } else {
-        (width, height)
+/// A monitor's logical bounds: x, y, width, height.
+type MonitorRect = (f64, f64, f64, f64);
+
+/// Logical bounds of every connected monitor, the primary first.
+fn monitor_logical_rects(app: &AppHandle) -> Vec<MonitorRect> {
+    let mut monitors = app.available_monitors().unwrap_or_default();
+    if let Ok(Some(primary)) = app.primary_monitor() {
+        monitors.sort_by_key(|m| m.name() != primary.name());
     }
+    monitors
+        .iter()
+        .map(|m| {
+            let sf = sane_scale_factor(Some(m.scale_factor()));
+            let (pos, size) = (m.position(), m.size());
+            (
+                pos.x as f64 / sf,
+                pos.y as f64 / sf,
+                size.width as f64 / sf,
+                size.height as f64 / sf,
+            )
+        })
+        .collect()
+}
+
+/// Where to place a window of `size` saved at `pos`: on the monitor holding
+/// `pos`, else the primary one (its display may be gone), moved so the
+/// window lies inside it. `None` without any monitor information.
+fn clamp_to_monitors(
+    pos: (f64, f64),
+    size: (f64, f64),
+    monitors: &[MonitorRect],
+) -> Option<(f64, f64)> {
+    let &(mx, my, mw, mh) = monitors
+        .iter()
+        .find(|&&(mx, my, mw, mh)| pos.0 >= mx && pos.0 < mx + mw && pos.1 >= my && pos.1 < my + mh)
+        .or_else(|| monitors.first())?;
+    Some((
+        pos.0.min(mx + mw - size.0).max(mx),
+        pos.1.min(my + mh - size.1).max(my),
+    ))
+}
+
+/// Logical pixels each further preview window is shifted right and down.
+const WINDOW_CASCADE_STEP: f64 = 28.0;
+
+/// The saved preview-window position, kept on screen for a window of `size`.
+/// Only the first preview window gets it as saved; later ones cascade from
+/// it so they don't open stacked exactly on top of each other.
+fn restored_window_position(
+    app: &AppHandle,
+    prefs: &AppPreferences,
+    size: (f64, f64),
+) -> Option<(f64, f64)> {
+    let pos = (f64::from(prefs.window_x?), f64::from(prefs.window_y?));
+    let open = app
+        .webview_windows()
+        .keys()
+        .filter(|label| is_preview_window_label(label))
+        .count();
+    clamp_to_monitors(
+        cascade_position(pos, open),
+        size,
+        &monitor_logical_rects(app),
+    )
+}
+
+/// `pos` moved `index` cascade steps right and down.
+fn cascade_position(pos: (f64, f64), index: usize) -> (f64, f64) {
+    let shift = WINDOW_CASCADE_STEP * index as f64;
+    (pos.0 + shift, pos.1 + shift)
+}
+
+pub(crate) fn position_to_logical(window: &tauri::Window, x: i32, y: i32) -> (i32, i32) {
+    let scale_factor = match window.current_monitor() {
+        Ok(Some(monitor)) => Some(monitor.scale_factor()),
+        _ => window.scale_factor().ok(),
+    };
+    let sf = sane_scale_factor(scale_factor);
+    (
+        (x as f64 / sf).round() as i32,
+        (y as f64 / sf).round() as i32,
+    )
+}
+
+pub(crate) fn convert_to_logical(window: &tauri::Window, width: u32, height: u32) -> (u32, u32) {
+    // The window keeps a scale factor even when its monitor can't be resolved.
+    let scale_factor = match window.current_monitor() {
+        Ok(Some(monitor)) => Some(monitor.scale_factor()),
+        _ => window.scale_factor().ok(),
+    };
+    let (logical_width, logical_height) = physical_to_logical(width, height, scale_factor);
+    debug_log!(
+        "Converting physical {}x{} to logical {}x{} (scale: {:?})",
+        width,
+        height,
+        logical_width,
+        logical_height,
+        scale_factor
+    );
+    (logical_width, logical_height)
 }
 
 // --- Window creation ---
@@ -139,17 +298,26 @@ pub(crate) async fn create_window_with_file(
 ) -> tauri::Result<String> {
     let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
 
+    if let Some(ref path) = file_path {
+        let verdict = io::validate_open_target_inner(path, io::MAX_OPEN_FILE_BYTES);
+        if !verdict.ok {
+            let reason = verdict.reason.unwrap_or_default();
+            return Err(tauri::Error::Io(std::io::Error::other(reason)));
+        }
+    }
+
+    let read_only = file_path
+        .as_deref()
+        .is_some_and(|p| !io::path_is_writable(p).unwrap_or(true));
     let (window_label, url, title) = if let Some(ref path) = file_path {
         let encoded_path = base64::engine::general_purpose::URL_SAFE_NO_PAD
             .encode(path.to_string_lossy().as_bytes());
         let label = format!("{WINDOW_PREFIX_FILE}{encoded_path}");
         let url = WebviewUrl::App("index.html".into());
-        let title = path
-            .file_name()
-            .and_then(|n| n.to_str())
+        let title = io::document_title(path)
             .map(|n| format!("BoltPage - {n}"))
             .unwrap_or_else(|| "BoltPage".to_string());
-        (label, url, title)
+        (label, url, read_only_title(title, read_only))
     } else {
         let label = format!("{WINDOW_PREFIX_MARKDOWN}{}", uuid::Uuid::new_v4());
         let url = WebviewUrl::App("index.html".into());
@@ -170,16 +338,33 @@ pub(crate) async fn create_window_with_file(
     }
 
     let (width, height) = calculate_window_size(app, &prefs)?;
+    let content_width = content_width_script(&prefs);
+    // A file's extension theme is set before first paint and handed to
+    // main.js as __DOCUMENT_THEME__ so loading preferences doesn't undo it.
+    let file_theme = file_path
+        .as_deref()
+        .map(|path| prefs::theme_for_path(&prefs, path));
 
-    let _window = WebviewWindowBuilder::new(app, &window_label, url)
+    let mut builder = WebviewWindowBuilder::new(app, &window_label, url)
         .title(&title)
         .inner_size(width, height)
         .visible(file_path.is_none())
         .initialization_script(format!(
-            "document.documentElement.setAttribute('data-theme', {});",
-            serde_json::to_string(&prefs.theme).unwrap()
-        ))
-        .build()?;
+            "window.__READ_ONLY__ = {read_only}; window.__DOCUMENT_THEME__ = {}; document.documentElement.setAttribute('data-theme', {});{content_width}",
+            serde_json::to_string(&file_theme).unwrap(),
+            serde_json::to_string(file_theme.unwrap_or(&prefs.theme)).unwrap()
+        ));
+    if let Some((x, y)) = restored_window_position(app, &prefs, (width, height)) {
+        builder = builder.position(x, y);
+    }
+    let window = builder.build()?;
+    if let Some(zoom) = prefs.zoom_level.map(clamp_zoom).filter(|z| *z != 1.0) {
+        if let Err(e) = window.set_zoom(zoom) {
+            eprintln!("Failed to apply zoom: {e}");
+        } else if let Ok(mut levels) = app.state::<AppState>().zoom_levels.lock() {
+            levels.insert(window_label.clone(), zoom);
+        }
+    }
 
     let _ = menu::rebuild_app_menu(app);
 
@@ -194,11 +379,409 @@ pub(crate) async fn create_window_with_file(
         if let Err(e) = io::session_add(app, &path_str).await {
             eprintln!("Failed to update session list: {e}");
         }
+        if let Err(e) = io::push_to_recents(app, &path_str).await {
+            eprintln!("Failed to push recents: {e}");
+        }
     }
 
     Ok(window_label)
 }
 
+/// A document shown without a backing file (piped to `--stdin`).
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct InlineDocument {
+    pub title: String,
+    pub content: String,
+    /// Already-rendered HTML (a diff) shown as-is; `content` is then empty.
+    pub html: Option<String>,
+}
+
+/// Open a viewer window for `content`, which the window fetches with
+/// get_inline_content. Nothing touches disk, recents or the session; the
+/// content is dropped with the window.
+pub(crate) fn create_window_with_inline_content(
+    app: &AppHandle,
+    content: String,
+    title: &str,
+) -> Result<String, String> {
+    open_inline_window(
+        app,
+        InlineDocument {
+            title: title.to_string(),
+            content,
+            html: None,
+        },
+    )
+}
+
+/// create_window_with_inline_content for HTML rendered by the backend.
+pub(crate) fn create_window_with_inline_html(
+    app: &AppHandle,
+    html: String,
+    title: &str,
+) -> Result<String, String> {
+    open_inline_window(
+        app,
+        InlineDocument {
+            title: title.to_string(),
+            content: String::new(),
+            html: Some(html),
+        },
+    )
+}
+
+fn open_inline_window(app: &AppHandle, doc: InlineDocument) -> Result<String, String> {
+    let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
+    let window_label = format!("{WINDOW_PREFIX_MARKDOWN}{}", uuid::Uuid::new_v4());
+    let title = doc.title.clone();
+    if let Ok(mut docs) = app.state::<AppState>().inline_documents.lock() {
+        docs.insert(window_label.clone(), doc);
+    }
+    let (width, height) =
+        calculate_window_size(app, &prefs).map_err(|e| format!("Failed to size window: {e}"))?;
+    WebviewWindowBuilder::new(app, &window_label, WebviewUrl::App("index.html".into()))
+        .title(format!("BoltPage - {title}"))
+        .inner_size(width, height)
+        .initialization_script(format!(
+            "document.documentElement.setAttribute('data-theme', {});{}",
+            serde_json::to_string(&prefs.theme).unwrap(),
+            content_width_script(&prefs)
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create window: {e}"))?;
+    let _ = menu::rebuild_app_menu(app);
+    Ok(window_label)
+}
+
+/// The calling window's inline document, if it was opened with one.
+#[tauri::command]
+pub(crate) fn get_inline_content(app: AppHandle, window: tauri::Window) -> Option<InlineDocument> {
+    let state = app.state::<AppState>();
+    let Ok(docs) = state.inline_documents.lock() else {
+        eprintln!("inline_documents lock poisoned");
+        return None;
+    };
+    docs.get(window.label()).cloned()
+}
+
+/// Open a Markdown preview of clipboard text. The webview reads the
+/// clipboard (the menu item asks the focused window to) and passes it in;
+/// blank or missing text is an error and opens nothing.
+#[tauri::command]
+pub(crate) fn new_window_from_clipboard(
+    app: AppHandle,
+    text: Option<String>,
+) -> Result<String, String> {
+    let text = text
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| "The clipboard is empty or doesn't hold text".to_string())?;
+    create_window_with_inline_content(&app, text, "Clipboard")
+}
+
+/// Open render_diff's side-by-side comparison of two files in a window of
+/// its own.
+#[tauri::command]
+pub(crate) async fn open_diff_window(
+    app: AppHandle,
+    left_path: String,
+    right_path: String,
+) -> Result<String, String> {
+    let title = format!(
+        "{} ↔ {}",
+        io::file_name_or_path(&left_path),
+        io::file_name_or_path(&right_path)
+    );
+    let html = io::render_diff(app.clone(), left_path, right_path).await?;
+    create_window_with_inline_html(&app, html, &title)
+}
+
+/// Marks a read-only file's window title with a lock glyph.
+fn read_only_title(title: String, read_only: bool) -> String {
+    if read_only {
+        format!("\u{1F512} {title}")
+    } else {
+        title
+    }
+}
+
+/// Open `path` in a viewer window flagged read-only when the file isn't
+/// writable. Unlike create_new_window_command, the path must already be
+/// allowed (dialog, CLI or directory grant).
+#[tauri::command]
+pub(crate) async fn open_file_readonly(app: AppHandle, path: String) -> Result<String, String> {
+    io::check_path_allowed(&app, &path)?;
+    create_window_with_file(&app, Some(PathBuf::from(path)))
+        .await
+        .map_err(|e| format!("Failed to create window: {e}"))
+}
+
+/// Resolve a wikilink href (already percent-decoded, e.g. `notes/Plan.md`)
+/// against the folder of the file it appears in. Absolute targets and `..`
+/// segments are refused so a note can only reach files beside or below it.
+pub(crate) fn resolve_wikilink(from_file: &Path, target: &str) -> Result<PathBuf, String> {
+    use std::path::Component;
+    let target = Path::new(target);
+    if target.as_os_str().is_empty()
+        || !target
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("Invalid wikilink: {}", target.display()));
+    }
+    let dir = from_file
+        .parent()
+        .ok_or_else(|| format!("No folder for {}", from_file.display()))?;
+    Ok(dir.join(target))
+}
+
+/// Open the note a `[[wikilink]]` in `from_path` points at in its own window.
+/// `from_path` must already be allowed; the target inherits that grant.
+#[tauri::command]
+pub(crate) async fn open_wikilink(
+    app: AppHandle,
+    from_path: String,
+    target: String,
+) -> Result<String, String> {
+    io::check_path_allowed(&app, &from_path)?;
+    let path = resolve_wikilink(Path::new(&from_path), &target)?;
+    if !path.is_file() {
+        return Err(format!("No note named {}", path.display()));
+    }
+    create_window_with_file(&app, Some(path))
+        .await
+        .map_err(|e| format!("Failed to create window: {e}"))
+}
+
+// --- Zoom ---
+
+pub(crate) const MIN_ZOOM: f64 = 0.5;
+pub(crate) const MAX_ZOOM: f64 = 3.0;
+/// Change per Zoom In / Zoom Out.
+pub(crate) const ZOOM_STEP: f64 = 0.1;
+
+/// Clamp to `MIN_ZOOM..=MAX_ZOOM`, rounded to hundredths so repeated steps
+/// don't drift; non-finite factors reset to 1.0.
+pub(crate) fn clamp_zoom(factor: f64) -> f64 {
+    if !factor.is_finite() {
+        return 1.0;
+    }
+    (factor.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0
+}
+
+fn current_zoom(app: &AppHandle, window_label: &str) -> f64 {
+    app.state::<AppState>()
+        .zoom_levels
+        .lock()
+        .ok()
+        .and_then(|z| z.get(window_label).copied())
+        .unwrap_or(1.0)
+}
+
+/// Zoom one window's webview and remember the factor as the default for new
+/// windows. Other windows are left alone. Returns the factor applied.
+#[tauri::command]
+pub(crate) async fn set_zoom(
+    app: AppHandle,
+    window_label: String,
+    factor: f64,
+) -> Result<f64, String> {
+    let factor = clamp_zoom(factor);
+    let window = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| format!("No window {window_label}"))?;
+    window
+        .set_zoom(factor)
+        .map_err(|e| format!("Failed to set zoom: {e}"))?;
+    if let Ok(mut levels) = app.state::<AppState>().zoom_levels.lock() {
+        levels.insert(window_label, factor);
+    }
+    prefs::save_preference_key_inner(&app, "zoom_level", serde_json::json!(factor)).await?;
+    Ok(factor)
+}
+
+#[tauri::command]
+pub(crate) fn get_zoom(app: AppHandle, window_label: String) -> f64 {
+    current_zoom(&app, &window_label)
+}
+
+/// Menu zoom for the focused window: step by `step`, or reset with `None`.
+pub(crate) fn zoom_focused_webview(app: &AppHandle, step: Option<f64>) {
+    let Some(label) = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+    else {
+        return;
+    };
+    let factor = step.map_or(1.0, |s| current_zoom(app, &label) + s);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = set_zoom(app, label, factor).await {
+            eprintln!("Failed to zoom: {e}");
+        }
+    });
+}
+
+// --- Always on top ---
+
+/// The pinned-window set. A poisoned lock is logged and recovered: the set
+/// only mirrors state the windows themselves hold.
+fn pinned_windows(app: &AppHandle) -> std::sync::MutexGuard<'_, HashSet<String>> {
+    app.state::<AppState>()
+        .inner()
+        .always_on_top
+        .lock()
+        .unwrap_or_else(|poisoned| {
+            eprintln!("always_on_top lock poisoned; recovering");
+            poisoned.into_inner()
+        })
+}
+
+pub(crate) fn is_always_on_top(app: &AppHandle, window_label: &str) -> bool {
+    pinned_windows(app).contains(window_label)
+}
+
+/// Pin one window above other apps, or unpin it. Per window and never
+/// saved; other windows keep their own state.
+#[tauri::command]
+pub(crate) fn set_always_on_top(
+    app: AppHandle,
+    window_label: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| format!("No window {window_label}"))?;
+    window
+        .set_always_on_top(enabled)
+        .map_err(|e| format!("Failed to set always on top: {e}"))?;
+    if window.is_focused().unwrap_or(false) {
+        menu::set_always_on_top_checked(&app, enabled);
+    }
+    let mut pinned = pinned_windows(&app);
+    if enabled {
+        pinned.insert(window_label);
+    } else {
+        pinned.remove(&window_label);
+    }
+    Ok(())
+}
+
+/// View > Always on Top: flip the focused window.
+pub(crate) fn toggle_always_on_top_focused(app: &AppHandle) {
+    let Some(label) = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+    else {
+        return;
+    };
+    let enabled = !is_always_on_top(app, &label);
+    if let Err(e) = set_always_on_top(app.clone(), label.clone(), enabled) {
+        eprintln!("{e}");
+    }
+    // The click already flipped the check mark; pin it to the real state.
+    menu::set_always_on_top_checked(app, is_always_on_top(app, &label));
+}
+
+// --- Window placement ---
+
+/// Top-left corner that centers a window of `size` on `monitor`; a window
+/// larger than the monitor keeps its top-left corner on screen.
+fn centered_position(size: (f64, f64), monitor: MonitorRect) -> (f64, f64) {
+    let (mx, my, mw, mh) = monitor;
+    (
+        (mx + (mw - size.0) / 2.0).max(mx),
+        (my + (mh - size.1) / 2.0).max(my),
+    )
+}
+
+/// Maximize the window, or restore it when it already is. Returns whether
+/// it is now maximized.
+#[tauri::command]
+pub(crate) fn toggle_maximize(app: AppHandle, window_label: String) -> Result<bool, String> {
+    let window = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| format!("No window {window_label}"))?;
+    let maximize = !window.is_maximized().unwrap_or(false);
+    if maximize {
+        window.maximize()
+    } else {
+        window.unmaximize()
+    }
+    .map_err(|e| format!("Failed to toggle maximize: {e}"))?;
+    Ok(maximize)
+}
+
+/// Center the window on the primary monitor at its current size. A
+/// maximized window is restored first, so it has a size to center.
+#[tauri::command]
+pub(crate) fn center_window(app: AppHandle, window_label: String) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| format!("No window {window_label}"))?;
+    if window.is_maximized().unwrap_or(false) {
+        window
+            .unmaximize()
+            .map_err(|e| format!("Failed to restore window: {e}"))?;
+    }
+    let size = window
+        .outer_size()
+        .map_err(|e| format!("Failed to read window size: {e}"))?;
+    let sf = sane_scale_factor(window.scale_factor().ok());
+    let size = (size.width as f64 / sf, size.height as f64 / sf);
+    // monitor_logical_rects lists the primary monitor first.
+    let monitor = monitor_logical_rects(&app)
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No monitor found".to_string())?;
+    let (x, y) = centered_position(size, monitor);
+    window
+        .set_position(tauri::LogicalPosition::new(x, y))
+        .map_err(|e| format!("Failed to move window: {e}"))
+}
+
+/// View > Maximize / Restore and View > Center Window, on the focused window.
+pub(crate) fn place_focused_window(app: &AppHandle, center: bool) {
+    let Some(label) = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+    else {
+        return;
+    };
+    let result = if center {
+        center_window(app.clone(), label)
+    } else {
+        toggle_maximize(app.clone(), label).map(|_| ())
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
+}
+
+/// File > Reveal: show the focused window's file in the file manager.
+pub(crate) async fn reveal_focused_file(app: &AppHandle) {
+    let Some(label) = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+    else {
+        return;
+    };
+    let open_windows = app.state::<AppState>().open_windows.read().await.clone();
+    let Some(path) = resolve_window_file_path(&label, &open_windows) else {
+        return;
+    };
+    if let Err(e) = io::reveal_in_file_manager(app.clone(), path) {
+        eprintln!("{e}");
+    }
+}
+
 // --- Print ---
 
 pub(crate) fn print_focused_webview(app: &AppHandle) {
@@ -240,6 +823,26 @@ pub(crate) fn refresh_preview(app: AppHandle, window: String) -> Result<(), Stri
     Ok(())
 }
 
+/// Force a full re-render everywhere, e.g. after editing a custom stylesheet:
+/// drop every cached render and stylesheet, have windows re-inject custom
+/// CSS, then refresh each preview window.
+#[tauri::command]
+pub(crate) async fn reload_all_windows(app: AppHandle) -> Result<(), String> {
+    app.state::<AppState>().html_cache.write().await.clear();
+    io::invalidate_css_caches(&app);
+    app.emit(EVENT_CUSTOM_CSS_CHANGED, ())
+        .map_err(|e| format!("Failed to broadcast stylesheet change: {e}"))?;
+    for (label, window) in app.webview_windows() {
+        if !is_preview_window_label(&label) {
+            continue;
+        }
+        if let Err(e) = window.eval("refreshFile()") {
+            eprintln!("Failed to refresh {label}: {e}");
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub(crate) async fn open_editor_window(
     app: AppHandle,
@@ -248,19 +851,51 @@ pub(crate) async fn open_editor_window(
 ) -> Result<(), String> {
     io::check_path_allowed(&app, &file_path)?;
 
+    // Already editing inline: that pane is this file's editor.
+    if is_inline_editing(&app, &preview_window) {
+        if let Some(w) = app.get_webview_window(&preview_window) {
+            let _ = w.set_focus();
+        }
+        return Ok(());
+    }
+
     let encoded_path =
         base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(file_path.as_bytes());
     let editor_label = format!("{WINDOW_PREFIX_EDITOR}{encoded_path}");
 
-    if let Some(existing) = app.get_webview_window(&editor_label) {
-        let _ = existing.set_focus();
-        return Ok(());
+    // The label only matches the same spelling of the path; the registry
+    // catches the file opened under another one.
+    let key = editor_window_key(&file_path);
+    let registered = app
+        .state::<AppState>()
+        .editor_windows
+        .read()
+        .await
+        .get(&key)
+        .cloned();
+    for label in registered.iter().chain([&editor_label]) {
+        if let Some(existing) = app.get_webview_window(label) {
+            let _ = existing.set_focus();
+            return Ok(());
+        }
     }
 
+    // The editor saves UTF-8, so a file in another encoding stays preview-only,
+    // and it reads the whole file, so the open limit applies.
+    let check_path = file_path.clone();
+    let limit = io::open_limit(&app, false);
+    tauri::async_runtime::spawn_blocking(move || {
+        io::read_editable_text(Path::new(&check_path), limit).map(drop)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))??;
+
     let file_name = Path::new(&file_path)
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("Untitled");
+    // A read-only editor still opens for viewing, but never saves.
+    let read_only = !io::path_is_writable(Path::new(&file_path)).unwrap_or(true);
     let prefs = prefs::get_preferences(app.clone()).unwrap_or_default();
     let (editor_width, editor_height) = stored_window_size(
         prefs.editor_window_width,
@@ -268,23 +903,38 @@ pub(crate) async fn open_editor_window(
         800,
         600,
     )
+    .map(|size| fit_to_monitor(size, monitor_logical_size(&app)))
     .unwrap_or((800.0, 600.0));
     let _editor_window =
         WebviewWindowBuilder::new(&app, &editor_label, WebviewUrl::App("editor.html".into()))
-            .title(format!("BoltPage Editor - {file_name}"))
+            .title(read_only_title(format!("BoltPage Editor - {file_name}"), read_only))
             .inner_size(editor_width, editor_height)
             .initialization_script(format!(
-                "window.__INITIAL_FILE_PATH__ = {}; window.__PREVIEW_WINDOW__ = {}; document.documentElement.setAttribute('data-theme', {});",
+                "window.__INITIAL_FILE_PATH__ = {}; window.__PREVIEW_WINDOW__ = {}; window.__READ_ONLY__ = {read_only}; document.documentElement.setAttribute('data-theme', {});",
                 serde_json::to_string(&file_path).unwrap(),
                 serde_json::to_string(&preview_window).unwrap(),
-                serde_json::to_string(&prefs.theme).unwrap()
+                serde_json::to_string(prefs::theme_for_path(&prefs, Path::new(&file_path))).unwrap()
             ))
             .build()
             .map_err(|e| format!("Failed to create editor window: {e}"))?;
 
+    app.state::<AppState>()
+        .editor_windows
+        .write()
+        .await
+        .insert(key, editor_label);
+    let _ = menu::rebuild_app_menu(&app);
     Ok(())
 }
 
+/// `editor_windows` key for a path: canonical when the file exists, so
+/// `a/../b.md` and `b.md` share one editor.
+pub(crate) fn editor_window_key(file_path: &str) -> String {
+    std::fs::canonicalize(file_path)
+        .map(|p| io::pathbuf_to_string(&p))
+        .unwrap_or_else(|_| file_path.to_string())
+}
+
 #[tauri::command]
 pub(crate) async fn create_new_window_command(app: AppHandle) -> Result<String, String> {
     // Always a blank window: the webview must not be able to pass an arbitrary
@@ -301,6 +951,19 @@ pub(crate) async fn remove_window_from_tracking(
     app: AppHandle,
     window_label: String,
 ) -> Result<(), String> {
+    set_inline_editing(&app, &window_label, false);
+    if let Ok(mut levels) = app.state::<AppState>().zoom_levels.lock() {
+        levels.remove(&window_label);
+    }
+    pinned_windows(&app).remove(&window_label);
+    if let Ok(mut docs) = app.state::<AppState>().inline_documents.lock() {
+        docs.remove(&window_label);
+    }
+    app.state::<AppState>()
+        .editor_windows
+        .write()
+        .await
+        .retain(|_, label| label != &window_label);
     let mut removed_paths: Vec<String> = Vec::new();
     {
         let state = app.state::<AppState>();
@@ -314,9 +977,17 @@ pub(crate) async fn remove_window_from_tracking(
             }
         });
     }
+    for path in &removed_paths {
+        remote::remove_download(path);
+    }
     // During quit, windows close as a side effect: keep the session so the
     // next launch restores it. Only user-initiated closes drop entries.
     if !crate::QUITTING.load(std::sync::atomic::Ordering::SeqCst) {
+        if let Ok(mut stack) = app.state::<AppState>().recently_closed.lock() {
+            for path in &removed_paths {
+                push_recently_closed(&mut stack, path);
+            }
+        }
         // Sequential awaits: read-modify-writes serialized under pref_lock.
         for path in removed_paths {
             if let Err(e) = io::session_remove(&app, &path).await {
@@ -327,6 +998,72 @@ pub(crate) async fn remove_window_from_tracking(
     Ok(())
 }
 
+pub(crate) fn is_inline_editing(app: &AppHandle, window_label: &str) -> bool {
+    app.state::<AppState>()
+        .inline_editors
+        .lock()
+        .map(|set| set.contains(window_label))
+        .unwrap_or(false)
+}
+
+fn set_inline_editing(app: &AppHandle, window_label: &str, editing: bool) {
+    if let Ok(mut set) = app.state::<AppState>().inline_editors.lock() {
+        if editing {
+            set.insert(window_label.to_string());
+        } else {
+            set.remove(window_label);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct InlineEditorPayload {
+    pub file_path: String,
+    pub content: String,
+}
+
+/// Show an editor pane inside preview window `window_label` for the file it
+/// displays, instead of spawning a separate editor window. The window gets
+/// the file content via `EVENT_INLINE_EDITOR_OPEN` and saves through
+/// `write_file` like the editor window does.
+#[tauri::command]
+pub(crate) async fn open_inline_editor(app: AppHandle, window_label: String) -> Result<(), String> {
+    let open_windows = app.state::<AppState>().open_windows.read().await.clone();
+    let file_path = resolve_window_file_path(&window_label, &open_windows)
+        .ok_or_else(|| "Window has no file to edit".to_string())?;
+    io::check_path_allowed(&app, &file_path)?;
+
+    let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(file_path.as_bytes());
+    if let Some(editor) = app.get_webview_window(&format!("{WINDOW_PREFIX_EDITOR}{encoded}")) {
+        let _ = editor.set_focus();
+        return Err("File is already open in an editor window".to_string());
+    }
+
+    let read_path = file_path.clone();
+    let limit = io::open_limit(&app, false);
+    let content = tauri::async_runtime::spawn_blocking(move || {
+        io::read_editable_text(Path::new(&read_path), limit).map_err(String::from)
+    })
+    .await
+    .map_err(|e| format!("Join error: {e}"))??;
+
+    set_inline_editing(&app, &window_label, true);
+    app.emit_to(
+        &window_label,
+        EVENT_INLINE_EDITOR_OPEN,
+        InlineEditorPayload { file_path, content },
+    )
+    .map_err(|e| format!("Failed to open inline editor: {e}"))
+}
+
+/// Leave inline edit mode. The window saves pending edits before calling.
+#[tauri::command]
+pub(crate) fn close_inline_editor(app: AppHandle, window_label: String) -> Result<(), String> {
+    set_inline_editing(&app, &window_label, false);
+    app.emit_to(&window_label, EVENT_INLINE_EDITOR_CLOSED, ())
+        .map_err(|e| format!("Failed to close inline editor: {e}"))
+}
+
 #[tauri::command]
 pub(crate) fn get_file_path_from_window_label(
     window: tauri::Window,
@@ -334,28 +1071,80 @@ pub(crate) fn get_file_path_from_window_label(
     decode_file_path_from_window_label_str(window.label())
 }
 
+/// How many closed windows "Reopen Closed Window" can walk back through.
+pub(crate) const RECENTLY_CLOSED_CAP: usize = 10;
+
+/// Record a closed file on the reopen stack; a repeat moves to the top.
+pub(crate) fn push_recently_closed(stack: &mut VecDeque<String>, path: &str) {
+    stack.retain(|p| p != path);
+    stack.push_back(path.to_string());
+    while stack.len() > RECENTLY_CLOSED_CAP {
+        stack.pop_front();
+    }
+}
+
+/// Reopen the most recently closed file window, skipping files that have
+/// since been deleted. Returns the reopened path, or `None` when the stack
+/// is empty.
+#[tauri::command]
+pub(crate) async fn reopen_last_closed(app: AppHandle) -> Result<Option<String>, String> {
+    loop {
+        let next = app
+            .state::<AppState>()
+            .recently_closed
+            .lock()
+            .map_err(|e| format!("Failed to read closed windows: {e}"))?
+            .pop_back();
+        let Some(path) = next else {
+            return Ok(None);
+        };
+        let resolved = PathBuf::from(&path);
+        if !resolved.is_file() {
+            continue;
+        }
+        // The file was open before, so reopening it is the same user intent.
+        io::allow_path(&app, &path);
+        create_window_with_file(&app, Some(resolved))
+            .await
+            .map_err(|e| format!("Failed to reopen {path}: {e}"))?;
+        return Ok(Some(path));
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct WindowInfo {
     pub label: String,
     pub title: String,
+    /// The file shown (see resolve_window_file_path); empty for a window
+    /// without one.
     pub file_path: String,
 }
 
+/// The file a window is showing. Preview windows can switch files in place,
+/// so `open_windows` (path -> label) wins over the path baked into the label
+/// at creation; editor labels always carry their file.
+pub(crate) fn resolve_window_file_path(
+    label: &str,
+    open_windows: &HashMap<String, String>,
+) -> Option<String> {
+    if let Ok(Some(path)) = decode_editor_file_path_from_window_label_str(label) {
+        return Some(path);
+    }
+    open_windows
+        .iter()
+        .find(|(_, l)| l.as_str() == label)
+        .map(|(p, _)| p.clone())
+        .or_else(|| decode_file_path_from_window_label_str(label).ok().flatten())
+}
+
 #[tauri::command]
-pub(crate) fn get_all_windows(app: AppHandle) -> Result<Vec<WindowInfo>, String> {
+pub(crate) async fn get_all_windows(app: AppHandle) -> Result<Vec<WindowInfo>, String> {
+    let open_windows = app.state::<AppState>().open_windows.read().await.clone();
     let mut windows = Vec::new();
 
     for (label, window) in app.webview_windows() {
         let title = window.title().unwrap_or_else(|_| "Untitled".to_string());
-        let file_path = if let Some(encoded) = label.strip_prefix(WINDOW_PREFIX_FILE) {
-            base64::engine::general_purpose::URL_SAFE_NO_PAD
-                .decode(encoded)
-                .ok()
-                .and_then(|b| String::from_utf8(b).ok())
-                .unwrap_or_default()
-        } else {
-            String::new()
-        };
+        let file_path = resolve_window_file_path(&label, &open_windows).unwrap_or_default();
 
         windows.push(WindowInfo {
             label: label.to_string(),
@@ -367,6 +1156,149 @@ pub(crate) fn get_all_windows(app: AppHandle) -> Result<Vec<WindowInfo>, String>
     Ok(windows)
 }
 
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WindowRole {
+    Preview,
+    Editor,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub(crate) struct WindowEntry {
+    pub label: String,
+    pub title: String,
+    pub role: WindowRole,
+    pub focused: bool,
+    pub visible: bool,
+}
+
+/// Every window showing one file; `file_path` is None for the untitled group.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub(crate) struct FileWindows {
+    pub file_path: Option<String>,
+    pub windows: Vec<WindowEntry>,
+}
+
+/// Groups sorted by path with the untitled group last; windows within a
+/// group list previews before editors, then by label.
+pub(crate) fn group_windows_by_file(
+    entries: Vec<(Option<String>, WindowEntry)>,
+) -> Vec<FileWindows> {
+    let mut groups: std::collections::BTreeMap<(bool, String), Vec<WindowEntry>> =
+        std::collections::BTreeMap::new();
+    for (path, entry) in entries {
+        let key = match path {
+            Some(p) => (false, p),
+            None => (true, String::new()),
+        };
+        groups.entry(key).or_default().push(entry);
+    }
+    groups
+        .into_iter()
+        .map(|((untitled, path), mut windows)| {
+            windows.sort_by(|a, b| {
+                (a.role == WindowRole::Editor, &a.label)
+                    .cmp(&(b.role == WindowRole::Editor, &b.label))
+            });
+            FileWindows {
+                file_path: (!untitled).then_some(path),
+                windows,
+            }
+        })
+        .collect()
+}
+
+/// Open windows grouped by the file they show, for a Windows panel.
+#[tauri::command]
+pub(crate) async fn window_overview(app: AppHandle) -> Result<Vec<FileWindows>, String> {
+    let open_windows = app.state::<AppState>().open_windows.read().await.clone();
+    let entries = app
+        .webview_windows()
+        .into_iter()
+        .map(|(label, window)| {
+            let path = resolve_window_file_path(&label, &open_windows);
+            let role = if label.starts_with(WINDOW_PREFIX_EDITOR) {
+                WindowRole::Editor
+            } else {
+                WindowRole::Preview
+            };
+            let entry = WindowEntry {
+                title: window.title().unwrap_or_else(|_| "Untitled".to_string()),
+                role,
+                focused: window.is_focused().unwrap_or(false),
+                visible: window.is_visible().unwrap_or(false),
+                label,
+            };
+            (path, entry)
+        })
+        .collect();
+    Ok(group_windows_by_file(entries))
+}
+
+/// Whether `window` is currently in zen mode. macOS has one global menu bar,
+/// so zen there is full-screen presentation; elsewhere it is the hidden
+/// per-window menu.
+fn zen_mode_active(window: &tauri::WebviewWindow) -> bool {
+    if cfg!(target_os = "macos") {
+        window.is_fullscreen().unwrap_or(false)
+    } else {
+        !window.is_menu_visible().unwrap_or(true)
+    }
+}
+
+/// Enter (`enabled: Some(true)`), leave, or flip (`None`) distraction-free
+/// mode for one window. The window is told via `EVENT_ZEN_MODE_CHANGED` so it
+/// can hide its own chrome, and the state is saved as `zen_mode` for the next
+/// window. Returns the new state.
+#[tauri::command]
+pub(crate) async fn toggle_zen_mode(
+    app: AppHandle,
+    window_label: String,
+    enabled: Option<bool>,
+) -> Result<bool, String> {
+    let window = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| "Window not found".to_string())?;
+    let enable = enabled.unwrap_or_else(|| !zen_mode_active(&window));
+
+    if cfg!(target_os = "macos") {
+        window
+            .set_fullscreen(enable)
+            .map_err(|e| format!("Failed to toggle full screen: {e}"))?;
+    } else if enable {
+        window
+            .hide_menu()
+            .map_err(|e| format!("Failed to hide menu: {e}"))?;
+    } else {
+        window
+            .show_menu()
+            .map_err(|e| format!("Failed to show menu: {e}"))?;
+    }
+
+    app.emit_to(&window_label, EVENT_ZEN_MODE_CHANGED, enable)
+        .map_err(|e| format!("Failed to emit zen mode change: {e}"))?;
+    prefs::save_preference_key_inner(&app, "zen_mode", serde_json::Value::Bool(enable)).await?;
+    Ok(enable)
+}
+
+/// Turn tail mode on or off for one window: while on, the window keeps its
+/// document scrolled to the end as the file grows. The window is told via
+/// `EVENT_TAIL_MODE_CHANGED`, and the state is saved as `tail_mode` for the
+/// next window.
+#[tauri::command]
+pub(crate) async fn set_tail_mode(
+    app: AppHandle,
+    window_label: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if app.get_webview_window(&window_label).is_none() {
+        return Err("Window not found".to_string());
+    }
+    app.emit_to(&window_label, EVENT_TAIL_MODE_CHANGED, enabled)
+        .map_err(|e| format!("Failed to emit tail mode change: {e}"))?;
+    prefs::save_preference_key_inner(&app, "tail_mode", serde_json::Value::Bool(enabled)).await
+}
+
 #[tauri::command]
 pub(crate) fn focus_window(app: AppHandle, window_label: String) -> Result<(), String> {
     if let Some(window) = app.get_webview_window(&window_label) {
@@ -388,6 +1320,36 @@ pub(crate) fn focus_window(app: AppHandle, window_label: String) -> Result<(), S
 mod tests {
     use super::*;
 
+    #[test]
+    fn recently_closed_stack_dedups_and_caps() {
+        let mut stack = VecDeque::new();
+        for i in 0..12 {
+            push_recently_closed(&mut stack, &format!("/docs/{i}.md"));
+        }
+        push_recently_closed(&mut stack, "/docs/5.md");
+
+        assert_eq!(stack.len(), RECENTLY_CLOSED_CAP);
+        assert_eq!(stack.front().map(String::as_str), Some("/docs/2.md"));
+        assert_eq!(stack.back().map(String::as_str), Some("/docs/5.md"));
+        assert_eq!(stack.iter().filter(|p| *p == "/docs/5.md").count(), 1);
+    }
+
+    #[test]
+    fn wikilinks_resolve_beside_the_current_file_only() {
+        let from = Path::new("/vault/daily/today.md");
+        assert_eq!(
+            resolve_wikilink(from, "Other Note.md").unwrap(),
+            PathBuf::from("/vault/daily/Other Note.md")
+        );
+        assert_eq!(
+            resolve_wikilink(from, "projects/Plan.md").unwrap(),
+            PathBuf::from("/vault/daily/projects/Plan.md")
+        );
+        assert!(resolve_wikilink(from, "../secret.md").is_err());
+        assert!(resolve_wikilink(from, "/etc/passwd.md").is_err());
+        assert!(resolve_wikilink(from, "").is_err());
+    }
+
     #[test]
     fn decode_file_path_from_window_label_round_trips() {
         let path = "/tmp/example.md";
@@ -404,6 +1366,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn window_paths_follow_in_place_switches() {
+        let enc = |p: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(p.as_bytes());
+        let preview = format!("markdown-file-{}", enc("/tmp/first.md"));
+        let mut open = HashMap::new();
+        open.insert("/tmp/second.md".to_string(), preview.clone());
+
+        assert_eq!(
+            resolve_window_file_path(&preview, &open).as_deref(),
+            Some("/tmp/second.md")
+        );
+        assert_eq!(
+            resolve_window_file_path(&preview, &HashMap::new()).as_deref(),
+            Some("/tmp/first.md")
+        );
+        let editor = format!("editor-{}", enc("/tmp/e.md"));
+        assert_eq!(
+            resolve_window_file_path(&editor, &open).as_deref(),
+            Some("/tmp/e.md")
+        );
+        assert_eq!(resolve_window_file_path("markdown-abc", &open), None);
+    }
+
+    #[test]
+    fn group_windows_by_file_puts_untitled_last() {
+        let entry = |label: &str, role: WindowRole| WindowEntry {
+            label: label.to_string(),
+            title: label.to_string(),
+            role,
+            focused: false,
+            visible: true,
+        };
+        let groups = group_windows_by_file(vec![
+            (None, entry("markdown-1", WindowRole::Preview)),
+            (Some("/b.md".into()), entry("editor-b", WindowRole::Editor)),
+            (
+                Some("/b.md".into()),
+                entry("markdown-b", WindowRole::Preview),
+            ),
+            (
+                Some("/a.md".into()),
+                entry("markdown-a", WindowRole::Preview),
+            ),
+            (None, entry("markdown-2", WindowRole::Preview)),
+        ]);
+        let paths: Vec<Option<&str>> = groups.iter().map(|g| g.file_path.as_deref()).collect();
+        assert_eq!(paths, [Some("/a.md"), Some("/b.md"), None]);
+        let b: Vec<&str> = groups[1].windows.iter().map(|w| w.label.as_str()).collect();
+        assert_eq!(b, ["markdown-b", "editor-b"]);
+        assert_eq!(groups[2].windows.len(), 2);
+    }
+
     #[test]
     fn stored_window_size_ignores_defaults_and_invalid_values() {
         assert_eq!(stored_window_size(Some(900), Some(800), 900, 800), None);
@@ -414,6 +1428,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn content_width_is_clamped_and_widens_new_windows() {
+        assert_eq!(clamp_content_width(100), MIN_CONTENT_WIDTH);
+        assert_eq!(clamp_content_width(5000), MAX_CONTENT_WIDTH);
+        assert_eq!(default_page_width(None), 900.0);
+        assert_eq!(default_page_width(Some(1400)), 1528.0);
+        assert_eq!(default_page_width(Some(10)), 528.0);
+    }
+
+    #[test]
+    fn centered_windows_stay_on_their_monitor() {
+        let primary = (0.0, 0.0, 1440.0, 900.0);
+        assert_eq!(centered_position((800.0, 600.0), primary), (320.0, 150.0));
+        // Monitors left of or above the origin keep their own offsets.
+        let left = (-1920.0, -200.0, 1920.0, 1080.0);
+        assert_eq!(centered_position((920.0, 680.0), left), (-1420.0, 0.0));
+        // Wider or taller than the monitor: pinned to its top-left edge.
+        assert_eq!(centered_position((2000.0, 1000.0), primary), (0.0, 0.0));
+    }
+
+    #[test]
+    fn editor_window_key_ignores_path_spelling() {
+        let dir = std::env::temp_dir().join(format!("boltpage-editor-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let file = dir.join("a.md");
+        std::fs::write(&file, "# a").unwrap();
+        let roundabout = dir.join("sub").join("..").join("a.md");
+        assert_eq!(
+            editor_window_key(&io::pathbuf_to_string(&roundabout)),
+            editor_window_key(&io::pathbuf_to_string(&file))
+        );
+        // A missing file keys by the path as given.
+        let missing = io::pathbuf_to_string(&dir.join("gone.md"));
+        assert_eq!(editor_window_key(&missing), missing);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn window_label_helpers() {
         assert!(is_preview_window_label("markdown-file-abc"));
@@ -422,4 +1473,93 @@ mod tests {
         assert!(!is_preview_window_label("editor-123"));
         assert!(!is_editor_window_label("markdown-file-abc"));
     }
+
+    #[test]
+    fn vanished_monitor_scale_factors_fall_back_to_one() {
+        assert_eq!(physical_to_logical(1800, 1200, Some(2.0)), (900, 600));
+        // Monitor gone, or reporting a zero/garbage scale mid-unplug.
+        assert_eq!(physical_to_logical(1800, 1200, None), (1800, 1200));
+        assert_eq!(physical_to_logical(1800, 1200, Some(0.0)), (1800, 1200));
+        assert_eq!(
+            physical_to_logical(1800, 1200, Some(f64::NAN)),
+            (1800, 1200)
+        );
+        assert_eq!(physical_to_logical(1800, 1200, Some(-2.0)), (1800, 1200));
+    }
+
+    #[test]
+    fn restored_positions_stay_on_a_connected_monitor() {
+        let laptop = (0.0, 0.0, 1440.0, 900.0);
+        let external = (1440.0, -200.0, 2560.0, 1440.0);
+        let size = (900.0, 800.0);
+        // Still on the external display: unchanged.
+        assert_eq!(
+            clamp_to_monitors((2000.0, 100.0), size, &[laptop, external]),
+            Some((2000.0, 100.0))
+        );
+        // External display unplugged: pulled onto the primary, fully visible.
+        assert_eq!(
+            clamp_to_monitors((2000.0, 100.0), size, &[laptop]),
+            Some((540.0, 100.0))
+        );
+        // Hanging off the bottom-right edge of its own monitor.
+        assert_eq!(
+            clamp_to_monitors((1000.0, 600.0), size, &[laptop]),
+            Some((540.0, 100.0))
+        );
+        // Window larger than the monitor: pinned to its top-left.
+        assert_eq!(
+            clamp_to_monitors((10.0, 10.0), (2000.0, 1000.0), &[laptop]),
+            Some((0.0, 0.0))
+        );
+        assert_eq!(clamp_to_monitors((10.0, 10.0), size, &[]), None);
+    }
+
+    #[test]
+    fn later_windows_cascade_from_the_saved_position() {
+        let laptop = (0.0, 0.0, 1440.0, 900.0);
+        let size = (900.0, 800.0);
+        assert_eq!(cascade_position((100.0, 40.0), 0), (100.0, 40.0));
+        assert_eq!(
+            cascade_position((100.0, 40.0), 2),
+            (
+                100.0 + 2.0 * WINDOW_CASCADE_STEP,
+                40.0 + 2.0 * WINDOW_CASCADE_STEP
+            )
+        );
+        // A long cascade still stays on screen.
+        assert_eq!(
+            clamp_to_monitors(cascade_position((100.0, 40.0), 50), size, &[laptop]),
+            Some((540.0, 100.0))
+        );
+    }
+
+    #[test]
+    fn zoom_factors_are_clamped_and_rounded() {
+        assert_eq!(clamp_zoom(0.1), MIN_ZOOM);
+        assert_eq!(clamp_zoom(10.0), MAX_ZOOM);
+        assert_eq!(clamp_zoom(f64::NAN), 1.0);
+        // Ten 0.1 steps from 1.0 land exactly on 2.0, not 1.9999999999999998.
+        let stepped = (0..10).fold(1.0, |z, _| clamp_zoom(z + ZOOM_STEP));
+        assert_eq!(stepped, 2.0);
+    }
+
+    #[test]
+    fn restored_sizes_fit_the_current_monitor() {
+        // Saved on a large external display, reopened on a laptop panel.
+        assert_eq!(
+            fit_to_monitor((2400.0, 1600.0), Some((1440.0, 900.0))),
+            (1440.0, 900.0)
+        );
+        assert_eq!(
+            fit_to_monitor((1200.0, 800.0), Some((1440.0, 900.0))),
+            (1200.0, 800.0)
+        );
+        assert_eq!(fit_to_monitor((1200.0, 800.0), None), (1200.0, 800.0));
+        // A degenerate monitor report must not shrink windows to nothing.
+        assert_eq!(
+            fit_to_monitor((1200.0, 800.0), Some((0.0, 0.0))),
+            (1200.0, 800.0)
+        );
+    }
 }
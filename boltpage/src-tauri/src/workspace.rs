@@ -8,13 +8,15 @@ use crate::prefs;
 
 /// Extensions surfaced in the workspace tree and quick switcher; matches the
 /// render allowlist plus pdf (viewable).
-const WORKSPACE_EXTENSIONS: &[&str] = &["md", "markdown", "json", "yaml", "yml", "txt", "pdf"];
+const WORKSPACE_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "json", "yaml", "yml", "toml", "xml", "txt", "log", "csv", "pdf",
+];
 
 /// Quick-switcher index caps; truncation is reported, never silent.
 const MAX_WORKSPACE_FILES: usize = 2000;
 const MAX_WORKSPACE_DEPTH: usize = 8;
 
-fn is_supported_file(path: &Path) -> bool {
+pub(crate) fn is_supported_file(path: &Path) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
         .map(|e| WORKSPACE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
@@ -1,5 +1,7 @@
 // Event names (must match Rust constants in src-tauri/src/constants.rs)
 export const EVENT_FILE_CHANGED = 'file-changed';
+export const EVENT_FILE_REMOVED = 'file-removed';
+export const EVENT_FILE_SAVED_AS = 'file-saved-as';
 export const EVENT_THEME_CHANGED = 'theme-changed';
 export const EVENT_FONT_SIZE_CHANGED = 'font-size-changed';
 export const EVENT_FONT_FAMILY_CHANGED = 'font-family-changed';
@@ -7,8 +9,18 @@ export const EVENT_TOOLBAR_DENSITY_CHANGED = 'toolbar-density-changed';
 export const EVENT_EDITOR_WINDOW_CLOSED = 'editor-window-closed';
 export const EVENT_EDITOR_BUFFER_CHANGED = 'editor-buffer-changed';
 export const EVENT_SCROLL_SYNC = 'scroll-sync';
+export const EVENT_RENDER_PROGRESS = 'render-progress';
+export const EVENT_RENDER_COMPLETE = 'render-complete';
+export const EVENT_LARGE_FILE_WARNING = 'large-file-warning';
+export const EVENT_SYNTAX_THEMES_CHANGED = 'syntax-themes-changed';
+export const EVENT_ZEN_MODE_CHANGED = 'zen-mode-changed';
+export const EVENT_TAIL_MODE_CHANGED = 'tail-mode-changed';
+export const EVENT_INLINE_EDITOR_OPEN = 'inline-editor-open';
+export const EVENT_INLINE_EDITOR_CLOSED = 'inline-editor-closed';
+export const EVENT_CUSTOM_CSS_CHANGED = 'custom-css-changed';
 export const EVENT_MENU_OPEN = 'menu-open';
 export const EVENT_MENU_OPEN_FOLDER = 'menu-open-folder';
+export const EVENT_MENU_NEW_FROM_CLIPBOARD = 'menu-new-from-clipboard';
 export const EVENT_MENU_CLOSE = 'menu-close';
 export const EVENT_MENU_FIND = 'menu-find';
 export const EVENT_MENU_FIND_NEXT = 'menu-find-next';
@@ -16,6 +28,7 @@ export const EVENT_MENU_FIND_PREV = 'menu-find-prev';
 export const EVENT_MENU_FIND_USE_SELECTION = 'menu-find-use-selection';
 export const EVENT_MENU_FIND_REPLACE = 'menu-find-replace';
 export const EVENT_MENU_EXPORT_HTML = 'menu-export-html';
+export const EVENT_MENU_EXPORT_PDF = 'menu-export-pdf';
 export const EVENT_MENU_PRINT = 'menu-print';
 export const EVENT_MENU_FORMAT_BOLD = 'menu-format-bold';
 export const EVENT_MENU_FORMAT_ITALIC = 'menu-format-italic';
@@ -27,4 +40,7 @@ export const EVENT_MENU_COMMAND_PALETTE = 'menu-command-palette';
 export const KIND_MARKDOWN = 'markdown';
 export const KIND_JSON = 'json';
 export const KIND_YAML = 'yaml';
+export const KIND_TOML = 'toml';
+export const KIND_XML = 'xml';
 export const KIND_TXT = 'txt';
+export const KIND_CSV = 'csv';
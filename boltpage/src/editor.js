@@ -2,6 +2,7 @@ import {
     SCROLL_SYNC_DEBOUNCE_MS,
     PROGRAMMATIC_SCROLL_TIMEOUT_MS,
     MIN_SCROLL_DELTA_LINES,
+    LINE_SYNC_KINDS,
     MIN_SCROLL_DELTA_PERCENT,
     DEFAULT_FONT_SIZE,
     MIN_FONT_SIZE,
@@ -13,6 +14,10 @@ import {
     createFindOverlay,
     updateFindCount,
     applyThemeToDocument,
+    THEME_IDS,
+    setThemeIds,
+    fileExtension,
+    extensionTheme,
     applyFontFamily,
     DEFAULT_DOCUMENT_FONT_ID,
     DEFAULT_EDITOR_FONT_ID,
@@ -20,9 +25,11 @@ import {
     setupChordShortcuts,
     createCommandPalette,
     isUrlLike,
+    fileErrorStatus,
 } from './shared.js';
 import {
     EVENT_FILE_CHANGED,
+    EVENT_FILE_REMOVED,
     EVENT_THEME_CHANGED,
     EVENT_FONT_SIZE_CHANGED,
     EVENT_FONT_FAMILY_CHANGED,
@@ -39,11 +46,15 @@ import {
     EVENT_MENU_FORMAT_LINK,
     EVENT_MENU_FORMAT_STRIKE,
     EVENT_MENU_COMMAND_PALETTE,
+    EVENT_MENU_NEW_FROM_CLIPBOARD,
     EVENT_MENU_PRINT,
     KIND_MARKDOWN,
     KIND_JSON,
     KIND_YAML,
+    KIND_TOML,
+    KIND_XML,
     KIND_TXT,
+    KIND_CSV,
 } from './constants.js';
 // Vendored CodeMirror 6 (pinned build, see scripts/vendor-codemirror.sh).
 // Assembled from explicit extensions; basicSetup is deliberately not used
@@ -101,6 +112,11 @@ let saveTimeout = null;
 let isSaving = false;
 let pendingSave = false;
 let previewWindow = null;
+// File isn't writable: the buffer is view-only and saves are skipped.
+let readOnly = false;
+// Theme this file shows regardless of broadcasts: its front-matter pin or
+// the theme remembered for its extension.
+let fileTheme = null;
 let isProgrammaticScroll = false;
 let scrollDebounce = null;
 let wordWrapEnabled = false;
@@ -135,7 +151,10 @@ function detectFileKind(filePath) {
     const lower = String(filePath || '').toLowerCase();
     if (lower.endsWith('.json')) return KIND_JSON;
     if (lower.endsWith('.yaml') || lower.endsWith('.yml')) return KIND_YAML;
-    if (lower.endsWith('.txt')) return KIND_TXT;
+    if (lower.endsWith('.toml')) return KIND_TOML;
+    if (lower.endsWith('.xml')) return KIND_XML;
+    if (lower.endsWith('.txt') || lower.endsWith('.log')) return KIND_TXT;
+    if (lower.endsWith('.csv')) return KIND_CSV;
     return KIND_MARKDOWN;
 }
 
@@ -147,6 +166,7 @@ function buildEditorExtensions() {
         drawSelection(),
         // Multi-cursor: Alt+click adds carets, Alt+drag column-selects.
         EditorState.allowMultipleSelections.of(true),
+        EditorState.readOnly.of(readOnly),
         rectangularSelection(),
         crosshairCursor(),
         highlightActiveLine(),
@@ -407,7 +427,7 @@ function updateStatus(status) {
     let tone = null;
     if (/saved|loaded/i.test(status)) tone = 'success';
     if (/modified/i.test(status)) tone = 'warning';
-    if (/changed/i.test(status)) tone = 'warning';
+    if (/changed|removed/i.test(status)) tone = 'warning';
     if (/error/i.test(status)) tone = 'warning';
     setBadgeState(statusBadge, status, tone, false);
 }
@@ -419,10 +439,30 @@ function markDirty() {
     }
 }
 
+// Write the buffer to a new file, then hand over to an editor for it; the
+// preview window follows via file-saved-as.
+async function saveFileAs() {
+    if (!currentFilePath || !editorView) return;
+    const content = editorView.state.doc.toString();
+    const onDisk = inspectorEol === 'CRLF' ? content.replace(/\n/g, '\r\n') : content;
+    try {
+        const newPath = await invoke('save_file_as', { currentPath: currentFilePath, content: onDisk });
+        if (!newPath) return;
+        // The buffer now lives at newPath; nothing is left to flush here.
+        isDirty = false;
+        clearTimeout(saveTimeout);
+        await invoke('open_editor_window', { filePath: newPath, previewWindow: previewWindow || '' });
+        await appWindow.close();
+    } catch (err) {
+        console.error('Save As failed:', err);
+        updateStatus('Error saving');
+    }
+}
+
 // Returns true when the buffer is persisted (or there was nothing to save),
 // false when the write failed. Callers that close the window must honor false.
 async function saveFile() {
-    if (!currentFilePath || !isDirty || !editorView) return true;
+    if (!currentFilePath || !isDirty || !editorView || readOnly) return true;
     // A save is already running: let it finish, then flush the newer buffer.
     if (isSaving) {
         pendingSave = true;
@@ -458,8 +498,10 @@ async function saveFile() {
         try {
             // Buffer text is LF-normalized; re-apply the file's on-disk EOL mode.
             const onDisk = inspectorEol === 'CRLF' ? content.replace(/\n/g, '\r\n') : content;
-            await invoke('write_file', { path: currentFilePath, content: onDisk });
-            lastKnownDiskText = content;
+            // write_file returns what landed on disk (it may trim trailing
+            // whitespace); the buffer is left alone so typing isn't disturbed.
+            const written = await invoke('write_file', { path: currentFilePath, content: onDisk });
+            lastKnownDiskText = typeof written === 'string' ? written.replace(/\r\n/g, '\n') : content;
             // Only clear the dirty flag when the buffer still matches what we
             // wrote; edits that landed during the write keep it set so the next
             // save persists them instead of seeing a falsely-clean buffer.
@@ -473,7 +515,7 @@ async function saveFile() {
             return true;
         } catch (err) {
             console.error('Failed to save file:', err);
-            updateStatus('Error saving');
+            updateStatus(fileErrorStatus(err, 'Saving'));
             return false;
         }
     } finally {
@@ -680,12 +722,8 @@ function onEditorScroll() {
     if (!currentFilePath || isProgrammaticScroll || !editorView) return;
     if (scrollDebounce) clearTimeout(scrollDebounce);
     scrollDebounce = setTimeout(async () => {
-        const lower = currentFilePath.toLowerCase();
-        const isJson = lower.endsWith('.json');
-        const isYaml = lower.endsWith('.yaml') || lower.endsWith('.yml');
-        const isTxt = lower.endsWith('.txt');
         let payload;
-        if (isJson || isYaml || isTxt) {
+        if (LINE_SYNC_KINDS.includes(currentFileKind)) {
             const line = getTopLineForEditor();
             if (line === null) return;
             // Filter micro-scrolls: only broadcast if line changed significantly
@@ -693,8 +731,7 @@ function onEditorScroll() {
                 return;
             }
             lastSyncedLine = line;
-            const kind = isJson ? KIND_JSON : (isYaml ? KIND_YAML : KIND_TXT);
-            payload = { source: appWindow.label, file_path: currentFilePath, kind, line, percent: null };
+            payload = { source: appWindow.label, file_path: currentFilePath, kind: currentFileKind, line, percent: null };
         } else {
             const scrollableHeight = editorScrollableHeight();
             if (scrollableHeight <= 0) {
@@ -723,13 +760,19 @@ async function initialize() {
     // Get file path from initialization script
     currentFilePath = window.__INITIAL_FILE_PATH__;
     previewWindow = window.__PREVIEW_WINDOW__;
+    readOnly = window.__READ_ONLY__ === true;
     currentFileKind = detectFileKind(currentFilePath);
 
     // Load preferences before the view exists so the first paint uses the
     // right gutter/wrap/typography configuration.
     try {
-        const prefs = await invoke('get_preferences');
-        applyThemeToDocument(prefs.theme);
+        const [prefs, themeIds] = await Promise.all([
+            invoke('get_preferences'),
+            invoke('get_theme_ids'),
+        ]);
+        setThemeIds(themeIds);
+        fileTheme = extensionTheme(prefs.per_extension_theme, currentFilePath);
+        applyThemeToDocument(fileTheme || prefs.theme);
         applyFontSize(prefs.font_size);
         wordWrapEnabled = prefs.word_wrap === true;
         showLineNumbers = prefs.show_line_numbers !== false;
@@ -746,6 +789,22 @@ async function initialize() {
         applyFontFamily({ documentId: DEFAULT_DOCUMENT_FONT_ID, editorId: DEFAULT_EDITOR_FONT_ID });
     }
 
+    // Front-matter pins (`boltpage: { line_numbers: true }`) win over the
+    // global preferences for this document only; toggles still save globally.
+    if (currentFilePath && currentFileKind === KIND_MARKDOWN) {
+        try {
+            const overrides = await invoke('get_document_overrides', { path: currentFilePath });
+            if (THEME_IDS.includes(overrides.theme)) {
+                fileTheme = overrides.theme;
+                applyThemeToDocument(fileTheme);
+            }
+            if (typeof overrides.word_wrap === 'boolean') wordWrapEnabled = overrides.word_wrap;
+            if (typeof overrides.line_numbers === 'boolean') showLineNumbers = overrides.line_numbers;
+        } catch (err) {
+            console.warn('Failed to read document overrides:', err);
+        }
+    }
+
     // Load file content into a fresh editor state.
     let initialDoc = '';
     let loadStatus = 'Ready';
@@ -756,14 +815,14 @@ async function initialize() {
             loadStatus = 'Loaded';
 
             const filename = currentFilePath.split(/[/\\]/).pop();
-            appWindow.setTitle(`BoltPage Editor - ${filename}`);
+            appWindow.setTitle(`${readOnly ? '\u{1F512} ' : ''}BoltPage Editor - ${filename}`);
         } catch (err) {
             console.error('Failed to load file:', err);
-            loadStatus = 'Error loading file';
+            loadStatus = fileErrorStatus(err, 'Loading');
         }
     }
     createEditorView(initialDoc);
-    updateStatus(loadStatus);
+    updateStatus(readOnly && loadStatus === 'Loaded' ? 'Read-only' : loadStatus);
     applyLineNumberVisibility();
     applyWordWrap();
 }
@@ -788,6 +847,9 @@ document.addEventListener('DOMContentLoaded', async () => {
         await listen(EVENT_FILE_CHANGED, () => {
             handleExternalFileChange();
         });
+        await listen(EVENT_FILE_REMOVED, () => {
+            updateStatus('File removed on disk');
+        });
     }
 
     // Close button just triggers close -- onCloseRequested handles the save
@@ -808,6 +870,7 @@ document.addEventListener('DOMContentLoaded', async () => {
         { key: 'g', ctrl: true, shift: true, action: () => findPrevious() },
         { key: 'g', ctrl: true, action: () => findNext() },
         { key: 'e', ctrl: true, action: () => useSelectionForFindFromEditor() },
+        { key: 's', ctrl: true, shift: true, action: () => saveFileAs() },
         { key: 's', ctrl: true, action: () => saveFile() },
         { key: 'w', ctrl: true, action: () => appWindow.close() },
         // Format shortcuts. Cmd+K is reserved as the chord prefix (Cmd+K Cmd+P);
@@ -889,6 +952,17 @@ document.addEventListener('DOMContentLoaded', async () => {
         cmToggleWrap(editorView, '~~', '~~');
     });
 
+    await listen(EVENT_MENU_NEW_FROM_CLIPBOARD, async () => {
+        if (!document.hasFocus()) return;
+        try {
+            const text = await navigator.clipboard.readText();
+            await invoke('new_window_from_clipboard', { text });
+        } catch (err) {
+            console.error('New window from clipboard failed:', err);
+            updateStatus('Error opening clipboard window');
+        }
+    });
+
     await listen(EVENT_MENU_COMMAND_PALETTE, () => {
         if (!document.hasFocus()) return;
         openPalette();
@@ -905,7 +979,7 @@ document.addEventListener('DOMContentLoaded', async () => {
 
     // Listen for theme changes
     await listen(EVENT_THEME_CHANGED, (event) => {
-        applyThemeToDocument(event.payload);
+        applyThemeToDocument(fileTheme || event.payload);
     });
 
     await listen(EVENT_FONT_SIZE_CHANGED, (event) => {
@@ -1265,6 +1339,13 @@ function openPalette() {
 }
 
 function applyThemeBroadcast(theme) {
+    // Picked here, it also becomes the theme for this file's extension.
+    const ext = fileExtension(currentFilePath);
+    if (ext) {
+        fileTheme = theme;
+        invoke('set_extension_theme', { ext, theme })
+            .catch(err => console.error('Failed to save extension theme:', err));
+    }
     applyThemeToDocument(theme);
     invoke('broadcast_theme_change', { theme })
         .catch(err => console.error('Failed to broadcast theme change:', err));
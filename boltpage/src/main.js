@@ -2,6 +2,7 @@ import {
     SCROLL_SYNC_DEBOUNCE_MS,
     PROGRAMMATIC_SCROLL_TIMEOUT_MS,
     MIN_SCROLL_DELTA_LINES,
+    LINE_SYNC_KINDS,
     MIN_SCROLL_DELTA_PERCENT,
     LINE_HEIGHT_FALLBACK_MULTIPLIER,
     DEFAULT_FONT_SIZE,
@@ -19,6 +20,10 @@ import {
     buildFindRegex,
     collectFindMatches,
     applyThemeToDocument,
+    THEME_IDS,
+    setThemeIds,
+    fileExtension,
+    extensionTheme,
     applyFontFamily,
     resolveFontStack,
     DEFAULT_DOCUMENT_FONT_ID,
@@ -27,9 +32,12 @@ import {
     setupChordShortcuts,
     createCommandPalette,
     setBadgeState,
+    fileErrorStatus,
 } from './shared.js';
 import {
     EVENT_FILE_CHANGED,
+    EVENT_FILE_REMOVED,
+    EVENT_FILE_SAVED_AS,
     EVENT_THEME_CHANGED,
     EVENT_FONT_SIZE_CHANGED,
     EVENT_FONT_FAMILY_CHANGED,
@@ -37,8 +45,18 @@ import {
     EVENT_EDITOR_WINDOW_CLOSED,
     EVENT_EDITOR_BUFFER_CHANGED,
     EVENT_SCROLL_SYNC,
+    EVENT_RENDER_PROGRESS,
+    EVENT_RENDER_COMPLETE,
+    EVENT_LARGE_FILE_WARNING,
+    EVENT_SYNTAX_THEMES_CHANGED,
+    EVENT_ZEN_MODE_CHANGED,
+    EVENT_TAIL_MODE_CHANGED,
+    EVENT_INLINE_EDITOR_OPEN,
+    EVENT_INLINE_EDITOR_CLOSED,
+    EVENT_CUSTOM_CSS_CHANGED,
     EVENT_MENU_OPEN,
     EVENT_MENU_OPEN_FOLDER,
+    EVENT_MENU_NEW_FROM_CLIPBOARD,
     EVENT_MENU_CLOSE,
     EVENT_MENU_FIND,
     EVENT_MENU_FIND_NEXT,
@@ -46,11 +64,15 @@ import {
     EVENT_MENU_FIND_USE_SELECTION,
     EVENT_MENU_FIND_REPLACE,
     EVENT_MENU_EXPORT_HTML,
+    EVENT_MENU_EXPORT_PDF,
     EVENT_MENU_COMMAND_PALETTE,
     KIND_MARKDOWN,
     KIND_JSON,
     KIND_YAML,
+    KIND_TOML,
+    KIND_XML,
     KIND_TXT,
+    KIND_CSV,
 } from './constants.js';
 
 const { invoke } = window.__TAURI__.core;
@@ -64,9 +86,32 @@ let currentFilePath = null;
 // registered by Rust, so this is seeded with the label-derived path at boot.
 let lastTrackedPath = null;
 let currentTheme = 'drac';
+// What this window's document shows instead of currentTheme, when set: a
+// front-matter pin or the theme remembered for the file's extension. Rust
+// seeds it for windows opened on a file so the first paint is already right.
+let documentTheme = window.__DOCUMENT_THEME__ || null;
+// True when documentTheme is the front-matter pin, which a picked theme
+// doesn't replace.
+let documentThemePinned = false;
+let extensionThemes = {};
+// Per-window; seeded from the show_whitespace preference.
+let showWhitespace = false;
+// Per-window distraction-free mode; the backend hides the menu (or goes full
+// screen on macOS) and reports back via EVENT_ZEN_MODE_CHANGED.
+let zenMode = false;
+// Per-window: follow the end of the file as it grows (EVENT_TAIL_MODE_CHANGED).
+// tailOffset is where render_file_tail resumes for .txt/.log, or null until
+// the current render has been marked. renderedBytes is how much of the file
+// the last render_file_view covered (its byte_len).
+let tailMode = false;
+let tailOffset = null;
+let renderedBytes = null;
+// Per-window 'rendered' | 'source'; null until a file's first render picks
+// the per-kind default (default_view_by_kind).
+let currentView = null;
 let currentKind = KIND_MARKDOWN; // KIND_JSON | KIND_MARKDOWN | KIND_TXT | 'pdf'
 let currentPdfUrl = null;
-let currentWritable = null;
+let currentWritable = window.__READ_ONLY__ === true ? false : null;
 let isProgrammaticScroll = false;
 let scrollDebounce = null;
 let contentEl = null; // scrolling container (.content-wrapper)
@@ -75,11 +120,17 @@ let findInput = null;
 let findVisible = false;
 let currentFontSize = 18;
 let updateStatusTimeout = null;
+// How long showActionError's note stays in the status pill.
+const ACTION_ERROR_STATUS_MS = 6000;
+// Path whose render_file_to_html call is in flight (render-progress filter).
+let renderingPath = null;
 let currentToolbarDensity = 'icon-label';
 let currentDocFontId = null;
 let currentEdFontId = null;
 let commandPalette = null;
 let katexReady = null;
+// Capabilities compiled into this build (app_features); null until loaded.
+let appFeatures = null;
 let mermaidReady = null;
 
 // Track last synced position to filter micro-scrolls
@@ -90,15 +141,24 @@ let cachedPreMetrics = null;
 
 async function loadPreferences() {
     try {
-        const prefs = await invoke('get_preferences');
+        const [prefs, themeIds] = await Promise.all([
+            invoke('get_preferences'),
+            invoke('get_theme_ids'),
+        ]);
+        setThemeIds(themeIds);
         applyFontSize(prefs.font_size);
+        extensionThemes = prefs.per_extension_theme || {};
         applyTheme(prefs.theme);
         tocVisible = prefs.toc_visible !== false;
+        tocMaxLevel = Math.min(6, Math.max(1, prefs.toc_max_level || 6));
+        showWhitespace = prefs.show_whitespace === true;
         applyToolbarDensity(normalizeDensity(prefs.toolbar_density), { save: false, broadcast: false });
         currentDocFontId = prefs.document_font_family || DEFAULT_DOCUMENT_FONT_ID;
         currentEdFontId = prefs.editor_font_family || DEFAULT_EDITOR_FONT_ID;
         applyFontFamily({ documentId: currentDocFontId, editorId: currentEdFontId });
         updateViewMenuState();
+        if (prefs.zen_mode === true) setZenMode(true);
+        tailMode = prefs.tail_mode === true;
     } catch (err) {
         console.error('Failed to load preferences:', err);
         applyFontSize(DEFAULT_FONT_SIZE);
@@ -130,6 +190,206 @@ function changeEditorFont(id) {
     updateViewMenuState();
 }
 
+// Window-local: other windows keep their own setting, and the preference only
+// seeds new windows.
+function setZenMode(enabled) {
+    invoke('toggle_zen_mode', { windowLabel: appWindow.label, enabled })
+        .catch(err => console.error('Failed to toggle zen mode:', err));
+}
+
+function applyZenMode(enabled) {
+    zenMode = enabled;
+    document.body.classList.toggle('zen-mode', enabled);
+}
+
+function setTailMode(enabled) {
+    invoke('set_tail_mode', { windowLabel: appWindow.label, enabled })
+        .catch(err => console.error('Failed to set tail mode:', err));
+}
+
+function applyTailMode(enabled) {
+    tailMode = enabled;
+    if (!enabled) return;
+    markTailOffset();
+    scrollToEnd();
+}
+
+// Remember where the render just shown ends, so the next change only needs
+// the lines after it. The render reports its own length: a stat taken now
+// could already include bytes appended since, which would never be shown.
+function markTailOffset() {
+    tailOffset = tailMode && currentKind === KIND_TXT && currentFilePath ? renderedBytes : null;
+}
+
+// Append the lines added since tailOffset to the open plain-text render.
+// False when the caller should re-render instead (other kinds and views,
+// truncated files, or jumps too big to append).
+async function appendFileTail() {
+    const pre = document.querySelector('#markdown-content pre.plain-text');
+    if (!tailMode || currentKind !== KIND_TXT || tailOffset === null || !pre) return false;
+    try {
+        const tail = await invoke('render_file_tail', {
+            path: currentFilePath,
+            fromByte: tailOffset,
+            showWhitespace,
+        });
+        if (tail.reset) return false;
+        if (tail.html) pre.insertAdjacentHTML('beforeend', tail.html);
+        tailOffset = tail.end_byte;
+        return true;
+    } catch (err) {
+        console.warn('Tail render failed:', err);
+        return false;
+    }
+}
+
+function scrollToEnd() {
+    if (!contentEl) return;
+    isProgrammaticScroll = true;
+    contentEl.scrollTop = contentEl.scrollHeight;
+    setTimeout(() => { isProgrammaticScroll = false; }, PROGRAMMATIC_SCROLL_TIMEOUT_MS);
+}
+
+// Read-only split: highlighted source left, rendered right, linked by the
+// data-source-line anchors render_dual puts on both sides.
+async function toggleDualView() {
+    currentView = currentView === 'dual' ? 'rendered' : 'dual';
+    await refreshFile();
+}
+
+function dualViewHtml(dual) {
+    const mapped = dual.mapped ? ' dual-mapped' : '';
+    return `<div class="dual-view${mapped}"><div class="dual-pane dual-source">${dual.source}</div>`
+        + `<div class="dual-pane dual-rendered">${dual.rendered}</div></div>`;
+}
+
+/** Highlight the rendered block and source lines around `line`. */
+function highlightDualLine(view, line) {
+    view.querySelectorAll('.dual-active').forEach(el => el.classList.remove('dual-active'));
+    if (line === null) return;
+    const blocks = Array.from(view.querySelectorAll('.dual-rendered .source-block'));
+    let index = -1;
+    blocks.forEach((block, i) => {
+        if (Number(block.dataset.sourceLine) <= line) index = i;
+    });
+    if (index < 0) return;
+    const start = Number(blocks[index].dataset.sourceLine);
+    const end = index + 1 < blocks.length ? Number(blocks[index + 1].dataset.sourceLine) : Infinity;
+    blocks[index].classList.add('dual-active');
+    view.querySelectorAll('.dual-source .source-line').forEach(el => {
+        const n = Number(el.dataset.sourceLine);
+        if (n >= start && n < end) el.classList.add('dual-active');
+    });
+}
+
+// A failed user action: a short note in the status pill, with the full
+// error on hover and in the console.
+function showActionError(summary, err) {
+    console.error(`${summary}:`, err);
+    const pill = document.getElementById('update-status');
+    if (!pill) return;
+    clearTimeout(updateStatusTimeout);
+    setBadgeState(pill, summary, 'warning', false);
+    pill.title = typeof err === 'string' ? err : (err && err.message) || String(err);
+    updateStatusTimeout = setTimeout(() => {
+        setBadgeState(pill, '', null, true);
+        pill.title = '';
+    }, ACTION_ERROR_STATUS_MS);
+}
+
+// --- Task list checkboxes ---
+
+// Task checkboxes the backend tagged with their source line; ones from
+// included files carry none and stay read-only.
+function taskCheckboxes() {
+    return Array.from(document.querySelectorAll('#markdown-content input[type="checkbox"][data-task-line]'));
+}
+
+/** Rendered checkboxes are disabled; make them live in writable Markdown. */
+function enableTaskCheckboxes() {
+    const live = currentKind === KIND_MARKDOWN && currentView === 'rendered' && currentWritable;
+    taskCheckboxes().forEach(box => { box.disabled = !live; });
+}
+
+function attachTaskToggleHandler() {
+    const container = document.getElementById('markdown-content');
+    if (!container) return;
+    container.addEventListener('change', async (e) => {
+        const box = e.target;
+        if (!(box instanceof HTMLInputElement) || box.type !== 'checkbox' || !currentFilePath) return;
+        const line = Number(box.dataset.taskLine);
+        if (!line) return;
+        try {
+            await invoke('toggle_task_item', { path: currentFilePath, line, checked: box.checked });
+        } catch (err) {
+            box.checked = !box.checked;
+            showActionError('Could not update the task', err);
+        }
+    });
+}
+
+function attachDualViewHover() {
+    const container = document.getElementById('markdown-content');
+    if (!container) return;
+    container.addEventListener('mouseover', (e) => {
+        const view = e.target.closest('.dual-view.dual-mapped');
+        if (!view) return;
+        const anchor = e.target.closest('[data-source-line]');
+        highlightDualLine(view, anchor ? Number(anchor.dataset.sourceLine) : null);
+    });
+}
+
+// Verbatim JSON keeps big integers and key order; the editor's Format action
+// is the explicit way to normalize.
+async function toggleJsonPreserve() {
+    try {
+        const prefs = await invoke('get_preferences');
+        await savePreference('json_preserve_formatting', prefs.json_preserve_formatting !== true);
+    } catch (err) {
+        console.error('Failed to toggle JSON formatting:', err);
+    }
+    await refreshFile();
+}
+
+// The render cache is keyed by the preference, so a refresh re-renders.
+async function toggleCodeLineNumbers() {
+    try {
+        const prefs = await invoke('get_preferences');
+        await savePreference('show_code_line_numbers', prefs.show_code_line_numbers !== true);
+    } catch (err) {
+        console.error('Failed to toggle code line numbers:', err);
+    }
+    await refreshFile();
+}
+
+// Unset, autolinking and smart punctuation follow the dialect; the first
+// toggle pins them explicitly.
+async function toggleMarkdownPreference(key) {
+    try {
+        const prefs = await invoke('get_preferences');
+        await savePreference(key, prefs[key] !== true);
+    } catch (err) {
+        console.error(`Failed to toggle ${key}:`, err);
+    }
+    await refreshFile();
+}
+
+async function toggleSourceView() {
+    currentView = currentView === 'source' ? 'rendered' : 'source';
+    await refreshFile();
+}
+
+async function toggleWhitespace() {
+    showWhitespace = !showWhitespace;
+    await refreshFile();
+}
+
+// The render cache is keyed by preset, so a re-render picks up the new dialect.
+async function changeMarkdownPreset(preset) {
+    await savePreference('markdown_preset', preset);
+    await refreshFile();
+}
+
 function normalizeDensity(v) {
     return v === 'icon' || v === 'label' ? v : 'icon-label';
 }
@@ -173,7 +433,7 @@ async function savePreference(key, value) {
 
 function restorePreviewAnchor(anchor) {
     if (!anchor || !contentEl) return;
-    if ((anchor.kind === KIND_JSON || anchor.kind === KIND_YAML || anchor.kind === KIND_TXT) && typeof anchor.line === 'number') {
+    if (LINE_SYNC_KINDS.includes(anchor.kind) && typeof anchor.line === 'number') {
         scrollPreviewToLine(anchor.line);
         return;
     }
@@ -210,14 +470,14 @@ function isCurrentFileViewOnly() {
 
 function currentSidebarModeLabel() {
     if (!currentFilePath) return 'Context Rail';
-    const headings = document.querySelectorAll('#markdown-content h1, #markdown-content h2, #markdown-content h3, #markdown-content h4, #markdown-content h5, #markdown-content h6');
+    const headings = outlineHeadings();
     return currentKind === KIND_MARKDOWN && headings.length > 0 ? 'Contents Rail' : 'Info Rail';
 }
 
 
 function updateViewMenuState() {
     document.querySelectorAll('.theme-option').forEach(btn => {
-        btn.classList.toggle('active', btn.dataset.theme === currentTheme);
+        btn.classList.toggle('active', btn.dataset.theme === (documentTheme || currentTheme));
     });
 
     const railToggle = document.getElementById('rail-toggle-option');
@@ -290,24 +550,67 @@ function updateFindButtonState() {
 
 function applyTheme(theme) {
     currentTheme = theme;
-    applyThemeToDocument(theme);
+    applyThemeToDocument(documentTheme || theme);
     savePreference('theme', theme);
     // Ensure syntax CSS for this theme is loaded
-    ensureSyntaxCss(theme);
+    ensureSyntaxCss(documentTheme || theme);
     updateViewMenuState();
     
     // Notify all windows of theme change
     broadcastThemeChange(theme);
 }
 
+// With a file open, a picked theme is remembered for its extension and shown
+// in this window only; the global theme changes only when no file is open.
+// A front-matter pin still wins for its document.
+function pickTheme(theme) {
+    const ext = fileExtension(currentFilePath);
+    if (!ext) {
+        applyTheme(theme);
+        return;
+    }
+    extensionThemes[ext] = theme;
+    invoke('set_extension_theme', { ext, theme })
+        .catch(err => console.error('Failed to save extension theme:', err));
+    if (documentThemePinned) return;
+    documentTheme = theme;
+    applyThemeToDocument(theme);
+    ensureSyntaxCss(theme);
+    updateViewMenuState();
+}
+
+// A document's front matter may pin a theme (`boltpage: { theme: dark }`).
+// The pin only affects this window and is never saved. Documents without
+// one use the theme remembered for their extension, then the global theme.
+async function applyDocumentOverrides(filePath) {
+    let overrides = {};
+    if (currentKind === KIND_MARKDOWN) {
+        try {
+            overrides = await invoke('get_document_overrides', { path: filePath });
+        } catch (err) {
+            console.warn('Failed to read document overrides:', err);
+        }
+    }
+    documentThemePinned = THEME_IDS.includes(overrides.theme);
+    documentTheme = documentThemePinned
+        ? overrides.theme
+        : extensionTheme(extensionThemes, filePath);
+    const theme = documentTheme || currentTheme;
+    applyThemeToDocument(theme);
+    ensureSyntaxCss(theme);
+}
+
 async function ensureSyntaxCss(theme) {
     try {
-        const css = await invoke('get_syntax_css', { theme });
+        // Scoped so syntect's generic class names (.string, .keyword, …)
+        // can't leak onto toolbar/sidebar chrome.
+        const css = await invoke('get_syntax_css_scoped', { theme, scope: '.markdown-body' });
         let styleEl = document.getElementById('syntax-css');
         if (!styleEl) {
             styleEl = document.createElement('style');
             styleEl.id = 'syntax-css';
-            document.head.appendChild(styleEl);
+            // The user's stylesheet stays last so it wins ties.
+            document.head.insertBefore(styleEl, document.getElementById('custom-css'));
         }
         styleEl.textContent = css;
     } catch (err) {
@@ -315,6 +618,42 @@ async function ensureSyntaxCss(theme) {
     }
 }
 
+// Inject (or clear) the `custom_css_path` stylesheet after the app's own.
+async function applyCustomCss() {
+    try {
+        const css = await invoke('get_custom_css');
+        let styleEl = document.getElementById('custom-css');
+        if (!css) {
+            if (styleEl) styleEl.remove();
+            return;
+        }
+        if (!styleEl) {
+            styleEl = document.createElement('style');
+            styleEl.id = 'custom-css';
+            document.head.appendChild(styleEl);
+        }
+        styleEl.textContent = css;
+    } catch (err) {
+        console.error('Failed to load custom CSS:', err);
+    }
+}
+
+// Re-scan custom .tmTheme/.sublime-syntax files. Every window re-renders via
+// EVENT_SYNTAX_THEMES_CHANGED; this one also reports what failed to load.
+async function reloadExtensions() {
+    try {
+        const report = await invoke('reload_extensions');
+        const failed = [...report.themes.failed, ...report.syntaxes.failed];
+        const loaded = report.themes.loaded.length + report.syntaxes.loaded.length;
+        if (failed.length) {
+            alert(`Loaded ${loaded} extension file(s). Failed:\n`
+                + failed.map((f) => `${f.path}: ${f.error}`).join('\n'));
+        }
+    } catch (err) {
+        console.error('Failed to reload extensions:', err);
+    }
+}
+
 function ensureKatex() {
     if (!katexReady) {
         katexReady = new Promise((resolve, reject) => {
@@ -361,8 +700,29 @@ function hashString(s) {
     return String(h);
 }
 
+// Source-line attributes shift with every edit above them, so they are left
+// out of the key and copied onto the nodes a patch keeps.
+const LINE_ATTR_RE = / data-(?:source|task)-line="\d+"/g;
+const LINE_ATTR_SELECTOR = '[data-source-line], [data-task-line]';
+
 function previewNodeKey(node) {
-    return hashString(node.nodeType === Node.ELEMENT_NODE ? node.outerHTML : (node.textContent || ''));
+    return hashString(node.nodeType === Node.ELEMENT_NODE
+        ? node.outerHTML.replace(LINE_ATTR_RE, '')
+        : (node.textContent || ''));
+}
+
+function copyLineAttrs(from, to) {
+    if (from.nodeType !== Node.ELEMENT_NODE) return;
+    const sources = [from, ...from.querySelectorAll(LINE_ATTR_SELECTOR)];
+    const targets = [to, ...to.querySelectorAll(LINE_ATTR_SELECTOR)];
+    sources.forEach((src, i) => {
+        const dst = targets[i];
+        if (!dst) return;
+        for (const name of ['data-source-line', 'data-task-line']) {
+            const value = src.getAttribute(name);
+            if (value !== null) dst.setAttribute(name, value);
+        }
+    });
 }
 
 function tocSignatureOf(container) {
@@ -407,6 +767,9 @@ function applyPreviewHtml(html) {
     }
 
     const full = prefix === 0 && suffix === 0;
+    for (let i = 0; i < suffix; i++) {
+        copyLineAttrs(newNodes[newNodes.length - 1 - i], oldNodes[oldNodes.length - 1 - i]);
+    }
 
     for (let i = oldNodes.length - 1 - suffix; i >= prefix; i--) {
         container.removeChild(oldNodes[i]);
@@ -521,29 +884,130 @@ async function reRenderMermaidForTheme() {
     await renderMermaid(container);
 }
 
-async function exportHtml() {
+async function exportHtml({ includeToc = false } = {}) {
     if (!currentFilePath) return;
     if (currentKind === 'pdf') return;
     try {
         const documentFontStack = currentDocFontId
             ? resolveFontStack('document', currentDocFontId)
             : null;
-        const result = await invoke('save_html_export', {
+        await invoke('save_html_export', {
             path: currentFilePath,
             theme: currentTheme,
             documentFontStack,
+            includeToc,
         });
-        void result;
     } catch (err) {
         console.error('HTML export failed:', err);
     }
 }
 
+// Dialog-free PDF; only offered where the backend supports it (appFeatures).
+async function exportPdf() {
+    if (!currentFilePath || currentKind === 'pdf') return;
+    if (!appFeatures || !appFeatures.pdf_export) return;
+    try {
+        await invoke('export_pdf', { path: currentFilePath, theme: currentTheme });
+    } catch (err) {
+        showActionError('PDF export failed', err);
+    }
+}
+
+// Save the inline editor's buffer (or the file as on disk) under a new
+// name; the backend retargets this window and emits file-saved-as.
+async function saveFileAs() {
+    if (!currentFilePath || currentKind === 'pdf') return;
+    try {
+        const editor = inlineEditor && inlineEditor.path === currentFilePath ? inlineEditor : null;
+        const content = editor
+            ? editor.textarea.value
+            : await invoke('read_file', { path: currentFilePath, force: currentFilePath === largeFileConfirmed });
+        const written = await invoke('save_file_as', { currentPath: currentFilePath, content });
+        // The new file got the buffer; the old one keeps its last save. A
+        // cancelled or failed Save As leaves the pending autosave alone.
+        if (written && editor && editor.textarea.value === content) {
+            clearTimeout(editor.saveTimer);
+            editor.dirty = false;
+        }
+    } catch (err) {
+        showActionError('Save As failed', err);
+    }
+}
+
+// Compare the open file with another one, side by side in a new window.
+async function compareWithFile() {
+    if (!currentFilePath) return;
+    try {
+        const other = await invoke('open_file_dialog');
+        if (!other) return;
+        await invoke('open_diff_window', { leftPath: currentFilePath, rightPath: other });
+    } catch (err) {
+        showActionError('Compare failed', err);
+    }
+}
+
+// Preview whatever Markdown is on the clipboard in a window of its own.
+async function newWindowFromClipboard() {
+    let text;
+    try {
+        text = await navigator.clipboard.readText();
+    } catch (err) {
+        showActionError('Could not read the clipboard', err);
+        return;
+    }
+    try {
+        await invoke('new_window_from_clipboard', { text });
+    } catch (err) {
+        showActionError('New window from clipboard failed', err);
+    }
+}
+
+// Structure-preserving plain text (headings, bullets, fenced code, "text (url)"
+// links, aligned tables) rather than the flattened DOM text of the preview.
+async function copyAsPlainText() {
+    if (!currentFilePath || currentKind !== KIND_MARKDOWN) return;
+    try {
+        const content = await invoke('read_file', { path: currentFilePath, force: currentFilePath === largeFileConfirmed });
+        const text = await invoke('markdown_to_plaintext', { content });
+        await navigator.clipboard.writeText(text);
+    } catch (err) {
+        console.error('Copy as plain text failed:', err);
+    }
+}
+
+// Rendered HTML for rich-text editors, with a plain-text flavor for fields
+// that only take text.
+async function copyRenderedHtml() {
+    if (!currentFilePath || currentKind === 'pdf') return;
+    try {
+        const doc = await invoke('rendered_clipboard_document', { path: currentFilePath, theme: currentTheme, force: currentFilePath === largeFileConfirmed });
+        await navigator.clipboard.write([new ClipboardItem({
+            'text/html': new Blob([doc.html], { type: 'text/html' }),
+            'text/plain': new Blob([doc.text], { type: 'text/plain' }),
+        })]);
+    } catch (err) {
+        console.error('Copy as rich text failed:', err);
+    }
+}
+
 let tocScrollDebounce = null;
 let tocVisible = true;
+let tocMaxLevel = 6;
+
+/** Rendered headings listed in the outline (h1 down to `tocMaxLevel`). */
+function outlineHeadings() {
+    const levels = [];
+    for (let level = 1; level <= tocMaxLevel; level++) {
+        levels.push(`#markdown-content h${level}`);
+    }
+    return document.querySelectorAll(levels.join(', '));
+}
 
 // --- Workspace folder (file tree + quick switcher) ---
 let workspaceFolder = null;
+// Folder of the app-wide folder watch (get_folder_watch), whichever window
+// started it; refreshed before the palette opens.
+let watchedFolder = null;
 let workspaceTabActive = 'outline'; // 'files' | 'outline'
 const expandedDirs = new Set();
 let workspaceFileIndex = [];
@@ -557,6 +1021,7 @@ async function initWorkspace() {
         workspaceFolder = null;
     }
     if (workspaceFolder && !currentFilePath) workspaceTabActive = 'files';
+    await refreshFolderWatch();
     updateSidebarTabs();
     if (workspaceFolder) {
         // buildTOC owns sidebar visibility; with a workspace it shows the
@@ -688,6 +1153,8 @@ async function closeFolder() {
         console.error('Failed to clear workspace folder:', err);
         return;
     }
+    await refreshFolderWatch();
+    if (watchedFolder === workspaceFolder) await setFolderWatch(null);
     workspaceFolder = null;
     expandedDirs.clear();
     workspaceFileIndex = [];
@@ -769,7 +1236,7 @@ function buildTOC() {
         return;
     }
 
-    const headings = document.querySelectorAll('#markdown-content h1, #markdown-content h2, #markdown-content h3, #markdown-content h4, #markdown-content h5, #markdown-content h6');
+    const headings = outlineHeadings();
     const isMarkdownWithHeadings = currentKind === KIND_MARKDOWN && headings.length > 0;
 
     if (sidebarLabel) sidebarLabel.textContent = isMarkdownWithHeadings ? 'Contents' : 'Document';
@@ -827,7 +1294,7 @@ function updateActiveTOCLink() {
     const tocNav = document.getElementById('toc-nav');
     if (!tocNav || !tocNav.children.length) return;
 
-    const headings = document.querySelectorAll('#markdown-content h1, #markdown-content h2, #markdown-content h3, #markdown-content h4, #markdown-content h5, #markdown-content h6');
+    const headings = outlineHeadings();
     if (!headings.length) return;
 
     const wrapper = document.querySelector('.content-wrapper');
@@ -876,11 +1343,39 @@ function toggleTOC() {
     updateViewMenuState();
 }
 
-async function openFile(filePath) {
+// Path the user chose to open despite max_open_size_mb; its reloads skip
+// the prompt.
+let largeFileConfirmed = null;
+
+// A render held back by max_open_size_mb (EVENT_LARGE_FILE_WARNING): say why
+// and offer to render it anyway.
+function showLargeFilePrompt({ path, size, limit }) {
+    if (!path) return;
+    const mib = (bytes) => Math.ceil(bytes / (1024 * 1024));
+    const container = document.getElementById('markdown-content');
+    container.innerHTML = `<div class="markdown-body large-file-prompt">
+        <p>${escapeHtml(String(path).split(/[/\\]/).pop())} is ${mib(size)} MiB, over the ${mib(limit)} MiB limit for opening files. Rendering it may be slow and use a lot of memory.</p>
+        <button type="button" class="wel-btn primary">Open Anyway</button>
+    </div>`;
+    container.querySelector('button').addEventListener('click', () => {
+        largeFileConfirmed = path;
+        openFile(path, { force: true });
+    });
+    // A new window stays hidden until its first render; show the prompt.
+    invoke('show_window', { windowLabel: appWindow.label })
+        .catch(err => console.error('Failed to show window:', err));
+}
+
+// `bypassCache` renders from disk without touching the HTML cache (hard reload).
+// `force` renders files over max_open_size_mb without asking.
+async function openFile(filePath, { bypassCache = false, force = false } = {}) {
     if (!filePath) {
         filePath = await invoke('open_file_dialog');
         if (!filePath) return;
     }
+    force = force || filePath === largeFileConfirmed;
+    // Switching files ends inline editing of the old one (saving it first).
+    if (inlineEditor && inlineEditor.path !== filePath) await closeInlineEditor();
     
     try {
         // Register in-window opens (welcome recents, dialog, workspace tree)
@@ -895,11 +1390,26 @@ async function openFile(filePath) {
             lastTrackedPath = filePath;
         }
 
+        // Refuse pipes, devices and huge files before a read can hang.
+        try {
+            const verdict = await invoke('validate_open_target', { path: filePath });
+            if (!verdict.ok) {
+                const container = document.getElementById('markdown-content');
+                container.innerHTML = `<div class="markdown-body"><pre style="color: var(--danger, #c00); white-space: pre-wrap;">${escapeHtml(verdict.reason || 'This file cannot be opened')}</pre></div>`;
+                return;
+            }
+        } catch (e) {
+            console.warn('Failed to validate open target:', e);
+        }
+
         const lowerPath = String(filePath).toLowerCase();
         if (lowerPath.endsWith('.pdf')) currentKind = 'pdf';
         else if (lowerPath.endsWith('.json')) currentKind = KIND_JSON;
         else if (lowerPath.endsWith('.yaml') || lowerPath.endsWith('.yml')) currentKind = KIND_YAML;
-        else if (lowerPath.endsWith('.txt')) currentKind = KIND_TXT;
+        else if (lowerPath.endsWith('.toml')) currentKind = KIND_TOML;
+        else if (lowerPath.endsWith('.xml')) currentKind = KIND_XML;
+        else if (lowerPath.endsWith('.txt') || lowerPath.endsWith('.log')) currentKind = KIND_TXT;
+        else if (lowerPath.endsWith('.csv')) currentKind = KIND_CSV;
         else currentKind = KIND_MARKDOWN;
 
         // Preserve scroll anchor if reloading same file
@@ -909,6 +1419,7 @@ async function openFile(filePath) {
         }
         let html;
         let usedPdf = false;
+        renderedBytes = null;
         if (currentKind === 'pdf') {
             try {
                 const b64 = await invoke('read_file_bytes_b64', { path: filePath });
@@ -929,15 +1440,34 @@ async function openFile(filePath) {
             }
         } else {
             try {
-                html = await invoke('render_file_to_html', { path: filePath, theme: currentTheme });
+                renderingPath = filePath;
+                // A different file starts from its kind's default view.
+                const view = filePath === currentFilePath ? currentView : null;
+                if (view === 'dual') {
+                    const dual = await invoke('render_dual', { path: filePath, theme: currentTheme, force });
+                    html = dualViewHtml(dual);
+                } else {
+                    const rendered = await invoke('render_file_view', { path: filePath, theme: currentTheme, showWhitespace, view, bypassCache, force });
+                    html = rendered.html;
+                    currentView = rendered.view;
+                    renderedBytes = rendered.byte_len;
+                }
             } catch (e) {
+                // The large-file-warning listener shows the prompt.
+                if (e && e.code === 'too_large') return;
                 console.error('Failed to render file:', e);
                 const msg = typeof e === 'string' ? e : (e && e.message) ? e.message : 'Failed to render file';
                 html = `<div class="markdown-body"><pre style="color: var(--danger, #c00); white-space: pre-wrap;">${escapeHtml(String(msg))}</pre></div>`;
+            } finally {
+                // Truncated renders stop before the last byte; clear any progress.
+                renderingPath = null;
+                const pill = document.getElementById('update-status');
+                if (pill && /^(Loading|Rendering)/.test(pill.textContent)) setBadgeState(pill, '', null, true);
             }
         }
 
         currentFilePath = filePath;
+        if (!usedPdf) await applyDocumentOverrides(filePath);
         let patchResult = { full: true, tocChanged: true };
         if (usedPdf) {
             // PDFs keep the simple full swap; nothing in them is patchable.
@@ -963,6 +1493,7 @@ async function openFile(filePath) {
         if (!usedPdf) attachLinkInterceptor();
         // Update edit button availability based on file writability
         currentWritable = await updateEditButtonState();
+        enableTaskCheckboxes();
         updateFindButtonState();
         updateExportButtonState();
         // Rebuild table of contents on full swaps (file/kind switches drive
@@ -974,8 +1505,10 @@ async function openFile(filePath) {
         // Restore scroll position only after a full swap; patches leave the
         // scroll untouched and the percent-based anchor would jolt it.
         if (anchor && patchResult.full) {
-            if ((anchor.kind === KIND_JSON || anchor.kind === KIND_YAML || anchor.kind === KIND_TXT) && typeof anchor.line === 'number') {
+            if (LINE_SYNC_KINDS.includes(anchor.kind) && typeof anchor.line === 'number') {
                 scrollPreviewToLine(anchor.line);
+            } else if (typeof anchor.line === 'number' && scrollPreviewToBlock(anchor.line, anchor.offset)) {
+                // Back on the block that was at the top.
             } else if (typeof anchor.percent === 'number') {
                 const scrollableHeight = contentEl.scrollHeight - contentEl.clientHeight;
                 if (scrollableHeight > 0) {
@@ -988,9 +1521,17 @@ async function openFile(filePath) {
 
         // Ensure window title reflects the opened file (overrides default index.html title)
         try {
-            const base = (String(currentFilePath).split(/[/\\]/).pop()) || '';
+            let base = (String(currentFilePath).split(/[/\\]/).pop()) || '';
+            // Markdown uses its front-matter `title:` when it has one.
+            if (currentKind === KIND_MARKDOWN) {
+                const title = await invoke('get_document_title', { path: currentFilePath }).catch((err) => {
+                    console.warn('Failed to read document title:', err);
+                    return null;
+                });
+                base = title || base;
+            }
             if (base) {
-                await appWindow.setTitle(`BoltPage - ${base}`);
+                await appWindow.setTitle(`${currentWritable === false ? '\u{1F512} ' : ''}BoltPage - ${base}`);
             }
         } catch (e) {
             console.warn('Failed to set window title:', e);
@@ -1008,12 +1549,34 @@ async function openFile(filePath) {
         
         // Start file watching
         await startFileWatcher();
-        
+
+        applyDocumentLanguage(currentFilePath);
+        markTailOffset();
     } catch (err) {
         console.error('[DEBUG] Failed to open file:', err);
     }
 }
 
+/**
+ * Tag the document with its detected language (hyphenation, spell-check).
+ * Runs after the render so detection never delays it.
+ */
+async function applyDocumentLanguage(filePath) {
+    const content = document.getElementById('markdown-content');
+    let lang = null;
+    try {
+        lang = await invoke('detect_language', { path: filePath });
+    } catch (err) {
+        console.warn('Language detection failed:', err);
+    }
+    if (filePath !== currentFilePath) return;
+    for (const el of [document.body, content]) {
+        if (!el) continue;
+        if (lang) el.lang = lang;
+        else el.removeAttribute('lang');
+    }
+}
+
 function getPreAndMetrics() {
     // Return cached metrics if available and DOM hasn't changed
     if (cachedPreMetrics && cachedPreMetrics.pre && cachedPreMetrics.pre.isConnected) {
@@ -1021,7 +1584,7 @@ function getPreAndMetrics() {
     }
 
     let pre = null;
-    if (currentKind === KIND_JSON || currentKind === KIND_YAML) {
+    if ([KIND_JSON, KIND_YAML, KIND_TOML, KIND_XML].includes(currentKind)) {
         pre = document.querySelector('#markdown-content .highlight pre');
     } else if (currentKind === KIND_TXT) {
         pre = document.querySelector('#markdown-content pre.plain-text');
@@ -1042,17 +1605,51 @@ function getPreAndMetrics() {
 }
 
 function getTopLineForPreview() {
-    if (currentKind === KIND_JSON || currentKind === KIND_YAML || currentKind === KIND_TXT) {
+    if (LINE_SYNC_KINDS.includes(currentKind)) {
         const m = getPreAndMetrics();
         if (!m) return null;
         const offset = Math.max(0, contentEl.scrollTop - m.preTop);
         const line = Math.floor(offset / m.lineHeight) + 1;
         return { kind: currentKind, line };
     }
-    // Fallback: percent scroll for markdown
+    // Markdown: the top block's source line (rendered view), and a percent
+    // scroll as the fallback.
     const maxScroll = Math.max(1, contentEl.scrollHeight - contentEl.clientHeight);
     const percent = Math.max(0, Math.min(1, contentEl.scrollTop / maxScroll));
-    return { kind: KIND_MARKDOWN, percent };
+    const blocks = previewBlockAnchors();
+    if (!blocks.length) return { kind: KIND_MARKDOWN, percent };
+    let top = blocks[0];
+    for (const el of blocks) {
+        if (offsetTopWithin(contentEl, el) > contentEl.scrollTop) break;
+        top = el;
+    }
+    const line = Number(top.dataset.sourceLine);
+    const offset = contentEl.scrollTop - offsetTopWithin(contentEl, top);
+    return { kind: KIND_MARKDOWN, percent, line, offset };
+}
+
+/** Rendered-view block anchors (render_file_view's data-source-line), in
+ *  document order; blocks hidden in a folded section are skipped. */
+function previewBlockAnchors() {
+    if (currentView === 'dual') return [];
+    return Array.from(document.querySelectorAll('#markdown-content [data-source-line]'))
+        .filter(el => el.offsetParent !== null);
+}
+
+/** Scroll to the last block starting at or before source `line`, `offset`
+ *  px into it when it starts on `line` exactly; false when there is none. */
+function scrollPreviewToBlock(line, offset) {
+    let el = null;
+    for (const block of previewBlockAnchors()) {
+        if (Number(block.dataset.sourceLine) > line) break;
+        el = block;
+    }
+    if (!el) return false;
+    const exact = Number(el.dataset.sourceLine) === line;
+    isProgrammaticScroll = true;
+    contentEl.scrollTop = offsetTopWithin(contentEl, el) + (exact ? Math.max(0, offset || 0) : 0);
+    setTimeout(() => { isProgrammaticScroll = false; }, PROGRAMMATIC_SCROLL_TIMEOUT_MS);
+    return true;
 }
 
 function scrollPreviewToLine(line) {
@@ -1074,9 +1671,9 @@ function offsetTopWithin(container, el) {
     return y;
 }
 
-async function refreshFile() {
+async function refreshFile({ bypassCache = false } = {}) {
     if (currentFilePath) {
-        await openFile(currentFilePath);
+        await openFile(currentFilePath, { bypassCache });
         // Clear the refresh indicator
         document.getElementById('refresh-indicator').classList.remove('show');
     }
@@ -1101,14 +1698,24 @@ async function renderBufferToPreview(kind, content) {
         let html;
         if (kind === KIND_JSON) {
             // Mid-typing JSON/YAML is usually invalid; keep the last good render.
-            try { html = await invoke('parse_json_with_theme', { content, theme: currentTheme }); }
+            try { html = await invoke('parse_json_with_theme', { content, theme: currentTheme, showWhitespace }); }
             catch (_) { return; }
         } else if (kind === KIND_YAML) {
-            try { html = await invoke('parse_yaml_with_theme', { content, theme: currentTheme }); }
+            try { html = await invoke('parse_yaml_with_theme', { content, theme: currentTheme, showWhitespace }); }
+            catch (_) { return; }
+        } else if (kind === KIND_TOML) {
+            try { html = await invoke('parse_toml_with_theme', { content, theme: currentTheme, showWhitespace }); }
+            catch (_) { return; }
+        } else if (kind === KIND_XML) {
+            try { html = await invoke('parse_xml_with_theme', { content, theme: currentTheme, showWhitespace }); }
+            catch (_) { return; }
+        } else if (kind === KIND_CSV) {
+            // Mid-edit CSV can have an open quote; keep the last good render.
+            try { html = await invoke('parse_csv_with_theme', { content, theme: currentTheme }); }
             catch (_) { return; }
         } else if (kind === KIND_TXT) {
-            // Same shape render_file_to_html emits for txt.
-            html = `<div class="markdown-body"><pre class="plain-text">${escapeHtml(content)}</pre></div>`;
+            // Same shape render_file_to_html emits for txt (links, wrap class).
+            html = await invoke('render_plain_text', { content, showWhitespace });
         } else {
             html = await invoke('parse_markdown_with_theme', { content, theme: currentTheme });
         }
@@ -1149,7 +1756,118 @@ async function openEditor() {
             previewWindow: appWindow.label
         });
     } catch (err) {
-        console.error('Failed to open editor:', err);
+        showActionError('Could not open the editor', err);
+    }
+}
+
+// --- Inline editor pane ---
+// A plain textarea beside the preview: typing re-renders through
+// renderBufferToPreview and autosaves through write_file, like the editor
+// window. The backend tracks the mode so a second editor isn't spawned.
+const INLINE_SAVE_DELAY_MS = 800;
+// `saving` holds the write in flight, so a close can wait for it.
+let inlineEditor = null; // { path, textarea, dirty, diskText, saveTimer, saving }
+
+async function openInlineEditor() {
+    if (!currentFilePath || !isEditableType(currentFilePath)) return;
+    try {
+        if (!(await invoke('is_writable', { path: currentFilePath }))) return;
+        await invoke('open_inline_editor', { windowLabel: appWindow.label });
+    } catch (err) {
+        showActionError('Could not open the inline editor', err);
+    }
+}
+
+function showInlineEditor(filePath, content) {
+    let pane = document.getElementById('inline-editor');
+    if (!pane) {
+        pane = document.createElement('div');
+        pane.id = 'inline-editor';
+        pane.className = 'inline-editor';
+        pane.innerHTML = `<div class="inline-editor-bar"><span class="inline-editor-status"></span><button type="button" class="toolbar-btn inline-editor-done">Done</button></div><textarea spellcheck="false"></textarea>`;
+        document.querySelector('.content-stage').prepend(pane);
+        pane.querySelector('.inline-editor-done').addEventListener('click', () => closeInlineEditor());
+    }
+    const textarea = pane.querySelector('textarea');
+    textarea.value = content;
+    inlineEditor = { path: filePath, textarea, dirty: false, diskText: content, saveTimer: null, saving: false };
+    textarea.oninput = () => {
+        inlineEditor.dirty = true;
+        setInlineStatus('Modified');
+        renderBufferToPreview(currentKind, textarea.value);
+        clearTimeout(inlineEditor.saveTimer);
+        inlineEditor.saveTimer = setTimeout(saveInlineEditor, INLINE_SAVE_DELAY_MS);
+    };
+    document.body.classList.add('inline-editing');
+    setInlineStatus('');
+    textarea.focus();
+}
+
+function setInlineStatus(text) {
+    const el = document.querySelector('#inline-editor .inline-editor-status');
+    if (el) el.textContent = text;
+}
+
+async function saveInlineEditor() {
+    if (!inlineEditor || !inlineEditor.dirty || inlineEditor.saving) return;
+    const editor = inlineEditor;
+    const content = editor.textarea.value;
+    editor.saving = (async () => {
+        try {
+            const written = await invoke('write_file', { path: editor.path, content });
+            editor.diskText = typeof written === 'string' ? written : content;
+            editor.dirty = editor.textarea.value !== content;
+            setInlineStatus(editor.dirty ? 'Modified' : 'Saved');
+        } catch (err) {
+            console.error('Failed to save file:', err);
+            setInlineStatus(fileErrorStatus(err, 'Saving'));
+        }
+    })();
+    try {
+        await editor.saving;
+    } finally {
+        editor.saving = false;
+    }
+}
+
+// Write pending inline edits now instead of after the autosave delay,
+// waiting out a save already in flight. False when edits are still unsaved.
+async function flushInlineEditor() {
+    const editor = inlineEditor;
+    if (!editor) return true;
+    clearTimeout(editor.saveTimer);
+    if (editor.saving) await editor.saving;
+    await saveInlineEditor();
+    return !editor.dirty;
+}
+
+async function closeInlineEditor() {
+    if (!inlineEditor) return;
+    await flushInlineEditor();
+    await invoke('close_inline_editor', { windowLabel: appWindow.label })
+        .catch(err => console.error('Failed to close inline editor:', err));
+}
+
+function hideInlineEditor() {
+    inlineEditor = null;
+    document.body.classList.remove('inline-editing');
+    const pane = document.getElementById('inline-editor');
+    if (pane) pane.remove();
+    refreshFile();
+}
+
+// External change to the file being edited inline: adopt it unless the pane
+// holds unsaved edits (those win and will overwrite on the next save).
+async function syncInlineEditorFromDisk() {
+    if (!inlineEditor || inlineEditor.dirty || inlineEditor.saving) return;
+    try {
+        const disk = await invoke('read_file', { path: inlineEditor.path });
+        if (disk !== inlineEditor.diskText) {
+            inlineEditor.diskText = disk;
+            inlineEditor.textarea.value = disk;
+        }
+    } catch (err) {
+        console.warn('Failed to reload inline editor:', err);
     }
 }
 
@@ -1268,7 +1986,7 @@ function setupEventListeners() {
     const railToggleBtn = document.getElementById('rail-toggle-option');
     if (railToggleBtn) railToggleBtn.addEventListener('click', toggleTOC);
     document.getElementById('edit-btn').addEventListener('click', openEditor);
-    document.getElementById('export-btn').addEventListener('click', exportHtml);
+    document.getElementById('export-btn').addEventListener('click', () => exportHtml());
     const findBtn = document.getElementById('find-btn');
     if (findBtn) findBtn.addEventListener('click', toggleFindOverlay);
     const welcomeOpenBtn = document.getElementById('welcome-open-btn');
@@ -1288,7 +2006,7 @@ function setupEventListeners() {
     // Theme menu
     document.querySelectorAll('.theme-option').forEach(btn => {
         btn.addEventListener('click', (e) => {
-            applyTheme(e.target.dataset.theme);
+            pickTheme(e.target.dataset.theme);
             toggleThemeMenu();
         });
     });
@@ -1326,10 +2044,12 @@ function setupEventListeners() {
         { key: 'f', ctrl: true, action: () => { if (currentKind !== 'pdf') openFindOverlay(); } },
         { key: 'g', ctrl: true, shift: true, action: () => { if (currentKind !== 'pdf') findPrevious(); } },
         { key: 'g', ctrl: true, action: () => { if (currentKind !== 'pdf') findNext(); } },
-        { key: 'p', ctrl: true, action: () => { if (currentKind !== 'pdf') invoke('print_current_window').catch(err => console.error('Print failed:', err)); } },
+        { key: 'p', ctrl: true, action: () => { if (currentKind !== 'pdf' && (!appFeatures || appFeatures.print)) invoke('print_current_window').catch(err => console.error('Print failed:', err)); } },
         { key: 'o', ctrl: true, shift: true, action: () => openFolder() },
         { key: 'o', ctrl: true, action: () => openFileSmart() },
+        { key: 'r', ctrl: true, shift: true, action: () => refreshFile({ bypassCache: true }) },
         { key: 'r', ctrl: true, action: () => refreshFile() },
+        { key: 'z', ctrl: true, shift: true, action: () => setZenMode(!zenMode) },
         { key: 't', ctrl: true, action: () => toggleThemeMenu() },
         { key: 'e', ctrl: true, shift: true, action: () => exportHtml() },
         { key: 'e', ctrl: true, action: () => {
@@ -1352,6 +2072,31 @@ function setupEventListeners() {
     }]);
 }
 
+// Watch `folder` recursively (refreshing, and with autoOpen opening, changed
+// documents), or stop the watch when `folder` is null. There is one watch
+// for the whole app; starting one replaces it.
+async function setFolderWatch(folder, autoOpen = false) {
+    try {
+        if (folder) {
+            await invoke('start_folder_watcher', { dir: folder, autoOpen });
+        } else {
+            await invoke('stop_folder_watcher');
+        }
+    } catch (err) {
+        showActionError('Failed to update folder watch', err);
+    }
+    await refreshFolderWatch();
+}
+
+async function refreshFolderWatch() {
+    try {
+        const watch = await invoke('get_folder_watch');
+        watchedFolder = watch ? watch.dir : null;
+    } catch (err) {
+        console.error('Failed to read folder watch:', err);
+    }
+}
+
 function buildPaletteActions() {
     const hasFile = !!currentFilePath;
     const isPdf = currentKind === 'pdf';
@@ -1364,9 +2109,16 @@ function buildPaletteActions() {
     ];
     if (workspaceFolder) {
         actions.push({ id: 'close-folder', label: 'Close Folder', run: () => closeFolder() });
+        if (watchedFolder === workspaceFolder) {
+            actions.push({ id: 'unwatch-folder', label: 'Stop Watching Folder', run: () => setFolderWatch(null) });
+        } else {
+            actions.push({ id: 'watch-folder',      label: 'Watch Folder for Changes',             run: () => setFolderWatch(workspaceFolder) });
+            actions.push({ id: 'watch-folder-open', label: 'Watch Folder and Open Changed Files', run: () => setFolderWatch(workspaceFolder, true) });
+        }
     }
     if (hasFile) {
         actions.push({ id: 'refresh', label: 'Refresh',       hint: '⌘R',    run: () => refreshFile() });
+        actions.push({ id: 'hard-reload', label: 'Hard Reload (Skip Cache)', hint: '⌘⇧R', run: () => refreshFile({ bypassCache: true }) });
     }
     actions.push({ id: 'toggle-sidebar', label: 'Toggle Sidebar',   run: () => toggleTOC() });
     if (hasFile && !isPdf) {
@@ -1374,12 +2126,55 @@ function buildPaletteActions() {
         actions.push({ id: 'find-next',    label: 'Find Next',        hint: '⌘G',   run: () => findNext() });
         actions.push({ id: 'find-prev',    label: 'Find Previous',    hint: '⇧⌘G',  run: () => findPrevious() });
         actions.push({ id: 'export-html',  label: 'Export as HTML…',  hint: '⌘⇧E',  run: () => exportHtml() });
+        if (appFeatures && appFeatures.pdf_export) {
+            actions.push({ id: 'export-pdf', label: 'Export as PDF…', run: () => exportPdf() });
+        }
+        actions.push({ id: 'new-from-clipboard', label: 'New Window from Clipboard', run: () => newWindowFromClipboard() });
+        actions.push({ id: 'compare-with', label: 'Compare with File…', run: () => compareWithFile() });
+        actions.push({ id: 'copy-rendered-html', label: 'Copy as Rich Text', run: () => copyRenderedHtml() });
+        actions.push({ id: 'save-as',      label: 'Save As…',         run: () => saveFileAs() });
+        actions.push({ id: 'reveal-file',  label: 'Reveal in File Manager', run: () => invoke('reveal_in_file_manager', { path: currentFilePath }).catch(console.error) });
+        if (currentKind === KIND_MARKDOWN) {
+            actions.push({ id: 'export-html-toc', label: 'Export as HTML with Contents…',  run: () => exportHtml({ includeToc: true }) });
+            actions.push({ id: 'copy-plain-text', label: 'Copy as Plain Text',           run: () => copyAsPlainText() });
+            actions.push({ id: 'preset-github',     label: 'Markdown Dialect: GitHub',     run: () => changeMarkdownPreset('github') });
+            actions.push({ id: 'preset-commonmark', label: 'Markdown Dialect: CommonMark', run: () => changeMarkdownPreset('commonmark') });
+            actions.push({ id: 'preset-obsidian',   label: 'Markdown Dialect: Obsidian',   run: () => changeMarkdownPreset('obsidian') });
+            actions.push({ id: 'toggle-autolink',   label: 'Toggle Bare URL Links',        run: () => toggleMarkdownPreference('markdown_autolink') });
+            actions.push({ id: 'toggle-smart-punctuation', label: 'Toggle Smart Punctuation', run: () => toggleMarkdownPreference('markdown_smart_punctuation') });
+            actions.push({ id: 'toggle-emoji',      label: 'Toggle Emoji Shortcodes',      run: () => toggleMarkdownPreference('markdown_emoji') });
+            actions.push({ id: 'toggle-foldable-headings', label: 'Toggle Collapsible Sections', run: () => toggleMarkdownPreference('markdown_foldable_headings') });
+        }
+        if (currentKind !== 'pdf' && currentKind !== KIND_TXT) {
+            actions.push({ id: 'toggle-source-view', label: 'Toggle Source View',     run: () => toggleSourceView() });
+            actions.push({ id: 'toggle-dual-view',   label: 'Toggle Side-by-Side Source', run: () => toggleDualView() });
+        }
+        if (currentKind === KIND_JSON) {
+            actions.push({ id: 'toggle-json-preserve', label: 'JSON: Toggle Preserve Original Formatting', run: () => toggleJsonPreserve() });
+        }
+        if (currentKind === KIND_MARKDOWN || [KIND_JSON, KIND_YAML, KIND_TOML, KIND_XML].includes(currentKind)) {
+            actions.push({ id: 'toggle-code-line-numbers', label: 'Toggle Code Line Numbers', run: () => toggleCodeLineNumbers() });
+        }
+        if (currentKind !== KIND_MARKDOWN || currentView === 'source') {
+            actions.push({ id: 'toggle-whitespace', label: 'Toggle Whitespace Characters', run: () => toggleWhitespace() });
+        }
         actions.push({ id: 'edit',         label: 'Edit…',                           run: () => openEditor() });
-    }
-    actions.push({ id: 'print',         label: 'Print…',             hint: '⌘P',  run: () => invoke('print_current_window').catch(console.error) });
-    actions.push({ id: 'theme-light',   label: 'Theme: Light',                     run: () => applyTheme('light') });
-    actions.push({ id: 'theme-dark',    label: 'Theme: Dark',                      run: () => applyTheme('dark') });
-    actions.push({ id: 'theme-drac',    label: 'Theme: Drac',                      run: () => applyTheme('drac') });
+        actions.push({ id: 'edit-inline',  label: inlineEditor ? 'Finish Editing Inline' : 'Edit Inline', run: () => (inlineEditor ? closeInlineEditor() : openInlineEditor()) });
+    }
+    if (!appFeatures || appFeatures.print) {
+        actions.push({ id: 'print',     label: 'Print…',             hint: '⌘P',  run: () => invoke('print_current_window').catch(console.error) });
+    }
+    actions.push({ id: 'reveal-prefs',  label: 'Reveal Preferences File',          run: () => invoke('reveal_preferences').catch(console.error) });
+    actions.push({ id: 'reopen-closed', label: 'Reopen Closed Window', hint: '⌘⇧T',  run: () => invoke('reopen_last_closed').catch(console.error) });
+    actions.push({ id: 'toggle-zen',    label: 'Toggle Zen Mode',   hint: '⌘⇧Z',  run: () => setZenMode(!zenMode) });
+    actions.push({ id: 'toggle-tail',   label: tailMode ? 'Stop Following File End' : 'Follow File End', run: () => setTailMode(!tailMode) });
+    actions.push({ id: 'toggle-maximize', label: 'Maximize / Restore Window', run: () => invoke('toggle_maximize', { windowLabel: appWindow.label }).catch(err => console.error('Failed to toggle maximize:', err)) });
+    actions.push({ id: 'center-window', label: 'Center Window',     run: () => invoke('center_window', { windowLabel: appWindow.label }).catch(err => console.error('Failed to center window:', err)) });
+    actions.push({ id: 'reload-extensions', label: 'Reload Themes and Syntaxes',   run: () => reloadExtensions() });
+    actions.push({ id: 'reload-all-windows', label: 'Reload All Windows',          run: () => invoke('reload_all_windows').catch(err => console.error('Failed to reload windows:', err)) });
+    actions.push({ id: 'theme-light',   label: 'Theme: Light',                     run: () => pickTheme('light') });
+    actions.push({ id: 'theme-dark',    label: 'Theme: Dark',                      run: () => pickTheme('dark') });
+    actions.push({ id: 'theme-drac',    label: 'Theme: Drac',                      run: () => pickTheme('drac') });
     actions.push({ id: 'font-size-inc', label: 'Text Size: Increase',              run: () => changeFontSize(1) });
     actions.push({ id: 'font-size-dec', label: 'Text Size: Decrease',              run: () => changeFontSize(-1) });
     actions.push({ id: 'close',         label: 'Close Window',       hint: '⌘W',   run: () => appWindow.close() });
@@ -1392,7 +2187,8 @@ function ensureCommandPalette() {
     return commandPalette;
 }
 
-function openPalette() {
+async function openPalette() {
+    await refreshFolderWatch();
     ensureCommandPalette().open();
 }
 
@@ -1437,6 +2233,26 @@ function attachLinkInterceptor() {
         if (sel) return;
         const href = a.getAttribute('href') || '';
         e.preventDefault();
+        if (a.classList.contains('wikilink')) {
+            if (!currentFilePath) return;
+            let target = href;
+            try {
+                target = decodeURIComponent(href);
+            } catch (_) {
+                // A stray `%` from hand-written HTML; use the href as is.
+            }
+            try {
+                await invoke('open_wikilink', { fromPath: currentFilePath, target });
+            } catch (err) {
+                showActionError('Could not open the linked note', err);
+            }
+            return;
+        }
+        if (href.startsWith('#')) {
+            const target = document.getElementById(decodeURIComponent(href.slice(1)));
+            if (target) target.scrollIntoView({ block: 'start' });
+            return;
+        }
         if (isAllowedExternalUrl(href)) {
             try {
                 await invoke('plugin:opener|open_url', { url: href });
@@ -1507,6 +2323,27 @@ async function stopFileWatcher() {
     }
 }
 
+// Fileless windows (`boltpage --stdin`): render the piped Markdown once.
+async function showInlineDocument() {
+    const doc = await invoke('get_inline_content').catch((err) => {
+        console.error('Failed to load inline document:', err);
+        return null;
+    });
+    if (!doc) return;
+    try {
+        currentKind = KIND_MARKDOWN;
+        // Backend-rendered documents (diffs) arrive as HTML.
+        const html = doc.html != null
+            ? doc.html
+            : await invoke('parse_markdown_with_theme', { content: doc.content, theme: currentTheme });
+        applyPreviewHtml(html);
+        attachLinkInterceptor();
+        buildTOC();
+    } catch (err) {
+        console.error('Failed to render inline document:', err);
+    }
+}
+
 // Window size persistence handled in Rust with debounce.
 
 
@@ -1517,7 +2354,17 @@ window.addEventListener('DOMContentLoaded', async () => {
         setupEventListeners();
         attachLinkInterceptor();
         attachRichCopyHandler();
+        attachDualViewHover();
+        attachTaskToggleHandler();
+        // Targeted at this window only; other windows keep their chrome.
+        // Bound before loadPreferences, which may enter zen mode.
+        await appWindow.listen(EVENT_ZEN_MODE_CHANGED, (event) => applyZenMode(event.payload === true));
         await loadPreferences();
+        applyCustomCss();
+        appFeatures = await invoke('app_features').catch(err => {
+            console.error('Failed to load app features:', err);
+            return null;
+        });
         await initWorkspace();
         // Initial button states
         currentWritable = await updateEditButtonState();
@@ -1544,13 +2391,51 @@ window.addEventListener('DOMContentLoaded', async () => {
             console.warn('Failed to bind focus-changed listener:', err);
         }
 
+        // Large JSON and Markdown files render with progress; show it in the status pill.
+        await listen(EVENT_RENDER_PROGRESS, (event) => {
+            const p = event.payload || {};
+            if (!renderingPath || p.path !== renderingPath) return;
+            const pill = document.getElementById('update-status');
+            if (!pill) return;
+            if (p.bytes_read >= p.total_bytes) {
+                setBadgeState(pill, 'Rendering…', 'accent', false);
+                return;
+            }
+            setBadgeState(pill, `Loading ${p.percent}%`, 'accent', false);
+        });
+
+        await listen(EVENT_RENDER_COMPLETE, (event) => {
+            const pill = document.getElementById('update-status');
+            if (pill && event.payload === renderingPath) setBadgeState(pill, '', null, true);
+        });
+
+        // The custom stylesheet preference changed or its file was edited.
+        await listen(EVENT_CUSTOM_CSS_CHANGED, () => applyCustomCss());
+
+        // Custom themes/syntaxes were reloaded: the render cache is gone, so
+        // refetch syntax CSS and re-render with the new highlighting.
+        await listen(EVENT_SYNTAX_THEMES_CHANGED, async () => {
+            if (!currentFilePath) {
+                ensureSyntaxCss(currentTheme);
+                return;
+            }
+            await applyDocumentOverrides(currentFilePath);
+            await refreshFile();
+        });
+
         // Listen for file change events -- auto-refresh the preview
         await listen(EVENT_FILE_CHANGED, async () => {
+            await syncInlineEditorFromDisk();
             const indicator = document.getElementById('refresh-indicator');
             if (indicator) indicator.classList.add('show');
             const pill = document.getElementById('update-status');
             if (pill) setBadgeState(pill, 'Updated', 'accent', false);
-            await refreshFile();
+            if (await appendFileTail()) {
+                if (indicator) indicator.classList.remove('show');
+            } else {
+                await refreshFile();
+            }
+            if (tailMode) scrollToEnd();
             if (pill) {
                 clearTimeout(updateStatusTimeout);
                 updateStatusTimeout = setTimeout(() => {
@@ -1559,6 +2444,23 @@ window.addEventListener('DOMContentLoaded', async () => {
             }
         });
 
+        // The file was deleted or moved away: keep showing the last render
+        // but say so until it reappears (the watcher then sends file-changed).
+        await listen(EVENT_FILE_REMOVED, () => {
+            const pill = document.getElementById('update-status');
+            if (!pill) return;
+            clearTimeout(updateStatusTimeout);
+            setBadgeState(pill, 'File removed', 'warning', false);
+        });
+
+        // Save As moved this window's file: show it from its new path. The
+        // backend already updated tracking, so skip open_tracked_file.
+        await appWindow.listen(EVENT_FILE_SAVED_AS, async (event) => {
+            if (typeof event.payload !== 'string') return;
+            lastTrackedPath = event.payload;
+            await openFile(event.payload);
+        });
+
         // Render unsaved editor buffers on type (ahead of autosave + watcher).
         await listen(EVENT_EDITOR_BUFFER_CHANGED, (event) => {
             const p = event.payload || {};
@@ -1588,8 +2490,8 @@ window.addEventListener('DOMContentLoaded', async () => {
             // DOM work from our own broadcast echo).
             if (event.payload === currentTheme) return;
             currentTheme = event.payload;
-            applyThemeToDocument(currentTheme);
-            await ensureSyntaxCss(currentTheme);
+            applyThemeToDocument(documentTheme || currentTheme);
+            await ensureSyntaxCss(documentTheme || currentTheme);
             updateViewMenuState();
             reRenderMermaidForTheme().catch(() => {});
         });
@@ -1599,6 +2501,29 @@ window.addEventListener('DOMContentLoaded', async () => {
             applyFontSize(event.payload);
         });
 
+        await appWindow.listen(EVENT_INLINE_EDITOR_OPEN, (event) => {
+            const p = event.payload || {};
+            showInlineEditor(p.file_path, p.content);
+        });
+        await appWindow.listen(EVENT_INLINE_EDITOR_CLOSED, () => hideInlineEditor());
+
+        await appWindow.listen(EVENT_TAIL_MODE_CHANGED, (event) => applyTailMode(event.payload === true));
+
+        // Like the editor window: the autosave is debounced, so write pending
+        // inline edits before the window goes. A failed save keeps the window
+        // and the buffer; the pane's status says why.
+        await appWindow.onCloseRequested(async (event) => {
+            if (!inlineEditor) return;
+            event.preventDefault();
+            if (!(await flushInlineEditor())) return;
+            appWindow.destroy();
+        });
+        await appWindow.listen(EVENT_LARGE_FILE_WARNING, (event) => showLargeFilePrompt(event.payload || {}));
+        // Escape leaves zen mode unless something else (find, palette) used it.
+        document.addEventListener('keydown', (e) => {
+            if (zenMode && e.key === 'Escape' && !e.defaultPrevented) setZenMode(false);
+        });
+
         await listen(EVENT_TOOLBAR_DENSITY_CHANGED, (event) => {
             if (event.payload === currentToolbarDensity) return;
             applyToolbarDensity(event.payload, { save: false, broadcast: false });
@@ -1636,6 +2561,13 @@ window.addEventListener('DOMContentLoaded', async () => {
             }, 50);
         });
 
+        await listen(EVENT_MENU_EXPORT_PDF, () => {
+            setTimeout(() => {
+                if (!document.hasFocus()) return;
+                exportPdf();
+            }, 50);
+        });
+
         // Listen for menu find
         await listen(EVENT_MENU_FIND, () => {
             if (!document.hasFocus()) return;
@@ -1670,6 +2602,11 @@ window.addEventListener('DOMContentLoaded', async () => {
             openFileSmart();
         });
 
+        await listen(EVENT_MENU_NEW_FROM_CLIPBOARD, () => {
+            if (!document.hasFocus()) return;
+            newWindowFromClipboard();
+        });
+
         await listen(EVENT_MENU_OPEN_FOLDER, () => {
             if (!document.hasFocus()) return;
             openFolder();
@@ -1687,7 +2624,7 @@ window.addEventListener('DOMContentLoaded', async () => {
             if (!currentFilePath) return;
             if (payload.source === appWindow.label) return; // ignore self
             if (payload.file_path !== currentFilePath) return;
-            if ((payload.kind === KIND_JSON || payload.kind === KIND_YAML || payload.kind === KIND_TXT) && typeof payload.line === 'number') {
+            if (LINE_SYNC_KINDS.includes(payload.kind) && typeof payload.line === 'number') {
                 // Scroll preview to the requested line
                 scrollPreviewToLine(payload.line);
                 lastSyncedLine = payload.line;
@@ -1718,6 +2655,8 @@ window.addEventListener('DOMContentLoaded', async () => {
             } catch (error) {
                 console.error('Failed to open file:', error);
             }
+        } else {
+            await showInlineDocument();
         }
     } catch (error) {
         console.error('[CRITICAL ERROR] Initialization failed:', error);
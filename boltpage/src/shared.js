@@ -3,13 +3,18 @@
 import {
     KIND_JSON,
     KIND_YAML,
+    KIND_TOML,
+    KIND_XML,
     KIND_TXT,
+    KIND_CSV,
 } from './constants.js';
 
 // Scroll sync configuration
 export const SCROLL_SYNC_DEBOUNCE_MS = 50;
 export const PROGRAMMATIC_SCROLL_TIMEOUT_MS = 100;
 export const MIN_SCROLL_DELTA_LINES = 0.5;
+// Kinds shown as one line-oriented <pre>; scroll sync maps them by line.
+export const LINE_SYNC_KINDS = [KIND_JSON, KIND_YAML, KIND_TOML, KIND_XML, KIND_TXT];
 export const MIN_SCROLL_DELTA_PERCENT = 0.01;
 export const LINE_HEIGHT_FALLBACK_MULTIPLIER = 1.4;
 
@@ -55,7 +60,10 @@ export function kindLabel(kind) {
         case 'pdf': return 'PDF';
         case KIND_JSON: return 'JSON';
         case KIND_YAML: return 'YAML';
+        case KIND_TOML: return 'TOML';
+        case KIND_XML: return 'XML';
         case KIND_TXT: return 'Text';
+        case KIND_CSV: return 'CSV';
         default: return 'Markdown';
     }
 }
@@ -77,6 +85,18 @@ export function setBadgeState(element, text, tone = null, hidden = false) {
     }
 }
 
+// Status-bar wording for a failed read or save. read_file, write_file,
+// is_writable and the render commands reject with an AppError
+// `{ code, message }`; other commands still reject with a string.
+export function fileErrorStatus(err, action) {
+    switch (err && err.code) {
+        case 'not_found': return `${action} failed: file not found`;
+        case 'permission_denied': return `${action} failed: permission denied`;
+        case 'invalid_encoding': return `${action} failed: not UTF-8 text`;
+        default: return `Error ${action.toLowerCase()}`;
+    }
+}
+
 export function escapeRegex(str) {
     return str.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
 }
@@ -177,10 +197,32 @@ export function nextFindIndex(currentIndex, totalResults, direction) {
     return currentIndex <= 0 ? totalResults - 1 : currentIndex - 1;
 }
 
+// Theme ids the backend knows (get_theme_ids); each window fills this in
+// with setThemeIds while loading preferences.
+export const THEME_IDS = [];
+
+export function setThemeIds(ids) {
+    THEME_IDS.splice(0, THEME_IDS.length, ...ids);
+}
+
 export function applyThemeToDocument(theme) {
     document.documentElement.setAttribute('data-theme', theme);
 }
 
+// Lowercase extension without the dot, as per_extension_theme keys it; ''
+// for dotfiles and names without one.
+export function fileExtension(filePath) {
+    const name = String(filePath || '').split(/[/\\]/).pop();
+    const dot = name.lastIndexOf('.');
+    return dot > 0 ? name.slice(dot + 1).toLowerCase() : '';
+}
+
+// The theme remembered for filePath's extension, or null when there is none.
+export function extensionTheme(themes, filePath) {
+    const theme = themes ? themes[fileExtension(filePath)] : null;
+    return THEME_IDS.includes(theme) ? theme : null;
+}
+
 // === Font presets ===============================================
 
 export const DEFAULT_DOCUMENT_FONT_ID = 'serif-iowan';